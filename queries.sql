@@ -146,3 +146,29 @@ GROUP BY player, streak_group
 HAVING COUNT(*) > 1
 ORDER BY streak_length DESC
 LIMIT 10;
+
+-- Disqualifications and where they happened
+SELECT
+    d.submission,
+    CASE
+        WHEN d.submission = m.player_a THEN m.player_b
+        ELSE m.player_a
+    END as opponent,
+    d.reason,
+    d.game_number,
+    d.turn_number,
+    d.timestamp
+FROM disqualifications d
+JOIN matchups m ON m.id = d.matchup_id
+ORDER BY d.submission, d.timestamp;
+
+-- Matchups and decided games per tournament
+SELECT
+    t.name as tournament,
+    COUNT(DISTINCT m.id) as matchups,
+    COUNT(CASE WHEN g.winner != 'pending' THEN 1 END) as decided_games
+FROM tournaments t
+LEFT JOIN matchups m ON m.tournament_id = t.id
+LEFT JOIN games g ON g.matchup_id = m.id
+GROUP BY t.id
+ORDER BY t.id;
@@ -7,8 +7,9 @@ import os
 import datetime
 
 class TournamentReportGenerator:
-    def __init__(self, db_path="results/results.sqlite"):
+    def __init__(self, db_path="results/results.sqlite", tournament=None):
         self.db_path = db_path
+        self.tournament = tournament
         self.report_dir = Path("report")
         self.report_dir.mkdir(exist_ok=True)
         self.plots_dir = self.report_dir / "plots"
@@ -237,8 +238,38 @@ class TournamentReportGenerator:
 
         return df
 
+    def generate_disqualifications(self, conn):
+        query = """
+        SELECT
+            d.submission,
+            CASE
+                WHEN d.submission = m.player_a THEN m.player_b
+                ELSE m.player_a
+            END as opponent,
+            d.reason,
+            d.game_number,
+            d.turn_number,
+            d.timestamp
+        FROM disqualifications d
+        JOIN matchups m ON m.id = d.matchup_id
+        ORDER BY d.submission, d.timestamp
+        """
+        return self._execute_query(query, conn)
+
+    def _scope_to_tournament(self, conn):
+        # A temporary view shadows the matchups table, so every query only sees this tournament
+        name = self.tournament.replace("'", "''")
+        conn.execute(f"""
+        CREATE TEMP VIEW matchups AS
+        SELECT m.* FROM main.matchups m
+        JOIN main.tournaments t ON t.id = m.tournament_id
+        WHERE t.name = '{name}'
+        """)
+
     def generate_html_report(self):
         with sqlite3.connect(self.db_path) as conn:
+            if self.tournament:
+                self._scope_to_tournament(conn)
             # Get all statistics
             win_rates_df = self.generate_win_rates_chart(conn)
             game_length_df = self.generate_game_length_chart(conn)
@@ -248,6 +279,7 @@ class TournamentReportGenerator:
             tie_stats_df = self.generate_tie_statistics(conn)
             game_extremes_df = self.generate_game_length_extremes(conn)
             win_streaks_df = self.generate_win_streaks(conn)
+            disqualifications_df = self.generate_disqualifications(conn)
 
             html_content = f"""
             <html>
@@ -304,6 +336,11 @@ class TournamentReportGenerator:
                     <img src="plots/elo_ratings.png">
                     {elo_df.to_html()}
                 </div>
+
+                <div class="section">
+                    <h2>Disqualifications</h2>
+                    {disqualifications_df.to_html() if not disqualifications_df.empty else "<p>No submission was disqualified.</p>"}
+                </div>
             </body>
             </html>
             """
@@ -312,7 +349,7 @@ class TournamentReportGenerator:
                 f.write(html_content)
 
 def main():
-    generator = TournamentReportGenerator()
+    generator = TournamentReportGenerator(tournament=os.environ.get("RPLCS_TOURNAMENT_NAME"))
     generator.generate_html_report()
 
 if __name__ == "__main__":
@@ -0,0 +1,103 @@
+use crate::submission::{ChoicesRequest, GambleResponse, Player};
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::{Rng, SeedableRng, random, rngs::StdRng};
+use rplcs_events::tournament_1::{ChoiceResponse, FightChoices, FightInfo, MapNodeType};
+use std::sync::{Arc, Mutex};
+
+/// Highest enemy power the greedy bot is willing to fight, equal to a player's starting power.
+const GREEDY_MAX_FIGHT_POWER: u32 = 5;
+
+/// Built-in players used for simulations, keyed by the name their games are recorded under.
+/// Randomized bots are seeded from `seed` when given.
+pub fn built_in_bots(seed: Option<u64>) -> Vec<(String, Arc<dyn Player>)> {
+    vec![
+        (
+            "random_bot".to_string(),
+            Arc::new(RandomBot::new(seed.unwrap_or_else(random))) as Arc<dyn Player>,
+        ),
+        ("greedy_bot".to_string(), Arc::new(GreedyBot)),
+    ]
+}
+
+/// Makes every decision uniformly at random.
+pub struct RandomBot {
+    rng: Mutex<StdRng>,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    fn with_rng<T>(&self, f: impl FnOnce(&mut StdRng) -> T) -> T {
+        let mut rng = self.rng.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut rng)
+    }
+}
+
+#[async_trait]
+impl Player for RandomBot {
+    async fn get_choices(&self, request: &ChoicesRequest, _game_id: i64) -> Result<ChoiceResponse> {
+        let moves = request.choices.choices.len();
+        let choice_index = self.with_rng(|rng| rng.random_range(0..moves.max(1)));
+        Ok(ChoiceResponse { choice_index })
+    }
+
+    async fn get_gamble_choice(&self, _game_id: i64) -> Result<GambleResponse> {
+        Ok(self.with_rng(|rng| match rng.random_range(0..4) {
+            0 => GambleResponse::Power,
+            1 => GambleResponse::Health,
+            2 => GambleResponse::MaxHealth,
+            _ => GambleResponse::Skip,
+        }))
+    }
+
+    async fn get_fight_choice(
+        &self,
+        _fight_info: &FightInfo,
+        _game_id: i64,
+    ) -> Result<FightChoices> {
+        Ok(self.with_rng(|rng| {
+            if rng.random_bool(0.5) {
+                FightChoices::Fight
+            } else {
+                FightChoices::Flee
+            }
+        }))
+    }
+}
+
+/// Heads for healing nodes (then gamble nodes) whenever one is adjacent and only fights enemies
+/// that are no stronger than a fresh player.
+pub struct GreedyBot;
+
+#[async_trait]
+impl Player for GreedyBot {
+    async fn get_choices(&self, request: &ChoicesRequest, _game_id: i64) -> Result<ChoiceResponse> {
+        let find = |node_type| request.choices.choices.iter().position(|&c| c == node_type);
+        let choice_index = find(MapNodeType::Healing)
+            .or_else(|| find(MapNodeType::Gamble))
+            .unwrap_or(0);
+        Ok(ChoiceResponse { choice_index })
+    }
+
+    async fn get_gamble_choice(&self, _game_id: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Power)
+    }
+
+    async fn get_fight_choice(
+        &self,
+        fight_info: &FightInfo,
+        _game_id: i64,
+    ) -> Result<FightChoices> {
+        let (FightInfo::Enemy(enemy) | FightInfo::Player(enemy)) = fight_info;
+        if enemy.power <= GREEDY_MAX_FIGHT_POWER {
+            Ok(FightChoices::Fight)
+        } else {
+            Ok(FightChoices::Flee)
+        }
+    }
+}
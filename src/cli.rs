@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+/// Tournament runner for RPLCS HTTP submissions
+#[derive(Parser)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Print a text board of every turn to stderr, keeping stdout free for `--ndjson`. Best used
+    /// with a single game, e.g. `simulate --rounds 1`
+    #[arg(long, global = true)]
+    pub verbose_game: bool,
+
+    /// Log a warning whenever a submission returns an out-of-range choice index, with the number
+    /// of moves it was offered
+    #[arg(long, global = true)]
+    pub choice_diagnostics: bool,
+
+    /// Print each finished matchup's result to stdout as a JSON line. Logs stay on stderr
+    #[arg(long, global = true)]
+    pub ndjson: bool,
+
+    /// Run on a single thread and play games one at a time, so runs with the same
+    /// `tournament_seed` produce identical results. Slow, meant for reproducing bugs
+    #[arg(long, global = true)]
+    pub deterministic: bool,
+
+    /// Write every request sent to a submission and its response, with the latency, to
+    /// `traces/<matchup_id>_<a>_vs_<b>/game_<id>.jsonl` in the results directory. Verbose
+    #[arg(long, global = true)]
+    pub trace: bool,
+
+    /// Write a fingerprint of the random number generator at every decision point of each game to
+    /// `rng_audit/<matchup_id>_<a>_vs_<b>/game_<id>.txt` in the results directory. Diffing two
+    /// same-seed runs shows where they stop being reproducible
+    #[arg(long, global = true)]
+    pub rng_audit: bool,
+
+    /// Write every value each game draws from its random number generator, with what it was
+    /// drawn for, to `rng/<game>.jsonl` in the results directory, `<game>` being the game's id in
+    /// the `games` table. A fresh `StdRng` seeded with the game's seed reproduces them
+    #[arg(long, global = true)]
+    pub rng_draws: bool,
+
+    /// Log how the time of each matchup and of the whole run splits between container startup,
+    /// submission calls, SVG rendering and the database
+    #[arg(long, global = true)]
+    pub profile_timing: bool,
+
+    /// Record a game whose task panicked as errored and play the rest of its matchup, instead of
+    /// failing the matchup
+    #[arg(long, global = true)]
+    pub continue_on_panic: bool,
+
+    /// Write into a results directory whose `.layout` marker is from an incompatible version of
+    /// the runner, replacing the marker
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// After a tournament or simulation, write a `.sql` text dump of the results database to this
+    /// path, as a portable backup
+    #[arg(long, global = true)]
+    pub dump: Option<PathBuf>,
+
+    /// Play only the submissions listed in this TOML manifest, with the image and directory each
+    /// one is launched from, instead of every folder in `submissions`
+    #[arg(long, global = true)]
+    pub manifest: Option<PathBuf>,
+
+    /// Worker threads of the async runtime, overriding `worker_threads`. Ignored with
+    /// `--deterministic`
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+
+    /// Seconds after which no more games or matchups are started, overriding `max_runtime`. Games
+    /// in progress finish, then the run shuts down its containers and writes its results
+    #[arg(long, global = true)]
+    pub max_runtime: Option<f32>,
+
+    /// Count only the games started at or after this UTC time, e.g. `2026-10-01` or
+    /// `2026-10-01 12:00:00`, in the standings, overriding `standings_since`
+    #[arg(long, global = true)]
+    pub since: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Play the built-in bots against each other without containers, for balancing mechanics.
+    /// Results go to a `simulation` folder inside the results directory.
+    Simulate {
+        /// Games per matchup, overriding `rounds_per_pair`
+        #[arg(long)]
+        rounds: Option<u64>,
+    },
+    /// Play games between two submissions only, skipping the rest of the round-robin
+    RunGame {
+        /// Name of the first submission, as in the `submissions` folder
+        submission_a: String,
+        /// Name of the second submission
+        submission_b: String,
+        /// Games to play, overriding `rounds_per_pair`
+        #[arg(long)]
+        rounds: Option<u64>,
+    },
+    /// List the submissions with their `meta.toml` details and whether they can be run, without
+    /// playing any games
+    List,
+    /// Write JSON Schemas of the submission HTTP protocol's request and response bodies
+    Schema {
+        /// Directory to write the `<Type>.json` files to
+        #[arg(long, default_value = "schema")]
+        out: PathBuf,
+    },
+    /// Render the map of a game seed to an SVG, to inspect generation issues. Uses the map stored
+    /// with a game of that seed when the results database has one
+    RenderMap {
+        /// Seed as stored in the `games` table
+        #[arg(long, allow_hyphen_values = true)]
+        seed: i64,
+        /// Where to write the SVG, `map_<seed>.svg` by default
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Draw small nodes labelled only with their index and occupant letters
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Write `standings.md` and `standings.json` from the games in the results database, without
+    /// playing any
+    Standings,
+    /// Delete this tournament's matchups, with their games and turn SVGs, from the results
+    /// database, so the next run plays them again. Other tournaments' results are kept
+    Reset {
+        /// Only delete the matchup between these two submissions, in either order
+        #[arg(long, num_args = 2, value_names = ["SUBMISSION_A", "SUBMISSION_B"])]
+        matchup: Option<Vec<String>>,
+    },
+    /// Generate maps from sequential seeds without playing any games, and print statistics of
+    /// their node types, degrees and connectivity, for balancing map generation
+    Maps {
+        /// How many maps to generate
+        #[arg(long, default_value_t = 1000)]
+        count: usize,
+        /// Seed of the first map, each next map using the next seed
+        #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+        first_seed: i64,
+    },
+}
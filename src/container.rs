@@ -1,73 +1,350 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use figment::{
+    Figment,
+    providers::{Format as _, Toml},
+};
 use log::{debug, info, warn};
-use reqwest::Client;
-use serde::Serialize;
+use reqwest::{Client, Response};
+use rplcs_events::tournament_1::{
+    ChoiceResponse, FightChoices, FightInfo, MapNodeType, MoveChoices,
+};
+use serde::{Deserialize, Serialize};
 use std::{
     env,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
     time::{Duration, Instant},
 };
-use tokio::{process::Command, time::sleep};
+use tokio::{
+    process::Command,
+    sync::{Mutex, OwnedSemaphorePermit, Semaphore},
+    time::{self, sleep},
+};
+
+use crate::{
+    Config,
+    port_utils::{get_next_port, reserve_port},
+    submission::{
+        ChoicesRequest, GambleResponse, STARTING_STATE, VisiblePositions, mark_call_sent,
+    },
+};
+
+mod tests;
+
+/// Number of consecutive connection failures after which a container is considered dead.
+pub const MAX_CONNECTION_FAILURES: u32 = 3;
+/// Game id sent with warmup requests. Real game ids are never negative.
+pub const WARMUP_GAME_ID: i64 = -1;
+
+/// Characters of an undeserializable response body quoted in the error.
+const MAX_BODY_SNIPPET: usize = 200;
+
+/// Default of `max_podman_ops`.
+pub const DEFAULT_MAX_PODMAN_OPS: usize = 8;
+
+/// The podman program, with the slots capping how many of its processes run at once across
+/// every matchup, so the container runtime isn't flooded with concurrent starts, stops and
+/// inspections.
+#[derive(Debug, Clone)]
+pub struct Podman {
+    command: String,
+    ops: Arc<Semaphore>,
+}
+
+impl Podman {
+    pub fn new(command: &str, ops: Arc<Semaphore>) -> Self {
+        Self {
+            command: command.to_string(),
+            ops,
+        }
+    }
+
+    /// Waits for a podman process slot, held until the returned permit is dropped.
+    async fn slot(&self) -> OwnedSemaphorePermit {
+        self.ops
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the podman semaphore is never closed")
+    }
+}
+
+/// Name of the container image built for a submission.
+pub fn image_name(submission_name: &str) -> String {
+    format!("localhost/rplcs-tournament-1/{}:latest", submission_name)
+}
+
+/// Whether the container image `image` exists, using the `podman` program.
+pub async fn image_exists(podman: &Podman, image: &str) -> Result<bool> {
+    let _slot = podman.slot().await;
+    let status = Command::new(&podman.command)
+        .args(["image", "exists", image])
+        .status()
+        .await
+        .context("Failed to run podman image exists")?;
+    Ok(status.success())
+}
+
+/// Label put on every container of the tournament called `tournament`, to filter them by.
+pub fn tournament_label(tournament: &str) -> String {
+    format!("tournament={}", tournament)
+}
+
+/// Name of a submission's container, `<prefix>__<submission>`.
+fn container_name(prefix: &str, submission_name: &str) -> String {
+    format!("{}__{}", prefix, submission_name)
+}
+
+/// Removes every container labelled with `tournament_label(tournament)`, such as ones left
+/// behind by a run that was killed. Returns how many there were.
+pub async fn remove_tournament_containers(podman: &Podman, tournament: &str) -> Result<usize> {
+    let filter = format!("label={}", tournament_label(tournament));
+    let output = {
+        let _slot = podman.slot().await;
+        Command::new(&podman.command)
+            .args(["ps", "-aq", "--filter", &filter])
+            .output()
+            .await
+            .context("Failed to list tournament containers")?
+    };
+    if !output.status.success() {
+        bail!(
+            "podman ps exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let ids: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let _slot = podman.slot().await;
+    let status = Command::new(&podman.command)
+        .args(["rm", "-f"])
+        .args(&ids)
+        .status()
+        .await
+        .context("Failed to remove tournament containers")?;
+    if !status.success() {
+        bail!("podman rm exited with {}", status);
+    }
+    Ok(ids.len())
+}
+
+/// Port a running container publishes its port 3000 on, or `None` if no container called `name`
+/// is running.
+async fn running_container_port(podman: &Podman, name: &str) -> Result<Option<u16>> {
+    let _slot = podman.slot().await;
+    let output = Command::new(&podman.command)
+        .args(["inspect", "-f", "{{.State.Running}}", name])
+        .output()
+        .await
+        .context("Failed to inspect container")?;
+    if String::from_utf8_lossy(&output.stdout).trim() != "true" {
+        return Ok(None);
+    }
+
+    let output = Command::new(&podman.command)
+        .args(["port", name, "3000"])
+        .output()
+        .await
+        .context("Failed to look up container port")?;
+    if !output.status.success() {
+        bail!(
+            "podman port exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    // One `<address>:<port>` line per published address, e.g. `0.0.0.0:3001`
+    let port = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().rsplit_once(':')?.1.parse().ok());
+    Ok(port)
+}
+
+/// Extra `podman run` arguments of a submission, from `run_args.toml` in its directory, e.g.
+/// `args = ["--memory", "256m", "-e", "MODEL=small"]`.
+#[derive(Debug, Default, Deserialize)]
+struct RunArgs {
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+/// The only options a submission's `run_args.toml` may pass, each followed by a value, either as
+/// the next argument or after `=`. Anything that could widen the container's access to the host,
+/// like volumes, devices, capabilities or host namespaces, is left out.
+const ALLOWED_RUN_FLAGS: &[&str] = &["-e", "--env", "-m", "--memory", "--cpus"];
+
+/// Checks a submission's extra `podman run` arguments. Arguments are passed to podman directly
+/// rather than through a shell, so each one only has to be an allowed option or its value.
+fn validate_run_args(args: &[String]) -> Result<()> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg.chars().any(char::is_control) {
+            bail!("Argument {:?} contains control characters", arg);
+        }
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        if !ALLOWED_RUN_FLAGS.contains(&flag) {
+            bail!(
+                "Argument {:?} is not one of the allowed options {:?}",
+                arg,
+                ALLOWED_RUN_FLAGS
+            );
+        }
+        let value = match value {
+            Some(value) => value,
+            None => args
+                .next()
+                .with_context(|| format!("Option {} is missing its value", flag))?
+                .as_str(),
+        };
+        if value.is_empty() || value.chars().any(char::is_control) {
+            bail!("Option {} has an invalid value {:?}", flag, value);
+        }
+    }
+    Ok(())
+}
+
+/// Reads and validates the extra `podman run` arguments in `submission_dir`'s `run_args.toml`,
+/// none if there is no such file.
+fn load_run_args(submission_dir: &Path) -> Result<Vec<String>> {
+    let path = submission_dir.join("run_args.toml");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let run_args: RunArgs = Figment::from(Toml::file(&path))
+        .extract()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    validate_run_args(&run_args.args)
+        .with_context(|| format!("Invalid arguments in {}", path.display()))?;
+    Ok(run_args.args)
+}
 
-use crate::port_utils::get_next_port;
+/// Arguments of the `podman run` command starting a container called `name` with `label` from
+/// `image` on `port`, with a submission's `extra` arguments after the runner's options.
+fn podman_run_args(
+    name: &str,
+    label: &str,
+    port: u16,
+    image: &str,
+    extra: &[String],
+) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "run",
+        "-d",
+        "--rm",
+        "--name",
+        name,
+        "--label",
+        label,
+        "-p",
+        &format!("{}:3000", port),
+        "-e",
+        "RUST_LOG=debug",
+    ]
+    .map(str::to_string)
+    .into();
+    args.extend_from_slice(extra);
+    // Anything after the image would be passed to the container's entrypoint instead
+    args.push(image.to_string());
+    args
+}
 
 #[derive(Debug)]
 pub struct Container {
     name: String,
     handle: ContainerHandle,
+    podman: Podman,
+    /// Containers are left running for the next run to adopt when reusing them.
+    keep_running: bool,
+    /// How long each podman command of a shutdown may take.
+    shutdown_timeout: Duration,
+    /// Whether the game endpoints have to answer too before the container counts as ready.
+    deep_readiness_check: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct ContainerHandle {
     port: u16,
     http_client: Client,
+    connection_failures: Arc<AtomicU32>,
+    min_call_interval: Duration,
+    last_call: Arc<Mutex<Option<time::Instant>>>,
 }
 
 impl Container {
-    pub async fn new(submission_name: &str) -> Result<Self> {
-        let port = get_next_port().await.context("Failed to get next port")?;
-        info!(
-            "Starting container for {} on port {}",
-            submission_name, port
-        );
-        let image_name = format!("localhost/rplcs-tournament-1/{}:latest", submission_name);
-        let name: String = format!("rplcs-tournament-1__{}", submission_name);
-
-        let mut command = Command::new("podman");
-        command.args([
-            "run",
-            "-d",
-            "--rm",
-            "--name",
-            &name,
-            "-p",
-            &format!("{}:3000", port),
-            "-e",
-            "RUST_LOG=debug",
-            &image_name,
-        ]);
-
-        debug!("Running command: {:?}", command);
-        command
-            .status()
-            .await
-            .context("Failed to start container")?;
+    pub async fn new(submission_name: &str, config: &Config) -> Result<Self> {
+        let podman = config.podman();
+        let name = container_name(&config.container_name_prefix, submission_name);
+
+        let existing_port = if config.reuse_existing_containers {
+            running_container_port(&podman, &name).await?
+        } else {
+            None
+        };
+        let port = match existing_port {
+            Some(port) => {
+                info!(
+                    "Reusing running container for {} on port {}",
+                    submission_name, port
+                );
+                // So no container started later is given the same port
+                reserve_port(port);
+                port
+            }
+            None => {
+                // Submissions from a manifest may use another image or directory
+                let image = match config
+                    .manifest
+                    .as_ref()
+                    .and_then(|m| m.entry(submission_name))
+                {
+                    Some(entry) => entry.image(),
+                    None => image_name(submission_name),
+                };
+                let submission_dir = config.submission_dir(submission_name);
+                let label = tournament_label(&config.tournament_name);
+                Self::start(
+                    &podman,
+                    submission_name,
+                    &image,
+                    &submission_dir,
+                    &name,
+                    &label,
+                )
+                .await?
+            }
+        };
 
         let timeout = env::var("CONTAINER_TIMEOUT")
             .ok()
             .and_then(|s| s.parse().ok())
             .unwrap_or(1);
 
-        let handle = ContainerHandle {
+        let handle = ContainerHandle::new(
             port,
-            http_client: Client::builder()
-                .timeout(Duration::from_secs(timeout))
-                .build()
-                .context("Failed to create HTTP client")?,
-        };
+            Duration::from_secs(timeout),
+            config.min_call_interval(),
+        )?;
 
         let container = Container {
             name: name.clone(),
             handle,
+            podman,
+            keep_running: config.reuse_existing_containers,
+            shutdown_timeout: config.shutdown_timeout(),
+            deep_readiness_check: config.deep_readiness_check,
         };
 
         // Wait for container to be ready
@@ -75,16 +352,106 @@ impl Container {
             .wait_until_ready()
             .await
             .context("Container failed to start")?;
+        // Some runtimes answer /health a moment before they serve reliably
+        let delay = config.post_ready_delay();
+        if !delay.is_zero() {
+            debug!("Letting container {} settle for {:?}", name, delay);
+            sleep(delay).await;
+        }
+        container.handle.warm_up(config.warmup_requests).await;
         Ok(container)
     }
 
+    /// Runs a fresh container called `name` with `label` from `image` for a submission, returning
+    /// the port it listens on. Extra `podman run` arguments come from `run_args.toml` in
+    /// `submission_dir`.
+    async fn start(
+        podman: &Podman,
+        submission_name: &str,
+        image: &str,
+        submission_dir: &Path,
+        name: &str,
+        label: &str,
+    ) -> Result<u16> {
+        let port = get_next_port().await.context("Failed to get next port")?;
+        info!(
+            "Starting container for {} on port {}",
+            submission_name, port
+        );
+        let _slot = podman.slot().await;
+        // Remove any container left behind by an earlier failed or timed out start
+        Command::new(&podman.command)
+            .args(["rm", "-f", name])
+            .output()
+            .await
+            .context("Failed to remove leftover container")?;
+
+        let run_args = load_run_args(submission_dir)?;
+        let args = podman_run_args(name, label, port, image, &run_args);
+        info!("Running {} {:?}", podman.command, args);
+        Command::new(&podman.command)
+            .args(&args)
+            .status()
+            .await
+            .context("Failed to start container")?;
+        Ok(port)
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
+        if self.keep_running {
+            debug!("Leaving container {} running for reuse", self.name);
+            return Ok(());
+        }
         debug!("Stopping container {}", self.name);
-        Command::new("podman")
+        let slot = self.podman.slot().await;
+        let stop = Command::new(&self.podman.command)
             .args(["stop", &self.name])
-            .output()
+            .kill_on_drop(true)
+            .output();
+        let stopped = time::timeout(self.shutdown_timeout, stop).await;
+        drop(slot);
+        match stopped {
+            Ok(Ok(output)) if output.status.success() => return Ok(()),
+            Ok(Ok(output)) => warn!(
+                "podman stop exited with {} for container {}, killing it: {}",
+                output.status,
+                self.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Ok(Err(e)) => warn!("Failed to stop container {}, killing it: {}", self.name, e),
+            Err(_) => warn!(
+                "Container {} did not stop within {:?}, killing it",
+                self.name, self.shutdown_timeout
+            ),
+        }
+
+        let kill_error = match self.run_podman(&["kill", &self.name]).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        warn!(
+            "Failed to kill container {}, removing it: {:#}",
+            self.name, kill_error
+        );
+        self.run_podman(&["rm", "-f", &self.name])
             .await
-            .context("Failed to stop container")?;
+            .with_context(|| format!("Failed to stop, kill or remove container {}", self.name))
+    }
+
+    /// Runs podman with `args` within the shutdown timeout, failing unless it exits successfully.
+    async fn run_podman(&self, args: &[&str]) -> Result<()> {
+        let _slot = self.podman.slot().await;
+        let status = Command::new(&self.podman.command)
+            .args(args)
+            .kill_on_drop(true)
+            .status();
+        let status = time::timeout(self.shutdown_timeout, status)
+            .await
+            .with_context(|| format!("podman {} timed out", args[0]))?
+            .with_context(|| format!("Failed to run podman {}", args[0]))?;
+        if !status.success() {
+            anyhow::bail!("podman {} exited with {}", args[0], status);
+        }
         Ok(())
     }
 
@@ -100,11 +467,14 @@ impl Container {
         debug!("Waiting for container {} to be ready", self.name);
         while start_time.elapsed() < timeout {
             // First check if container is running
-            let output = Command::new("podman")
-                .args(["inspect", "-f", "{{.State.Running}}", &self.name])
-                .output()
-                .await
-                .context("Failed to inspect container")?;
+            let output = {
+                let _slot = self.podman.slot().await;
+                Command::new(&self.podman.command)
+                    .args(["inspect", "-f", "{{.State.Running}}", &self.name])
+                    .output()
+                    .await
+                    .context("Failed to inspect container")?
+            };
 
             let running = String::from_utf8(output.stdout)
                 .context("Failed to parse container status")?
@@ -117,7 +487,7 @@ impl Container {
             }
 
             // Then check if HTTP endpoint is responding
-            match self.health_check().await {
+            match self.handle.readiness_check(self.deep_readiness_check).await {
                 Ok(()) => {
                     info!("Container {} is ready and responding", self.name);
                     return Ok(());
@@ -135,20 +505,48 @@ impl Container {
         );
         anyhow::bail!("Container failed to start within timeout period")
     }
-
-    async fn health_check(&self) -> Result<()> {
-        self.handle.health_check().await
-    }
 }
 
 impl ContainerHandle {
+    fn new(port: u16, timeout: Duration, min_call_interval: Duration) -> Result<Self> {
+        Ok(ContainerHandle {
+            port,
+            http_client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("Failed to create HTTP client")?,
+            connection_failures: Arc::new(AtomicU32::new(0)),
+            min_call_interval,
+            last_call: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Waits until at least `min_call_interval` has passed since the previous call to this
+    /// container. Callers queue up behind the lock, so calls go out one interval apart.
+    async fn pace(&self) {
+        if self.min_call_interval.is_zero() {
+            return;
+        }
+
+        let mut last_call = self.last_call.lock().await;
+        if let Some(last) = *last_call {
+            time::sleep_until(last + self.min_call_interval).await;
+        }
+        *last_call = Some(time::Instant::now());
+    }
+
+    /// Whether calls to this container have repeatedly failed to connect, e.g. because it crashed.
+    pub fn is_dead(&self) -> bool {
+        self.connection_failures.load(Ordering::SeqCst) >= MAX_CONNECTION_FAILURES
+    }
+
     fn get_url(&self) -> String {
         format!("http://localhost:{}", self.port)
     }
 
     pub async fn health_check(&self) -> Result<()> {
         self.http_client
-            .get(&format!("{}/health", self.get_url()))
+            .get(format!("{}/health", self.get_url()))
             .send()
             .await
             .context("Failed to send health check request")?
@@ -157,6 +555,79 @@ impl ContainerHandle {
         Ok(())
     }
 
+    /// Checks `/health` and, if `deep`, that `/choices`, `/gamble` and `/fight` answer a minimal
+    /// request with a success status and a valid response, so submissions that initialize lazily
+    /// aren't called by a game before they can answer.
+    pub async fn readiness_check(&self, deep: bool) -> Result<()> {
+        self.health_check().await?;
+        if !deep {
+            return Ok(());
+        }
+        let choices = ChoicesRequest {
+            choices: MoveChoices {
+                choices: vec![MapNodeType::Normal, MapNodeType::Healing],
+            },
+            player: STARTING_STATE,
+            opponent: Some(STARTING_STATE),
+            positions: VisiblePositions::default(),
+        };
+        self.probe::<_, ChoiceResponse>("choices", &choices).await?;
+        self.probe::<_, GambleResponse>("gamble", &()).await?;
+        self.probe::<_, FightChoices>("fight", &FightInfo::Enemy(STARTING_STATE))
+            .await?;
+        Ok(())
+    }
+
+    /// Posts `payload` to `endpoint` as a readiness check under `WARMUP_GAME_ID`, failing unless
+    /// it answers with a success status and an `R`.
+    async fn probe<T: Serialize, R: serde::de::DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        payload: &T,
+    ) -> Result<()> {
+        let response = self
+            .http_client
+            .post(format!("{}/{}", self.get_url(), endpoint))
+            .query(&[("game_id", WARMUP_GAME_ID.to_string())])
+            .json(payload)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send /{} readiness request", endpoint))?
+            .error_for_status()
+            .with_context(|| format!("/{} is not ready", endpoint))?;
+        read_json::<R>(response)
+            .await
+            .with_context(|| format!("/{} returned an invalid response", endpoint))?;
+        Ok(())
+    }
+
+    /// Sends `requests` throwaway `/choices` calls so the submission is warmed up before the
+    /// first game, e.g. for JIT compiled languages. Answers and failures are ignored, and failing
+    /// to connect doesn't count toward the container being dead, since it may still be starting.
+    pub async fn warm_up(&self, requests: u32) {
+        if requests == 0 {
+            return;
+        }
+
+        debug!(
+            "Warming up container on port {} with {} requests",
+            self.port, requests
+        );
+        let request = ChoicesRequest {
+            choices: MoveChoices {
+                choices: vec![MapNodeType::Normal, MapNodeType::Healing],
+            },
+            player: STARTING_STATE,
+            opponent: Some(STARTING_STATE),
+            positions: VisiblePositions::default(),
+        };
+        for _ in 0..requests {
+            if let Err(e) = self.post("choices", WARMUP_GAME_ID, &request).await {
+                debug!("Warmup request to port {} failed: {:#}", self.port, e);
+            }
+        }
+    }
+
     pub async fn call<T: Serialize, R: serde::de::DeserializeOwned>(
         &self,
         endpoint: &str,
@@ -167,15 +638,63 @@ impl ContainerHandle {
             "Calling {} on port {} for game {}",
             endpoint, self.port, game_id
         );
+        if self.is_dead() {
+            anyhow::bail!("Container on port {} is dead", self.port);
+        }
+
+        let response = match self.post(endpoint, game_id, payload).await {
+            Ok(response) => {
+                self.connection_failures.store(0, Ordering::SeqCst);
+                response
+            }
+            Err(e) => {
+                if e.is_connect() {
+                    let failures = self.connection_failures.fetch_add(1, Ordering::SeqCst) + 1;
+                    warn!(
+                        "Connection to container on port {} failed ({} in a row)",
+                        self.port, failures
+                    );
+                }
+                return Err(e).context("Failed to send request");
+            }
+        };
+
+        read_json(response).await
+    }
+
+    /// Sends `payload` to `endpoint`, paced like every call but without tracking failures.
+    async fn post<T: Serialize>(
+        &self,
+        endpoint: &str,
+        game_id: i64,
+        payload: &T,
+    ) -> reqwest::Result<Response> {
+        self.pace().await;
+        mark_call_sent();
         self.http_client
-            .post(&format!("{}/{}", self.get_url(), endpoint))
+            .post(format!("{}/{}", self.get_url(), endpoint))
             .query(&[("game_id", game_id.to_string())])
             .json(payload)
             .send()
             .await
-            .context("Failed to send request")?
-            .json()
-            .await
-            .context("Failed to deserialize response")
+    }
+}
+
+/// Deserializes the body of `response`, quoting the start of the body if it isn't a valid `R`
+/// so submission authors can see what they sent.
+async fn read_json<R: serde::de::DeserializeOwned>(response: Response) -> Result<R> {
+    let body = response
+        .text()
+        .await
+        .context("Failed to read response body")?;
+    serde_json::from_str(&body)
+        .with_context(|| format!("Failed to deserialize response {}", body_snippet(&body)))
+}
+
+/// `body` quoted, cut off after `MAX_BODY_SNIPPET` characters.
+fn body_snippet(body: &str) -> String {
+    match body.char_indices().nth(MAX_BODY_SNIPPET) {
+        Some((end, _)) => format!("{:?}...", &body[..end]),
+        None => format!("{:?}", body),
     }
 }
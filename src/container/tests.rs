@@ -0,0 +1,512 @@
+#![cfg(test)]
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rplcs_events::tournament_1::{ChoiceResponse, MapNodeType, MoveChoices};
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+
+use crate::test_utils::{
+    fake_podman, mock_server, podman_commands, recording_server, routed_server, running_podman,
+};
+use crate::{Config, port_utils::is_port_reserved};
+
+use super::{
+    Container, ContainerHandle, DEFAULT_MAX_PODMAN_OPS, MAX_BODY_SNIPPET, MAX_CONNECTION_FAILURES,
+    Podman, WARMUP_GAME_ID, body_snippet, container_name, load_run_args, podman_run_args,
+    remove_tournament_containers, tournament_label,
+};
+
+fn move_choices() -> MoveChoices {
+    MoveChoices {
+        choices: vec![MapNodeType::Normal, MapNodeType::Healing],
+    }
+}
+
+#[tokio::test]
+async fn test_handle_marked_dead_when_endpoint_stops_responding() {
+    let (port, server) = mock_server(r#"{"choice_index":1}"#).await;
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), Duration::ZERO).unwrap();
+
+    // The first part of the series goes through normally
+    for game_id in 0..3 {
+        let response: ChoiceResponse = handle
+            .call("choices", game_id, &move_choices())
+            .await
+            .unwrap();
+        assert_eq!(response.choice_index, 1);
+        assert!(!handle.is_dead());
+    }
+
+    // Then the container goes away
+    server.abort();
+    let _ = server.await;
+
+    for game_id in 0..MAX_CONNECTION_FAILURES as i64 {
+        assert!(!handle.is_dead());
+        let result: anyhow::Result<ChoiceResponse> =
+            handle.call("choices", game_id, &move_choices()).await;
+        assert!(result.is_err());
+    }
+    assert!(handle.is_dead());
+
+    // Clones share the same state, so every game in the matchup sees the container as dead
+    let clone = handle.clone();
+    assert!(clone.is_dead());
+    let result: anyhow::Result<ChoiceResponse> = clone.call("choices", 99, &move_choices()).await;
+    assert!(result.unwrap_err().to_string().contains("dead"));
+}
+
+#[tokio::test]
+async fn test_successful_call_resets_failure_count() {
+    let (port, server) = mock_server(r#"{"choice_index":0}"#).await;
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), Duration::ZERO).unwrap();
+    let dead_port = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let unreachable = ContainerHandle {
+        port: dead_port,
+        ..handle.clone()
+    };
+
+    for game_id in 0..(MAX_CONNECTION_FAILURES - 1) as i64 {
+        let result: anyhow::Result<ChoiceResponse> =
+            unreachable.call("choices", game_id, &move_choices()).await;
+        assert!(result.is_err());
+    }
+
+    // A single successful call clears the streak
+    let _: ChoiceResponse = handle.call("choices", 0, &move_choices()).await.unwrap();
+    let result: anyhow::Result<ChoiceResponse> =
+        unreachable.call("choices", 0, &move_choices()).await;
+    assert!(result.is_err());
+    assert!(!handle.is_dead());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_invalid_json_error_quotes_the_response_body() {
+    let (port, server) = mock_server(r#"{"choice_idx":1,}"#).await;
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), Duration::ZERO).unwrap();
+
+    let error = handle
+        .call::<_, ChoiceResponse>("choices", 0, &move_choices())
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", error).contains(r#""{\"choice_idx\":1,}""#));
+
+    server.abort();
+}
+
+#[test]
+fn test_long_bodies_are_cut_off_in_the_error() {
+    let body = "é".repeat(MAX_BODY_SNIPPET * 2);
+    let snippet = body_snippet(&body);
+    assert!(snippet.ends_with("\"..."));
+    assert_eq!(
+        snippet.chars().filter(|&c| c == 'é').count(),
+        MAX_BODY_SNIPPET
+    );
+}
+
+#[tokio::test]
+async fn test_calls_are_spaced_by_min_call_interval() {
+    let (port, server) = mock_server(r#"{"choice_index":0}"#).await;
+    let interval = Duration::from_millis(100);
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), interval).unwrap();
+
+    let choices = move_choices();
+    let clone = handle.clone();
+
+    let start = Instant::now();
+    let (first, second) = tokio::join!(
+        handle.call::<_, ChoiceResponse>("choices", 0, &choices),
+        clone.call::<_, ChoiceResponse>("choices", 1, &choices)
+    );
+    first.unwrap();
+    second.unwrap();
+    assert!(start.elapsed() >= interval);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_warmup_requests_are_sent_before_the_first_game_call() {
+    let (port, requests, server) = recording_server(r#"{"choice_index":0}"#).await;
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), Duration::ZERO).unwrap();
+
+    handle.warm_up(3).await;
+    let _: ChoiceResponse = handle.call("choices", 0, &move_choices()).await.unwrap();
+
+    let warmup = format!("POST /choices?game_id={} HTTP/1.1", WARMUP_GAME_ID);
+    assert_eq!(
+        *requests.lock().unwrap(),
+        [
+            warmup.as_str(),
+            &warmup,
+            &warmup,
+            "POST /choices?game_id=0 HTTP/1.1"
+        ]
+    );
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_failed_warmup_requests_do_not_mark_the_container_dead() {
+    let dead_port = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let handle = ContainerHandle::new(dead_port, Duration::from_secs(1), Duration::ZERO).unwrap();
+
+    // Still starting up, so nothing answers yet
+    handle.warm_up(MAX_CONNECTION_FAILURES * 2).await;
+
+    assert!(!handle.is_dead());
+}
+
+#[tokio::test]
+async fn test_zero_warmup_requests_send_nothing() {
+    let (port, requests, server) = recording_server(r#"{"choice_index":0}"#).await;
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), Duration::ZERO).unwrap();
+
+    handle.warm_up(0).await;
+
+    assert!(requests.lock().unwrap().is_empty());
+    server.abort();
+}
+
+/// Answers like a submission whose game endpoints work, unless `choices_status` says otherwise.
+fn submission_route(choices_status: u16) -> impl Fn(&str) -> (u16, &'static str) {
+    move |request_line| {
+        let path = request_line.split(' ').nth(1).unwrap_or_default();
+        match path.split('?').next().unwrap_or_default() {
+            "/health" => (200, ""),
+            "/choices" => (choices_status, r#"{"choice_index":0}"#),
+            "/gamble" => (200, r#""Skip""#),
+            "/fight" => (200, r#""Flee""#),
+            _ => (404, ""),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_deep_readiness_check_waits_for_game_endpoints() {
+    let (port, _, server) = routed_server(submission_route(500)).await;
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), Duration::ZERO).unwrap();
+
+    // Only the deep check notices that /choices still fails
+    handle.readiness_check(false).await.unwrap();
+    let error = handle.readiness_check(true).await.unwrap_err();
+    assert!(format!("{error:#}").contains("/choices"), "{error:#}");
+    server.abort();
+
+    let (port, requests, server) = routed_server(submission_route(200)).await;
+    let handle = ContainerHandle::new(port, Duration::from_secs(1), Duration::ZERO).unwrap();
+    handle.readiness_check(true).await.unwrap();
+    let warmup = |endpoint| format!("POST /{endpoint}?game_id={WARMUP_GAME_ID} HTTP/1.1");
+    assert_eq!(
+        *requests.lock().unwrap(),
+        [
+            "GET /health HTTP/1.1".to_string(),
+            warmup("choices"),
+            warmup("gamble"),
+            warmup("fight")
+        ]
+    );
+    server.abort();
+}
+
+/// Adopting a running container only inspects it and looks up its port.
+#[cfg(unix)]
+#[tokio::test]
+async fn test_running_container_is_adopted_without_podman_run() {
+    let (port, requests, _server) = recording_server(r#"{"choice_index":0}"#).await;
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("podman.log");
+    let config = Config {
+        podman_command: running_podman(dir.path(), port),
+        reuse_existing_containers: true,
+        ..Config::default()
+    };
+
+    let container = Container::new("adoptee", &config).await.unwrap();
+    container.shutdown().await.unwrap();
+
+    assert_eq!(container.handle().port, port);
+    assert!(is_port_reserved(port));
+    assert_eq!(
+        requests.lock().unwrap().first().unwrap(),
+        "GET /health HTTP/1.1"
+    );
+    let commands: Vec<String> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|line| line.split(' ').next().unwrap().to_string())
+        .collect();
+    assert!(commands.contains(&"port".to_string()), "{commands:?}");
+    assert!(
+        commands
+            .iter()
+            .all(|command| command == "inspect" || command == "port"),
+        "{commands:?}"
+    );
+}
+
+/// A running container whose port can't be looked up is an error, not a reason to start another.
+#[cfg(unix)]
+#[tokio::test]
+async fn test_failed_port_lookup_does_not_start_a_second_container() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        podman_command: fake_podman(
+            dir.path(),
+            "case \"$1\" in\n  inspect) echo true ;;\n  port) echo 'cannot talk to podman' >&2; exit 125 ;;\nesac",
+        ),
+        reuse_existing_containers: true,
+        ..Config::default()
+    };
+
+    let err = Container::new("stuck", &config).await.unwrap_err();
+
+    assert!(
+        format!("{err:#}").contains("cannot talk to podman"),
+        "{err:#}"
+    );
+    let commands = podman_commands(dir.path());
+    assert!(
+        commands.iter().all(|command| !command.starts_with("run")),
+        "{commands:?}"
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_container_is_used_only_after_the_post_ready_delay() {
+    let ready_at = Arc::new(Mutex::new(None));
+    let ready = ready_at.clone();
+    let (port, _, _server) = routed_server(move |request_line| {
+        if request_line.starts_with("GET /health") {
+            ready.lock().unwrap().get_or_insert_with(Instant::now);
+        }
+        (200, "")
+    })
+    .await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        podman_command: running_podman(dir.path(), port),
+        reuse_existing_containers: true,
+        post_ready_delay: 0.3,
+        ..Config::default()
+    };
+
+    Container::new("sleepy", &config).await.unwrap();
+
+    let ready_at = ready_at.lock().unwrap().expect("readiness was checked");
+    assert!(ready_at.elapsed() >= Duration::from_millis(300));
+}
+
+#[test]
+fn test_run_args_file_extends_podman_run() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("run_args.toml"),
+        r#"args = ["-e", "MODEL=small", "--memory=256m"]"#,
+    )
+    .unwrap();
+
+    let extra = load_run_args(dir.path()).unwrap();
+    let args = podman_run_args("bot", "tournament=t", 3001, "localhost/bot:latest", &extra);
+
+    assert_eq!(args[..2], ["run", "-d"]);
+    assert_eq!(
+        args[args.len() - 4..],
+        ["-e", "MODEL=small", "--memory=256m", "localhost/bot:latest"]
+    );
+    // No file, no extra arguments
+    let empty = tempfile::tempdir().unwrap();
+    assert!(load_run_args(empty.path()).unwrap().is_empty());
+}
+
+#[test]
+fn test_run_args_only_allow_listed_options() {
+    let dir = tempfile::tempdir().unwrap();
+    for args in [
+        r#"["--name=other"]"#,
+        r#"["-p", "80:3000"]"#,
+        r#"["-p3001:3000"]"#,
+        r#"["--privileged"]"#,
+        r#"["-v", "/:/host"]"#,
+        r#"["--network=host"]"#,
+        r#"["--pid=host"]"#,
+        r#"["--cap-add", "SYS_ADMIN"]"#,
+        r#"["--device", "/dev/kmsg"]"#,
+        r#"["--security-opt", "label=disable"]"#,
+        r#"["-eFOO=1"]"#,
+        r#"["other/image"]"#,
+        r#"["--cpus"]"#,
+        r#"["--memory", ""]"#,
+        r#"["-e", "a\nb"]"#,
+        r#"[""]"#,
+    ] {
+        std::fs::write(dir.path().join("run_args.toml"), format!("args = {args}")).unwrap();
+        assert!(load_run_args(dir.path()).is_err(), "{args}");
+    }
+}
+
+/// A container managed by a shell script standing in for podman, which logs its arguments and
+/// runs `script` on them.
+#[cfg(unix)]
+fn scripted_container(dir: &std::path::Path, script: &str) -> Container {
+    Container {
+        name: "stubborn".to_string(),
+        handle: ContainerHandle::new(1, Duration::from_secs(1), Duration::ZERO).unwrap(),
+        podman: Podman::new(
+            &fake_podman(dir, script),
+            Arc::new(Semaphore::new(DEFAULT_MAX_PODMAN_OPS)),
+        ),
+        keep_running: false,
+        shutdown_timeout: Duration::from_millis(200),
+        deep_readiness_check: false,
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_slow_stop_escalates_to_kill() {
+    let dir = tempfile::tempdir().unwrap();
+    let container = scripted_container(dir.path(), "[ \"$1\" = stop ] && sleep 5\nexit 0");
+
+    let start = Instant::now();
+    container.shutdown().await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(
+        podman_commands(dir.path()),
+        ["stop stubborn", "kill stubborn"]
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_failed_stop_escalates_to_kill() {
+    let dir = tempfile::tempdir().unwrap();
+    let container = scripted_container(dir.path(), "[ \"$1\" = stop ] && exit 125\nexit 0");
+
+    container.shutdown().await.unwrap();
+    assert_eq!(
+        podman_commands(dir.path()),
+        ["stop stubborn", "kill stubborn"]
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shutdown_fails_only_when_kill_and_remove_fail() {
+    let dir = tempfile::tempdir().unwrap();
+    let container = scripted_container(dir.path(), "[ \"$1\" = stop ] && sleep 5\nexit 1");
+
+    assert!(container.shutdown().await.is_err());
+    assert_eq!(
+        podman_commands(dir.path()),
+        ["stop stubborn", "kill stubborn", "rm -f stubborn"]
+    );
+}
+
+#[test]
+fn test_run_args_carry_name_prefix_and_tournament_label() {
+    let config = Config {
+        container_name_prefix: "spring-cup".to_string(),
+        tournament_name: "finals".to_string(),
+        ..Config::default()
+    };
+    let name = container_name(&config.container_name_prefix, "bot");
+    let label = tournament_label(&config.tournament_name);
+
+    let args = podman_run_args(&name, &label, 3001, "localhost/bot:latest", &[]);
+
+    let name_at = args.iter().position(|arg| arg == "--name").unwrap();
+    assert_eq!(args[name_at + 1], "spring-cup__bot");
+    let label_at = args.iter().position(|arg| arg == "--label").unwrap();
+    assert_eq!(args[label_at + 1], "tournament=finals");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_tournament_containers_are_removed_by_label() {
+    let dir = tempfile::tempdir().unwrap();
+    let container =
+        scripted_container(dir.path(), "[ \"$1\" = ps ] && printf 'a1\\nb2\\n'\nexit 0");
+
+    let removed = remove_tournament_containers(&container.podman, "finals")
+        .await
+        .unwrap();
+
+    assert_eq!(removed, 2);
+    assert_eq!(
+        podman_commands(dir.path()),
+        ["ps -aq --filter label=tournament=finals", "rm -f a1 b2"]
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_failed_podman_ps_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let container = scripted_container(dir.path(), "echo 'cannot connect' >&2\nexit 125");
+
+    let error = remove_tournament_containers(&container.podman, "finals")
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("cannot connect"), "{error:#}");
+    assert_eq!(
+        podman_commands(dir.path()),
+        ["ps -aq --filter label=tournament=finals"]
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_concurrent_container_starts_respect_max_podman_ops() {
+    let dir = tempfile::tempdir().unwrap();
+    // Each podman command notes how many are running, itself included, while it runs
+    let running = dir.path().join("running");
+    std::fs::create_dir(&running).unwrap();
+    let container = scripted_container(
+        dir.path(),
+        &format!(
+            "touch '{0}/'$$\nls '{0}' | wc -l >> '{1}'\nsleep 0.1\nrm '{0}/'$$",
+            running.display(),
+            dir.path().join("counts").display()
+        ),
+    );
+    let podman = Podman::new(&container.podman.command, Arc::new(Semaphore::new(2)));
+
+    let starts: Vec<_> = (0..6)
+        .map(|i| {
+            let name = format!("bot{i}");
+            let (podman, dir) = (podman.clone(), dir.path().to_path_buf());
+            tokio::spawn(async move {
+                Container::start(&podman, &name, "image", &dir, &name, "tournament=t").await
+            })
+        })
+        .collect();
+    for start in starts {
+        start.await.unwrap().unwrap();
+    }
+
+    // A remove and a run per start
+    assert_eq!(podman_commands(dir.path()).len(), 12);
+    let counts = std::fs::read_to_string(dir.path().join("counts")).unwrap();
+    let most: usize = counts
+        .lines()
+        .map(|n| n.trim().parse().unwrap())
+        .max()
+        .unwrap();
+    assert!(most <= 2, "{counts}");
+}
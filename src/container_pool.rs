@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{Mutex, OwnedMutexGuard},
+    time::timeout,
+};
+
+use crate::{Config, container::Container};
+
+mod tests;
+
+/// A container the pool knows how to start and stop.
+#[async_trait]
+pub trait PooledContainer: Send + Sync + Sized {
+    async fn start(submission_name: &str, config: &Config) -> Result<Self>;
+    async fn shutdown(&self) -> Result<()>;
+    fn is_dead(&self) -> bool;
+}
+
+#[async_trait]
+impl PooledContainer for Container {
+    async fn start(submission_name: &str, config: &Config) -> Result<Self> {
+        Container::new(submission_name, config).await
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Container::shutdown(self).await
+    }
+
+    fn is_dead(&self) -> bool {
+        self.handle().is_dead()
+    }
+}
+
+struct PoolEntry<C> {
+    container: Arc<C>,
+    /// Held while a matchup uses the container, so no one else starts a second one under the
+    /// same name.
+    lease: Option<OwnedMutexGuard<()>>,
+    idle_since: Instant,
+}
+
+/// Keeps containers alive between matchups so a submission that plays again soon doesn't pay
+/// the startup cost twice. With `keep_containers_warm` off, containers are stopped on release.
+pub struct ContainerPool<C = Container> {
+    entries: Mutex<HashMap<String, PoolEntry<C>>>,
+    leases: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    keep_warm: bool,
+    max_size: usize,
+    idle_ttl: Duration,
+    launches: AtomicUsize,
+    reuses: AtomicUsize,
+}
+
+impl<C: PooledContainer> ContainerPool<C> {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            leases: Mutex::new(HashMap::new()),
+            keep_warm: config.keep_containers_warm,
+            max_size: config.container_pool_size,
+            idle_ttl: config.container_idle_ttl(),
+            launches: AtomicUsize::new(0),
+            reuses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Acquires the containers of both submissions of a matchup, starting them concurrently.
+    /// Both are claimed in name order, so matchups sharing submissions can't wait on each other.
+    pub async fn acquire_pair(
+        &self,
+        submission_a: &str,
+        submission_b: &str,
+        config: &Config,
+    ) -> (Result<Arc<C>>, Result<Arc<C>>) {
+        let (lease_a, lease_b) = if submission_a <= submission_b {
+            let lease_a = self.lease(submission_a).await;
+            (lease_a, self.lease(submission_b).await)
+        } else {
+            let lease_b = self.lease(submission_b).await;
+            (self.lease(submission_a).await, lease_b)
+        };
+        tokio::join!(
+            self.acquire_leased(submission_a, lease_a, config),
+            self.acquire_leased(submission_b, lease_b, config)
+        )
+    }
+
+    #[cfg(test)]
+    pub async fn acquire(&self, submission_name: &str, config: &Config) -> Result<Arc<C>> {
+        let lease = self.lease(submission_name).await;
+        self.acquire_leased(submission_name, lease, config).await
+    }
+
+    /// Returns a warm container for `submission_name` if one is idle, otherwise starts a new one.
+    /// `lease` keeps anyone else from starting a container under the same name until release.
+    async fn acquire_leased(
+        &self,
+        submission_name: &str,
+        lease: OwnedMutexGuard<()>,
+        config: &Config,
+    ) -> Result<Arc<C>> {
+        let mut stale = self.take_expired().await;
+        {
+            let mut entries = self.entries.lock().await;
+            if let Some(entry) = entries.get_mut(submission_name) {
+                if entry.container.is_dead() {
+                    stale.extend(entries.remove(submission_name).map(|e| e.container));
+                } else {
+                    debug!("Reusing warm container for {}", submission_name);
+                    entry.lease = Some(lease);
+                    self.reuses.fetch_add(1, Ordering::SeqCst);
+                    let container = entry.container.clone();
+                    drop(entries);
+                    shutdown_all(stale).await;
+                    return Ok(container);
+                }
+            }
+        }
+        shutdown_all(stale).await;
+
+        let container = Arc::new(Self::start_with_retries(submission_name, config).await?);
+        self.launches.fetch_add(1, Ordering::SeqCst);
+        let replaced = self.entries.lock().await.insert(
+            submission_name.to_string(),
+            PoolEntry {
+                container: container.clone(),
+                lease: Some(lease),
+                idle_since: Instant::now(),
+            },
+        );
+        shutdown_all(replaced.map(|e| e.container)).await;
+        Ok(container)
+    }
+
+    /// Waits until no one else is using or starting `submission_name`'s container, since
+    /// containers are named after their submission, then claims it.
+    async fn lease(&self, submission_name: &str) -> OwnedMutexGuard<()> {
+        let lease = self
+            .leases
+            .lock()
+            .await
+            .entry(submission_name.to_string())
+            .or_default()
+            .clone();
+        lease.lock_owned().await
+    }
+
+    /// Starts a container, retrying up to `container_start_retries` times on failure or timeout.
+    /// Every attempt gets a fresh port.
+    async fn start_with_retries(submission_name: &str, config: &Config) -> Result<C> {
+        let attempts = config.container_start_retries + 1;
+        let mut attempt = 1;
+        loop {
+            info!(
+                "Starting container for {} (attempt {}/{})",
+                submission_name, attempt, attempts
+            );
+            let result = timeout(
+                config.container_timeout(),
+                C::start(submission_name, config),
+            )
+            .await
+            .context("Container startup timed out")
+            .and_then(|started| started);
+
+            match result {
+                Ok(container) => return Ok(container),
+                Err(e) if attempt < attempts => {
+                    warn!(
+                        "Attempt {}/{} to start container for {} failed: {:#}",
+                        attempt, attempts, submission_name, e
+                    );
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(e).context(format!(
+                        "Failed to start container for {} in {} attempts",
+                        submission_name, attempts
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Hands a container back after a matchup. It stays warm unless pooling is off, it died, or
+    /// the pool is over capacity, in which case the least recently used idle container is stopped.
+    pub async fn release(&self, submission_name: &str) {
+        let mut stopped = Vec::new();
+        let lease;
+        {
+            let mut entries = self.entries.lock().await;
+            let Some(entry) = entries.get_mut(submission_name) else {
+                return;
+            };
+
+            lease = entry.lease.take();
+            if !self.keep_warm || entry.container.is_dead() {
+                stopped.extend(entries.remove(submission_name).map(|e| e.container));
+            } else {
+                entry.idle_since = Instant::now();
+            }
+
+            while entries.len() > self.max_size {
+                let Some(oldest) = entries
+                    .iter()
+                    .filter(|(_, entry)| entry.lease.is_none())
+                    .min_by_key(|(_, entry)| entry.idle_since)
+                    .map(|(name, _)| name.clone())
+                else {
+                    break;
+                };
+                debug!("Pool is full, stopping idle container for {}", oldest);
+                stopped.extend(entries.remove(&oldest).map(|e| e.container));
+            }
+        }
+        // Only let the next matchup start a container under this name once the old one is gone
+        shutdown_all(stopped).await;
+        drop(lease);
+    }
+
+    /// Stops every pooled container and logs how many startups the pool saved.
+    pub async fn shutdown(&self) {
+        let containers: Vec<_> = self
+            .entries
+            .lock()
+            .await
+            .drain()
+            .map(|(_, entry)| entry.container)
+            .collect();
+        shutdown_all(containers).await;
+
+        if self.keep_warm {
+            info!(
+                "Container pool started {} containers and reused warm ones {} times",
+                self.launches.load(Ordering::SeqCst),
+                self.reuses.load(Ordering::SeqCst)
+            );
+        }
+    }
+
+    async fn take_expired(&self) -> Vec<Arc<C>> {
+        let mut entries = self.entries.lock().await;
+        let expired: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry.lease.is_none() && entry.idle_since.elapsed() >= self.idle_ttl
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|name| {
+                debug!("Idle container for {} expired", name);
+                entries.remove(&name).map(|e| e.container)
+            })
+            .collect()
+    }
+}
+
+async fn shutdown_all<C: PooledContainer>(containers: impl IntoIterator<Item = Arc<C>>) {
+    for container in containers {
+        if let Err(e) = container.shutdown().await {
+            warn!("Failed to shutdown pooled container: {}", e);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+#![cfg(test)]
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::Config;
+
+use super::{ContainerPool, PooledContainer};
+
+/// `podman run` / `podman stop` counts per submission name. Each test uses its own names.
+static STARTS: LazyLock<Mutex<HashMap<String, usize>>> = LazyLock::new(Default::default);
+static STOPS: LazyLock<Mutex<HashMap<String, usize>>> = LazyLock::new(Default::default);
+/// How many more times starting a submission's container should fail.
+static FAILURES: LazyLock<Mutex<HashMap<String, usize>>> = LazyLock::new(Default::default);
+
+fn count(counts: &Mutex<HashMap<String, usize>>, name: &str) -> usize {
+    counts.lock().unwrap().get(name).copied().unwrap_or(0)
+}
+
+struct FakeContainer {
+    name: String,
+}
+
+#[async_trait]
+impl PooledContainer for FakeContainer {
+    async fn start(submission_name: &str, _config: &Config) -> Result<Self> {
+        *STARTS
+            .lock()
+            .unwrap()
+            .entry(submission_name.to_string())
+            .or_default() += 1;
+        if let Some(failures) = FAILURES.lock().unwrap().get_mut(submission_name)
+            && *failures > 0
+        {
+            *failures -= 1;
+            anyhow::bail!("Failed to start {}", submission_name);
+        }
+        Ok(FakeContainer {
+            name: submission_name.to_string(),
+        })
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        *STOPS.lock().unwrap().entry(self.name.clone()).or_default() += 1;
+        Ok(())
+    }
+
+    fn is_dead(&self) -> bool {
+        false
+    }
+}
+
+fn warm_config() -> Config {
+    Config {
+        keep_containers_warm: true,
+        container_pool_size: 2,
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn test_reused_submission_is_started_once() {
+    let config = warm_config();
+    let pool = ContainerPool::<FakeContainer>::new(&config);
+
+    for opponent in ["reuse_b", "reuse_c", "reuse_d"] {
+        pool.acquire("reuse_a", &config).await.unwrap();
+        pool.acquire(opponent, &config).await.unwrap();
+        pool.release("reuse_a").await;
+        pool.release(opponent).await;
+    }
+
+    assert_eq!(count(&STARTS, "reuse_a"), 1);
+    assert_eq!(count(&STOPS, "reuse_a"), 0);
+
+    pool.shutdown().await;
+    assert_eq!(count(&STOPS, "reuse_a"), 1);
+}
+
+#[tokio::test]
+async fn test_full_pool_stops_least_recently_used_container() {
+    let config = warm_config();
+    let pool = ContainerPool::<FakeContainer>::new(&config);
+
+    for name in ["lru_a", "lru_b", "lru_c"] {
+        pool.acquire(name, &config).await.unwrap();
+        pool.release(name).await;
+    }
+
+    assert_eq!(count(&STOPS, "lru_a"), 1);
+    assert_eq!(count(&STOPS, "lru_b"), 0);
+    assert_eq!(count(&STOPS, "lru_c"), 0);
+}
+
+#[tokio::test]
+async fn test_containers_are_stopped_on_release_without_warm_pool() {
+    let config = Config::default();
+    let pool = ContainerPool::<FakeContainer>::new(&config);
+
+    for _ in 0..2 {
+        pool.acquire("cold_a", &config).await.unwrap();
+        pool.release("cold_a").await;
+    }
+
+    assert_eq!(count(&STARTS, "cold_a"), 2);
+    assert_eq!(count(&STOPS, "cold_a"), 2);
+}
+
+#[tokio::test]
+async fn test_failed_start_is_retried() {
+    let config = Config {
+        container_start_retries: 1,
+        ..Config::default()
+    };
+    let pool = ContainerPool::<FakeContainer>::new(&config);
+    FAILURES.lock().unwrap().insert("flaky_a".to_string(), 1);
+
+    let container = pool.acquire("flaky_a", &config).await.unwrap();
+
+    assert_eq!(container.name, "flaky_a");
+    assert_eq!(count(&STARTS, "flaky_a"), 2);
+}
+
+#[tokio::test]
+async fn test_start_gives_up_after_retries() {
+    let config = Config {
+        container_start_retries: 2,
+        ..Config::default()
+    };
+    let pool = ContainerPool::<FakeContainer>::new(&config);
+    FAILURES.lock().unwrap().insert("broken_a".to_string(), 5);
+
+    assert!(pool.acquire("broken_a", &config).await.is_err());
+    assert_eq!(count(&STARTS, "broken_a"), 3);
+}
+
+#[tokio::test]
+async fn test_container_in_use_is_not_started_twice() {
+    let config = Config::default();
+    let pool = Arc::new(ContainerPool::<FakeContainer>::new(&config));
+    pool.acquire("shared_a", &config).await.unwrap();
+
+    let second = tokio::spawn({
+        let pool = pool.clone();
+        let config = config.clone();
+        async move { pool.acquire("shared_a", &config).await.map(|_| ()) }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!second.is_finished());
+    assert_eq!(count(&STARTS, "shared_a"), 1);
+
+    // The second matchup gets its container once the first one's is stopped
+    pool.release("shared_a").await;
+    second.await.unwrap().unwrap();
+    assert_eq!(count(&STOPS, "shared_a"), 1);
+    assert_eq!(count(&STARTS, "shared_a"), 2);
+}
+
+#[tokio::test]
+async fn test_matchups_sharing_submissions_do_not_wait_on_each_other() {
+    let config = Config::default();
+    let pool = ContainerPool::<FakeContainer>::new(&config);
+
+    let play = |a: &'static str, b: &'static str| {
+        let (pool, config) = (&pool, &config);
+        async move {
+            let (first, second) = pool.acquire_pair(a, b, config).await;
+            first.unwrap();
+            second.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            pool.release(a).await;
+            pool.release(b).await;
+        }
+    };
+    let matchups = async {
+        tokio::join!(
+            play("cycle_a", "cycle_b"),
+            play("cycle_b", "cycle_c"),
+            play("cycle_c", "cycle_a")
+        )
+    };
+    tokio::time::timeout(Duration::from_secs(5), matchups)
+        .await
+        .unwrap();
+
+    assert_eq!(count(&STARTS, "cycle_a"), 2);
+}
@@ -1,110 +1,659 @@
-use anyhow::{Context, Result};
-use log::debug;
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use r2d2_sqlite::rusqlite::params;
-use rusqlite::OptionalExtension;
+use rusqlite::{OptionalExtension, types::ValueRef};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
-use std::{collections::HashMap, fs, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::sync::Mutex;
 use tokio::time::sleep;
 
-use crate::game::GameResult;
+use crate::{
+    Config,
+    game::{GameEvent, GameResult},
+    game_map::{NODE_TYPES, node_type_name},
+    layout,
+    profile::{self, Bucket},
+    submission::CallLatency,
+};
+
+mod tests;
+
+type MatchupCache = Arc<Mutex<HashMap<(String, String), (String, String)>>>;
+
+/// How long to keep retrying a statement while the database is locked by another writer.
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            max_retries: config.db_max_retries,
+            base_delay: Duration::from_secs_f32(config.db_retry_base_delay),
+            max_delay: Duration::from_secs_f32(config.db_retry_max_delay),
+        }
+    }
+
+    /// Exponential backoff for the given retry, capped at `max_delay`.
+    fn delay(&self, retries: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(self.max_delay)
+    }
+}
 
 #[derive(Clone)]
 pub struct Database {
     pool: Pool<SqliteConnectionManager>,
-    matchup_cache: Arc<Mutex<HashMap<(String, String), (String, String)>>>,
+    matchup_cache: MatchupCache,
+    retry: RetryPolicy,
+    games_per_matchup: i64,
+    /// Row of `tournaments` this run's matchups belong to.
+    tournament_id: i64,
 }
 
-impl Database {
-    pub fn new() -> Result<Self> {
-        fs::create_dir_all("results")?;
-        let manager = SqliteConnectionManager::file("results/results.sqlite");
-        let pool = Pool::new(manager).context("Failed to create connection pool")?;
+/// A row of the `matchups` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchupRow {
+    pub id: i64,
+    pub player_a: String,
+    pub player_b: String,
+    pub timestamp: String,
+}
 
-        // Create tables if they don't exist
-        let mut conn = pool.get()?;
-        let tx = conn.transaction()?;
+/// A game with the names of its matchup's submissions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRow {
+    pub matchup_id: i64,
+    pub player_a: String,
+    pub player_b: String,
+    pub game_number: i64,
+    /// Submission that moved first, unknown for games recorded by older versions.
+    pub first_mover: Option<String>,
+    /// `player_a` if the first mover won, `player_b` if the second mover did, `tie`, `pending`,
+    /// `error` or `timeout`.
+    pub winner: String,
+}
+
+impl GameRow {
+    /// Result of a finished game, `None` for pending, errored and timed out games.
+    pub fn result(&self) -> Option<GameResult> {
+        match self.winner.as_str() {
+            "player_a" => Some(GameResult::Player1Win),
+            "player_b" => Some(GameResult::Player2Win),
+            "tie" => Some(GameResult::Tie),
+            _ => None,
+        }
+    }
+
+    /// Name of the submission that won, `None` for ties, pending games and games whose first
+    /// mover is unknown.
+    pub fn winner_name(&self) -> Option<&str> {
+        let first_mover = self.first_mover.as_deref()?;
+        let result = self.result()?;
+        let second_mover = if first_mover == self.player_a {
+            &self.player_b
+        } else {
+            &self.player_a
+        };
+        result.to_winner_submission(first_mover, second_mover)
+    }
+}
+
+/// How long a finished game took to play.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameDuration {
+    pub matchup_id: i64,
+    pub player_a: String,
+    pub player_b: String,
+    pub game_number: i64,
+    pub duration_ms: f64,
+}
+
+/// How long a submission took to answer its calls over the whole tournament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallLatencyStats {
+    pub submission: String,
+    pub calls: usize,
+    pub mean_ms: f64,
+    /// Nearest-rank 95th percentile.
+    pub p95_ms: f64,
+}
+
+/// What a run does at its start with the games an interrupted run left `pending`. Pending games
+/// never count in standings or reports, whatever the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingPolicy {
+    /// They are played again from the start when their matchup resumes.
+    #[default]
+    Rerun,
+    /// They are given up on like errored games, and not played again.
+    Exclude,
+    /// They are deleted with everything they recorded, freeing their game numbers.
+    Delete,
+}
+
+/// Why a submission was penalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisqualificationReason {
+    Timeout,
+    InvalidJson,
+    TooManyInvalidMoves,
+    ContainerCrash,
+    /// Too many failed games across the tournament, see `max_submission_failures`.
+    RepeatedFailures,
+}
+
+impl DisqualificationReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            DisqualificationReason::Timeout => "timeout",
+            DisqualificationReason::InvalidJson => "invalid_json",
+            DisqualificationReason::TooManyInvalidMoves => "too_many_invalid_moves",
+            DisqualificationReason::ContainerCrash => "container_crash",
+            DisqualificationReason::RepeatedFailures => "repeated_failures",
+        }
+    }
+
+    fn parse(reason: &str) -> Option<Self> {
+        [
+            DisqualificationReason::Timeout,
+            DisqualificationReason::InvalidJson,
+            DisqualificationReason::TooManyInvalidMoves,
+            DisqualificationReason::ContainerCrash,
+            DisqualificationReason::RepeatedFailures,
+        ]
+        .into_iter()
+        .find(|known| known.as_str() == reason)
+    }
+}
+
+impl fmt::Display for DisqualificationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A row of the `disqualifications` table. The game and turn are unknown when the whole
+/// container went down rather than a single request failing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Disqualification {
+    pub submission: String,
+    pub matchup_id: i64,
+    pub reason: DisqualificationReason,
+    pub game_number: Option<i64>,
+    pub turn_number: Option<i64>,
+}
+
+/// Whether `e` is SQLite reporting that another connection holds the lock. Only the primary
+/// result code is checked, since the message wording differs between SQLite versions.
+fn is_busy(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<rusqlite::Error>()
+            .and_then(rusqlite::Error::sqlite_error_code),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Value of the `winner` column for a finished game's `result`.
+fn winner_column(result: GameResult) -> &'static str {
+    match result {
+        GameResult::Player1Win => "player_a",
+        GameResult::Player2Win => "player_b",
+        GameResult::Tie => "tie",
+    }
+}
+
+/// Whether `e` is an insert running into a `UNIQUE` constraint, such as a row another connection
+/// inserted since we checked for it.
+fn is_unique_violation(e: &rusqlite::Error) -> bool {
+    matches!(
+        e,
+        rusqlite::Error::SqliteFailure(error, _)
+            if error.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+    )
+}
 
+/// SQL literal of a stored value, for the INSERT statements of a dump. Infinite reals have no
+/// literal SQLite reads back, so they are an error rather than a dump that fails to import.
+fn sql_literal(value: ValueRef<'_>) -> Result<String> {
+    Ok(match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) if !f.is_finite() => bail!("Can't dump the non-finite value {}", f),
+        ValueRef::Real(f) => format!("{:?}", f),
+        ValueRef::Text(text) => format!("'{}'", String::from_utf8_lossy(text).replace('\'', "''")),
+        ValueRef::Blob(blob) => {
+            let hex: String = blob.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("X'{}'", hex)
+        }
+    })
+}
+
+/// Adds `column` to `table` unless it already exists.
+fn add_missing_column(
+    tx: &rusqlite::Transaction<'_>,
+    table: &str,
+    column: &str,
+    column_type: &str,
+) -> Result<()> {
+    let exists = tx
+        .prepare("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2")?
+        .exists(params![table, column])?;
+    if !exists {
         tx.execute(
-            "CREATE TABLE IF NOT EXISTS matchups (
+            &format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, column_type
+            ),
+            [],
+        )
+        .with_context(|| format!("Failed to add {} column", column))?;
+    }
+    Ok(())
+}
+
+/// A schema change. Each one runs once, in order, but must also cope with databases created
+/// before versioning that already have some of its changes.
+type Migration = fn(&rusqlite::Transaction<'_>) -> Result<()>;
+
+/// Every migration in order; the schema version is the number applied.
+const MIGRATIONS: &[Migration] = &[
+    create_base_tables,
+    add_game_map_json,
+    add_game_first_mover,
+    create_disqualifications,
+    add_tournaments,
+    add_game_rematches,
+    add_game_duration,
+    create_call_latencies,
+    create_game_events,
+    create_node_visits,
+    add_game_timestamp,
+    create_unplayed_matchups,
+];
+
+/// The latest version recorded in `schema_version`, 0 if none is.
+fn schema_version(tx: &rusqlite::Transaction<'_>) -> Result<usize> {
+    let version: i64 = tx.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(version as usize)
+}
+
+fn create_base_tables(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS matchups (
+            id INTEGER PRIMARY KEY,
+            player_a TEXT NOT NULL,
+            player_b TEXT NOT NULL,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(player_a, player_b)
+        )",
+        [],
+    )
+    .context("Failed to create matchups table")?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS games (
+            id INTEGER PRIMARY KEY,
+            matchup_id INTEGER NOT NULL,
+            game_number INTEGER NOT NULL,
+            winner TEXT NOT NULL,
+            seed INTEGER NOT NULL,
+            FOREIGN KEY(matchup_id) REFERENCES matchups(id),
+            UNIQUE(matchup_id, game_number)
+        )",
+        [],
+    )
+    .context("Failed to create games table")?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS turns (
+            id INTEGER PRIMARY KEY,
+            game_id INTEGER NOT NULL,
+            turn_number INTEGER NOT NULL,
+            svg_path TEXT NOT NULL,
+            FOREIGN KEY(game_id) REFERENCES games(id),
+            UNIQUE(game_id, turn_number)
+        )",
+        [],
+    )
+    .context("Failed to create turns table")?;
+    Ok(())
+}
+
+fn add_game_map_json(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    add_missing_column(tx, "games", "map_json", "TEXT")
+}
+
+fn add_game_first_mover(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    add_missing_column(tx, "games", "first_mover", "TEXT")
+}
+
+fn create_disqualifications(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS disqualifications (
+            id INTEGER PRIMARY KEY,
+            submission TEXT NOT NULL,
+            matchup_id INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            game_number INTEGER,
+            turn_number INTEGER,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(matchup_id) REFERENCES matchups(id)
+        )",
+        [],
+    )
+    .context("Failed to create disqualifications table")?;
+    Ok(())
+}
+
+/// Matchups a run stopped before reaching, e.g. at its `max_runtime`, until one plays them.
+fn create_unplayed_matchups(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS unplayed_matchups (
+            id INTEGER PRIMARY KEY,
+            tournament_id INTEGER NOT NULL,
+            player_a TEXT NOT NULL,
+            player_b TEXT NOT NULL,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(tournament_id) REFERENCES tournaments(id),
+            UNIQUE(tournament_id, player_a, player_b)
+        )",
+        [],
+    )
+    .context("Failed to create unplayed_matchups table")?;
+    Ok(())
+}
+
+/// Name of the tournament that runs without a `tournament_name`, and that matchups recorded
+/// before tournaments existed belong to.
+pub const DEFAULT_TOURNAMENT: &str = "default";
+
+/// Adds the `tournaments` table and scopes matchups to a tournament. Existing matchups move to
+/// the implicit `default` tournament. SQLite can't change a table's constraints, so `matchups` is
+/// rebuilt with its pair only unique within a tournament.
+fn add_tournaments(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS tournaments (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )
+    .context("Failed to create tournaments table")?;
+    tx.execute(
+        "INSERT OR IGNORE INTO tournaments (id, name) VALUES (1, ?1)",
+        params![DEFAULT_TOURNAMENT],
+    )
+    .context("Failed to create the default tournament")?;
+
+    let scoped = tx
+        .prepare("SELECT 1 FROM pragma_table_info('matchups') WHERE name = 'tournament_id'")?
+        .exists([])?;
+    if !scoped {
+        tx.execute_batch(
+            "CREATE TABLE matchups_scoped (
                 id INTEGER PRIMARY KEY,
+                tournament_id INTEGER NOT NULL DEFAULT 1,
                 player_a TEXT NOT NULL,
                 player_b TEXT NOT NULL,
                 timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
-                UNIQUE(player_a, player_b)
-            )",
-            [],
+                FOREIGN KEY(tournament_id) REFERENCES tournaments(id),
+                UNIQUE(tournament_id, player_a, player_b)
+            );
+            INSERT INTO matchups_scoped (id, player_a, player_b, timestamp)
+                SELECT id, player_a, player_b, timestamp FROM matchups;
+            DROP TABLE matchups;
+            ALTER TABLE matchups_scoped RENAME TO matchups;",
         )
-        .context("Failed to create matchups table")?;
+        .context("Failed to scope matchups to tournaments")?;
+    }
+    Ok(())
+}
 
-        tx.execute(
-            "CREATE TABLE IF NOT EXISTS games (
-                id INTEGER PRIMARY KEY,
-                matchup_id INTEGER NOT NULL,
-                game_number INTEGER NOT NULL,
-                winner TEXT NOT NULL,
-                seed INTEGER NOT NULL,
-                FOREIGN KEY(matchup_id) REFERENCES matchups(id),
-                UNIQUE(matchup_id, game_number)
-            )",
-            [],
+fn add_game_rematches(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    add_missing_column(tx, "games", "rematches", "INTEGER NOT NULL DEFAULT 0")
+}
+
+fn add_game_duration(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    add_missing_column(tx, "games", "duration_ms", "REAL")
+}
+
+/// When each game was started, NULL for games from before it was recorded.
+fn add_game_timestamp(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    add_missing_column(tx, "games", "timestamp", "DATETIME")
+}
+
+fn create_call_latencies(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS call_latencies (
+            id INTEGER PRIMARY KEY,
+            game_id INTEGER NOT NULL,
+            turn_number INTEGER NOT NULL,
+            submission TEXT NOT NULL,
+            endpoint TEXT NOT NULL,
+            latency_ms REAL NOT NULL,
+            FOREIGN KEY(game_id) REFERENCES games(id)
+        )",
+        [],
+    )
+    .context("Failed to create call_latencies table")?;
+    Ok(())
+}
+
+fn create_game_events(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS game_events (
+            id INTEGER PRIMARY KEY,
+            game_id INTEGER NOT NULL,
+            turn_number INTEGER NOT NULL,
+            event_json TEXT NOT NULL,
+            FOREIGN KEY(game_id) REFERENCES games(id)
+        )",
+        [],
+    )
+    .context("Failed to create game_events table")?;
+    Ok(())
+}
+
+/// Deletes everything recorded for the game `id` but its row, returning the paths of its turn
+/// SVGs to remove once the transaction is committed.
+fn clear_game(tx: &rusqlite::Transaction<'_>, id: i64) -> Result<Vec<String>> {
+    let stale_svgs = tx
+        .prepare("SELECT svg_path FROM turns WHERE game_id = ?1")?
+        .query_map(params![id], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to query turns of game")?;
+    tx.execute("DELETE FROM turns WHERE game_id = ?1", params![id])
+        .context("Failed to clear turns of game")?;
+    tx.execute("DELETE FROM call_latencies WHERE game_id = ?1", params![id])
+        .context("Failed to clear call latencies of game")?;
+    tx.execute("DELETE FROM game_events WHERE game_id = ?1", params![id])
+        .context("Failed to clear events of game")?;
+    tx.execute("DELETE FROM node_visits WHERE game_id = ?1", params![id])
+        .context("Failed to clear node visits of game")?;
+    Ok(stale_svgs)
+}
+
+/// Deletes the matchup `id` with its games and disqualifications, returning the paths of its
+/// turn SVGs to remove once the transaction is committed.
+fn delete_matchup(tx: &rusqlite::Transaction<'_>, id: i64) -> Result<Vec<String>> {
+    let games = tx
+        .prepare("SELECT id FROM games WHERE matchup_id = ?1")?
+        .query_map(params![id], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to query games of matchup")?;
+    let mut stale_svgs = Vec::new();
+    for game in games {
+        stale_svgs.extend(clear_game(tx, game)?);
+    }
+    tx.execute("DELETE FROM games WHERE matchup_id = ?1", params![id])
+        .context("Failed to delete games of matchup")?;
+    tx.execute(
+        "DELETE FROM disqualifications WHERE matchup_id = ?1",
+        params![id],
+    )
+    .context("Failed to delete disqualifications of matchup")?;
+    tx.execute("DELETE FROM matchups WHERE id = ?1", params![id])
+        .context("Failed to delete matchup")?;
+    Ok(stale_svgs)
+}
+
+/// Drops the pair, in either order, from the unplayed matchups of the tournament.
+fn forget_unplayed_matchup(
+    tx: &rusqlite::Transaction<'_>,
+    tournament_id: i64,
+    player_a: &str,
+    player_b: &str,
+) -> Result<()> {
+    tx.execute(
+        "DELETE FROM unplayed_matchups WHERE tournament_id = ?1
+         AND ((player_a = ?2 AND player_b = ?3) OR (player_a = ?3 AND player_b = ?2))",
+        params![tournament_id, player_a, player_b],
+    )
+    .context("Failed to delete unplayed matchup")?;
+    Ok(())
+}
+
+fn remove_stale_svgs(svg_paths: Vec<String>) {
+    for svg_path in svg_paths {
+        if let Err(e) = fs::remove_file(&svg_path)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove stale turn SVG {}: {}", svg_path, e);
+        }
+    }
+}
+
+fn create_node_visits(tx: &rusqlite::Transaction<'_>) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS node_visits (
+            id INTEGER PRIMARY KEY,
+            game_id INTEGER NOT NULL,
+            submission TEXT NOT NULL,
+            node_type TEXT NOT NULL,
+            visits INTEGER NOT NULL,
+            FOREIGN KEY(game_id) REFERENCES games(id)
+        )",
+        [],
+    )
+    .context("Failed to create node_visits table")?;
+    Ok(())
+}
+
+impl Database {
+    /// Where the database of a run with `config` is stored.
+    pub fn file(config: &Config) -> PathBuf {
+        Path::new(&config.results_dir).join("results.sqlite")
+    }
+
+    pub fn new(config: &Config) -> Result<Self> {
+        fs::create_dir_all(&config.results_dir)?;
+        layout::ensure_layout(Path::new(&config.results_dir), config.force_layout)?;
+        let manager = SqliteConnectionManager::file(Self::file(config));
+        let pool = Pool::new(manager).context("Failed to create connection pool")?;
+
+        let mut db = Self {
+            pool,
+            matchup_cache: Arc::new(Mutex::new(HashMap::new())),
+            retry: RetryPolicy::from_config(config),
+            games_per_matchup: config.matchup_length().max_games() as i64,
+            tournament_id: 0,
+        };
+        db.migrate()?;
+        db.tournament_id = db.tournament(&config.tournament_name)?;
+        Ok(db)
+    }
+
+    /// Id of the tournament called `name`, creating it if needed.
+    fn tournament(&self, name: &str) -> Result<i64> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO tournaments (name) VALUES (?1)",
+            params![name],
         )
-        .context("Failed to create games table")?;
+        .context("Failed to create tournament")?;
+        conn.query_row(
+            "SELECT id FROM tournaments WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .context("Failed to look up tournament")
+    }
 
+    /// Applies the migrations the database hasn't seen yet, in order, recording each applied
+    /// version in `schema_version`. Databases from before versioning count as version 0.
+    pub fn migrate(&self) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
         tx.execute(
-            "CREATE TABLE IF NOT EXISTS turns (
-                id INTEGER PRIMARY KEY,
-                game_id INTEGER NOT NULL,
-                turn_number INTEGER NOT NULL,
-                svg_path TEXT NOT NULL,
-                FOREIGN KEY(game_id) REFERENCES games(id),
-                UNIQUE(game_id, turn_number)
+            "CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
             )",
             [],
         )
-        .context("Failed to create turns table")?;
+        .context("Failed to create schema_version table")?;
 
+        let current = schema_version(&tx)?;
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = version + 1;
+            migration(&tx).with_context(|| format!("Failed to migrate to version {}", version))?;
+            tx.execute(
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                params![version as i64],
+            )?;
+        }
         tx.commit()?;
 
-        Ok(Self {
-            pool,
-            matchup_cache: Arc::new(Mutex::new(HashMap::new())),
-        })
+        if current < MIGRATIONS.len() {
+            info!(
+                "Migrated results database from version {} to {}",
+                current,
+                MIGRATIONS.len()
+            );
+        }
+        Ok(())
     }
 
     async fn retry_on_locked<F, T>(&self, mut f: F) -> Result<T>
     where
         F: FnMut() -> Result<T>,
     {
-        let mut retries = 0;
-        let max_retries = 10;
-        loop {
-            match f() {
-                Ok(result) => return Ok(result),
-                Err(e) => {
-                    if retries >= max_retries {
-                        return Err(e);
-                    }
-                    if let Some(sqlite_error) = e.downcast_ref::<rusqlite::Error>() {
-                        if sqlite_error
-                            == &rusqlite::Error::SqliteFailure(
-                                rusqlite::ffi::Error::new(5), // SQLITE_BUSY
-                                Some("database is locked".to_string()),
-                            )
-                        {
-                            let delay = Duration::from_millis(10 * (1 << retries)); // exponential backoff
-                            debug!("Database locked, retrying in {:?}", delay);
-                            sleep(delay).await;
-                            retries += 1;
-                            continue;
-                        }
+        profile::time_async(Bucket::Database, async {
+            let mut retries = 0;
+            loop {
+                match f() {
+                    Ok(result) => return Ok(result),
+                    Err(e) if is_busy(&e) && retries < self.retry.max_retries => {
+                        let delay = self.retry.delay(retries);
+                        debug!("Database locked, retrying in {:?}", delay);
+                        sleep(delay).await;
+                        retries += 1;
                     }
-                    return Err(e);
+                    Err(e) => return Err(e),
                 }
             }
-        }
+        })
+        .await
     }
 
     pub async fn get_matchup_order(&self, player_a: &str, player_b: &str) -> (String, String) {
@@ -124,21 +673,104 @@ impl Database {
         order
     }
 
+    /// Drops the cached order of the pair, so the next `get_matchup_order` establishes it anew.
+    pub async fn forget_matchup_order(&self, player_a: &str, player_b: &str) {
+        let mut cache = self.matchup_cache.lock().await;
+        cache.remove(&(player_a.to_string(), player_b.to_string()));
+        cache.remove(&(player_b.to_string(), player_a.to_string()));
+    }
+
+    /// Deletes every matchup of this tournament with all that was recorded for it, returning how
+    /// many there were. The next run plays them all again.
+    pub async fn reset(&self) -> Result<usize> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        let (count, stale_svgs) = self
+            .retry_on_locked(move || {
+                let mut conn = pool.get().context("Failed to get connection from pool")?;
+                let tx = conn.transaction()?;
+                let ids = tx
+                    .prepare("SELECT id FROM matchups WHERE tournament_id = ?1")?
+                    .query_map(params![tournament_id], |row| row.get::<_, i64>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+                    .context("Failed to query matchups")?;
+                let mut stale_svgs = Vec::new();
+                for &id in &ids {
+                    stale_svgs.extend(delete_matchup(&tx, id)?);
+                }
+                tx.execute(
+                    "DELETE FROM unplayed_matchups WHERE tournament_id = ?1",
+                    params![tournament_id],
+                )
+                .context("Failed to delete unplayed matchups")?;
+                tx.commit()?;
+                Ok((ids.len(), stale_svgs))
+            })
+            .await?;
+
+        self.matchup_cache.lock().await.clear();
+        remove_stale_svgs(stale_svgs);
+        Ok(count)
+    }
+
+    /// Deletes the matchup of this tournament between the two submissions, in either order, with
+    /// all that was recorded for it. Returns whether there was one.
+    pub async fn reset_matchup(&self, player_a: &str, player_b: &str) -> Result<bool> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        let (a, b) = (player_a.to_string(), player_b.to_string());
+        let (found, stale_svgs) = self
+            .retry_on_locked(move || {
+                let mut conn = pool.get().context("Failed to get connection from pool")?;
+                let tx = conn.transaction()?;
+                forget_unplayed_matchup(&tx, tournament_id, &a, &b)?;
+                let id: Option<i64> = tx
+                    .query_row(
+                        "SELECT id FROM matchups WHERE tournament_id = ?1
+                         AND ((player_a = ?2 AND player_b = ?3) OR (player_a = ?3 AND player_b = ?2))",
+                        params![tournament_id, a, b],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .context("Failed to query matchup")?;
+                let Some(id) = id else {
+                    tx.commit()?;
+                    return Ok((false, Vec::new()));
+                };
+                let stale_svgs = delete_matchup(&tx, id)?;
+                tx.commit()?;
+                Ok((true, stale_svgs))
+            })
+            .await?;
+
+        self.forget_matchup_order(player_a, player_b).await;
+        remove_stale_svgs(stale_svgs);
+        Ok(found)
+    }
+
     pub async fn start_matchup(&self, player_a: &str, player_b: &str) -> Result<i64> {
         let (first, second) = self.get_matchup_order(player_a, player_b).await;
         let pool = self.pool.clone();
         let first = first.clone();
         let second = second.clone();
+        let tournament_id = self.tournament_id;
 
         self.retry_on_locked(move || {
             let mut conn = pool.get().context("Failed to get connection from pool")?;
+            let tx = conn.transaction()?;
+            forget_unplayed_matchup(&tx, tournament_id, &first, &second)?;
 
             // Check for existing matchup
-            if let Ok(id) = conn.query_row(
-                "SELECT id FROM matchups WHERE player_a = ?1 AND player_b = ?2",
-                params![first, second],
-                |row| row.get::<_, i64>(0),
-            ) {
+            let existing: Option<i64> = tx
+                .query_row(
+                    "SELECT id FROM matchups WHERE tournament_id = ?1 AND player_a = ?2 AND player_b = ?3",
+                    params![tournament_id, first, second],
+                    |row| row.get(0),
+                )
+                .optional()
+                .context("Failed to query existing matchup")?;
+            if let Some(id) = existing {
+                tx.commit()?;
                 return Ok(id);
             }
 
@@ -147,10 +779,9 @@ impl Database {
                 first, second
             );
 
-            let tx = conn.transaction()?;
             tx.execute(
-                "INSERT INTO matchups (player_a, player_b) VALUES (?1, ?2)",
-                params![first, second],
+                "INSERT INTO matchups (tournament_id, player_a, player_b) VALUES (?1, ?2, ?3)",
+                params![tournament_id, first, second],
             )
             .context("Failed to insert new matchup")?;
             let id = tx.last_insert_rowid();
@@ -160,42 +791,405 @@ impl Database {
         .await
     }
 
-    pub async fn create_game(&self, matchup_id: i64, game_number: i64, seed: i64) -> Result<i64> {
+    /// Creates the game row, storing which submission moved first and the serialized map if there
+    /// is one.
+    ///
+    /// A game left `pending` by an interrupted run is restarted cleanly: its recorded turns and
+    /// their SVGs are removed and it takes the new seed and map, so it doesn't mix two playthroughs.
+    pub async fn create_game(
+        &self,
+        matchup_id: i64,
+        game_number: i64,
+        first_mover: &str,
+        seed: i64,
+        map_json: Option<&str>,
+    ) -> Result<i64> {
         debug!(
-            "Creating game: matchup_id={}, game_number={}, seed={}",
-            matchup_id, game_number, seed
+            "Creating game: matchup_id={}, game_number={}, first_mover={}, seed={}",
+            matchup_id, game_number, first_mover, seed
         );
 
         let pool = self.pool.clone();
-        self.retry_on_locked(move || {
+        let first_mover = first_mover.to_string();
+        let map_json = map_json.map(str::to_string);
+        let (id, stale_svgs) = self.retry_on_locked(move || {
             let mut conn = pool.get().context("Failed to get connection from pool")?;
             let tx = conn.transaction()?;
 
-            let existing_id: Option<i64> = tx
+            let existing: Option<(i64, String)> = tx
                 .query_row(
-                    "SELECT id FROM games WHERE matchup_id = ?1 AND game_number = ?2",
+                    "SELECT id, winner FROM games WHERE matchup_id = ?1 AND game_number = ?2",
                     params![matchup_id, game_number],
-                    |row| row.get(0),
+                    |row| Ok((row.get(0)?, row.get(1)?)),
                 )
                 .optional()
                 .context("Failed to query existing game")?;
 
-            if let Some(id) = existing_id {
-                debug!("Found existing game with id={}", id);
-                tx.rollback()?;
-                return Ok(id);
+            match existing {
+                Some((id, winner)) if winner == "pending" => {
+                    let stale_svgs = clear_game(&tx, id)?;
+                    tx.execute(
+                        "UPDATE games SET seed = ?1, map_json = ?2, first_mover = ?3, rematches = 0,
+                         duration_ms = NULL, timestamp = CURRENT_TIMESTAMP
+                         WHERE id = ?4",
+                        params![seed, map_json, first_mover, id],
+                    )
+                    .context("Failed to reset pending game")?;
+                    tx.commit()?;
+                    info!(
+                        "Restarting pending game with id={} (cleared {} recorded turns)",
+                        id,
+                        stale_svgs.len()
+                    );
+                    Ok((id, stale_svgs))
+                }
+                Some((id, _)) => {
+                    debug!("Found existing game with id={}", id);
+                    tx.rollback()?;
+                    Ok((id, Vec::new()))
+                }
+                None => {
+                    let inserted = tx.execute(
+                        "INSERT INTO games (matchup_id, game_number, winner, seed, map_json, first_mover, timestamp) VALUES (?1, ?2, 'pending', ?3, ?4, ?5, CURRENT_TIMESTAMP)",
+                        params![matchup_id, game_number, seed, map_json, first_mover],
+                    );
+                    if let Err(e) = &inserted
+                        && is_unique_violation(e)
+                    {
+                        // Another task created the game between our check and insert
+                        tx.rollback()?;
+                        let id = conn
+                            .query_row(
+                                "SELECT id FROM games WHERE matchup_id = ?1 AND game_number = ?2",
+                                params![matchup_id, game_number],
+                                |row| row.get(0),
+                            )
+                            .context("Failed to query concurrently created game")?;
+                        debug!("Game was created concurrently with id={}", id);
+                        return Ok((id, Vec::new()));
+                    }
+                    inserted.context("Failed to insert new game")?;
+
+                    let id = tx.last_insert_rowid();
+                    tx.commit()?;
+                    debug!("Created new game with id={}", id);
+                    Ok((id, Vec::new()))
+                }
             }
+        }).await?;
 
-            tx.execute(
-                "INSERT INTO games (matchup_id, game_number, winner, seed) VALUES (?1, ?2, 'pending', ?3)",
-                params![matchup_id, game_number, seed],
-            ).context("Failed to insert new game")?;
+        remove_stale_svgs(stale_svgs);
+        Ok(id)
+    }
 
-            let id = tx.last_insert_rowid();
-            tx.commit()?;
-            debug!("Created new game with id={}", id);
-            Ok(id)
-        }).await
+    /// Applies `policy` to the games of this tournament left `pending` by an interrupted run,
+    /// returning how many there were. Meant for the start of a run, when no game is in progress.
+    pub async fn sweep_pending_games(&self, policy: PendingPolicy) -> Result<usize> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        let (count, stale_svgs) = self
+            .retry_on_locked(move || {
+                let mut conn = pool.get().context("Failed to get connection from pool")?;
+                let tx = conn.transaction()?;
+                let ids = tx
+                    .prepare(
+                        "SELECT g.id FROM games g
+                         JOIN matchups m ON m.id = g.matchup_id
+                         WHERE m.tournament_id = ?1 AND g.winner = 'pending'",
+                    )?
+                    .query_map(params![tournament_id], |row| row.get::<_, i64>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+                    .context("Failed to query pending games")?;
+
+                let mut stale_svgs = Vec::new();
+                for &id in &ids {
+                    match policy {
+                        PendingPolicy::Rerun => {}
+                        PendingPolicy::Exclude => {
+                            tx.execute(
+                                "UPDATE games SET winner = 'error' WHERE id = ?1",
+                                params![id],
+                            )
+                            .context("Failed to exclude pending game")?;
+                        }
+                        PendingPolicy::Delete => {
+                            stale_svgs.extend(clear_game(&tx, id)?);
+                            tx.execute("DELETE FROM games WHERE id = ?1", params![id])
+                                .context("Failed to delete pending game")?;
+                        }
+                    }
+                }
+                tx.commit()?;
+                Ok((ids.len(), stale_svgs))
+            })
+            .await?;
+
+        remove_stale_svgs(stale_svgs);
+        Ok(count)
+    }
+
+    /// Checks that the pool still hands out connections that answer queries, for readiness
+    /// probes.
+    #[cfg(feature = "live")]
+    pub async fn health(&self) -> Result<()> {
+        let pool = self.pool.clone();
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// All matchups of this tournament that have been started, in the order they were started.
+    pub async fn list_matchups(&self) -> Result<Vec<MatchupRow>> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            let mut stmt = conn.prepare(
+                "SELECT id, player_a, player_b, timestamp FROM matchups
+                 WHERE tournament_id = ?1 ORDER BY id",
+            )?;
+            let matchups = stmt
+                .query_map(params![tournament_id], |row| {
+                    Ok(MatchupRow {
+                        id: row.get(0)?,
+                        player_a: row.get(1)?,
+                        player_b: row.get(2)?,
+                        timestamp: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to list matchups")?;
+            Ok(matchups)
+        })
+        .await
+    }
+
+    /// Records that the run stopped before playing the matchup, until a later run starts it.
+    pub async fn record_unplayed_matchup(&self, player_a: &str, player_b: &str) -> Result<()> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        let (a, b) = (player_a.to_string(), player_b.to_string());
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.execute(
+                "INSERT OR IGNORE INTO unplayed_matchups (tournament_id, player_a, player_b)
+                 VALUES (?1, ?2, ?3)",
+                params![tournament_id, a, b],
+            )
+            .context("Failed to record unplayed matchup")?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// The pairs of this tournament's matchups that no run has started since one stopped before
+    /// them, in the order they were recorded.
+    pub async fn list_unplayed_matchups(&self) -> Result<Vec<(String, String)>> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            let mut stmt = conn.prepare(
+                "SELECT player_a, player_b FROM unplayed_matchups
+                 WHERE tournament_id = ?1 ORDER BY id",
+            )?;
+            let pairs = stmt
+                .query_map(params![tournament_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to list unplayed matchups")?;
+            Ok(pairs)
+        })
+        .await
+    }
+
+    pub async fn record_disqualification(&self, disqualification: &Disqualification) -> Result<()> {
+        info!(
+            "Disqualifying {} in matchup {}: {}",
+            disqualification.submission, disqualification.matchup_id, disqualification.reason
+        );
+
+        let pool = self.pool.clone();
+        let disqualification = disqualification.clone();
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.execute(
+                "INSERT INTO disqualifications
+                    (submission, matchup_id, reason, game_number, turn_number)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    &disqualification.submission,
+                    disqualification.matchup_id,
+                    disqualification.reason.as_str(),
+                    disqualification.game_number,
+                    disqualification.turn_number
+                ],
+            )
+            .context("Failed to insert disqualification")?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// All disqualifications recorded in this tournament, in the order they happened.
+    pub async fn list_disqualifications(&self) -> Result<Vec<Disqualification>> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            let mut stmt = conn.prepare(
+                "SELECT d.submission, d.matchup_id, d.reason, d.game_number, d.turn_number
+                 FROM disqualifications d
+                 JOIN matchups m ON m.id = d.matchup_id
+                 WHERE m.tournament_id = ?1
+                 ORDER BY d.id",
+            )?;
+            let rows = stmt
+                .query_map(params![tournament_id], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to list disqualifications")?;
+
+            rows.into_iter()
+                .map(
+                    |(submission, matchup_id, reason, game_number, turn_number)| {
+                        Ok(Disqualification {
+                            reason: DisqualificationReason::parse(&reason).with_context(|| {
+                                format!("Unknown disqualification reason {}", reason)
+                            })?,
+                            submission,
+                            matchup_id,
+                            game_number,
+                            turn_number,
+                        })
+                    },
+                )
+                .collect()
+        })
+        .await
+    }
+
+    /// Every game of this tournament played with `seed`. Usually at most one, but nothing stops
+    /// seeds from repeating.
+    pub async fn get_game_by_seed(&self, seed: i64) -> Result<Vec<GameRow>> {
+        self.query_games("g.seed = ?1", seed).await
+    }
+
+    /// Every game of a matchup, in the order they were created.
+    pub async fn list_matchup_games(&self, matchup_id: i64) -> Result<Vec<GameRow>> {
+        self.query_games("g.matchup_id = ?1", matchup_id).await
+    }
+
+    /// Every game of this tournament that has a result, in the order they were created. Errored
+    /// and timed out games have none.
+    pub async fn list_decided_games(&self) -> Result<Vec<GameRow>> {
+        self.query_games("g.winner NOT IN (?1, 'error', 'timeout')", "pending")
+            .await
+    }
+
+    /// Like `list_decided_games`, but only the games started at or after `since`, a time such as
+    /// `2026-10-01` or `2026-10-01 12:00:00` in UTC. Games from before start times were recorded
+    /// are left out.
+    pub async fn list_decided_games_since(&self, since: &str) -> Result<Vec<GameRow>> {
+        let pool = self.pool.clone();
+        let time = since.to_string();
+        let valid: Option<String> = self
+            .retry_on_locked(move || {
+                let conn = pool.get().context("Failed to get connection from pool")?;
+                Ok(conn.query_row("SELECT datetime(?1)", params![time], |row| row.get(0))?)
+            })
+            .await?;
+        if valid.is_none() {
+            bail!(
+                "Invalid time {:?}, expected e.g. 2026-10-01 or 2026-10-01 12:00:00",
+                since
+            );
+        }
+        self.query_games(
+            "g.winner NOT IN ('pending', 'error', 'timeout') AND g.timestamp >= datetime(?1)",
+            since.to_string(),
+        )
+        .await
+    }
+
+    async fn query_games(
+        &self,
+        filter: &'static str,
+        value: impl rusqlite::ToSql + Send,
+    ) -> Result<Vec<GameRow>> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT g.matchup_id, m.player_a, m.player_b, g.game_number, g.first_mover, g.winner
+                 FROM games g
+                 JOIN matchups m ON m.id = g.matchup_id
+                 WHERE m.tournament_id = ?2 AND {}
+                 ORDER BY g.id",
+                filter
+            ))?;
+            let games = stmt
+                .query_map(params![value, tournament_id], |row| {
+                    Ok(GameRow {
+                        matchup_id: row.get(0)?,
+                        player_a: row.get(1)?,
+                        player_b: row.get(2)?,
+                        game_number: row.get(3)?,
+                        first_mover: row.get(4)?,
+                        winner: row.get(5)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to query games")?;
+            Ok(games)
+        })
+        .await
+    }
+
+    /// Returns `(completed, expected)` games of a matchup, where completed games are no longer
+    /// pending and `expected` is the most games its `matchup_length` can last.
+    pub async fn count_completed_games(&self, matchup_id: i64) -> Result<(i64, i64)> {
+        let pool = self.pool.clone();
+        let completed = self
+            .retry_on_locked(move || {
+                let conn = pool.get().context("Failed to get connection from pool")?;
+                conn.query_row(
+                    "SELECT COUNT(*) FROM games WHERE matchup_id = ?1 AND winner != 'pending'",
+                    params![matchup_id],
+                    |row| row.get(0),
+                )
+                .context("Failed to count completed games")
+            })
+            .await?;
+        Ok((completed, self.games_per_matchup))
+    }
+
+    /// Loads the serialized map stored for a game, if the game exists and has one.
+    pub async fn get_game_map(&self, matchup_id: i64, game_number: i64) -> Result<Option<String>> {
+        let pool = self.pool.clone();
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            let map_json = conn
+                .query_row(
+                    "SELECT map_json FROM games WHERE matchup_id = ?1 AND game_number = ?2",
+                    params![matchup_id, game_number],
+                    |row| row.get::<_, Option<String>>(0),
+                )
+                .optional()
+                .context("Failed to query game map")?;
+            Ok(map_json.flatten())
+        })
+        .await
     }
 
     pub async fn update_game_result(
@@ -204,13 +1198,8 @@ impl Database {
         game_number: i64,
         result: GameResult,
     ) -> Result<()> {
-        let winner = match result {
-            GameResult::Player1Win => "player_a",
-            GameResult::Player2Win => "player_b",
-            GameResult::Tie => "tie",
-        };
         let pool = self.pool.clone();
-        let winner = winner.to_string();
+        let winner = winner_column(result).to_string();
 
         debug!(
             "UPDATE games SET winner = {} WHERE matchup_id = {} AND game_number = {}",
@@ -231,6 +1220,371 @@ impl Database {
         .await
     }
 
+    /// Records a game as forfeited with `result`. A game left `pending` keeps its seed, map and
+    /// recorded turns, unlike when `create_game` restarts it, and finished games are left alone.
+    pub async fn record_forfeit(
+        &self,
+        matchup_id: i64,
+        game_number: i64,
+        first_mover: &str,
+        result: GameResult,
+    ) -> Result<()> {
+        let pool = self.pool.clone();
+        let first_mover = first_mover.to_string();
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.execute(
+                "INSERT INTO games (matchup_id, game_number, winner, seed, first_mover, timestamp)
+                 VALUES (?1, ?2, ?3, 0, ?4, CURRENT_TIMESTAMP)
+                 ON CONFLICT(matchup_id, game_number) DO UPDATE SET winner = excluded.winner
+                 WHERE winner = 'pending'",
+                params![matchup_id, game_number, winner_column(result), first_mover],
+            )
+            .context("Failed to record forfeited game")?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Records that a game ran out of its `game_timeout`. The turns it recorded are kept, and it
+    /// isn't played again.
+    pub async fn mark_game_timed_out(&self, matchup_id: i64, game_number: i64) -> Result<()> {
+        let pool = self.pool.clone();
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.execute(
+                "UPDATE games SET winner = 'timeout'
+                 WHERE matchup_id = ?1 AND game_number = ?2 AND winner = 'pending'",
+                params![matchup_id, game_number],
+            )
+            .context("Failed to mark game as timed out")?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Records that a game was given up on without a result, so it isn't played again.
+    pub async fn mark_game_errored(&self, matchup_id: i64, game_number: i64) -> Result<()> {
+        let pool = self.pool.clone();
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.execute(
+                "UPDATE games SET winner = 'error' WHERE matchup_id = ?1 AND game_number = ?2",
+                params![matchup_id, game_number],
+            )
+            .context("Failed to mark game as errored")?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Writes a `.sql` text dump of every table, schema and rows, to `path`. Running it with
+    /// `sqlite3 new.sqlite < dump.sql` recreates the database, across SQLite versions.
+    pub async fn export_sqlite_dump(&self, path: &Path) -> Result<()> {
+        let pool = self.pool.clone();
+        let dump = self
+            .retry_on_locked(move || {
+                let conn = pool.get().context("Failed to get connection from pool")?;
+                // Like `sqlite3 .dump`, so rows can reference tables created later in the dump
+                let mut dump = String::from("PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\n");
+
+                // Tables first with their rows, then the indexes, so inserts don't check them
+                let mut stmt = conn.prepare(
+                    "SELECT type, name, sql FROM sqlite_master
+                     WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+                     ORDER BY type = 'table' DESC, rowid",
+                )?;
+                let objects = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect::<rusqlite::Result<Vec<(String, String, String)>>>()
+                    .context("Failed to list tables")?;
+                for (object_type, name, sql) in objects {
+                    dump.push_str(&format!("{};\n", sql));
+                    if object_type != "table" {
+                        continue;
+                    }
+
+                    let mut rows_stmt = conn.prepare(&format!("SELECT * FROM \"{}\"", name))?;
+                    let column_count = rows_stmt.column_count();
+                    let mut rows = rows_stmt.query([])?;
+                    while let Some(row) = rows.next()? {
+                        let values = (0..column_count)
+                            .map(|i| {
+                                sql_literal(row.get_ref(i)?)
+                                    .with_context(|| format!("Failed to dump a row of {}", name))
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        dump.push_str(&format!(
+                            "INSERT INTO \"{}\" VALUES ({});\n",
+                            name,
+                            values.join(", ")
+                        ));
+                    }
+                }
+                dump.push_str("COMMIT;\n");
+                Ok(dump)
+            })
+            .await
+            .context("Failed to dump database")?;
+
+        fs::write(path, dump).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Counts a tie-resolving rematch played as part of a game.
+    pub async fn record_rematch(&self, matchup_id: i64, game_number: i64) -> Result<()> {
+        let pool = self.pool.clone();
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.execute(
+                "UPDATE games SET rematches = rematches + 1
+                 WHERE matchup_id = ?1 AND game_number = ?2",
+                params![matchup_id, game_number],
+            )
+            .context("Failed to record rematch")?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Records how long a finished game took to play.
+    pub async fn record_game_duration(
+        &self,
+        matchup_id: i64,
+        game_number: i64,
+        duration: Duration,
+    ) -> Result<()> {
+        let pool = self.pool.clone();
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            conn.execute(
+                "UPDATE games SET duration_ms = ?1 WHERE matchup_id = ?2 AND game_number = ?3",
+                params![duration_ms, matchup_id, game_number],
+            )
+            .context("Failed to record game duration")?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Records the calls `submission` answered during a turn of the game with id `game_id`.
+    pub async fn record_call_latencies(
+        &self,
+        game_id: i64,
+        turn_number: i64,
+        submission: &str,
+        latencies: Vec<CallLatency>,
+    ) -> Result<()> {
+        let pool = self.pool.clone();
+        let submission = submission.to_string();
+        self.retry_on_locked(move || {
+            let mut conn = pool.get().context("Failed to get connection from pool")?;
+            let tx = conn.transaction()?;
+            for latency in &latencies {
+                tx.execute(
+                    "INSERT INTO call_latencies (game_id, turn_number, submission, endpoint, latency_ms)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        game_id,
+                        turn_number,
+                        &submission,
+                        latency.endpoint,
+                        latency.latency_ms
+                    ],
+                )
+                .context("Failed to record call latency")?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Stores the events of a finished game, each with the turn it happened on.
+    pub async fn record_game_events(
+        &self,
+        game_id: i64,
+        events: Vec<(i64, GameEvent)>,
+    ) -> Result<()> {
+        let pool = self.pool.clone();
+        let events = events
+            .into_iter()
+            .map(|(turn, event)| Ok((turn, serde_json::to_string(&event)?)))
+            .collect::<Result<Vec<_>>>()?;
+        self.retry_on_locked(move || {
+            let mut conn = pool.get().context("Failed to get connection from pool")?;
+            let tx = conn.transaction()?;
+            for (turn_number, event_json) in &events {
+                tx.execute(
+                    "INSERT INTO game_events (game_id, turn_number, event_json) VALUES (?1, ?2, ?3)",
+                    params![game_id, turn_number, event_json],
+                )
+                .context("Failed to record game event")?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Events of every game of a matchup as `(game_number, turn_number, event)`, in the order
+    /// they happened within each game.
+    pub async fn list_matchup_events(&self, matchup_id: i64) -> Result<Vec<(i64, i64, GameEvent)>> {
+        let pool = self.pool.clone();
+        let rows = self
+            .retry_on_locked(move || {
+                let conn = pool.get().context("Failed to get connection from pool")?;
+                let mut stmt = conn.prepare(
+                    "SELECT g.game_number, e.turn_number, e.event_json
+                     FROM game_events e
+                     JOIN games g ON g.id = e.game_id
+                     WHERE g.matchup_id = ?1
+                     ORDER BY g.game_number, e.id",
+                )?;
+                let rows = stmt
+                    .query_map(params![matchup_id], |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<(i64, i64, String)>>>()
+                    .context("Failed to query game events")?;
+                Ok(rows)
+            })
+            .await?;
+        rows.into_iter()
+            .map(|(game_number, turn_number, event_json)| {
+                let event = serde_json::from_str(&event_json)
+                    .with_context(|| format!("Invalid event of game {}", game_number))?;
+                Ok((game_number, turn_number, event))
+            })
+            .collect()
+    }
+
+    /// Stores how often `submission` landed on each node type in a finished game, in
+    /// `NODE_TYPES` order.
+    pub async fn record_node_visits(
+        &self,
+        game_id: i64,
+        submission: &str,
+        visits: [u32; NODE_TYPES.len()],
+    ) -> Result<()> {
+        let pool = self.pool.clone();
+        let submission = submission.to_string();
+        self.retry_on_locked(move || {
+            let mut conn = pool.get().context("Failed to get connection from pool")?;
+            let tx = conn.transaction()?;
+            for (node_type, count) in NODE_TYPES.into_iter().zip(visits) {
+                tx.execute(
+                    "INSERT INTO node_visits (game_id, submission, node_type, visits)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![game_id, &submission, node_type_name(node_type), count],
+                )
+                .context("Failed to record node visits")?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Mean visits per game of each submission to each node type over the tournament, as
+    /// `(submission, node_type, mean)` ordered by submission.
+    pub async fn list_node_visit_means(&self) -> Result<Vec<(String, String, f64)>> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            let mut stmt = conn.prepare(
+                "SELECT v.submission, v.node_type, AVG(v.visits)
+                 FROM node_visits v
+                 JOIN games g ON g.id = v.game_id
+                 JOIN matchups m ON m.id = g.matchup_id
+                 WHERE m.tournament_id = ?1
+                 GROUP BY v.submission, v.node_type
+                 ORDER BY v.submission",
+            )?;
+            let means = stmt
+                .query_map(params![tournament_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to query node visits")?;
+            Ok(means)
+        })
+        .await
+    }
+
+    /// The `limit` longest games of the tournament, longest first. Games without a recorded
+    /// duration, such as forfeits, are left out.
+    pub async fn list_slowest_games(&self, limit: usize) -> Result<Vec<GameDuration>> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            let mut stmt = conn.prepare(
+                "SELECT g.matchup_id, m.player_a, m.player_b, g.game_number, g.duration_ms
+                 FROM games g
+                 JOIN matchups m ON m.id = g.matchup_id
+                 WHERE m.tournament_id = ?1 AND g.duration_ms IS NOT NULL
+                 ORDER BY g.duration_ms DESC, g.id
+                 LIMIT ?2",
+            )?;
+            let games = stmt
+                .query_map(params![tournament_id, limit as i64], |row| {
+                    Ok(GameDuration {
+                        matchup_id: row.get(0)?,
+                        player_a: row.get(1)?,
+                        player_b: row.get(2)?,
+                        game_number: row.get(3)?,
+                        duration_ms: row.get(4)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to query game durations")?;
+            Ok(games)
+        })
+        .await
+    }
+
+    /// Call count, mean and 95th percentile latency of each submission's calls recorded in the
+    /// tournament, by submission name.
+    pub async fn call_latency_stats(&self) -> Result<Vec<CallLatencyStats>> {
+        let pool = self.pool.clone();
+        let tournament_id = self.tournament_id;
+        self.retry_on_locked(move || {
+            let conn = pool.get().context("Failed to get connection from pool")?;
+            // The nearest rank of the 95th percentile of `calls` latencies is ceil(0.95 * calls)
+            let mut stmt = conn.prepare(
+                "WITH ranked AS (
+                     SELECT c.submission, c.latency_ms,
+                            ROW_NUMBER() OVER (PARTITION BY c.submission ORDER BY c.latency_ms) AS rank,
+                            COUNT(*) OVER (PARTITION BY c.submission) AS calls,
+                            AVG(c.latency_ms) OVER (PARTITION BY c.submission) AS mean_ms
+                     FROM call_latencies c
+                     JOIN games g ON g.id = c.game_id
+                     JOIN matchups m ON m.id = g.matchup_id
+                     WHERE m.tournament_id = ?1
+                 )
+                 SELECT submission, calls, mean_ms, latency_ms
+                 FROM ranked
+                 WHERE rank = (calls * 95 + 99) / 100
+                 ORDER BY submission",
+            )?;
+            let stats = stmt
+                .query_map(params![tournament_id], |row| {
+                    Ok(CallLatencyStats {
+                        submission: row.get(0)?,
+                        calls: row.get(1)?,
+                        mean_ms: row.get(2)?,
+                        p95_ms: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to query call latencies")?;
+            Ok(stats)
+        })
+        .await
+    }
+
     pub async fn record_turn(&self, game_id: i64, turn_number: i64, svg_path: &str) -> Result<()> {
         debug!(
             "Recording turn: game_id={}, turn_number={}, svg_path={}",
@@ -0,0 +1,808 @@
+#![cfg(test)]
+
+use std::{collections::HashSet, fs, time::Duration};
+
+use petgraph::visit::EdgeRef;
+use rand::{SeedableRng, rngs::StdRng};
+
+use anyhow::Result;
+use rusqlite::{Connection, ffi};
+use tempfile::TempDir;
+
+use crate::{
+    Config,
+    game::GameResult,
+    game_map::{GameMap, MapConfig},
+};
+
+use super::{
+    Database, Disqualification, DisqualificationReason, GameRow, MIGRATIONS, MatchupRow,
+    PendingPolicy, RetryPolicy, is_unique_violation,
+};
+
+fn temp_db() -> (TempDir, Database) {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    (results_dir, db)
+}
+
+fn sqlite_error(code: i32, message: &str) -> anyhow::Error {
+    rusqlite::Error::SqliteFailure(ffi::Error::new(code), Some(message.to_string())).into()
+}
+
+#[tokio::test]
+async fn test_stored_map_deserializes_to_equivalent_graph() {
+    let (_results_dir, db) = temp_db();
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let map = GameMap::new(&mut rng, &MapConfig::default()).unwrap();
+
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    db.create_game(matchup_id, 0, "a", 42, Some(&map.to_json().unwrap()))
+        .await
+        .unwrap();
+
+    let stored = db.get_game_map(matchup_id, 0).await.unwrap().unwrap();
+    let restored = GameMap::from_json(&stored).unwrap();
+
+    assert_eq!(restored.node_weights(), map.node_weights());
+    for node in map.node_indices() {
+        let targets = |m: &GameMap| {
+            m.get_outgoing_edges(node)
+                .iter()
+                .map(|e| e.target())
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(targets(&restored), targets(&map));
+    }
+}
+
+#[tokio::test]
+async fn test_forfeited_game_has_no_map() {
+    let (_results_dir, db) = temp_db();
+
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    db.create_game(matchup_id, 0, "a", 0, None).await.unwrap();
+
+    assert!(db.get_game_map(matchup_id, 0).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_games_are_found_by_seed() {
+    let (_results_dir, db) = temp_db();
+    let first = db.start_matchup("alice", "bob").await.unwrap();
+    let second = db.start_matchup("alice", "carol").await.unwrap();
+    db.create_game(first, 0, "alice", 111, None).await.unwrap();
+    db.create_game(first, 1, "bob", 222, None).await.unwrap();
+    db.create_game(second, 3, "carol", 111, None).await.unwrap();
+    db.update_game_result(first, 0, GameResult::Player2Win)
+        .await
+        .unwrap();
+
+    let game = |matchup_id, player_b: &str, game_number, first_mover: &str, winner: &str| GameRow {
+        matchup_id,
+        player_a: "alice".to_string(),
+        player_b: player_b.to_string(),
+        game_number,
+        first_mover: Some(first_mover.to_string()),
+        winner: winner.to_string(),
+    };
+    assert_eq!(
+        db.get_game_by_seed(111).await.unwrap(),
+        [
+            game(first, "bob", 0, "alice", "player_b"),
+            game(second, "carol", 3, "carol", "pending")
+        ]
+    );
+    assert_eq!(
+        db.get_game_by_seed(222).await.unwrap(),
+        [game(first, "bob", 1, "bob", "pending")]
+    );
+    assert!(db.get_game_by_seed(333).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_pending_game_is_restarted_cleanly() {
+    let (results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    // A run that crashed after two turns
+    let id = db
+        .create_game(matchup_id, 0, "a", 1, Some("old"))
+        .await
+        .unwrap();
+    let svg_paths: Vec<_> = (0..2)
+        .map(|turn| results_dir.path().join(format!("turn_{turn}.svg")))
+        .collect();
+    for (turn, svg_path) in svg_paths.iter().enumerate() {
+        fs::write(svg_path, "<svg/>").unwrap();
+        db.record_turn(id, turn as i64, svg_path.to_str().unwrap())
+            .await
+            .unwrap();
+    }
+
+    let resumed_id = db
+        .create_game(matchup_id, 0, "a", 2, Some("new"))
+        .await
+        .unwrap();
+
+    assert_eq!(resumed_id, id);
+    assert!(svg_paths.iter().all(|path| !path.exists()));
+    assert_eq!(
+        db.get_game_map(matchup_id, 0).await.unwrap().unwrap(),
+        "new"
+    );
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let (turns, seed): (i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM turns WHERE game_id = ?1), seed FROM games WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((turns, seed), (0, 2));
+}
+
+#[tokio::test]
+async fn test_forfeit_keeps_what_a_pending_game_recorded() {
+    let (results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    // A game whose container died after one turn, and one that finished
+    let id = db
+        .create_game(matchup_id, 0, "a", 7, Some("map"))
+        .await
+        .unwrap();
+    let svg_path = results_dir.path().join("turn_0.svg");
+    fs::write(&svg_path, "<svg/>").unwrap();
+    db.record_turn(id, 0, svg_path.to_str().unwrap())
+        .await
+        .unwrap();
+    db.create_game(matchup_id, 1, "b", 8, None).await.unwrap();
+    db.update_game_result(matchup_id, 1, GameResult::Tie)
+        .await
+        .unwrap();
+
+    for game_number in 0..3 {
+        db.record_forfeit(matchup_id, game_number, "a", GameResult::Player2Win)
+            .await
+            .unwrap();
+    }
+
+    let winners: Vec<_> = db
+        .list_matchup_games(matchup_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|game| (game.game_number, game.winner))
+        .collect();
+    assert_eq!(
+        winners,
+        [
+            (0, "player_b".to_string()),
+            (1, "tie".to_string()),
+            (2, "player_b".to_string())
+        ]
+    );
+    assert!(svg_path.exists());
+    assert_eq!(
+        db.get_game_map(matchup_id, 0).await.unwrap().as_deref(),
+        Some("map")
+    );
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let (turns, seed): (i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM turns WHERE game_id = ?1), seed FROM games WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((turns, seed), (1, 7));
+}
+
+#[tokio::test]
+async fn test_pending_games_are_swept_by_policy() {
+    for policy in [
+        PendingPolicy::Rerun,
+        PendingPolicy::Exclude,
+        PendingPolicy::Delete,
+    ] {
+        let (results_dir, db) = temp_db();
+        let matchup_id = db.start_matchup("a", "b").await.unwrap();
+        db.create_game(matchup_id, 0, "a", 0, None).await.unwrap();
+        db.update_game_result(matchup_id, 0, GameResult::Tie)
+            .await
+            .unwrap();
+        let id = db.create_game(matchup_id, 1, "b", 1, None).await.unwrap();
+        let svg_path = results_dir.path().join("turn_0.svg");
+        fs::write(&svg_path, "<svg/>").unwrap();
+        db.record_turn(id, 0, svg_path.to_str().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(db.sweep_pending_games(policy).await.unwrap(), 1);
+
+        let winners: Vec<String> = db
+            .list_matchup_games(matchup_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|game| game.winner)
+            .collect();
+        let expected: &[&str] = match policy {
+            PendingPolicy::Rerun => &["tie", "pending"],
+            PendingPolicy::Exclude => &["tie", "error"],
+            PendingPolicy::Delete => &["tie"],
+        };
+        assert_eq!(winners, expected, "{policy:?}");
+        assert_eq!(svg_path.exists(), policy != PendingPolicy::Delete);
+        assert_eq!(db.list_decided_games().await.unwrap().len(), 1);
+    }
+}
+
+#[tokio::test]
+async fn test_finished_game_keeps_its_turns() {
+    let (results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let id = db
+        .create_game(matchup_id, 0, "a", 1, Some("map"))
+        .await
+        .unwrap();
+    db.record_turn(id, 0, "turn_0.svg").await.unwrap();
+    db.update_game_result(matchup_id, 0, GameResult::Tie)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        db.create_game(matchup_id, 0, "a", 2, None).await.unwrap(),
+        id
+    );
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let turns: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM turns WHERE game_id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(turns, 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_game_creation_returns_the_same_id() {
+    let (results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    for game_number in 0..20 {
+        let creations: Vec<_> = (0..4)
+            .map(|seed| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    db.create_game(matchup_id, game_number, "a", seed, None)
+                        .await
+                })
+            })
+            .collect();
+        let mut ids = HashSet::new();
+        for creation in creations {
+            ids.insert(creation.await.unwrap().unwrap());
+        }
+        assert_eq!(ids.len(), 1, "game {game_number}: {ids:?}");
+    }
+
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let games: i64 = conn
+        .query_row("SELECT COUNT(*) FROM games", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(games, 20);
+}
+
+#[test]
+fn test_duplicate_game_insert_is_a_unique_violation() {
+    let (results_dir, _db) = temp_db();
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    conn.execute(
+        "INSERT INTO matchups (player_a, player_b) VALUES ('a', 'b')",
+        [],
+    )
+    .unwrap();
+    let insert = || {
+        conn.execute(
+            "INSERT INTO games (matchup_id, game_number, winner, seed) VALUES (1, 0, 'pending', 0)",
+            [],
+        )
+    };
+    insert().unwrap();
+
+    assert!(is_unique_violation(&insert().unwrap_err()));
+    // Other constraints don't mean the row already exists
+    let missing_column = conn
+        .execute(
+            "INSERT INTO games (matchup_id, game_number) VALUES (1, 1)",
+            [],
+        )
+        .unwrap_err();
+    assert!(!is_unique_violation(&missing_column));
+}
+
+#[tokio::test]
+async fn test_matchup_listing_and_completion_counts() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 4,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+
+    let first = db.start_matchup("a", "b").await.unwrap();
+    let second = db.start_matchup("c", "a").await.unwrap();
+    for game_number in 0..4 {
+        db.create_game(first, game_number, "a", 0, None)
+            .await
+            .unwrap();
+        db.update_game_result(first, game_number, GameResult::Player1Win)
+            .await
+            .unwrap();
+    }
+    db.create_game(second, 0, "a", 0, None).await.unwrap();
+    db.update_game_result(second, 0, GameResult::Tie)
+        .await
+        .unwrap();
+    db.create_game(second, 1, "a", 0, None).await.unwrap();
+
+    let matchups = db.list_matchups().await.unwrap();
+    let pairs: Vec<_> = matchups
+        .iter()
+        .map(
+            |MatchupRow {
+                 id,
+                 player_a,
+                 player_b,
+                 ..
+             }| (*id, player_a.as_str(), player_b.as_str()),
+        )
+        .collect();
+    assert_eq!(pairs, [(first, "a", "b"), (second, "c", "a")]);
+
+    assert_eq!(db.count_completed_games(first).await.unwrap(), (4, 4));
+    assert_eq!(db.count_completed_games(second).await.unwrap(), (1, 4));
+    assert_eq!(db.count_completed_games(999).await.unwrap(), (0, 4));
+}
+
+#[tokio::test]
+async fn test_unplayed_matchups_are_listed_until_started() {
+    let (_results_dir, db) = temp_db();
+    db.record_unplayed_matchup("alice", "bob").await.unwrap();
+    db.record_unplayed_matchup("carol", "dave").await.unwrap();
+    db.record_unplayed_matchup("alice", "bob").await.unwrap();
+    assert_eq!(
+        db.list_unplayed_matchups().await.unwrap(),
+        [
+            ("alice".to_string(), "bob".to_string()),
+            ("carol".to_string(), "dave".to_string())
+        ]
+    );
+
+    // Starting it in either order takes it off the list, as does a reset
+    db.start_matchup("bob", "alice").await.unwrap();
+    assert_eq!(
+        db.list_unplayed_matchups().await.unwrap(),
+        [("carol".to_string(), "dave".to_string())]
+    );
+    db.reset().await.unwrap();
+    assert!(db.list_unplayed_matchups().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_disqualifications_roundtrip_with_their_location() {
+    let (_results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("alice", "bob").await.unwrap();
+
+    let recorded = [
+        Disqualification {
+            submission: "alice".to_string(),
+            matchup_id,
+            reason: DisqualificationReason::TooManyInvalidMoves,
+            game_number: Some(3),
+            turn_number: Some(17),
+        },
+        Disqualification {
+            submission: "bob".to_string(),
+            matchup_id,
+            reason: DisqualificationReason::ContainerCrash,
+            game_number: None,
+            turn_number: None,
+        },
+    ];
+    for disqualification in &recorded {
+        db.record_disqualification(disqualification).await.unwrap();
+    }
+
+    assert_eq!(db.list_disqualifications().await.unwrap(), recorded);
+}
+
+#[tokio::test]
+async fn test_unversioned_database_is_migrated_keeping_its_rows() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+
+    // The schema written before games stored their map and first mover
+    let conn = Connection::open(Database::file(&config)).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE matchups (
+            id INTEGER PRIMARY KEY,
+            player_a TEXT NOT NULL,
+            player_b TEXT NOT NULL,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(player_a, player_b)
+        );
+        CREATE TABLE games (
+            id INTEGER PRIMARY KEY,
+            matchup_id INTEGER NOT NULL,
+            game_number INTEGER NOT NULL,
+            winner TEXT NOT NULL,
+            seed INTEGER NOT NULL,
+            UNIQUE(matchup_id, game_number)
+        );
+        INSERT INTO matchups (id, player_a, player_b) VALUES (1, 'alice', 'bob');
+        INSERT INTO games (matchup_id, game_number, winner, seed) VALUES (1, 0, 'player_a', 7);",
+    )
+    .unwrap();
+    drop(conn);
+
+    let db = Database::new(&config).unwrap();
+
+    assert_eq!(
+        db.get_game_by_seed(7).await.unwrap(),
+        [GameRow {
+            matchup_id: 1,
+            player_a: "alice".to_string(),
+            player_b: "bob".to_string(),
+            game_number: 0,
+            first_mover: None,
+            winner: "player_a".to_string(),
+        }]
+    );
+    assert!(db.list_disqualifications().await.unwrap().is_empty());
+    let versions = |db: &Database| -> Vec<i64> {
+        let conn = db.pool.get().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT version FROM schema_version ORDER BY version")
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    };
+    let all: Vec<i64> = (1..=MIGRATIONS.len() as i64).collect();
+    assert_eq!(versions(&db), all);
+
+    // Migrating again is a no-op
+    db.migrate().unwrap();
+    let reopened = Database::new(&config).unwrap();
+    assert_eq!(versions(&reopened), all);
+    assert_eq!(reopened.list_matchups().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_tournaments_keep_their_matchups_separate() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let open = |tournament_name: &str| {
+        Database::new(&Config {
+            tournament_name: tournament_name.to_string(),
+            results_dir: results_dir.path().to_str().unwrap().to_string(),
+            ..Config::default()
+        })
+        .unwrap()
+    };
+    let qualifier = open("qualifier");
+    let finals = open("finals");
+
+    let qualifier_matchup = qualifier.start_matchup("alice", "bob").await.unwrap();
+    qualifier.start_matchup("alice", "carol").await.unwrap();
+    let finals_matchup = finals.start_matchup("alice", "bob").await.unwrap();
+    assert_ne!(qualifier_matchup, finals_matchup);
+
+    for (db, matchup_id) in [(&qualifier, qualifier_matchup), (&finals, finals_matchup)] {
+        db.create_game(matchup_id, 0, "alice", 9, None)
+            .await
+            .unwrap();
+        db.update_game_result(matchup_id, 0, GameResult::Tie)
+            .await
+            .unwrap();
+    }
+    finals
+        .record_disqualification(&Disqualification {
+            submission: "bob".to_string(),
+            matchup_id: finals_matchup,
+            reason: DisqualificationReason::Timeout,
+            game_number: None,
+            turn_number: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(qualifier.list_matchups().await.unwrap().len(), 2);
+    assert_eq!(finals.list_matchups().await.unwrap().len(), 1);
+    for (db, matchup_id) in [(&qualifier, qualifier_matchup), (&finals, finals_matchup)] {
+        let games = db.list_decided_games().await.unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].matchup_id, matchup_id);
+        assert_eq!(db.get_game_by_seed(9).await.unwrap(), games);
+    }
+    assert!(qualifier.list_disqualifications().await.unwrap().is_empty());
+    assert_eq!(finals.list_disqualifications().await.unwrap().len(), 1);
+
+    // Reopening a tournament finds its matchups again
+    assert_eq!(
+        open("qualifier")
+            .start_matchup("alice", "bob")
+            .await
+            .unwrap(),
+        qualifier_matchup
+    );
+    assert!(open("default").list_matchups().await.unwrap().is_empty());
+}
+
+/// Row count of every table of a database, by table name.
+fn table_row_counts(conn: &Connection) -> Vec<(String, i64)> {
+    let tables: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    tables
+        .into_iter()
+        .map(|table| {
+            let count = conn
+                .query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            (table, count)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_reset_forgets_matchup_orders() {
+    let (results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("alice", "bob").await.unwrap();
+    db.start_matchup("alice", "carol").await.unwrap();
+    let game_id = db
+        .create_game(matchup_id, 0, "alice", 1, None)
+        .await
+        .unwrap();
+    let svg = results_dir.path().join("turn_0.svg");
+    fs::write(&svg, "<svg/>").unwrap();
+    db.record_turn(game_id, 0, svg.to_str().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(db.reset().await.unwrap(), 2);
+    assert!(db.list_matchups().await.unwrap().is_empty());
+    assert!(!svg.exists());
+
+    // The pair is ordered as it is first seen again, like in a fresh database
+    assert_eq!(
+        db.get_matchup_order("bob", "alice").await,
+        ("bob".to_string(), "alice".to_string())
+    );
+    db.start_matchup("alice", "bob").await.unwrap();
+    let matchups = db.list_matchups().await.unwrap();
+    assert_eq!(
+        (matchups[0].player_a.as_str(), matchups[0].player_b.as_str()),
+        ("bob", "alice")
+    );
+}
+
+#[tokio::test]
+async fn test_reset_matchup_deletes_only_that_pair() {
+    let (_results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("alice", "bob").await.unwrap();
+    db.create_game(matchup_id, 0, "alice", 1, None)
+        .await
+        .unwrap();
+    db.start_matchup("alice", "carol").await.unwrap();
+
+    assert!(db.reset_matchup("bob", "alice").await.unwrap());
+    assert!(!db.reset_matchup("bob", "alice").await.unwrap());
+    let matchups = db.list_matchups().await.unwrap();
+    assert_eq!(matchups.len(), 1);
+    assert_eq!(matchups[0].player_b, "carol");
+    assert!(db.list_matchup_games(matchup_id).await.unwrap().is_empty());
+
+    assert_eq!(
+        db.get_matchup_order("bob", "alice").await,
+        ("bob".to_string(), "alice".to_string())
+    );
+    assert_eq!(
+        db.get_matchup_order("carol", "alice").await,
+        ("alice".to_string(), "carol".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_dump_reimports_with_the_same_row_counts() {
+    let (results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("o'brien", "bob").await.unwrap();
+    let map = GameMap::new(&mut StdRng::seed_from_u64(1), &MapConfig::default()).unwrap();
+    db.create_game(matchup_id, 0, "o'brien", -7, Some(&map.to_json().unwrap()))
+        .await
+        .unwrap();
+    db.create_game(matchup_id, 1, "bob", 8, None).await.unwrap();
+    db.update_game_result(matchup_id, 0, GameResult::Tie)
+        .await
+        .unwrap();
+    let game_id: i64 = Connection::open(results_dir.path().join("results.sqlite"))
+        .unwrap()
+        .query_row("SELECT id FROM games WHERE game_number = 0", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    db.record_turn(game_id, 0, "turn_0.svg").await.unwrap();
+    db.record_disqualification(&Disqualification {
+        submission: "bob".to_string(),
+        matchup_id,
+        reason: DisqualificationReason::Timeout,
+        game_number: Some(1),
+        turn_number: None,
+    })
+    .await
+    .unwrap();
+
+    let dump_path = results_dir.path().join("dump.sql");
+    db.export_sqlite_dump(&dump_path).await.unwrap();
+
+    let original = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let restored = Connection::open_in_memory().unwrap();
+    restored
+        .execute_batch(&fs::read_to_string(&dump_path).unwrap())
+        .unwrap();
+    let counts = table_row_counts(&original);
+    assert!(
+        counts
+            .iter()
+            .any(|(table, count)| table == "games" && *count == 2)
+    );
+    assert_eq!(table_row_counts(&restored), counts);
+
+    let players: (String, String) = restored
+        .query_row("SELECT player_a, player_b FROM matchups", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(players, ("o'brien".to_string(), "bob".to_string()));
+}
+
+#[tokio::test]
+async fn test_dump_rejects_infinite_values() {
+    let (results_dir, db) = temp_db();
+    let matchup_id = db.start_matchup("alice", "bob").await.unwrap();
+    db.create_game(matchup_id, 0, "alice", 1, None)
+        .await
+        .unwrap();
+    Connection::open(results_dir.path().join("results.sqlite"))
+        .unwrap()
+        .execute(
+            "INSERT INTO call_latencies (game_id, turn_number, submission, endpoint, latency_ms)
+             SELECT id, 0, 'alice', 'choices', ?1 FROM games",
+            [f64::INFINITY],
+        )
+        .unwrap();
+
+    let error = db
+        .export_sqlite_dump(&results_dir.path().join("dump.sql"))
+        .await
+        .unwrap_err();
+
+    assert!(
+        format!("{:#}", error).contains("non-finite value inf"),
+        "{error:#}"
+    );
+}
+
+#[tokio::test]
+async fn test_transient_busy_error_is_retried() {
+    let (_results_dir, db) = temp_db();
+
+    let mut attempts = 0;
+    let result: Result<i32> = db
+        .retry_on_locked(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(sqlite_error(ffi::SQLITE_BUSY, "database is locked"))
+            } else {
+                Ok(7)
+            }
+        })
+        .await;
+
+    assert_eq!(result.unwrap(), 7);
+    assert_eq!(attempts, 3);
+}
+
+#[tokio::test]
+async fn test_busy_error_is_retried_regardless_of_message() {
+    let (_results_dir, db) = temp_db();
+
+    for code in [
+        ffi::SQLITE_BUSY,
+        ffi::SQLITE_BUSY_SNAPSHOT,
+        ffi::SQLITE_LOCKED,
+        ffi::SQLITE_LOCKED_SHAREDCACHE,
+    ] {
+        let mut attempts = 0;
+        let result: Result<()> = db
+            .retry_on_locked(|| {
+                attempts += 1;
+                if attempts < 2 {
+                    Err(sqlite_error(code, "database is busy (another writer)"))
+                } else {
+                    Ok(())
+                }
+            })
+            .await;
+
+        assert!(result.is_ok(), "code {code} was not retried");
+        assert_eq!(attempts, 2);
+    }
+}
+
+#[tokio::test]
+async fn test_other_errors_are_not_retried() {
+    let (_results_dir, db) = temp_db();
+
+    let mut attempts = 0;
+    let result: Result<()> = db
+        .retry_on_locked(|| {
+            attempts += 1;
+            Err(sqlite_error(
+                ffi::SQLITE_CONSTRAINT,
+                "UNIQUE constraint failed",
+            ))
+        })
+        .await;
+
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+fn test_backoff_is_capped_at_max_delay() {
+    let policy = RetryPolicy {
+        max_retries: 40,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(500),
+    };
+
+    assert_eq!(policy.delay(0), Duration::from_millis(10));
+    assert_eq!(policy.delay(3), Duration::from_millis(80));
+    assert_eq!(policy.delay(6), Duration::from_millis(500));
+    assert_eq!(policy.delay(39), Duration::from_millis(500));
+}
+
+#[cfg(feature = "live")]
+#[tokio::test]
+async fn test_health_fails_once_connections_cannot_be_opened() {
+    let (results_dir, mut db) = temp_db();
+    db.health().await.unwrap();
+
+    // Stands in for a closed pool: nothing idle, and every new connection fails to open
+    let unreachable = results_dir.path().join("missing").join("results.sqlite");
+    db.pool = r2d2::Pool::builder()
+        .min_idle(Some(0))
+        .connection_timeout(Duration::from_millis(100))
+        .build_unchecked(r2d2_sqlite::SqliteConnectionManager::file(unreachable));
+    assert!(db.health().await.is_err());
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// Why a run failed, for callers that handle some failures differently from others. The error
+/// behind each is kept as its source.
+#[derive(Debug, Error)]
+pub enum TournamentError {
+    /// The config couldn't be read, or has values of the wrong type.
+    #[error("Invalid config")]
+    Config(#[source] anyhow::Error),
+    /// The container runtime failed, e.g. while removing leftover containers.
+    #[error("Container runtime failed")]
+    Container(#[source] anyhow::Error),
+    /// The submissions couldn't be found, or one of them can't be launched.
+    #[error("Invalid submission")]
+    Submission(#[source] anyhow::Error),
+    /// The results database couldn't be opened.
+    #[error("Results database failed")]
+    Database(#[source] anyhow::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// A matchup ran past `matchup_timeout` and its stuck games were aborted. Replaying it would most
+/// likely get stuck the same way, so it isn't retried.
+#[derive(Debug, Error)]
+#[error(
+    "Matchup {submission_a} vs {submission_b} did not finish within {timeout:?}, {pending} games were left pending"
+)]
+pub struct MatchupTimeout {
+    pub submission_a: String,
+    pub submission_b: String,
+    pub timeout: Duration,
+    pub pending: usize,
+}
+
+/// A call to `submission` failed, or was still running when its game ran out of `game_timeout`,
+/// so the game failed because of it.
+#[derive(Debug, Error)]
+#[error("{submission} failed its game")]
+pub struct SubmissionFault {
+    pub submission: String,
+}
@@ -1,32 +1,311 @@
-use crate::{db::Database, game_map::GameMap, submission::Submission, Config};
-use anyhow::{Context, Result, anyhow};
-use log::{debug, info};
+use crate::{
+    Config,
+    db::{Database, Disqualification, DisqualificationReason},
+    game_map::{GameMap, NODE_TYPES, node_type_index},
+    profile::{self, Bucket},
+    recording_rng::{Draw, RecordingRng},
+    report,
+    submission::{GambleResponse, Submission, VisiblePositions},
+};
+use anyhow::{Context, Result, anyhow, bail};
+use log::{debug, info, warn};
 use petgraph::graph::NodeIndex;
-use petgraph::visit::EdgeRef;
 use rand::{prelude::*, random, rngs::StdRng};
-use rplcs_events::tournament_1::{
-    FightChoices, FightInfo, GambleChoices, MapNodeType, MoveChoices, PlayerState,
+use rplcs_events::tournament_1::{FightChoices, FightInfo, MapNodeType, MoveChoices, PlayerState};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{HashSet, VecDeque},
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Instant,
 };
-use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy)]
+mod tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameResult {
     Player1Win,
     Player2Win,
     Tie,
 }
 
+impl GameResult {
+    /// Name of the winning submission given who moved `first` and `second` in the game, `None`
+    /// for a tie.
+    pub fn to_winner_submission<'a>(self, first: &'a str, second: &'a str) -> Option<&'a str> {
+        match self {
+            GameResult::Player1Win => Some(first),
+            GameResult::Player2Win => Some(second),
+            GameResult::Tie => None,
+        }
+    }
+}
+
+/// How a game that reaches `turns_per_game` with both players alive is decided.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// The game is a tie.
+    #[default]
+    None,
+    /// The player with more health wins.
+    Health,
+    /// The player with more power wins.
+    Power,
+    /// The player with more health wins, with power deciding between equal health.
+    HealthThenPower,
+}
+
+impl TieBreak {
+    /// Decides the result of a game that ran out of turns from both players' final states.
+    pub fn decide(self, first: &PlayerState, second: &PlayerState) -> GameResult {
+        let ordering = match self {
+            TieBreak::None => Ordering::Equal,
+            TieBreak::Health => first.health.cmp(&second.health),
+            TieBreak::Power => first.power.cmp(&second.power),
+            TieBreak::HealthThenPower => first
+                .health
+                .cmp(&second.health)
+                .then(first.power.cmp(&second.power)),
+        };
+
+        match ordering {
+            Ordering::Greater => GameResult::Player1Win,
+            Ordering::Less => GameResult::Player2Win,
+            Ordering::Equal => GameResult::Tie,
+        }
+    }
+}
+
+/// What happens to a submission that doesn't answer a single call in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutPolicy {
+    /// The game fails with the timeout's error, to be retried or recorded as errored.
+    #[default]
+    FailGame,
+    /// The submission loses the game.
+    LoseGame,
+    /// The submission takes 1 damage and its turn ends, as with an invalid move.
+    SkipTurnWithDamage,
+}
+
+/// What happens to a player on a node without outgoing edges, which has no move to choose from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoMovesPolicy {
+    /// The player stays put and its turn ends, without damage.
+    #[default]
+    SkipTurn,
+    /// The player is teleported to a random empty node, as on a teleport node.
+    Teleport,
+}
+
+/// How enemies pick the node they move to after every turn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnemyMovement {
+    /// A random neighbouring node, if the enemy moves at all under `enemy_move_chance`.
+    #[default]
+    Random,
+    /// Always the neighbouring node with the lowest index, without drawing from the game's rng,
+    /// so scripted tests don't depend on it.
+    LowestIndex,
+}
+
+/// Which player acts first in each round of two turns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnOrder {
+    /// The first mover of the game, every round.
+    #[default]
+    Alternating,
+    /// The player with more power as the round starts, the first mover on equal power.
+    Initiative,
+    /// A coin flip from the game's rng every round.
+    Random,
+}
+
+/// How the winner of a fight is decided.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FightModel {
+    /// A draw from the game's rng, each side winning in proportion to its power.
+    #[default]
+    Probabilistic,
+    /// The side with more power wins, the defender on equal power. Draws nothing from the rng.
+    Deterministic,
+}
+
+/// Which stat pays the `gamble_cost` of a gamble.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GambleCostStat {
+    #[default]
+    Health,
+    Power,
+}
+
+/// Something that happened during a game, recorded for replays. Nodes are map node indices.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GameEvent {
+    Move {
+        player: String,
+        from: usize,
+        to: usize,
+    },
+    /// A player ending up on a random empty node, from a teleport node or without moves.
+    Teleport { player: String, to: usize },
+    /// A fight started by `player`, walking into `opponent` or being walked into by an enemy.
+    Fight {
+        player: String,
+        opponent: String,
+        won: bool,
+    },
+    Gamble {
+        player: String,
+        stat: GambleResponse,
+        before: PlayerState,
+        after: PlayerState,
+    },
+    /// A submission that didn't answer in time, losing the game or 1 health by `timeout_policy`.
+    /// Not recorded when the policy fails the game instead.
+    Timeout { player: String },
+    /// A move choice out of range, costing 1 health.
+    InvalidMove { player: String },
+    /// The outcome, with the states of both players, the first mover first.
+    End {
+        winner: Option<String>,
+        players: [PlayerState; 2],
+    },
+}
+
+/// How much a `/choices` request reveals about where the opponent and the enemies are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Every position, the player's own included.
+    Full,
+    /// No positions and no opponent stats, only the player's own stats.
+    #[default]
+    SelfOnly,
+    /// The player's own position, and the opponent's and enemies' when they are one move away.
+    /// The opponent's stats are only shown while its position is.
+    Adjacent,
+}
+
+/// Whether `e` comes from a response that wasn't valid JSON for what was asked.
+fn is_invalid_json(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| cause.is::<serde_json::Error>())
+}
+
+/// Whether `e` comes from a call to a submission that didn't answer in time.
+fn is_timeout(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        cause.is::<tokio::time::error::Elapsed>()
+            || cause
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(reqwest::Error::is_timeout)
+    })
+}
+
+/// Explains an out-of-range `choice_index` to the submission's author.
+fn invalid_choice_message(submission: &str, choice_index: usize, available: usize) -> String {
+    match available {
+        0 => format!(
+            "{} returned choice_index {} but no moves were available, losing 1 health",
+            submission, choice_index
+        ),
+        _ => format!(
+            "{} returned choice_index {} but only {} moves were available (valid indices are 0 to {}), losing 1 health",
+            submission,
+            choice_index,
+            available,
+            available - 1
+        ),
+    }
+}
+
+/// Power a fighter brings to a fight: its power, reduced by `health_factor` times the fraction of
+/// health it is missing.
+fn effective_power(state: &PlayerState, health_factor: f64) -> f64 {
+    let missing_health = if state.max_health == 0 {
+        0.0
+    } else {
+        1.0 - f64::from(state.health) / f64::from(state.max_health)
+    };
+    f64::from(state.power) * (1.0 - health_factor * missing_health).clamp(0.0, 1.0)
+}
+
+/// Chance that `fighter` beats `opponent`, from the ratio of their effective powers.
+fn fight_win_chance(fighter: &PlayerState, opponent: &PlayerState, health_factor: f64) -> f64 {
+    let fighter_power = effective_power(fighter, health_factor);
+    let opponent_power = effective_power(opponent, health_factor);
+    if fighter_power + opponent_power == 0.0 {
+        0.5
+    } else {
+        fighter_power / (fighter_power + opponent_power)
+    }
+}
+
+/// Whether `attacker` beats `defender` under `FightModel::Deterministic`: only with more effective
+/// power, so equal power goes to the defender.
+fn attacker_wins(attacker: &PlayerState, defender: &PlayerState, health_factor: f64) -> bool {
+    effective_power(attacker, health_factor) > effective_power(defender, health_factor)
+}
+
+/// Power gained by beating an enemy: half of its power. With a `scaling` above 0, the reward
+/// shrinks for enemies weaker than the player, by their power ratio raised to `scaling`.
+fn enemy_reward(enemy_power: u32, player_power: u32, scaling: f64) -> u32 {
+    if scaling == 0.0 || player_power == 0 {
+        return enemy_power / 2;
+    }
+    let difficulty = (f64::from(enemy_power) / f64::from(player_power)).min(1.0);
+    (f64::from(enemy_power) / 2.0 * difficulty.powf(scaling)) as u32
+}
+
+/// Enemies of a game unless `enemies_per_nodes` scales them with the map.
+pub const DEFAULT_ENEMIES: usize = 2;
+
 pub struct Game {
     players: [Submission; 2],
     player_positions: [NodeIndex; 2],
-    enemies: [PlayerState; 2],
-    enemy_positions: [NodeIndex; 2],
+    enemies: Vec<PlayerState>,
+    enemy_positions: Vec<NodeIndex>,
     map: GameMap,
-    rng: StdRng,
+    rng: RecordingRng,
     seed: i64,
     game_id: i64,
     matchup_id: i64,
     config: Config,
+    /// Enemies set with `GameBuilder::enemy_count`, instead of from the config.
+    fixed_enemies: Option<usize>,
+    /// Fingerprints of the rng state at each decision point, with `rng_audit` on.
+    rng_log: Vec<String>,
+    /// Nodes each player ended its last `stall_penalty_turns` turns on, oldest first.
+    recent_positions: [VecDeque<NodeIndex>; 2],
+    /// Turns in a row each player ended on one of its recent nodes.
+    stalled_turns: [u64; 2],
+    /// Turn each player last teleported on, for `teleport_cooldown`.
+    last_teleports: [Option<i64>; 2],
+    /// Turn being played.
+    turn: i64,
+    /// What happened so far, by turn, written to the database once the game ends.
+    events: Vec<(i64, GameEvent)>,
+    /// How often each player landed on each node type, in `NODE_TYPES` order.
+    node_visits: [[u32; NODE_TYPES.len()]; 2],
+}
+
+/// The pieces as a turn starts, which is what the turn's SVG shows.
+struct TurnSnapshot {
+    turn: i64,
+    player_positions: [NodeIndex; 2],
+    enemy_positions: Vec<NodeIndex>,
+    players: [PlayerState; 2],
+    enemies: Vec<PlayerState>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,48 +320,341 @@ enum FightTarget {
     Enemy(usize),
 }
 
-impl Game {
-    pub fn new(player_a: Submission, player_b: Submission, game_id: i64, matchup_id: i64, config: Config) -> Self {
+/// Seed of a game. With a tournament seed it is derived from the seed and the game's identity, so
+/// rerunning the tournament replays the same games, otherwise it is random.
+fn game_seed(tournament_seed: Option<u64>, first: &str, second: &str, game_id: i64) -> i64 {
+    let Some(tournament_seed) = tournament_seed else {
+        return random();
+    };
+
+    // FNV-1a, which unlike `DefaultHasher` is guaranteed to stay the same across Rust versions
+    let bytes = tournament_seed
+        .to_le_bytes()
+        .into_iter()
+        .chain(first.bytes())
+        .chain([0])
+        .chain(second.bytes())
+        .chain([0])
+        .chain(game_id.to_le_bytes());
+    bytes.fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    }) as i64
+}
+
+/// Sets up a `Game` between two players. Its seed, map and pieces are generated from the config
+/// unless fixed, which tests use to script games.
+pub struct GameBuilder {
+    players: [Submission; 2],
+    game_id: i64,
+    matchup_id: i64,
+    config: Config,
+    seed: Option<i64>,
+    map: Option<GameMap>,
+    enemies: Option<usize>,
+    positions: Option<([NodeIndex; 2], Vec<NodeIndex>)>,
+}
+
+impl GameBuilder {
+    /// A game 0 of matchup 0 with the default config.
+    pub fn new(player_a: Submission, player_b: Submission) -> Self {
+        GameBuilder {
+            players: [player_a, player_b],
+            game_id: 0,
+            matchup_id: 0,
+            config: Config::default(),
+            seed: None,
+            map: None,
+            enemies: None,
+            positions: None,
+        }
+    }
+
+    pub fn ids(mut self, game_id: i64, matchup_id: i64) -> Self {
+        self.game_id = game_id;
+        self.matchup_id = matchup_id;
+        self
+    }
+
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Generates the map unless one was given and places the players and then the enemies on
+    /// distinct nodes of it, failing if the map runs out of empty nodes, or if positions given
+    /// with `positions` are off the map or shared by two pieces.
+    pub fn build(self) -> Result<Game> {
+        let [player_a, player_b] = &self.players;
         info!(
             "Creating game {} between {} and {}",
-            game_id,
+            self.game_id,
             player_a.name(),
             player_b.name()
         );
 
-        // Generate random seed
-        let seed = random::<i64>();
-        let mut rng = StdRng::seed_from_u64(seed as u64);
-        let map = GameMap::new(&mut rng).expect("Failed to generate map");
-
-        let player_a_position = map
-            .get_random_empty_node(&[], &mut rng)
-            .expect("No nodes in map");
-        let player_b_position = map
-            .get_random_empty_node(&[player_a_position], &mut rng)
-            .expect("No nodes in map");
+        let seed = self.seed.unwrap_or_else(|| {
+            game_seed(
+                self.config.tournament_seed(),
+                player_a.name(),
+                player_b.name(),
+                self.game_id,
+            )
+        });
+        let mut rng = RecordingRng::new(StdRng::seed_from_u64(seed as u64));
+        if self.config.rng_draws {
+            rng.start_recording();
+        }
+        let map = match self.map {
+            Some(map) => map,
+            None => {
+                rng.set_context("map");
+                let map =
+                    GameMap::new(&mut rng, &self.config.map).context("Failed to generate map")?;
+                debug!("Map of game {}:\n{}", self.game_id, map.adjacency_list());
+                map
+            }
+        };
 
         let mut game = Game {
-            players: [player_a, player_b],
-            player_positions: [player_a_position, player_b_position],
-            enemies: [PlayerState::default(), PlayerState::default()],
-            enemy_positions: [NodeIndex::new(0), NodeIndex::new(0)],
+            players: self.players,
+            player_positions: [NodeIndex::new(0); 2],
+            enemies: Vec::new(),
+            enemy_positions: Vec::new(),
             map,
             rng,
             seed,
-            game_id,
-            matchup_id,
-            config,
+            game_id: self.game_id,
+            matchup_id: self.matchup_id,
+            config: self.config,
+            fixed_enemies: self.enemies,
+            rng_log: Vec::new(),
+            recent_positions: Default::default(),
+            stalled_turns: [0; 2],
+            last_teleports: [None; 2],
+            turn: 0,
+            events: Vec::new(),
+            node_visits: [[0; NODE_TYPES.len()]; 2],
         };
+        game.place_pieces()?;
+        if let Some((players, enemies)) = self.positions {
+            if enemies.len() != game.enemies.len() {
+                bail!(
+                    "Got {} enemy positions for {} enemies",
+                    enemies.len(),
+                    game.enemies.len()
+                );
+            }
+            let mut occupied = HashSet::new();
+            for &node in players.iter().chain(&enemies) {
+                if node.index() >= game.map.node_count() {
+                    bail!(
+                        "Position {} is not on the map of {} nodes",
+                        node.index(),
+                        game.map.node_count()
+                    );
+                }
+                if !occupied.insert(node) {
+                    bail!("Two pieces were placed on node {}", node.index());
+                }
+            }
+            game.player_positions = players;
+            game.enemy_positions = enemies;
+        }
+        Ok(game)
+    }
+}
+
+// Overrides for scripted games and embedders. The runner derives all of these from the config,
+// so the binary itself never calls them.
+#[allow(dead_code)]
+impl GameBuilder {
+    /// Seeds the game with `seed` instead of one from the tournament seed or a random one.
+    pub fn seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Plays on `map` instead of one generated from the seed.
+    pub fn map(mut self, map: GameMap) -> Self {
+        self.map = Some(map);
+        self
+    }
 
-        // Initialize both enemies
-        game.generate_enemy(0);
-        game.generate_enemy(1);
+    /// Places `enemies` enemies instead of as many as the config asks for.
+    pub fn enemy_count(mut self, enemies: usize) -> Self {
+        self.enemies = Some(enemies);
+        self
+    }
+
+    /// Moves the pieces to these nodes once they have been placed, one per enemy.
+    pub fn positions(mut self, players: [NodeIndex; 2], enemies: Vec<NodeIndex>) -> Self {
+        self.positions = Some((players, enemies));
+        self
+    }
+}
+
+impl Game {
+    /// How many enemies a game on this map has: `DEFAULT_ENEMIES`, or one per
+    /// `enemies_per_nodes` nodes if that is set, unless fixed by the builder. Scaled counts are at
+    /// least one, and at most as many as the nodes the players leave free for enemies.
+    fn enemy_count(&self) -> usize {
+        if let Some(enemies) = self.fixed_enemies {
+            return enemies;
+        }
+        match self.config.enemies_per_nodes {
+            Some(nodes) => {
+                // Nobody is placed on teleport nodes
+                let free = self
+                    .map
+                    .node_indices()
+                    .into_iter()
+                    .filter(|&node| self.map.get_node_type(node) != Some(MapNodeType::Teleport))
+                    .count()
+                    .saturating_sub(2);
+                (self.map.node_count() / nodes.get()).clamp(1, free.max(1))
+            }
+            None => DEFAULT_ENEMIES,
+        }
+    }
 
-        game
+    /// Places the players and then the enemies, with fresh stats, on distinct random nodes.
+    fn place_pieces(&mut self) -> Result<()> {
+        self.rng.set_context("placement");
+        let player_a_position = self
+            .map
+            .get_random_empty_node(&[], &mut self.rng)
+            .context("No empty node for player A")?;
+        let player_b_position = if self.config.min_start_distance > 1 {
+            self.map
+                .get_random_node_away_from(
+                    player_a_position,
+                    self.config.min_start_distance,
+                    &mut self.rng,
+                )
+                .context("No empty node for player B")?
+        } else {
+            self.map
+                .get_random_empty_node(&[player_a_position], &mut self.rng)
+                .context("No empty node for player B")?
+        };
+        self.player_positions = [player_a_position, player_b_position];
+        self.recent_positions = Default::default();
+        self.stalled_turns = [0; 2];
+        self.last_teleports = [None; 2];
+        // Enemies start out on a player's node so they don't block anything until placed
+        let enemies = self.enemy_count();
+        self.enemies = vec![PlayerState::default(); enemies];
+        self.enemy_positions = vec![player_a_position; enemies];
+        for enemy in 0..enemies {
+            self.generate_enemy(enemy)?;
+        }
+        Ok(())
     }
 
+    /// Plays the game to the end and records its result. Unless rendering is turned off, an
+    /// `index.html` to browse the turn SVGs, and with `combine_turn_svgs` a `game.svg` stacking
+    /// them, is written next to them.
     pub async fn result(&mut self, db: &Database) -> Result<GameResult> {
+        #[cfg(feature = "live")]
+        let live = crate::live::LiveGameGuard::new(self.matchup_id, self.game_id);
+        let mut turn_paths = Vec::new();
+        let played = self.play(db, &mut turn_paths).await;
+        #[cfg(feature = "live")]
+        drop(live);
+        let (result, game_db_id) = played?;
+
+        if let Some(game_dir) = turn_paths.first().and_then(|path| path.parent()) {
+            report::write_game_index(&turn_paths, &game_dir.join("index.html"))?;
+            if self.config.combine_turn_svgs {
+                report::combine_game_svgs(&turn_paths, &game_dir.join("game.svg"))?;
+            }
+        }
+        if self.config.rng_audit {
+            self.write_rng_log(db).await?;
+        }
+        if let Some(draws) = self.rng.draws() {
+            self.write_rng_draws(game_db_id, draws)?;
+        }
+        info!("{}", self.completion_message(result));
+        Ok(result)
+    }
+
+    /// Line logged when the game ends, with its seed and, with `reproduce_hints`, a command to
+    /// reproduce its map.
+    fn completion_message(&self, result: GameResult) -> String {
+        let mut message = format!(
+            "Game {} of {} vs {} ended: {:?}, seed {}",
+            self.game_id,
+            self.players[0].name(),
+            self.players[1].name(),
+            result,
+            self.seed
+        );
+        if self.config.reproduce_hints {
+            message.push_str(&format!(
+                ". Render its map with `cargo run -- render-map --seed {}`",
+                self.seed
+            ));
+        }
+        message
+    }
+
+    /// Records a fingerprint of the rng state at a decision point, so two runs with the same seed
+    /// can be diffed to find where they diverge. Draws from a copy, leaving the game's rng as is.
+    /// Also labels the draws that follow, if they are recorded.
+    fn rng_checkpoint(&mut self, point: impl std::fmt::Display) {
+        self.rng.set_context(&point);
+        if !self.config.rng_audit {
+            return;
+        }
+        let fingerprint = self.rng.peek_u64();
+        let line = format!("{} {:016x}", point, fingerprint);
+        debug!("Game {} rng {}", self.game_id, line);
+        self.rng_log.push(line);
+    }
+
+    /// Writes the rng checkpoints to `rng_audit/<matchup_id>_<a>_vs_<b>/game_<id>.txt` in the
+    /// results directory.
+    async fn write_rng_log(&self, db: &Database) -> Result<()> {
+        let (first_name, second_name) = db
+            .get_matchup_order(self.players[0].name(), self.players[1].name())
+            .await;
+        let path = self
+            .config
+            .matchup_results_dir("rng_audit", self.matchup_id, &first_name, &second_name)
+            .join(format!("game_{}.txt", self.game_id));
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut log = self.rng_log.join("\n");
+        log.push('\n');
+        fs::write(&path, log).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Writes the recorded rng draws to `rng/<game>.jsonl` in the results directory, one JSON
+    /// object per line, where `<game>` is the game's id in the `games` table, next to its seed.
+    fn write_rng_draws(&self, game_db_id: i64, draws: &[Draw]) -> Result<()> {
+        let path = Path::new(&self.config.results_dir)
+            .join("rng")
+            .join(format!("{}.jsonl", game_db_id));
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut lines = String::new();
+        for draw in draws {
+            lines.push_str(&serde_json::to_string(draw)?);
+            lines.push('\n');
+        }
+        fs::write(&path, lines).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Plays every turn, adding the path of each rendered SVG to `turn_paths`, and returns the
+    /// result with the game's id in the `games` table.
+    async fn play(
+        &mut self,
+        db: &Database,
+        turn_paths: &mut Vec<PathBuf>,
+    ) -> Result<(GameResult, i64)> {
         info!(
             "Starting game {} between {} and {}",
             self.game_id,
@@ -90,40 +662,153 @@ impl Game {
             self.players[1].name()
         );
 
-        // Create game record with seed before starting turns
+        let start = Instant::now();
+        // Create game record with seed and map before starting turns
+        let map_json = self.map.to_json()?;
         let game_db_id = db
-            .create_game(self.matchup_id, self.game_id, self.seed)
+            .create_game(
+                self.matchup_id,
+                self.game_id,
+                self.players[0].name(),
+                self.seed,
+                Some(&map_json),
+            )
             .await?;
 
+        if !self.config.record_turns_without_render && !self.renders_turns() {
+            warn!(
+                "Game {} map has {} nodes, more than max_render_nodes, skipping its SVGs",
+                self.game_id,
+                self.map.node_count()
+            );
+        }
+
+        let turns = self.config.turns_per_game as i64;
+        let mut result = match self
+            .play_turns(db, game_db_id, 0..turns, turn_paths)
+            .await?
+        {
+            Some(result) => result,
+            None => {
+                let result = self.config.tie_break.decide(
+                    self.players[0].player_state(),
+                    self.players[1].player_state(),
+                );
+                info!(
+                    "Game {} ran out of turns after {}: {:?} (tie break: {:?})",
+                    self.game_id, self.config.turns_per_game, result, self.config.tie_break
+                );
+                result
+            }
+        };
+        if result == GameResult::Tie && self.config.tie_rematch {
+            result = self.play_rematch(db, game_db_id, turns, turn_paths).await?;
+        }
+
+        db.update_game_result(self.matchup_id, self.game_id, result)
+            .await?;
+        db.record_game_duration(self.matchup_id, self.game_id, start.elapsed())
+            .await?;
+
+        let winner = result
+            .to_winner_submission(self.players[0].name(), self.players[1].name())
+            .map(str::to_string);
+        let players = self.players.each_ref().map(|p| *p.player_state());
+        self.push_event(GameEvent::End { winner, players });
+        for disqualification in self.disqualifications() {
+            db.record_disqualification(&disqualification).await?;
+        }
+        db.record_game_events(game_db_id, std::mem::take(&mut self.events))
+            .await?;
+        for (player, visits) in self.players.iter().zip(self.node_visits) {
+            db.record_node_visits(game_db_id, player.name(), visits)
+                .await?;
+        }
+        Ok((result, game_db_id))
+    }
+
+    #[cfg(feature = "live")]
+    fn live_state(&self, turn: i64) -> crate::live::LiveGame {
+        crate::live::LiveGame {
+            matchup_id: self.matchup_id,
+            game_id: self.game_id,
+            turn,
+            players: self.players.each_ref().map(|p| p.name().to_string()),
+            player_positions: self.player_positions.map(NodeIndex::index),
+            player_states: self.players.each_ref().map(|p| *p.player_state()),
+            enemy_positions: self.enemy_positions.iter().map(|n| n.index()).collect(),
+            enemies: self.enemies.clone(),
+        }
+    }
+
+    /// Whether turn SVGs are written, which they aren't when only turn records are wanted or the
+    /// map is larger than `max_render_nodes`.
+    fn renders_turns(&self) -> bool {
+        !self.config.record_turns_without_render
+            && self
+                .config
+                .max_render_nodes
+                .is_none_or(|max| self.map.node_count() <= max)
+    }
+
+    /// Plays the turns numbered `turns`, the first player moving first, and returns the result
+    /// if the game ends before they run out.
+    async fn play_turns(
+        &mut self,
+        db: &Database,
+        game_db_id: i64,
+        turns: Range<i64>,
+        turn_paths: &mut Vec<PathBuf>,
+    ) -> Result<Option<GameResult>> {
         // Get consistent folder names using the cache
         let (first_name, second_name) = db
             .get_matchup_order(self.players[0].name(), self.players[1].name())
             .await;
+        let game_dir = self
+            .config
+            .matchup_visualization_dir(self.matchup_id, &first_name, &second_name)
+            .join(format!("game_{}", self.game_id));
+
+        // Only every `render_every_nth_turn`th turn is kept, and the last one played
+        let every = self.config.render_every_nth_turn.max(1) as i64;
+        let mut unsampled = None;
+        let mut outcome = None;
+        let mut round_first = 0;
+        for current_turn in turns.clone() {
+            // First, save the current state
+            let snapshot = self.snapshot(current_turn);
+            if current_turn % every == 0 {
+                self.record_snapshot(db, game_db_id, &snapshot, &game_dir, turn_paths)
+                    .await?;
+                unsampled = None;
+            } else {
+                unsampled = Some(snapshot);
+            }
 
-        for current_turn in 0..(self.config.turns_per_game as i64) {
-            // First, save the current state as SVG
-            let svg_path = PathBuf::from(format!(
-                "results/visualizations/{}_vs_{}/game_{}/turn_{}.svg",
-                first_name, second_name, self.game_id, current_turn
-            ));
+            if self.config.verbose_game {
+                eprintln!(
+                    "Game {} turn {}\n{}",
+                    self.game_id,
+                    current_turn,
+                    self.render_text()
+                );
+            }
 
-            // Render current state
-            self.map.render_to_file(
-                self.player_positions,
-                self.enemy_positions,
-                &[
-                    self.players[0].player_state().clone(),
-                    self.players[1].player_state().clone(),
-                ],
-                &self.enemies,
-                &svg_path,
-            )?;
-
-            // Record the turn in database
-            db.record_turn(game_db_id, current_turn, svg_path.to_str().unwrap())
-                .await?;
+            #[cfg(feature = "live")]
+            crate::live::publish(self.live_state(current_turn));
 
-            let player = (current_turn % 2) as usize;
+            self.turn = current_turn;
+            self.rng_checkpoint(format_args!("turn {}", current_turn));
+            // The round's first player is picked on its first turn
+            let offset = current_turn - turns.start;
+            if offset % 2 == 0 {
+                round_first = self.round_first_player();
+            }
+            let player = if offset % 2 == 0 {
+                round_first
+            } else {
+                1 - round_first
+            };
             debug!(
                 "Game {} Turn {}: Player {}'s turn",
                 self.game_id,
@@ -131,67 +816,351 @@ impl Game {
                 self.players[player].name()
             );
 
-            let choices = self.get_available_moves(player);
-            let response = self.players[player]
-                .get_choices(
-                    &MoveChoices {
-                        choices: choices.node_types,
-                    },
-                    self.game_id,
-                )
-                .await
-                .context("result()")?;
-
-            if let Some(&node_to) = choices.internal_choices.get(response.choice_index) {
-                self.handle_player_movement(player, self.player_positions[player], node_to)
-                    .await
-                    .context("result()")?;
-            } else {
-                // Invalid choice, damage player and skip turn
-                self.damage_player(player);
+            let turn = self.play_turn(player, current_turn).await;
+            self.record_latencies(db, game_db_id, current_turn).await?;
+            match turn {
+                Ok(()) => {}
+                Err(e)
+                    if is_timeout(&e) && self.config.timeout_policy != TimeoutPolicy::FailGame =>
+                {
+                    self.push_event(GameEvent::Timeout {
+                        player: self.players[player].name().to_string(),
+                    });
+                    match self.config.timeout_policy {
+                        TimeoutPolicy::FailGame => unreachable!("fails the game above"),
+                        TimeoutPolicy::LoseGame => {
+                            let result = if player == 0 {
+                                GameResult::Player2Win
+                            } else {
+                                GameResult::Player1Win
+                            };
+                            warn!(
+                                "Game {} turn {}: {} timed out and loses the game",
+                                self.game_id,
+                                current_turn,
+                                self.players[player].name()
+                            );
+                            outcome = Some(result);
+                            break;
+                        }
+                        TimeoutPolicy::SkipTurnWithDamage => {
+                            warn!(
+                                "Game {} turn {}: {} timed out, losing 1 health",
+                                self.game_id,
+                                current_turn,
+                                self.players[player].name()
+                            );
+                            self.damage_player(player);
+                        }
+                    }
+                }
+                Err(e) => {
+                    if is_invalid_json(&e) {
+                        let disqualification = self.disqualification(
+                            player,
+                            DisqualificationReason::InvalidJson,
+                            current_turn,
+                        );
+                        db.record_disqualification(&disqualification).await?;
+                    }
+                    return Err(e);
+                }
             }
+            self.check_stall(player, current_turn);
+            self.apply_sudden_death(player, current_turn);
 
             if let Some(result) = self.check_game_over() {
                 info!(
                     "Game {} ended early on turn {}: {:?}",
                     self.game_id, current_turn, result
                 );
-                // Update game result
-                db.update_game_result(self.matchup_id, game_db_id, result)
-                    .await?;
-                return Ok(result);
+                outcome = Some(result);
+                break;
             }
 
-            self.handle_enemy_turn().await.context("result()")?;
+            self.rng_checkpoint("enemies");
+            let enemy_turn = self.handle_enemy_turn().await;
+            self.record_latencies(db, game_db_id, current_turn).await?;
+            enemy_turn.context("result()")?;
 
             if let Some(result) = self.check_game_over() {
                 info!(
                     "Game {} ended early on turn {}: {:?}",
                     self.game_id, current_turn, result
                 );
-                // Update game result
-                db.update_game_result(self.matchup_id, game_db_id, result)
+                outcome = Some(result);
+                break;
+            }
+        }
+
+        if let Some(snapshot) = unsampled {
+            self.record_snapshot(db, game_db_id, &snapshot, &game_dir, turn_paths)
+                .await?;
+        }
+        Ok(outcome)
+    }
+
+    fn snapshot(&self, turn: i64) -> TurnSnapshot {
+        TurnSnapshot {
+            turn,
+            player_positions: self.player_positions,
+            enemy_positions: self.enemy_positions.clone(),
+            players: self.players.each_ref().map(|p| *p.player_state()),
+            enemies: self.enemies.clone(),
+        }
+    }
+
+    /// Renders `snapshot` to its turn SVG, unless only the turn records are wanted or the map is
+    /// too large, and records the turn in the database.
+    async fn record_snapshot(
+        &self,
+        db: &Database,
+        game_db_id: i64,
+        snapshot: &TurnSnapshot,
+        game_dir: &Path,
+        turn_paths: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let svg_path = game_dir.join(format!("turn_{}.svg", snapshot.turn));
+        if self.renders_turns() {
+            profile::time(Bucket::Rendering, || {
+                self.map.render_to_file(
+                    snapshot.player_positions,
+                    &snapshot.enemy_positions,
+                    &snapshot.players,
+                    &snapshot.enemies,
+                    &self.config.render,
+                    &svg_path,
+                )
+            })?;
+            turn_paths.push(svg_path.clone());
+        }
+        db.record_turn(game_db_id, snapshot.turn, svg_path.to_str().unwrap())
+            .await
+    }
+
+    /// The player acting first in a new round of two turns, under `turn_order`.
+    fn round_first_player(&mut self) -> usize {
+        match self.config.turn_order {
+            TurnOrder::Alternating => 0,
+            TurnOrder::Initiative => {
+                let [first, second] = self.players.each_ref().map(|p| p.player_state().power);
+                usize::from(second > first)
+            }
+            TurnOrder::Random => usize::from(self.rng.random_bool(0.5)),
+        }
+    }
+
+    /// Records the latencies of the calls both players answered during `turn`, with
+    /// `record_call_latencies` on.
+    async fn record_latencies(&self, db: &Database, game_db_id: i64, turn: i64) -> Result<()> {
+        for player in &self.players {
+            let latencies = player.take_latencies();
+            if self.config.record_call_latencies && !latencies.is_empty() {
+                db.record_call_latencies(game_db_id, turn, player.name(), latencies)
                     .await?;
-                return Ok(result);
             }
         }
+        Ok(())
+    }
 
+    /// Breaks a tie with a sudden-death rematch on the same map: the players keep their power but
+    /// have a single health point, and pieces are placed anew. The first player to take damage
+    /// loses, and if nobody does within `tie_rematch_turns`, the game stays a tie. Turns continue
+    /// to be numbered from `first_turn`.
+    async fn play_rematch(
+        &mut self,
+        db: &Database,
+        game_db_id: i64,
+        first_turn: i64,
+        turn_paths: &mut Vec<PathBuf>,
+    ) -> Result<GameResult> {
         info!(
-            "Game {} ended in tie after {} turns",
-            self.game_id,
-            self.config.turns_per_game,
+            "Game {} tied, playing a sudden-death rematch of up to {} turns",
+            self.game_id, self.config.tie_rematch_turns
         );
-        let result = GameResult::Tie;
-        db.update_game_result(self.matchup_id, game_db_id, result)
-            .await?;
+        db.record_rematch(self.matchup_id, self.game_id).await?;
+        for player in &mut self.players {
+            let state = player.player_state_mut();
+            state.health = 1;
+            state.max_health = 1;
+        }
+        self.place_pieces()?;
+
+        let turns = first_turn..first_turn + self.config.tie_rematch_turns as i64;
+        let result = self
+            .play_turns(db, game_db_id, turns, turn_paths)
+            .await?
+            .unwrap_or(GameResult::Tie);
+        info!("Game {} rematch ended: {:?}", self.game_id, result);
         Ok(result)
     }
 
+    /// Asks `player` for its move and carries it out, including any gamble or fight it leads to.
+    async fn play_turn(&mut self, player: usize, current_turn: i64) -> Result<()> {
+        let choices = self.get_available_moves(player);
+        if choices.internal_choices.is_empty() {
+            // Any answer would be an invalid move, so don't ask
+            return self.handle_no_moves(player, current_turn);
+        }
+        let positions = self.visible_positions(player);
+        // The opponent's stats are shown exactly when its position is
+        let opponent = positions
+            .opponent_position
+            .is_some()
+            .then(|| *self.players[1 - player].player_state());
+        let response = self.players[player]
+            .get_choices(
+                MoveChoices {
+                    choices: choices.node_types,
+                },
+                opponent,
+                positions,
+                self.game_id,
+            )
+            .await
+            .context("result()")?;
+
+        if let Some(&node_to) = choices.internal_choices.get(response.choice_index) {
+            self.handle_player_movement(player, self.player_positions[player], node_to)
+                .await
+                .context("result()")?;
+        } else {
+            // Invalid choice, damage player and skip turn
+            self.push_event(GameEvent::InvalidMove {
+                player: self.players[player].name().to_string(),
+            });
+            if self.config.choice_diagnostics {
+                warn!(
+                    "Game {} turn {}: {}",
+                    self.game_id,
+                    current_turn,
+                    invalid_choice_message(
+                        self.players[player].name(),
+                        response.choice_index,
+                        choices.internal_choices.len()
+                    )
+                );
+            }
+            self.damage_player(player);
+        }
+        Ok(())
+    }
+
+    fn handle_no_moves(&mut self, player: usize, current_turn: i64) -> Result<()> {
+        debug!(
+            "Game {} turn {}: {} has no moves from node {}, applying {:?}",
+            self.game_id,
+            current_turn,
+            self.players[player].name(),
+            self.player_positions[player].index(),
+            self.config.no_moves_policy
+        );
+        if self.config.no_moves_policy == NoMovesPolicy::Teleport {
+            self.player_positions[player] =
+                self.get_random_empty_node().context("handle_no_moves()")?;
+            self.push_teleport(player);
+        }
+        Ok(())
+    }
+
+    /// A disqualification of `player` in this game for `reason`, at `turn`.
+    fn disqualification(
+        &self,
+        player: usize,
+        reason: DisqualificationReason,
+        turn: i64,
+    ) -> Disqualification {
+        Disqualification {
+            submission: self.players[player].name().to_string(),
+            matchup_id: self.matchup_id,
+            reason,
+            game_number: Some(self.game_id),
+            turn_number: Some(turn),
+        }
+    }
+
+    /// What each player is penalized for in the events so far: its first timeout, and the invalid
+    /// move taking it past `max_invalid_moves`.
+    fn disqualifications(&self) -> Vec<Disqualification> {
+        let mut disqualifications = Vec::new();
+        for player in 0..2 {
+            let name = self.players[player].name();
+            let timeout = self.events.iter().find_map(|(turn, event)| match event {
+                GameEvent::Timeout { player } if player == name => Some(*turn),
+                _ => None,
+            });
+            if let Some(turn) = timeout {
+                disqualifications.push(self.disqualification(
+                    player,
+                    DisqualificationReason::Timeout,
+                    turn,
+                ));
+            }
+            let too_many_invalid_moves = self
+                .events
+                .iter()
+                .filter_map(|(turn, event)| match event {
+                    GameEvent::InvalidMove { player } if player == name => Some(*turn),
+                    _ => None,
+                })
+                .nth(self.config.max_invalid_moves as usize);
+            if let Some(turn) = too_many_invalid_moves {
+                disqualifications.push(self.disqualification(
+                    player,
+                    DisqualificationReason::TooManyInvalidMoves,
+                    turn,
+                ));
+            }
+        }
+        disqualifications
+    }
+
+    fn push_event(&mut self, event: GameEvent) {
+        self.events.push((self.turn, event));
+    }
+
+    fn push_teleport(&mut self, player: usize) {
+        self.push_event(GameEvent::Teleport {
+            player: self.players[player].name().to_string(),
+            to: self.player_positions[player].index(),
+        });
+    }
+
+    /// Text summary of the current state: both players, then the board with everyone's position.
+    pub fn render_text(&self) -> String {
+        let mut text = String::new();
+        for (tag, player, position) in [
+            ("A", &self.players[0], self.player_positions[0]),
+            ("B", &self.players[1], self.player_positions[1]),
+        ] {
+            let state = player.player_state();
+            text.push_str(&format!(
+                "{} = {} at node {} (health {}/{}, power {})\n",
+                tag,
+                player.name(),
+                position.index(),
+                state.health,
+                state.max_health,
+                state.power
+            ));
+        }
+        text.push_str(&self.map.render_to_text(
+            self.player_positions,
+            &self.enemy_positions,
+            &[
+                *self.players[0].player_state(),
+                *self.players[1].player_state(),
+            ],
+            &self.enemies,
+        ));
+        text
+    }
+
     fn check_game_over(&self) -> Option<GameResult> {
-        if self.players.iter().any(|p| p.player_state().health <= 0) {
-            if self.players[0].player_state().health <= 0 {
+        if self.players.iter().any(|p| p.player_state().health == 0) {
+            if self.players[0].player_state().health == 0 {
                 Some(GameResult::Player2Win)
-            } else if self.players[1].player_state().health <= 0 {
+            } else if self.players[1].player_state().health == 0 {
                 Some(GameResult::Player1Win)
             } else {
                 None
@@ -201,32 +1170,78 @@ impl Game {
         }
     }
 
-    async fn handle_fight(&mut self, player: usize, target: FightTarget) -> Result<bool> {
-        let player_power = self.players[player].player_state().power;
-        let player_name = self.players[player].name();
+    /// The positions `player` gets to see under the configured `Visibility`.
+    fn visible_positions(&self, player: usize) -> VisiblePositions {
+        if self.config.visibility == Visibility::SelfOnly {
+            return VisiblePositions::default();
+        }
+        let position = self.player_positions[player];
+        let adjacent = self.map.get_outgoing_nodes(position);
+        let is_visible = |node: &NodeIndex| {
+            self.config.visibility == Visibility::Full || adjacent.contains(node)
+        };
+
+        VisiblePositions {
+            position: Some(position.index()),
+            opponent_position: Some(self.player_positions[1 - player])
+                .filter(is_visible)
+                .map(NodeIndex::index),
+            enemy_positions: self
+                .enemy_positions
+                .iter()
+                .filter(|node| is_visible(node))
+                .map(|node| node.index())
+                .collect(),
+        }
+    }
+
+    /// Fights `target`, which `player` walked into if `player_attacks`, or which walked into
+    /// `player` otherwise, returning whether `player` won.
+    async fn handle_fight(
+        &mut self,
+        player: usize,
+        target: FightTarget,
+        player_attacks: bool,
+    ) -> Result<bool> {
+        let player_state = *self.players[player].player_state();
+        let player_power = player_state.power;
+        let player_name = self.players[player].name().to_string();
 
-        let (enemy_power, target_name) = match target {
+        let (enemy_state, target_name) = match target {
             FightTarget::Opponent => {
                 let other_player = 1 - player;
                 (
-                    self.players[other_player].player_state().power,
+                    *self.players[other_player].player_state(),
                     self.players[other_player].name().to_string(),
                 )
             }
-            FightTarget::Enemy(enemy_idx) => (
-                self.enemies[enemy_idx].power,
-                format!("Enemy {}", enemy_idx),
-            ),
+            FightTarget::Enemy(enemy_idx) => {
+                (self.enemies[enemy_idx], format!("Enemy {}", enemy_idx))
+            }
         };
+        let enemy_power = enemy_state.power;
 
         debug!(
             "Game {} Fight: {} ({} power) vs {} ({} power)",
             self.game_id, player_name, player_power, target_name, enemy_power
         );
 
-        let player_wins = self
-            .rng
-            .random_ratio(player_power, player_power + enemy_power);
+        self.rng_checkpoint(format_args!("fight {} vs {}", player_name, target_name));
+        let health_factor = self.config.fight_health_factor;
+        let player_wins = match self.config.fight_model {
+            FightModel::Deterministic if player_attacks => {
+                attacker_wins(&player_state, &enemy_state, health_factor)
+            }
+            FightModel::Deterministic => !attacker_wins(&enemy_state, &player_state, health_factor),
+            FightModel::Probabilistic if health_factor == 0.0 && player_power + enemy_power > 0 => {
+                self.rng
+                    .random_ratio(player_power, player_power + enemy_power)
+            }
+            FightModel::Probabilistic => {
+                let chance = fight_win_chance(&player_state, &enemy_state, health_factor);
+                self.rng.random_bool(chance)
+            }
+        };
 
         if player_wins {
             debug!(
@@ -241,9 +1256,11 @@ impl Game {
                         self.get_random_empty_node().context("handle_fight()")?;
                 }
                 FightTarget::Enemy(enemy_idx) => {
-                    let power_gain = enemy_power / 2;
-                    self.players[player].player_state_mut().power += power_gain;
-                    self.generate_enemy(enemy_idx);
+                    let power_gain =
+                        enemy_reward(enemy_power, player_power, self.config.reward_scaling);
+                    let state = self.players[player].player_state_mut();
+                    state.power = (state.power + power_gain).max(self.config.min_power);
+                    self.generate_enemy(enemy_idx).context("handle_fight()")?;
                 }
             }
         } else {
@@ -256,6 +1273,11 @@ impl Game {
                 self.get_random_empty_node().context("handle_fight()")?;
         }
 
+        self.push_event(GameEvent::Fight {
+            player: player_name,
+            opponent: target_name,
+            won: player_wins,
+        });
         Ok(player_wins)
     }
 
@@ -264,13 +1286,41 @@ impl Game {
         debug!("Game {} Player {} gambling", self.game_id, player_name);
 
         let response = self.players[player].get_gamble_choice(self.game_id).await?;
+        let before = *self.players[player].player_state();
+        self.rng_checkpoint(format_args!("gamble {}", player_name));
         let roll = self.rng.random::<f64>();
         let player_state = self.players[player].player_state_mut();
 
+        if response != GambleResponse::Skip && self.config.gamble_cost > 0 {
+            let cost = self.config.gamble_cost;
+            let paid_from = match self.config.gamble_cost_stat {
+                GambleCostStat::Health => &mut player_state.health,
+                GambleCostStat::Power => &mut player_state.power,
+            };
+            *paid_from = paid_from.saturating_sub(cost);
+            player_state.power = player_state.power.max(self.config.min_power);
+            debug!(
+                "Game {} Player {} paid {} {:?} to gamble",
+                self.game_id, player_name, cost, self.config.gamble_cost_stat
+            );
+            // A lethal cost ends the game, however the gamble would have gone
+            if player_state.health == 0 {
+                let after = *player_state;
+                self.push_event(GameEvent::Gamble {
+                    player: player_name,
+                    stat: response,
+                    before,
+                    after,
+                });
+                return Ok(());
+            }
+        }
+
         let value = match response {
-            GambleChoices::Power => &mut player_state.power,
-            GambleChoices::Health => &mut player_state.health,
-            GambleChoices::Skip => {
+            GambleResponse::Power => &mut player_state.power,
+            GambleResponse::Health => &mut player_state.health,
+            GambleResponse::MaxHealth => &mut player_state.max_health,
+            GambleResponse::Skip => {
                 debug!(
                     "Game {} Player {} skipped gambling",
                     self.game_id, player_name
@@ -286,37 +1336,51 @@ impl Game {
             _ => *value = value.saturating_sub(1), // 40% chance to lose 1
         }
 
-        // cap health if it was gambled
-        if matches!(response, GambleChoices::Health) {
+        // cap health if it or its maximum was gambled
+        if matches!(response, GambleResponse::Health | GambleResponse::MaxHealth) {
             player_state.health = player_state.health.min(player_state.max_health);
         }
 
         match response {
-            GambleChoices::Power => {
+            GambleResponse::Power => {
                 let old_power = player_state.power;
                 debug!(
                     "Game {} Player {} power gamble: {} -> {}",
                     self.game_id, player_name, old_power, player_state.power
                 );
             }
-            GambleChoices::Health => {
+            GambleResponse::Health => {
                 let old_health = player_state.health;
                 debug!(
                     "Game {} Player {} health gamble: {} -> {}",
                     self.game_id, player_name, old_health, player_state.health
                 );
             }
+            GambleResponse::MaxHealth => {
+                debug!(
+                    "Game {} Player {} max health gamble: now {}/{}",
+                    self.game_id, player_name, player_state.health, player_state.max_health
+                );
+            }
             _ => {}
         }
 
+        player_state.power = player_state.power.max(self.config.min_power);
+        let after = *player_state;
+        self.push_event(GameEvent::Gamble {
+            player: player_name,
+            stat: response,
+            before,
+            after,
+        });
         Ok(())
     }
 
-    fn heal_player(&mut self, player: usize) {
+    fn heal_player(&mut self, player: usize, amount: u32) {
         let (old_health, new_health) = {
             let player_state = self.players[player].player_state_mut();
             let old_health = player_state.health;
-            player_state.health = (player_state.health + 1).min(player_state.max_health);
+            player_state.health = (player_state.health + amount).min(player_state.max_health);
             (old_health, player_state.health)
         };
         debug!(
@@ -328,11 +1392,67 @@ impl Game {
         );
     }
 
+    /// With `stall_penalty_turns` set, deals `stall_damage` to a player that ended that many
+    /// turns in a row on a node it already ended one of its previous `stall_penalty_turns` turns
+    /// on, such as one moving back and forth between two nodes.
+    fn check_stall(&mut self, player: usize, current_turn: i64) {
+        let Some(window) = self.config.stall_penalty_turns.filter(|&turns| turns > 0) else {
+            return;
+        };
+        let position = self.player_positions[player];
+        let recent = &mut self.recent_positions[player];
+        if recent.contains(&position) {
+            self.stalled_turns[player] += 1;
+        } else {
+            self.stalled_turns[player] = 0;
+        }
+        recent.push_back(position);
+        if recent.len() > window as usize {
+            recent.pop_front();
+        }
+
+        if self.stalled_turns[player] >= window {
+            self.stalled_turns[player] = 0;
+            info!(
+                "Game {} turn {}: {} made no progress for {} turns, losing {} health",
+                self.game_id,
+                current_turn,
+                self.players[player].name(),
+                window,
+                self.config.stall_damage
+            );
+            self.damage_player_by(player, self.config.stall_damage);
+        }
+    }
+
+    /// With `sudden_death_after` set, deals 1 damage to a player at the end of each of its turns
+    /// from that turn on, so games can't drag on to the turn limit. Rematches, already played at
+    /// 1 health, are left alone.
+    fn apply_sudden_death(&mut self, player: usize, current_turn: i64) {
+        let Some(after) = self.config.sudden_death_after else {
+            return;
+        };
+        if current_turn < after as i64 || current_turn >= self.config.turns_per_game as i64 {
+            return;
+        }
+        debug!(
+            "Game {} turn {}: sudden death, {} loses 1 health",
+            self.game_id,
+            current_turn,
+            self.players[player].name()
+        );
+        self.damage_player(player);
+    }
+
     fn damage_player(&mut self, player: usize) {
+        self.damage_player_by(player, 1);
+    }
+
+    fn damage_player_by(&mut self, player: usize, damage: u32) {
         let (old_health, new_health) = {
             let player_state = self.players[player].player_state_mut();
             let old_health = player_state.health;
-            player_state.health = player_state.health.saturating_sub(1);
+            player_state.health = player_state.health.saturating_sub(damage);
             (old_health, player_state.health)
         };
         debug!(
@@ -345,22 +1465,37 @@ impl Game {
     }
 
     async fn handle_enemy_turn(&mut self) -> Result<()> {
+        let move_chance = self.config.enemy_move_chance;
         for i in 0..self.enemies.len() {
             let current_pos = self.enemy_positions[i];
-            let blocked = [self.enemy_positions[0], self.enemy_positions[1]];
+            let blocked = self.enemy_positions.clone();
 
-            let moves = self
-                .map
-                .shuffle_available_moves(current_pos, &blocked, &mut self.rng);
+            let new_pos = match self.config.enemy_movement {
+                EnemyMovement::Random => {
+                    // Only rolled for calmer enemies, so games with the default keep their draws
+                    if move_chance < 1.0 && !self.rng.random_bool(move_chance.max(0.0)) {
+                        continue;
+                    }
+                    self.map
+                        .shuffle_available_moves(current_pos, &blocked, &mut self.rng)
+                        .first()
+                        .copied()
+                }
+                EnemyMovement::LowestIndex => self
+                    .map
+                    .get_available_moves(current_pos, &blocked)
+                    .into_iter()
+                    .min(),
+            };
 
-            if let Some(new_pos) = moves.first() {
-                self.enemy_positions[i] = *new_pos;
+            if let Some(new_pos) = new_pos {
+                self.enemy_positions[i] = new_pos;
 
                 // Check if landed on player
-                let player_positions = self.player_positions.clone();
+                let player_positions = self.player_positions;
                 for (player_idx, &player_pos) in player_positions.iter().enumerate() {
-                    if player_pos == *new_pos {
-                        self.handle_fight(player_idx, FightTarget::Enemy(i))
+                    if player_pos == new_pos {
+                        self.handle_fight(player_idx, FightTarget::Enemy(i), false)
                             .await
                             .context("handle_enemy_turn()")?;
                     }
@@ -387,42 +1522,40 @@ impl Game {
         None
     }
 
+    /// Nodes taken by a player or an enemy.
+    fn occupied_nodes(&self) -> Vec<NodeIndex> {
+        self.player_positions
+            .iter()
+            .chain(&self.enemy_positions)
+            .copied()
+            .collect()
+    }
+
     fn get_random_empty_node(&mut self) -> Result<NodeIndex> {
-        let blocked = [
-            self.player_positions[0],
-            self.player_positions[1],
-            self.enemy_positions[0],
-            self.enemy_positions[1],
-        ];
+        let blocked = self.occupied_nodes();
         self.map
             .get_random_empty_node(&blocked, &mut self.rng)
             .ok_or(anyhow!("get_random_empty_node: No empty nodes"))
     }
 
     // Helper method to create/recreate a single enemy
-    fn generate_enemy(&mut self, index: usize) {
+    fn generate_enemy(&mut self, index: usize) -> Result<()> {
         self.enemies[index] = PlayerState {
             health: 1,
             max_health: 1,
             power: self.rng.random_range(2..=7),
         };
-        self.enemy_positions[index] = self.get_random_empty_node().unwrap_or_default();
+        self.enemy_positions[index] = self
+            .get_random_empty_node()
+            .with_context(|| format!("No empty node for enemy {}", index))?;
+        Ok(())
     }
 
     fn get_available_moves(&mut self, player: usize) -> WrappedChoices {
         let current_pos = self.player_positions[player];
-        let targets = self.map.get_outgoing_edges(current_pos);
-
-        let mut node_types = Vec::new();
-        let mut internal_choices = Vec::new();
-
-        for target in targets {
-            let target = target.target();
-            if let Some(node_type) = self.map.get_node_type(target) {
-                node_types.push(node_type);
-                internal_choices.push(target);
-            }
-        }
+        // Every node has a type, so the choices line up with their types
+        let node_types = self.map.node_types_adjacent(current_pos);
+        let internal_choices = self.map.get_outgoing_nodes(current_pos);
 
         WrappedChoices {
             node_types,
@@ -431,9 +1564,15 @@ impl Game {
     }
 
     async fn handle_node_effect(&mut self, player: usize, node_type: MapNodeType) -> Result<bool> {
+        self.node_visits[player][node_type_index(node_type)] += 1;
         match node_type {
             MapNodeType::Healing => {
-                self.heal_player(player);
+                let amount = if self.map.is_greater_healing(self.player_positions[player]) {
+                    self.config.greater_healing_amount
+                } else {
+                    1
+                };
+                self.heal_player(player, amount);
                 Ok(false)
             }
             MapNodeType::Gamble => {
@@ -441,6 +1580,19 @@ impl Game {
                 Ok(false)
             }
             MapNodeType::Teleport => {
+                // For `teleport_cooldown` turns after its last teleport, the node acts as normal
+                let cooldown = self.config.teleport_cooldown as i64;
+                let cooling_down = cooldown > 0
+                    && self.last_teleports[player].is_some_and(|turn| self.turn - turn <= cooldown);
+                if cooling_down {
+                    debug!(
+                        "Game {} Player {} teleport is cooling down",
+                        self.game_id,
+                        self.players[player].name()
+                    );
+                    return Ok(false);
+                }
+                self.last_teleports[player] = Some(self.turn);
                 self.player_positions[player] = self
                     .get_random_empty_node()
                     .context("handle_node_effect()")?;
@@ -457,7 +1609,7 @@ impl Game {
     ) -> Result<()> {
         match fight_target {
             FightTarget::Opponent => {
-                self.handle_fight(player, fight_target).await?;
+                self.handle_fight(player, fight_target, true).await?;
                 Ok(())
             }
             FightTarget::Enemy(enemy) => {
@@ -469,7 +1621,7 @@ impl Game {
 
                 match response {
                     FightChoices::Fight => {
-                        self.handle_fight(player, fight_target)
+                        self.handle_fight(player, fight_target, true)
                             .await
                             .context("handle_combat_encounter()")?;
                         Ok(())
@@ -484,13 +1636,10 @@ impl Game {
     }
 
     async fn handle_flee(&mut self, player: usize) -> Result<()> {
+        self.rng
+            .set_context(format_args!("flee {}", self.players[player].name()));
         let current_pos = self.player_positions[player];
-        let blocked = [
-            self.player_positions[0],
-            self.player_positions[1],
-            self.enemy_positions[0],
-            self.enemy_positions[1],
-        ];
+        let blocked = self.occupied_nodes();
 
         let moves = self
             .map
@@ -515,21 +1664,23 @@ impl Game {
         self.player_positions[player] = node_to;
 
         // Handle node effects first
-        if let Some(node_type) = self.map.get_node_type(node_to) {
-            if self
+        if let Some(node_type) = self.map.get_node_type(node_to)
+            && self
                 .handle_node_effect(player, node_type)
                 .await
                 .context("handle_regular_move()")?
-            {
-                // If true was returned, this was a teleport
-                let new_pos = self
-                    .get_random_empty_node()
-                    .context("handle_regular_move()")?;
-                return self
-                    .handle_escape_move(player, node_to, new_pos)
-                    .await
-                    .context("handle_regular_move()");
-            }
+        {
+            // If true was returned, this was a teleport
+            let new_pos = self
+                .get_random_empty_node()
+                .context("handle_regular_move()")?;
+            // Record where the player actually lands, before any effect there
+            self.player_positions[player] = new_pos;
+            self.push_teleport(player);
+            return self
+                .handle_escape_move(player, node_to, new_pos)
+                .await
+                .context("handle_regular_move()");
         }
 
         // Then check for fights
@@ -552,13 +1703,13 @@ impl Game {
         self.player_positions[player] = node_to;
 
         // Handle node effects, but ignore teleport results since we don't chain escapes
-        if let Some(node_type) = self.map.get_node_type(node_to) {
-            if node_type != MapNodeType::Teleport {
-                let _ = self
-                    .handle_node_effect(player, node_type)
-                    .await
-                    .context("handle_escape_move()")?;
-            }
+        if let Some(node_type) = self.map.get_node_type(node_to)
+            && node_type != MapNodeType::Teleport
+        {
+            let _ = self
+                .handle_node_effect(player, node_type)
+                .await
+                .context("handle_escape_move()")?;
         }
 
         Ok(())
@@ -577,6 +1728,11 @@ impl Game {
             node_from,
             node_to
         );
+        self.push_event(GameEvent::Move {
+            player: self.players[player].name().to_string(),
+            from: node_from.index(),
+            to: node_to.index(),
+        });
         self.handle_regular_move(player, node_from, node_to)
             .await
             .context("handle_player_movement()")
@@ -0,0 +1,1483 @@
+#![cfg(test)]
+
+use std::{
+    collections::HashSet,
+    future::pending,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use petgraph::graph::{DiGraph, NodeIndex};
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+use rplcs_events::tournament_1::{
+    ChoiceResponse, FightChoices, FightInfo, MapNodeType, PlayerState,
+};
+use rusqlite::Connection;
+use tokio::time::timeout;
+
+use crate::{
+    Config,
+    bots::built_in_bots,
+    db::{Database, DisqualificationReason},
+    game_map::{GameMap, node_type_index},
+    recording_rng::{Draw, DrawKind},
+    submission::{
+        ChoicesRequest, GambleResponse, Player, STARTING_STATE, Submission, VisiblePositions,
+    },
+};
+
+use super::{
+    DEFAULT_ENEMIES, EnemyMovement, FightModel, FightTarget, GambleCostStat, Game, GameBuilder,
+    GameEvent, GameResult, NoMovesPolicy, TieBreak, TimeoutPolicy, TurnOrder, Visibility,
+    enemy_reward, fight_win_chance, invalid_choice_message, is_timeout,
+};
+
+fn state(health: u32, power: u32) -> PlayerState {
+    PlayerState {
+        health,
+        max_health: 3,
+        power,
+    }
+}
+
+#[test]
+fn test_no_tie_break_keeps_tie() {
+    assert_eq!(
+        TieBreak::None.decide(&state(3, 5), &state(1, 9)),
+        GameResult::Tie
+    );
+}
+
+#[test]
+fn test_health_tie_break() {
+    assert_eq!(
+        TieBreak::Health.decide(&state(3, 1), &state(1, 9)),
+        GameResult::Player1Win
+    );
+    assert_eq!(
+        TieBreak::Health.decide(&state(1, 9), &state(2, 1)),
+        GameResult::Player2Win
+    );
+    assert_eq!(
+        TieBreak::Health.decide(&state(2, 9), &state(2, 1)),
+        GameResult::Tie
+    );
+}
+
+#[test]
+fn test_winner_submission_follows_move_order() {
+    assert_eq!(
+        GameResult::Player1Win.to_winner_submission("alice", "bob"),
+        Some("alice")
+    );
+    assert_eq!(
+        GameResult::Player1Win.to_winner_submission("bob", "alice"),
+        Some("bob")
+    );
+    assert_eq!(
+        GameResult::Player2Win.to_winner_submission("alice", "bob"),
+        Some("bob")
+    );
+    assert_eq!(
+        GameResult::Player2Win.to_winner_submission("bob", "alice"),
+        Some("alice")
+    );
+    assert_eq!(GameResult::Tie.to_winner_submission("alice", "bob"), None);
+    assert_eq!(GameResult::Tie.to_winner_submission("bob", "alice"), None);
+}
+
+#[test]
+fn test_power_tie_break() {
+    assert_eq!(
+        TieBreak::Power.decide(&state(1, 9), &state(3, 5)),
+        GameResult::Player1Win
+    );
+    assert_eq!(
+        TieBreak::Power.decide(&state(3, 5), &state(1, 9)),
+        GameResult::Player2Win
+    );
+    assert_eq!(
+        TieBreak::Power.decide(&state(3, 5), &state(1, 5)),
+        GameResult::Tie
+    );
+}
+
+#[test]
+fn test_health_then_power_tie_break() {
+    assert_eq!(
+        TieBreak::HealthThenPower.decide(&state(3, 1), &state(2, 9)),
+        GameResult::Player1Win
+    );
+    assert_eq!(
+        TieBreak::HealthThenPower.decide(&state(2, 5), &state(2, 7)),
+        GameResult::Player2Win
+    );
+    assert_eq!(
+        TieBreak::HealthThenPower.decide(&state(2, 5), &state(2, 5)),
+        GameResult::Tie
+    );
+}
+
+#[test]
+fn test_text_render_shows_both_player_positions() {
+    let mut bots = built_in_bots(None).into_iter();
+    let (name_a, bot_a) = bots.next().unwrap();
+    let (name_b, bot_b) = bots.next().unwrap();
+    let mut game = GameBuilder::new(
+        Submission::new(&name_a, bot_a),
+        Submission::new(&name_b, bot_b),
+    )
+    .build()
+    .unwrap();
+    game.players[1].player_state_mut().health = 1;
+    let [a, b] = game.player_positions;
+
+    let text = game.render_text();
+
+    assert!(text.contains(&format!(
+        "A = {} at node {} (health 3/3, power 5)",
+        name_a,
+        a.index()
+    )));
+    assert!(text.contains(&format!(
+        "B = {} at node {} (health 1/3, power 5)",
+        name_b,
+        b.index()
+    )));
+    let node_line = |index: usize| {
+        text.lines()
+            .find(|line| line.trim_start().starts_with(&format!("{} ", index)))
+            .unwrap()
+            .to_string()
+    };
+    assert!(node_line(a.index()).contains("A 3/3 5"));
+    assert!(node_line(b.index()).contains("B 1/3 5"));
+}
+
+/// Messages logged by the `game` module, captured by a logger installed on first use.
+fn captured_game_logs() -> &'static Mutex<Vec<String>> {
+    struct Capture(Mutex<Vec<String>>);
+
+    impl log::Log for Capture {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == module_path!().trim_end_matches("::tests")
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: OnceLock<&'static Capture> = OnceLock::new();
+    let capture = CAPTURE.get_or_init(|| {
+        let capture: &'static Capture = Box::leak(Box::new(Capture(Mutex::new(Vec::new()))));
+        log::set_logger(capture).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+        capture
+    });
+    &capture.0
+}
+
+#[test]
+fn test_map_is_logged_at_debug_level() {
+    let logs = captured_game_logs();
+    let mut bots = built_in_bots(None).into_iter();
+    let (name_a, bot_a) = bots.next().unwrap();
+    let (name_b, bot_b) = bots.next().unwrap();
+    let game = GameBuilder::new(
+        Submission::new(&name_a, bot_a),
+        Submission::new(&name_b, bot_b),
+    )
+    .ids(1966, 0)
+    .build()
+    .unwrap();
+
+    let logs = logs.lock().unwrap();
+    let map_log = logs
+        .iter()
+        .find(|message| message.starts_with("Map of game 1966:"))
+        .unwrap();
+    let lines: Vec<_> = map_log.lines().skip(1).collect();
+    assert_eq!(lines.len(), game.map.node_count());
+    for (index, node) in game.map.node_indices().into_iter().enumerate() {
+        let (node_type, targets) = lines[index].split_once(" -> ").unwrap();
+        assert!(node_type.starts_with(&format!("{} ", node.index())));
+        assert_eq!(
+            targets.split(',').count(),
+            game.map.get_outgoing_nodes(node).len()
+        );
+    }
+}
+
+#[test]
+fn test_health_factor_lowers_win_chance_of_wounded_player() {
+    let healthy = state(3, 5);
+    let wounded = state(1, 5);
+
+    // Without the factor only power matters
+    assert_eq!(fight_win_chance(&wounded, &healthy, 0.0), 0.5);
+
+    let wounded_chance = fight_win_chance(&wounded, &healthy, 0.5);
+    assert!(wounded_chance < 0.5);
+    assert!((wounded_chance + fight_win_chance(&healthy, &wounded, 0.5) - 1.0).abs() < 1e-9);
+
+    // A stronger factor hurts more, and equal health stays even
+    assert!(fight_win_chance(&wounded, &healthy, 1.0) < wounded_chance);
+    assert_eq!(fight_win_chance(&wounded, &wounded, 1.0), 0.5);
+}
+
+/// Whether player A, with `power` against 5 power, wins a deterministic fight against `target`,
+/// attacking it or attacked by it, on every one of a few seeds.
+async fn deterministic_fight(power: u32, target: FightTarget, player_attacks: bool) -> bool {
+    let mut outcomes = HashSet::new();
+    for seed in 0..10 {
+        let config = Config {
+            fight_model: FightModel::Deterministic,
+            ..Config::default()
+        };
+        let mut game = game_on_with(ring_map(&[MapNodeType::Normal; 6]), seed, config).unwrap();
+        game.players[0].player_state_mut().power = power;
+        game.players[1].player_state_mut().power = 5;
+        game.enemies[0].power = 5;
+        outcomes.insert(game.handle_fight(0, target, player_attacks).await.unwrap());
+    }
+    assert_eq!(outcomes.len(), 1, "the outcome depends on the seed");
+    outcomes.into_iter().next().unwrap()
+}
+
+#[tokio::test]
+async fn test_deterministic_fights_go_to_the_stronger_side() {
+    for target in [FightTarget::Opponent, FightTarget::Enemy(0)] {
+        for player_attacks in [true, false] {
+            assert!(deterministic_fight(6, target, player_attacks).await);
+            assert!(!deterministic_fight(4, target, player_attacks).await);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_deterministic_fights_on_equal_power_go_to_the_defender() {
+    for target in [FightTarget::Opponent, FightTarget::Enemy(0)] {
+        assert!(!deterministic_fight(5, target, true).await);
+        assert!(deterministic_fight(5, target, false).await);
+    }
+}
+
+#[test]
+fn test_reward_scaling_shrinks_rewards_of_weak_enemies() {
+    // Without scaling every enemy is worth half its power
+    assert_eq!(enemy_reward(2, 6, 0.0), 1);
+    assert_eq!(enemy_reward(7, 6, 0.0), 3);
+
+    // A weak enemy is worth less, one at least as strong as the player keeps the full reward
+    assert_eq!(enemy_reward(2, 6, 1.0), 0);
+    assert_eq!(enemy_reward(7, 6, 1.0), 3);
+    assert_eq!(enemy_reward(4, 6, 0.5), 1);
+    assert_eq!(enemy_reward(4, 6, 2.0), 0);
+}
+
+#[tokio::test]
+async fn test_turns_are_recorded_without_rendering() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 5,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let mut bots = built_in_bots(None).into_iter();
+    let (_, bot_a) = bots.next().unwrap();
+    let (_, bot_b) = bots.next().unwrap();
+    let mut game = GameBuilder::new(Submission::new("a", bot_a), Submission::new("b", bot_b))
+        .ids(0, matchup_id)
+        .config(config)
+        .build()
+        .unwrap();
+    game.result(&db).await.unwrap();
+
+    assert!(!results_dir.path().join("visualizations").exists());
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let svg_paths: Vec<String> = conn
+        .prepare("SELECT svg_path FROM turns ORDER BY turn_number")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(!svg_paths.is_empty());
+    let expected = format!("visualizations/{matchup_id}_a_vs_b/game_0/turn_0.svg");
+    assert!(svg_paths[0].ends_with(&expected), "{}", svg_paths[0]);
+}
+
+#[tokio::test]
+async fn test_map_above_max_render_nodes_is_not_rendered() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 5,
+        max_render_nodes: Some(5),
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(Gambler(GambleResponse::MaxHealth))),
+        Submission::new("b", Arc::new(Gambler(GambleResponse::MaxHealth))),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(0)
+    .map(ring_map(&[MapNodeType::Normal; 6]))
+    .build()
+    .unwrap();
+    game.result(&db).await.unwrap();
+
+    assert!(!results_dir.path().join("visualizations").exists());
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let winner: String = conn
+        .query_row("SELECT winner FROM games", [], |row| row.get(0))
+        .unwrap();
+    assert_ne!(winner, "pending");
+}
+
+#[tokio::test]
+async fn test_only_every_nth_turn_and_the_last_are_rendered() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 23,
+        render_every_nth_turn: 5,
+        enemy_move_chance: 0.0,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    // Fleeing players on a ring of normal nodes, with enemies that stay put, never get hurt
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(Gambler(GambleResponse::Skip))),
+        Submission::new("b", Arc::new(Gambler(GambleResponse::Skip))),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(0)
+    .map(ring_map(&[MapNodeType::Normal; 8]))
+    .build()
+    .unwrap();
+    game.result(&db).await.unwrap();
+
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let turns: Vec<i64> = conn
+        .prepare("SELECT turn_number FROM turns ORDER BY turn_number")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(turns, [0, 5, 10, 15, 20, 22]);
+    let game_dir = results_dir
+        .path()
+        .join(format!("visualizations/{matchup_id}_a_vs_b/game_0"));
+    for turn in 0..23 {
+        let rendered = game_dir.join(format!("turn_{turn}.svg")).exists();
+        assert_eq!(rendered, turns.contains(&turn), "turn {turn}");
+    }
+}
+
+#[test]
+fn test_invalid_choice_message_names_index_and_available_moves() {
+    assert_eq!(
+        invalid_choice_message("my_bot", 4, 3),
+        "my_bot returned choice_index 4 but only 3 moves were available (valid indices are 0 to 2), losing 1 health"
+    );
+    assert_eq!(
+        invalid_choice_message("my_bot", 0, 0),
+        "my_bot returned choice_index 0 but no moves were available, losing 1 health"
+    );
+}
+
+/// A ring of nodes of the given types.
+fn ring_map(node_types: &[MapNodeType]) -> GameMap {
+    let mut graph = DiGraph::new();
+    let nodes: Vec<_> = node_types.iter().map(|&t| graph.add_node(t)).collect();
+    for (i, &node) in nodes.iter().enumerate() {
+        graph.add_edge(node, nodes[(i + 1) % nodes.len()], ());
+    }
+    GameMap::from_graph(graph)
+}
+
+fn game_on(map: GameMap, seed: u64) -> anyhow::Result<Game> {
+    game_on_with(map, seed, Config::default())
+}
+
+fn game_on_with(map: GameMap, seed: u64, config: Config) -> anyhow::Result<Game> {
+    let mut bots = built_in_bots(None).into_iter();
+    let (name_a, bot_a) = bots.next().unwrap();
+    let (name_b, bot_b) = bots.next().unwrap();
+    GameBuilder::new(
+        Submission::new(&name_a, bot_a),
+        Submission::new(&name_b, bot_b),
+    )
+    .config(config)
+    .seed(seed as i64)
+    .map(map)
+    .build()
+}
+
+#[test]
+fn test_builder_rejects_positions_off_the_map_or_shared() {
+    let build = |players: [usize; 2], enemies: [usize; 2]| {
+        let mut bots = built_in_bots(None).into_iter();
+        let (_, bot_a) = bots.next().unwrap();
+        let (_, bot_b) = bots.next().unwrap();
+        GameBuilder::new(Submission::new("a", bot_a), Submission::new("b", bot_b))
+            .seed(0)
+            .map(ring_map(&[MapNodeType::Normal; 6]))
+            .positions(
+                players.map(NodeIndex::new),
+                enemies.map(NodeIndex::new).to_vec(),
+            )
+            .build()
+    };
+
+    assert!(build([0, 3], [4, 5]).is_ok());
+    let Err(error) = build([0, 6], [4, 5]) else {
+        panic!("node 6 is not on a map of 6 nodes");
+    };
+    assert!(error.to_string().contains("not on the map"), "{error}");
+    let Err(error) = build([0, 3], [3, 5]) else {
+        panic!("an enemy can't share the node of player B");
+    };
+    assert!(error.to_string().contains("node 3"), "{error}");
+}
+
+#[test]
+fn test_completion_message_names_seed_and_render_command() {
+    let mut game = game_on(ring_map(&[MapNodeType::Normal; 4]), 0).unwrap();
+    game.seed = -42;
+
+    let message = game.completion_message(GameResult::Tie);
+    assert!(message.contains("seed -42"), "{message}");
+    assert!(message.contains("render-map --seed -42"), "{message}");
+
+    game.config.reproduce_hints = false;
+    assert!(
+        !game
+            .completion_message(GameResult::Tie)
+            .contains("render-map")
+    );
+}
+
+#[test]
+fn test_players_and_enemies_start_on_distinct_nodes() {
+    for seed in 0..50 {
+        // Exactly enough room, so every node ends up occupied, node 0 included
+        let game = game_on(ring_map(&[MapNodeType::Normal; 4]), seed).unwrap();
+
+        let occupied: HashSet<_> = game
+            .player_positions
+            .iter()
+            .chain(&game.enemy_positions)
+            .copied()
+            .collect();
+        assert_eq!(occupied.len(), 4, "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_players_start_at_least_min_start_distance_apart() {
+    for seed in 0..50 {
+        let config = Config {
+            min_start_distance: 4,
+            ..Config::default()
+        };
+        let game = game_on_with(ring_map(&[MapNodeType::Normal; 10]), seed, config).unwrap();
+
+        // On a one-way ring of 10, the closer player is at most 5 moves behind the other
+        let [a, b] = game.player_positions;
+        let ahead = (b.index() + 10 - a.index()) % 10;
+        assert!((4..=6).contains(&ahead), "seed {}: {:?}", seed, [a, b]);
+        assert!(game.map.moves_apart(a, b).unwrap() >= 4);
+    }
+}
+
+#[test]
+fn test_enemy_count_scales_with_map_size() {
+    let config = Config {
+        enemies_per_nodes: NonZeroUsize::new(6),
+        ..Config::default()
+    };
+    for (nodes, enemies) in [(4, 1), (12, 2), (24, 4), (36, 6)] {
+        let map = ring_map(&vec![MapNodeType::Normal; nodes]);
+        let game = game_on_with(map, 0, config.clone()).unwrap();
+        assert_eq!(game.enemies.len(), enemies, "{nodes} nodes");
+        assert_eq!(game.enemy_positions.len(), enemies, "{nodes} nodes");
+        let occupied: HashSet<_> = game.enemy_positions.iter().collect();
+        assert_eq!(occupied.len(), enemies, "{nodes} nodes");
+    }
+
+    // One enemy per node would leave no room for them, so they fill the nodes left free
+    let config = Config {
+        enemies_per_nodes: NonZeroUsize::new(1),
+        ..Config::default()
+    };
+    for nodes in [3, 8] {
+        let map = ring_map(&vec![MapNodeType::Normal; nodes]);
+        let game = game_on_with(map, 0, config.clone()).unwrap();
+        assert_eq!(game.enemies.len(), nodes - 2, "{nodes} nodes");
+    }
+
+    // Without it, any map gets the fixed number
+    let game = game_on(ring_map(&[MapNodeType::Normal; 36]), 0).unwrap();
+    assert_eq!(game.enemies.len(), DEFAULT_ENEMIES);
+}
+
+#[tokio::test]
+async fn test_enemies_stay_put_without_move_chance() {
+    for (move_chance, expect_moves) in [(0.0, false), (1.0, true)] {
+        let mut game = game_on(ring_map(&[MapNodeType::Normal; 8]), 3).unwrap();
+        game.config.enemy_move_chance = move_chance;
+        let start = game.enemy_positions.clone();
+
+        let mut moved = false;
+        for _ in 0..5 {
+            game.handle_enemy_turn().await.unwrap();
+            moved |= game.enemy_positions != start;
+        }
+        assert_eq!(moved, expect_moves, "enemy_move_chance {move_chance}");
+    }
+}
+
+#[tokio::test]
+async fn test_lowest_index_enemies_move_predictably_without_the_rng() {
+    // Every node leads to every other
+    let mut graph = DiGraph::new();
+    let nodes: Vec<_> = (0..7)
+        .map(|_| graph.add_node(MapNodeType::Normal))
+        .collect();
+    for &from in &nodes {
+        for &to in &nodes {
+            if from != to {
+                graph.add_edge(from, to, ());
+            }
+        }
+    }
+
+    for seed in [1, 2] {
+        let mut game = game_on(GameMap::from_graph(graph.clone()), seed).unwrap();
+        game.config.enemy_movement = EnemyMovement::LowestIndex;
+        game.player_positions = [nodes[5], nodes[6]];
+        game.enemy_positions = vec![nodes[2], nodes[3]];
+        let rng = game.rng.clone();
+
+        let mut visited: Vec<Vec<usize>> = Vec::new();
+        for _ in 0..3 {
+            game.handle_enemy_turn().await.unwrap();
+            visited.push(game.enemy_positions.iter().map(|n| n.index()).collect());
+        }
+
+        // Each enemy takes the lowest node the other one isn't on
+        assert_eq!(visited, [vec![0, 1], vec![2, 0], vec![1, 2]], "seed {seed}");
+        assert_eq!(game.rng, rng, "seed {seed}");
+    }
+}
+
+/// Plays a single turn with player B the stronger one, returning which players moved.
+async fn first_turn_movers(turn_order: TurnOrder) -> [bool; 2] {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 1,
+        turn_order,
+        enemy_move_chance: 0.0,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(Gambler(GambleResponse::Skip))),
+        Submission::new("b", Arc::new(Gambler(GambleResponse::Skip))),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(0)
+    .map(ring_map(&[MapNodeType::Normal; 8]))
+    .build()
+    .unwrap();
+    game.players[1].player_state_mut().power = 9;
+
+    let start = game.player_positions;
+    game.result(&db).await.unwrap();
+    [0, 1].map(|player| game.player_positions[player] != start[player])
+}
+
+#[tokio::test]
+async fn test_initiative_lets_the_stronger_player_move_first() {
+    assert_eq!(
+        first_turn_movers(TurnOrder::Alternating).await,
+        [true, false]
+    );
+    assert_eq!(
+        first_turn_movers(TurnOrder::Initiative).await,
+        [false, true]
+    );
+}
+
+#[test]
+fn test_map_without_room_for_enemies_is_an_error() {
+    // Teleport nodes are never used as starting positions
+    let map = ring_map(&[
+        MapNodeType::Normal,
+        MapNodeType::Normal,
+        MapNodeType::Normal,
+        MapNodeType::Teleport,
+    ]);
+
+    let Err(error) = game_on(map, 0) else {
+        panic!("placing a fourth entity on three usable nodes should fail");
+    };
+    assert!(error.to_string().contains("enemy 1"), "{error}");
+}
+
+/// Never answers a move request in time.
+struct TimingOutPlayer;
+
+#[async_trait]
+impl Player for TimingOutPlayer {
+    async fn get_choices(
+        &self,
+        _request: &ChoicesRequest,
+        _game_id: i64,
+    ) -> Result<ChoiceResponse> {
+        timeout(Duration::ZERO, pending::<()>()).await?;
+        unreachable!()
+    }
+
+    async fn get_gamble_choice(&self, _game_id: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(
+        &self,
+        _fight_info: &FightInfo,
+        _game_id: i64,
+    ) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+/// Plays a game of two timing out players on a full ring, so nobody ever moves.
+async fn play_timing_out_game(policy: TimeoutPolicy) -> (Game, Result<GameResult>) {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 4,
+        timeout_policy: policy,
+        enemy_move_chance: 0.0,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(TimingOutPlayer)),
+        Submission::new("b", Arc::new(TimingOutPlayer)),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(0)
+    .map(ring_map(&[MapNodeType::Normal; 4]))
+    .build()
+    .unwrap();
+    let result = game.result(&db).await;
+    (game, result)
+}
+
+#[tokio::test]
+async fn test_greater_healing_heals_more_than_standard_healing() {
+    let mut map = ring_map(&[
+        MapNodeType::Healing,
+        MapNodeType::Healing,
+        MapNodeType::Normal,
+        MapNodeType::Normal,
+        MapNodeType::Normal,
+        MapNodeType::Normal,
+    ]);
+    map.set_greater_healing(NodeIndex::new(1));
+    let mut game = game_on(map, 0).unwrap();
+
+    let greater = Config::default().greater_healing_amount;
+    for (node, heal) in [(0, 1), (1, greater)] {
+        game.player_positions[0] = NodeIndex::new(node);
+        *game.players[0].player_state_mut() = PlayerState {
+            health: 1,
+            max_health: 5,
+            power: 5,
+        };
+        game.handle_node_effect(0, MapNodeType::Healing)
+            .await
+            .unwrap();
+        assert_eq!(
+            game.players[0].player_state().health,
+            1 + heal,
+            "node {node}"
+        );
+    }
+    assert!(greater > 1);
+}
+
+#[tokio::test]
+async fn test_teleports_within_the_cooldown_have_no_effect() {
+    let mut map_nodes = [MapNodeType::Normal; 6];
+    map_nodes[0] = MapNodeType::Teleport;
+    let config = Config {
+        teleport_cooldown: 2,
+        ..Config::default()
+    };
+    let mut game = game_on_with(ring_map(&map_nodes), 0, config).unwrap();
+
+    let teleport = NodeIndex::new(0);
+    for (turn, teleports) in [(0, true), (1, false), (2, false), (3, true)] {
+        game.turn = turn;
+        game.player_positions[0] = teleport;
+        let teleported = game
+            .handle_node_effect(0, MapNodeType::Teleport)
+            .await
+            .unwrap();
+        assert_eq!(teleported, teleports, "turn {turn}");
+        assert_eq!(
+            game.player_positions[0] != teleport,
+            teleports,
+            "turn {turn}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_teleport_event_records_where_the_player_ends() {
+    let mut map_nodes = [MapNodeType::Normal; 8];
+    map_nodes[0] = MapNodeType::Teleport;
+    let mut game = game_on(ring_map(&map_nodes), 0).unwrap();
+
+    let teleport = NodeIndex::new(0);
+    game.handle_regular_move(0, NodeIndex::new(1), teleport)
+        .await
+        .unwrap();
+
+    let name = game.players[0].name().to_string();
+    let teleports: Vec<_> = game
+        .events
+        .iter()
+        .filter_map(|(_, event)| match event {
+            GameEvent::Teleport { player, to } if *player == name => Some(*to),
+            _ => None,
+        })
+        .collect();
+    assert_ne!(game.player_positions[0], teleport);
+    assert_eq!(teleports, [game.player_positions[0].index()]);
+}
+
+#[tokio::test]
+async fn test_timeout_fails_the_game_by_default() {
+    let (game, result) = play_timing_out_game(TimeoutPolicy::default()).await;
+
+    assert!(is_timeout(&result.unwrap_err()));
+    assert_eq!(game.players[0].player_state().health, 3);
+}
+
+#[tokio::test]
+async fn test_timeout_loses_the_game() {
+    let (game, result) = play_timing_out_game(TimeoutPolicy::LoseGame).await;
+    let result = result.unwrap();
+
+    // The first mover times out on the very first turn
+    assert_eq!(result, GameResult::Player2Win);
+    assert_eq!(game.players[0].player_state().health, 3);
+}
+
+#[tokio::test]
+async fn test_timeout_skips_turn_with_damage() {
+    let (game, result) = play_timing_out_game(TimeoutPolicy::SkipTurnWithDamage).await;
+    let result = result.unwrap();
+
+    // Both players time out on their two turns and survive with 1 health
+    assert_eq!(result, GameResult::Tie);
+    for player in &game.players {
+        assert_eq!(player.player_state().health, 1);
+    }
+}
+
+#[test]
+fn test_first_timeout_and_too_many_invalid_moves_are_disqualifications() {
+    let config = Config {
+        max_invalid_moves: 1,
+        ..Config::default()
+    };
+    let mut game = game_on_with(ring_map(&[MapNodeType::Normal; 4]), 0, config).unwrap();
+    let [a, b] = game.players.each_ref().map(|p| p.name().to_string());
+    for (turn, event) in [
+        (1, GameEvent::InvalidMove { player: b.clone() }),
+        (2, GameEvent::Timeout { player: a.clone() }),
+        (3, GameEvent::InvalidMove { player: b.clone() }),
+        (4, GameEvent::Timeout { player: a.clone() }),
+        (5, GameEvent::InvalidMove { player: b.clone() }),
+    ] {
+        game.turn = turn;
+        game.push_event(event);
+    }
+
+    let reasons: Vec<_> = game
+        .disqualifications()
+        .into_iter()
+        .map(|dq| (dq.submission, dq.reason, dq.turn_number))
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            (a, DisqualificationReason::Timeout, Some(2)),
+            (b, DisqualificationReason::TooManyInvalidMoves, Some(3)),
+        ]
+    );
+}
+
+/// A player whose `/choices` responses aren't valid JSON.
+struct GarbledPlayer;
+
+#[async_trait]
+impl Player for GarbledPlayer {
+    async fn get_choices(
+        &self,
+        _request: &ChoicesRequest,
+        _game_id: i64,
+    ) -> Result<ChoiceResponse> {
+        Ok(serde_json::from_str("{\"choice_idx\":")?)
+    }
+
+    async fn get_gamble_choice(&self, _game_id: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(
+        &self,
+        _fight_info: &FightInfo,
+        _game_id: i64,
+    ) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_json_is_recorded_as_disqualification() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(GarbledPlayer)),
+        Submission::new("b", Arc::new(GarbledPlayer)),
+    )
+    .ids(3, matchup_id)
+    .config(config)
+    .seed(0)
+    .map(ring_map(&[MapNodeType::Normal; 4]))
+    .build()
+    .unwrap();
+
+    assert!(game.result(&db).await.is_err());
+    let disqualifications = db.list_disqualifications().await.unwrap();
+    assert_eq!(disqualifications.len(), 1);
+    assert_eq!(disqualifications[0].submission, "a");
+    assert_eq!(
+        disqualifications[0].reason,
+        DisqualificationReason::InvalidJson
+    );
+    assert_eq!(disqualifications[0].game_number, Some(3));
+}
+
+#[tokio::test]
+async fn test_player_without_moves_follows_no_moves_policy() {
+    // A ring with a dead end hanging off it
+    let mut graph = DiGraph::new();
+    let nodes: Vec<_> = (0..5)
+        .map(|_| graph.add_node(MapNodeType::Normal))
+        .collect();
+    for i in 0..4 {
+        graph.add_edge(nodes[i], nodes[(i + 1) % 4], ());
+    }
+    graph.add_edge(nodes[3], nodes[4], ());
+
+    for (policy, expected) in [
+        (NoMovesPolicy::SkipTurn, nodes[4]),
+        // The only node left empty
+        (NoMovesPolicy::Teleport, nodes[3]),
+    ] {
+        let mut game = game_on(GameMap::from_graph(graph.clone()), 0).unwrap();
+        game.config.no_moves_policy = policy;
+        game.player_positions = [nodes[4], nodes[0]];
+        game.enemy_positions = vec![nodes[1], nodes[2]];
+
+        game.play_turn(0, 0).await.unwrap();
+        assert_eq!(game.player_positions[0], expected, "{policy:?}");
+        assert_eq!(game.players[0].player_state().health, 3, "{policy:?}");
+    }
+}
+
+/// Always makes the same gamble, and otherwise takes the first move and flees.
+struct Gambler(GambleResponse);
+
+#[async_trait]
+impl Player for Gambler {
+    async fn get_choices(
+        &self,
+        _request: &ChoicesRequest,
+        _game_id: i64,
+    ) -> Result<ChoiceResponse> {
+        Ok(ChoiceResponse { choice_index: 0 })
+    }
+
+    async fn get_gamble_choice(&self, _game_id: i64) -> Result<GambleResponse> {
+        Ok(self.0)
+    }
+
+    async fn get_fight_choice(
+        &self,
+        _fight_info: &FightInfo,
+        _game_id: i64,
+    ) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+fn gamblers(stat: GambleResponse, config: Config, seed: u64) -> Game {
+    GameBuilder::new(
+        Submission::new("a", Arc::new(Gambler(stat))),
+        Submission::new("b", Arc::new(Gambler(stat))),
+    )
+    .config(config)
+    .seed(seed as i64)
+    .map(ring_map(&[MapNodeType::Normal; 4]))
+    .build()
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_max_health_gamble_moves_the_cap_and_clamps_health() {
+    let mut outcomes = HashSet::new();
+    for seed in 0..100 {
+        let mut game = gamblers(GambleResponse::MaxHealth, Config::default(), seed);
+        game.handle_gamble(0).await.unwrap();
+
+        let state = *game.players[0].player_state();
+        // Halved, lost 1, gained 1 or doubled from 3, with health only lowered to fit
+        assert!([1, 2, 4, 6].contains(&state.max_health), "{state:?}");
+        assert_eq!(state.health, state.max_health.min(3), "{state:?}");
+        assert_eq!(state.power, 5);
+        outcomes.insert(state.max_health);
+    }
+    assert_eq!(outcomes.len(), 4);
+}
+
+#[tokio::test]
+async fn test_gamble_cost_is_deducted_and_can_end_the_game() {
+    let power_cost = Config {
+        gamble_cost: 2,
+        gamble_cost_stat: GambleCostStat::Power,
+        ..Config::default()
+    };
+    for seed in 0..20 {
+        let mut game = gamblers(GambleResponse::MaxHealth, power_cost.clone(), seed);
+        game.handle_gamble(0).await.unwrap();
+        assert_eq!(game.players[0].player_state().power, 3);
+        assert_eq!(game.check_game_over(), None);
+    }
+
+    let lethal_cost = Config {
+        gamble_cost: 3,
+        ..Config::default()
+    };
+    let mut game = gamblers(GambleResponse::MaxHealth, lethal_cost, 0);
+    game.handle_gamble(0).await.unwrap();
+    let state = *game.players[0].player_state();
+    assert_eq!(state.health, 0);
+    // The gamble itself never happens
+    assert_eq!(state.max_health, 3);
+    assert_eq!(game.check_game_over(), Some(GameResult::Player2Win));
+}
+
+#[tokio::test]
+async fn test_power_gambles_never_go_below_min_power() {
+    for min_power in [1, 3] {
+        let config = Config {
+            min_power,
+            gamble_cost: 1,
+            gamble_cost_stat: GambleCostStat::Power,
+            ..Config::default()
+        };
+        for seed in 0..10 {
+            let mut game = gamblers(GambleResponse::Power, config.clone(), seed);
+            for _ in 0..50 {
+                game.handle_gamble(0).await.unwrap();
+                let power = game.players[0].player_state().power;
+                assert!(power >= min_power, "{power} < {min_power}, seed {seed}");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_stepping_onto_healing_node_counts_a_healing_visit() {
+    let mut node_types = [MapNodeType::Normal; 6];
+    node_types[1] = MapNodeType::Healing;
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(Gambler(GambleResponse::MaxHealth))),
+        Submission::new("b", Arc::new(Gambler(GambleResponse::MaxHealth))),
+    )
+    .seed(0)
+    .map(ring_map(&node_types))
+    .positions(
+        [NodeIndex::new(0), NodeIndex::new(3)],
+        vec![NodeIndex::new(4), NodeIndex::new(5)],
+    )
+    .build()
+    .unwrap();
+
+    // The only move on the ring is onto the healing node
+    game.play_turn(0, 0).await.unwrap();
+
+    let healing = node_type_index(MapNodeType::Healing);
+    assert_eq!(game.player_positions[0], NodeIndex::new(1));
+    assert_eq!(game.node_visits[0][healing], 1);
+    assert_eq!(game.node_visits[0].iter().sum::<u32>(), 1);
+    assert_eq!(game.node_visits[1], [0; 4]);
+}
+
+/// Plays a scripted game with rng auditing on, returning the written rng log.
+async fn rng_audit_log(seed: u64) -> String {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 20,
+        rng_audit: true,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(Gambler(GambleResponse::MaxHealth))),
+        Submission::new("b", Arc::new(Gambler(GambleResponse::MaxHealth))),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(seed as i64)
+    .map(ring_map(&[
+        MapNodeType::Normal,
+        MapNodeType::Gamble,
+        MapNodeType::Normal,
+        MapNodeType::Healing,
+        MapNodeType::Gamble,
+        MapNodeType::Normal,
+    ]))
+    .build()
+    .unwrap();
+    game.result(&db).await.unwrap();
+
+    let path = format!("rng_audit/{matchup_id}_a_vs_b/game_0.txt");
+    std::fs::read_to_string(results_dir.path().join(path)).unwrap()
+}
+
+#[tokio::test]
+async fn test_same_seed_games_have_identical_rng_logs() {
+    let log = rng_audit_log(7).await;
+    assert!(log.starts_with("turn 0 "), "{log}");
+    assert!(
+        log.lines().any(|line| line.starts_with("gamble a ")),
+        "{log}"
+    );
+    assert_eq!(log, rng_audit_log(7).await);
+    assert_ne!(log, rng_audit_log(8).await);
+}
+
+/// Plays a game of two built-in bots with rng draws recorded, returning its seed and the written
+/// draws.
+async fn recorded_rng_draws(game_id: i64) -> (i64, Vec<Draw>) {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 20,
+        rng_draws: true,
+        tournament_seed: Some(42),
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    let mut bots = built_in_bots(Some(42)).into_iter();
+    let mut game = GameBuilder::new(
+        Submission::new("a", bots.next().unwrap().1),
+        Submission::new("b", bots.next().unwrap().1),
+    )
+    .ids(game_id, matchup_id)
+    .config(config)
+    .build()
+    .unwrap();
+    game.result(&db).await.unwrap();
+
+    // The only game of a fresh database is the first row of `games`
+    let path = results_dir.path().join("rng/1.jsonl");
+    let draws = std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    (game.seed, draws)
+}
+
+#[tokio::test]
+async fn test_recorded_rng_draws_replay_from_the_seed() {
+    let (seed, draws) = recorded_rng_draws(0).await;
+    assert_eq!(draws[0].context, "map");
+    assert!(draws.iter().any(|draw| draw.context == "placement"));
+
+    let mut fresh = StdRng::seed_from_u64(seed as u64);
+    for (i, draw) in draws.iter().enumerate() {
+        let value = match draw.kind {
+            DrawKind::U32 => format!("{:08x}", fresh.next_u32()),
+            DrawKind::U64 => format!("{:016x}", fresh.next_u64()),
+            DrawKind::Bytes => {
+                let mut bytes = vec![0; draw.value.len() / 2];
+                fresh.fill_bytes(&mut bytes);
+                bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+            }
+        };
+        assert_eq!(value, draw.value, "draw {i} ({})", draw.context);
+    }
+    assert_eq!(recorded_rng_draws(0).await, (seed, draws));
+}
+
+#[test]
+fn test_visibility_controls_revealed_positions() {
+    let mut game = game_on(ring_map(&[MapNodeType::Normal; 6]), 0).unwrap();
+    game.player_positions = [NodeIndex::new(0), NodeIndex::new(1)];
+    game.enemy_positions = vec![NodeIndex::new(3), NodeIndex::new(4)];
+
+    let mut positions = |visibility| {
+        game.config.visibility = visibility;
+        game.visible_positions(0)
+    };
+    assert_eq!(
+        positions(Visibility::Full),
+        VisiblePositions {
+            position: Some(0),
+            opponent_position: Some(1),
+            enemy_positions: vec![3, 4],
+        }
+    );
+    assert_eq!(positions(Visibility::SelfOnly), VisiblePositions::default());
+    // Only node 1 is one move away from node 0 on the ring
+    assert_eq!(
+        positions(Visibility::Adjacent),
+        VisiblePositions {
+            position: Some(0),
+            opponent_position: Some(1),
+            enemy_positions: vec![],
+        }
+    );
+}
+
+/// Takes the first move and flees, keeping the opponent state of every `/choices` request.
+#[derive(Default)]
+struct OpponentWatcher(Mutex<Vec<Option<PlayerState>>>);
+
+#[async_trait]
+impl Player for OpponentWatcher {
+    async fn get_choices(&self, request: &ChoicesRequest, _game_id: i64) -> Result<ChoiceResponse> {
+        self.0.lock().unwrap().push(request.opponent);
+        Ok(ChoiceResponse { choice_index: 0 })
+    }
+
+    async fn get_gamble_choice(&self, _game_id: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(
+        &self,
+        _fight_info: &FightInfo,
+        _game_id: i64,
+    ) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+#[tokio::test]
+async fn test_self_only_visibility_hides_opponent_state() {
+    // The players start three moves apart on the ring, node 1 is next to the first one
+    for (visibility, opponent_at, expected) in [
+        (Visibility::SelfOnly, 3, None),
+        (Visibility::SelfOnly, 1, None),
+        (Visibility::Adjacent, 3, None),
+        (Visibility::Adjacent, 1, Some(STARTING_STATE)),
+        (Visibility::Full, 3, Some(STARTING_STATE)),
+    ] {
+        let watcher = Arc::new(OpponentWatcher::default());
+        let mut game = GameBuilder::new(
+            Submission::new("a", watcher.clone()),
+            Submission::new("b", Arc::new(OpponentWatcher::default())),
+        )
+        .config(Config {
+            visibility,
+            ..Config::default()
+        })
+        .seed(0)
+        .map(ring_map(&[MapNodeType::Normal; 6]))
+        .positions(
+            [NodeIndex::new(0), NodeIndex::new(opponent_at)],
+            vec![NodeIndex::new(4), NodeIndex::new(5)],
+        )
+        .build()
+        .unwrap();
+
+        game.play_turn(0, 0).await.unwrap();
+        assert_eq!(
+            *watcher.0.lock().unwrap(),
+            [expected],
+            "{visibility:?} with the opponent at {opponent_at}"
+        );
+    }
+}
+
+/// Stays put through its node's self-loop. With `folds_in_rematch`, it instead returns an invalid
+/// choice once a rematch leaves it with a single health point.
+struct Stayer {
+    folds_in_rematch: bool,
+}
+
+#[async_trait]
+impl Player for Stayer {
+    async fn get_choices(&self, request: &ChoicesRequest, _game_id: i64) -> Result<ChoiceResponse> {
+        let folds = self.folds_in_rematch && request.player.max_health == 1;
+        Ok(ChoiceResponse {
+            choice_index: if folds { 99 } else { 0 },
+        })
+    }
+
+    async fn get_gamble_choice(&self, _game_id: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(
+        &self,
+        _fight_info: &FightInfo,
+        _game_id: i64,
+    ) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+/// Plays a game of two `Stayer`s on isolated self-looping nodes, so the game itself always ties,
+/// and returns the result with the recorded winner and rematch count.
+async fn play_stayers_game(tie_rematch: bool) -> (GameResult, String, i64) {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 4,
+        tie_rematch,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let mut graph = DiGraph::new();
+    for _ in 0..4 {
+        let node = graph.add_node(MapNodeType::Normal);
+        graph.add_edge(node, node, ());
+    }
+    let mut game = GameBuilder::new(
+        Submission::new(
+            "a",
+            Arc::new(Stayer {
+                folds_in_rematch: true,
+            }),
+        ),
+        Submission::new(
+            "b",
+            Arc::new(Stayer {
+                folds_in_rematch: false,
+            }),
+        ),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(0)
+    .map(GameMap::from_graph(graph))
+    .build()
+    .unwrap();
+    let result = game.result(&db).await.unwrap();
+
+    let conn = Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    let (winner, rematches) = conn
+        .query_row("SELECT winner, rematches FROM games", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    (result, winner, rematches)
+}
+
+#[tokio::test]
+async fn test_tie_is_kept_without_rematch() {
+    assert_eq!(
+        play_stayers_game(false).await,
+        (GameResult::Tie, "tie".to_string(), 0)
+    );
+}
+
+#[tokio::test]
+async fn test_tie_rematch_decides_the_game() {
+    assert_eq!(
+        play_stayers_game(true).await,
+        (GameResult::Player2Win, "player_b".to_string(), 1)
+    );
+}
+
+/// Plays `turns` turns with the first player bouncing between two nodes and the second staying on
+/// its self-looping node, returning both players' health.
+async fn play_stall_game(stall_penalty_turns: Option<u64>, turns: u64) -> [u32; 2] {
+    play_on_cycle(Config {
+        turns_per_game: turns,
+        stall_penalty_turns,
+        ..Config::default()
+    })
+    .await
+}
+
+/// Plays a game with `config` and the players of `play_stall_game`, returning their health.
+async fn play_on_cycle(config: Config) -> [u32; 2] {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..config
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let mut graph = DiGraph::new();
+    let [from, to] = [(); 2].map(|_| graph.add_node(MapNodeType::Normal));
+    graph.add_edge(from, to, ());
+    graph.add_edge(to, from, ());
+    for _ in 0..3 {
+        let node = graph.add_node(MapNodeType::Normal);
+        graph.add_edge(node, node, ());
+    }
+    let stayer = || {
+        Arc::new(Stayer {
+            folds_in_rematch: false,
+        })
+    };
+    let mut game = GameBuilder::new(
+        Submission::new("a", stayer()),
+        Submission::new("b", stayer()),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(0)
+    .map(GameMap::from_graph(graph))
+    // The only move from either end of the two-node cycle is to the other end
+    .positions(
+        [from, NodeIndex::new(2)],
+        vec![NodeIndex::new(3), NodeIndex::new(4)],
+    )
+    .build()
+    .unwrap();
+
+    game.result(&db).await.unwrap();
+    game.players.each_ref().map(|p| p.player_state().health)
+}
+
+#[tokio::test]
+async fn test_oscillating_player_takes_stall_damage() {
+    // Without the mechanic nobody is punished for going nowhere
+    assert_eq!(play_stall_game(None, 20).await, [3, 3]);
+
+    // Staying put revisits a node from the second turn on, bouncing between two nodes from the
+    // third, and 3 such turns in a row cost a health point. Each player has 4 turns in 8
+    assert_eq!(play_stall_game(Some(3), 8).await, [3, 2]);
+    assert_eq!(play_stall_game(Some(3), 10).await, [2, 2]);
+    // The count starts over after each penalty
+    assert_eq!(play_stall_game(Some(3), 14).await, [2, 1]);
+}
+
+/// Health of the `play_stall_game` players after `turns` turns with `sudden_death_after`.
+async fn play_sudden_death_game(sudden_death_after: Option<u64>, turns: u64) -> [u32; 2] {
+    play_on_cycle(Config {
+        turns_per_game: turns,
+        sudden_death_after,
+        ..Config::default()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_sudden_death_damages_both_players_every_turn() {
+    assert_eq!(play_sudden_death_game(None, 20).await, [3, 3]);
+
+    // Each player loses a health point on each of its turns from turn 4 on
+    assert_eq!(play_sudden_death_game(Some(4), 4).await, [3, 3]);
+    assert_eq!(play_sudden_death_game(Some(4), 6).await, [2, 2]);
+    assert_eq!(play_sudden_death_game(Some(4), 8).await, [1, 1]);
+    // Until one of them runs out and the game ends
+    assert_eq!(play_sudden_death_game(Some(4), 20).await, [0, 1]);
+}
+
+/// Plays a game of two scripted gamblers with one enemy on the map generated from `seed`,
+/// returning its result and where and how the players ended up.
+async fn scripted_game(seed: i64) -> (GameResult, [NodeIndex; 2], [PlayerState; 2]) {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 30,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    let mut game = GameBuilder::new(
+        Submission::new("a", Arc::new(Gambler(GambleResponse::MaxHealth))),
+        Submission::new("b", Arc::new(Gambler(GambleResponse::Power))),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .seed(seed)
+    .enemy_count(1)
+    .build()
+    .unwrap();
+    assert_eq!(game.enemies.len(), 1);
+
+    let result = game.result(&db).await.unwrap();
+    let players = game.players.each_ref().map(|p| *p.player_state());
+    (result, game.player_positions, players)
+}
+
+#[tokio::test]
+async fn test_builder_with_fixed_seed_plays_deterministically() {
+    assert_eq!(scripted_game(1970).await, scripted_game(1970).await);
+}
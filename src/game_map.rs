@@ -7,14 +7,16 @@ use layout::{
     std_shapes::shapes::{Arrow, Element, LineEndKind, ShapeKind},
     topo::layout::VisualGraph,
 };
+use log::debug;
 use petgraph::{
     graph::{DiGraph, EdgeReference, NodeIndex},
     visit::EdgeRef,
 };
-use rand::{prelude::*, rngs::StdRng};
+use rand::prelude::*;
 use rplcs_events::tournament_1::{MapNodeType, PlayerState};
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     path::Path,
 };
 
@@ -22,31 +24,383 @@ mod tests;
 
 pub const MAX_DEGREE: usize = 4;
 pub const MIN_DEGREE: usize = 3;
+/// How many maps are generated before giving up on getting a strongly connected one.
+pub const MAX_GENERATION_ATTEMPTS: usize = 100;
+pub const MIN_NODES: usize = 12;
+pub const MAX_NODES: usize = 16;
+/// Every node type, in the order statistics per node type are kept.
+pub const NODE_TYPES: [MapNodeType; 4] = [
+    MapNodeType::Teleport,
+    MapNodeType::Healing,
+    MapNodeType::Gamble,
+    MapNodeType::Normal,
+];
+
+/// Position of `node_type` in `NODE_TYPES`.
+pub fn node_type_index(node_type: MapNodeType) -> usize {
+    NODE_TYPES.iter().position(|&t| t == node_type).unwrap()
+}
+
+/// Name of a node type in configs and the database, such as `healing`.
+pub fn node_type_name(node_type: MapNodeType) -> String {
+    format!("{:?}", node_type).to_lowercase()
+}
+/// Normal nodes every map keeps, however many special nodes are configured.
+pub const MIN_NORMAL_NODES: usize = 4;
+
+/// Map generation settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapConfig {
+    /// Relative weights of each node type. When set, node counts scale with the map size instead
+    /// of using the fixed ranges.
+    pub node_proportions: Option<NodeProportions>,
+    /// Number of teleport nodes, when `node_proportions` is unset.
+    pub teleport_nodes: NodeCount,
+    /// Number of healing nodes, when `node_proportions` is unset.
+    pub healing_nodes: NodeCount,
+    /// Number of gamble nodes, when `node_proportions` is unset.
+    pub gamble_nodes: NodeCount,
+    /// Chance that a new edge also gets a back edge. Lower values produce more one-way corridors.
+    /// When unset, edges get a back edge whenever that keeps in- and out-degrees balanced, which
+    /// yields almost entirely two-way maps.
+    pub bidirectional_edge_chance: Option<f64>,
+    /// Chance that a healing node is a greater healing node, which heals more.
+    pub greater_healing_chance: f64,
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            node_proportions: None,
+            teleport_nodes: NodeCount::Fixed(1),
+            healing_nodes: NodeCount::Range { min: 1, max: 2 },
+            gamble_nodes: NodeCount::Range { min: 1, max: 2 },
+            bidirectional_edge_chance: None,
+            greater_healing_chance: 0.0,
+        }
+    }
+}
+
+impl MapConfig {
+    /// Checks that the special node counts leave room for `MIN_NORMAL_NODES` on the smallest map.
+    fn validate_node_counts(&self) -> Result<()> {
+        let counts = [
+            ("teleport_nodes", self.teleport_nodes),
+            ("healing_nodes", self.healing_nodes),
+            ("gamble_nodes", self.gamble_nodes),
+        ];
+        for (name, count) in counts {
+            if let NodeCount::Range { min, max } = count
+                && min > max
+            {
+                bail!("{} range is empty ({}..={})", name, min, max);
+            }
+        }
+        if self.teleport_nodes.min() == 0 {
+            bail!("teleport_nodes must be at least 1");
+        }
+
+        let max_special: usize = counts.iter().map(|(_, count)| count.max()).sum();
+        if max_special > MIN_NODES - MIN_NORMAL_NODES {
+            bail!(
+                "Up to {} special nodes are configured, but maps can have as few as {} nodes \
+                 and keep at least {} normal nodes, so at most {} special nodes fit",
+                max_special,
+                MIN_NODES,
+                MIN_NORMAL_NODES,
+                MIN_NODES - MIN_NORMAL_NODES
+            );
+        }
+        Ok(())
+    }
+}
+
+/// How many nodes of a type a map gets: a fixed number, or a random one in `min..=max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NodeCount {
+    Fixed(usize),
+    Range { min: usize, max: usize },
+}
+
+impl NodeCount {
+    fn min(self) -> usize {
+        match self {
+            NodeCount::Fixed(count) => count,
+            NodeCount::Range { min, .. } => min,
+        }
+    }
+
+    fn max(self) -> usize {
+        match self {
+            NodeCount::Fixed(count) => count,
+            NodeCount::Range { max, .. } => max,
+        }
+    }
+
+    fn sample(self, rng: &mut impl Rng) -> usize {
+        match self {
+            NodeCount::Fixed(count) => count,
+            NodeCount::Range { min, max } => rng.random_range(min..=max),
+        }
+    }
+}
+
+/// Relative weights of each node type, normalized before use.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NodeProportions {
+    pub normal: f64,
+    pub healing: f64,
+    pub gamble: f64,
+    pub teleport: f64,
+}
+
+impl NodeProportions {
+    /// Splits `num_nodes` into `[teleport, healing, gamble, normal]` counts that follow these
+    /// proportions as closely as possible while still having at least one teleport node.
+    pub fn node_counts(&self, num_nodes: usize) -> Result<[usize; 4]> {
+        let weights = [self.teleport, self.healing, self.gamble, self.normal];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            bail!(
+                "Node proportions must be finite and non-negative: {:?}",
+                self
+            );
+        }
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            bail!("Node proportions must not all be zero");
+        }
+
+        let exact = weights.map(|w| w / total * num_nodes as f64);
+        let mut counts = exact.map(|x| x.floor() as usize);
+
+        // Hand out the nodes lost to rounding down by largest remainder
+        let mut by_remainder = [0, 1, 2, 3];
+        by_remainder.sort_by(|&a, &b| {
+            (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor()))
+        });
+        let assigned: usize = counts.iter().sum();
+        for &i in by_remainder.iter().take(num_nodes - assigned) {
+            counts[i] += 1;
+        }
 
+        // The teleport count stays pinned to at least one
+        if counts[0] == 0 {
+            let largest = (1..4)
+                .max_by_key(|&i| counts[i])
+                .context("No node types to take a teleport node from")?;
+            counts[largest] -= 1;
+            counts[0] = 1;
+        }
+
+        Ok(counts)
+    }
+}
+
+/// How maps are drawn to SVG.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RenderConfig {
+    /// Width and height of each node box.
+    pub node_size: f64,
+    /// Whether occupant labels include health/max health and power, or only their letter.
+    pub show_stats: bool,
+    /// Font size of the node labels.
+    pub font_size: usize,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            node_size: 100.0,
+            show_stats: true,
+            font_size: 32,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Small nodes labelled only with their index and occupant letters, for large maps.
+    pub fn compact() -> Self {
+        Self {
+            node_size: 40.0,
+            show_stats: false,
+            font_size: 16,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct GameMap {
+    #[serde(flatten)]
     graph: DiGraph<MapNodeType, ()>,
+    /// Healing nodes that heal `greater_healing_amount` instead of 1. Left out of the JSON when
+    /// empty, so maps without any serialize like the bare graph.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    greater_healing: BTreeSet<NodeIndex>,
+}
+
+/// Route to `to` from where the `GameMap::breadth_first` that found it started, including both
+/// ends, following the predecessors it recorded.
+fn path_to(to: NodeIndex, previous: &HashMap<NodeIndex, NodeIndex>) -> Vec<NodeIndex> {
+    let mut path = vec![to];
+    while let Some(&node) = path.last().and_then(|last| previous.get(last)) {
+        path.push(node);
+    }
+    path.reverse();
+    path
 }
 
 impl GameMap {
-    pub fn new(rng: &mut StdRng) -> Result<Self> {
+    pub fn new(rng: &mut impl Rng, config: &MapConfig) -> Result<Self> {
+        Self::new_counting_attempts(rng, config).map(|(map, _)| map)
+    }
+
+    /// Like `new`, also returning how many maps were generated to get a strongly connected one.
+    pub fn new_counting_attempts(rng: &mut impl Rng, config: &MapConfig) -> Result<(Self, usize)> {
+        if let Some(chance) = config.bidirectional_edge_chance
+            && !(0.0..=1.0).contains(&chance)
+        {
+            bail!(
+                "bidirectional_edge_chance must be between 0 and 1 (is {})",
+                chance
+            );
+        }
+        if !(0.0..=1.0).contains(&config.greater_healing_chance) {
+            bail!(
+                "greater_healing_chance must be between 0 and 1 (is {})",
+                config.greater_healing_chance
+            );
+        }
+        if config.node_proportions.is_none() {
+            config.validate_node_counts()?;
+        }
+
+        // One-way edges can split the map, so regenerate until every node can reach every other.
+        // Without them the first map is kept, so existing seeds generate the same maps.
+        for attempt in 1..=MAX_GENERATION_ATTEMPTS {
+            let map = Self::generate(rng, config)?;
+            if config.bidirectional_edge_chance.is_none() || map.is_strongly_connected() {
+                // Catch generation regressions early in development builds
+                if cfg!(debug_assertions) {
+                    map.validate(config).context("Generated map is invalid")?;
+                }
+                return Ok((map, attempt));
+            }
+            debug!(
+                "Generated map is not strongly connected (attempt {})",
+                attempt
+            );
+        }
+
+        bail!(
+            "Failed to generate a strongly connected map in {} attempts",
+            MAX_GENERATION_ATTEMPTS
+        )
+    }
+
+    /// Checks the invariants every generated map upholds: the map size, special node counts
+    /// allowed by `config`, degree bounds, fewer than 3 loops per node and strong connectivity.
+    pub fn validate(&self, config: &MapConfig) -> Result<()> {
+        let node_count = self.graph.node_count();
+        if !(MIN_NODES..=MAX_NODES).contains(&node_count) {
+            bail!(
+                "Map should have between {} and {} nodes (has {})",
+                MIN_NODES,
+                MAX_NODES,
+                node_count
+            );
+        }
+
+        let count = |node_type| {
+            self.graph
+                .node_weights()
+                .filter(|&&weight| weight == node_type)
+                .count()
+        };
+        let expected = match config.node_proportions {
+            // Proportions may leave out any special node except the teleport
+            Some(_) => vec![(MapNodeType::Teleport, 1, usize::MAX)],
+            None => [
+                (MapNodeType::Teleport, config.teleport_nodes),
+                (MapNodeType::Healing, config.healing_nodes),
+                (MapNodeType::Gamble, config.gamble_nodes),
+            ]
+            .into_iter()
+            .map(|(node_type, expected)| (node_type, expected.min(), expected.max()))
+            .collect(),
+        };
+        for (node_type, min, max) in expected {
+            let actual = count(node_type);
+            if !(min..=max).contains(&actual) {
+                bail!(
+                    "Map should have between {} and {} {:?} nodes (has {})",
+                    min,
+                    max,
+                    node_type,
+                    actual
+                );
+            }
+        }
+
+        for node in self.node_indices() {
+            let loops = self.get_loops(node).len();
+            if loops >= 3 {
+                bail!("Node {:?} has invalid number of loops: {}", node, loops);
+            }
+
+            let degree = self.get_node_degree(node);
+            if !(MIN_DEGREE..=MAX_DEGREE).contains(&degree) {
+                bail!("Node {:?} has invalid degree: {}", node, degree);
+            }
+        }
+
+        if !self.is_strongly_connected() {
+            bail!("Map is not strongly connected");
+        }
+        Ok(())
+    }
+
+    fn generate(rng: &mut impl Rng, config: &MapConfig) -> Result<Self> {
         let mut map = Self {
             graph: DiGraph::new(),
+            greater_healing: BTreeSet::new(),
         };
 
         // Create nodes
-        let num_nodes = rng.random_range(12..=16);
-        let num_teleport_nodes = 1;
-        let num_healing_nodes = rng.random_range(1..=2);
-        let num_gamble_nodes = rng.random_range(1..=2);
-        let num_normal_nodes =
-            num_nodes - num_teleport_nodes - num_healing_nodes - num_gamble_nodes;
+        let num_nodes = rng.random_range(MIN_NODES..=MAX_NODES);
+        let (num_teleport_nodes, num_healing_nodes, num_gamble_nodes, num_normal_nodes) =
+            match &config.node_proportions {
+                Some(proportions) => {
+                    let [teleport, healing, gamble, normal] = proportions.node_counts(num_nodes)?;
+                    (teleport, healing, gamble, normal)
+                }
+                None => {
+                    let num_teleport_nodes = config.teleport_nodes.sample(rng);
+                    let num_healing_nodes = config.healing_nodes.sample(rng);
+                    let num_gamble_nodes = config.gamble_nodes.sample(rng);
+                    let num_normal_nodes = num_nodes
+                        .checked_sub(num_teleport_nodes + num_healing_nodes + num_gamble_nodes)
+                        .context("More special nodes than nodes in the map")?;
+                    (
+                        num_teleport_nodes,
+                        num_healing_nodes,
+                        num_gamble_nodes,
+                        num_normal_nodes,
+                    )
+                }
+            };
 
         // Add all nodes first
         for _ in 0..num_teleport_nodes {
             map.graph.add_node(MapNodeType::Teleport);
         }
         for _ in 0..num_healing_nodes {
-            map.graph.add_node(MapNodeType::Healing);
+            let node = map.graph.add_node(MapNodeType::Healing);
+            // Only rolled when enabled, so maps of existing seeds stay the same
+            if config.greater_healing_chance > 0.0 && rng.random_bool(config.greater_healing_chance)
+            {
+                map.greater_healing.insert(node);
+            }
         }
         for _ in 0..num_gamble_nodes {
             map.graph.add_node(MapNodeType::Gamble);
@@ -86,10 +440,9 @@ impl GameMap {
                     bail!("Failed to find valid targets for node {:?}", node);
                 }
 
-                available_targets
+                *available_targets
                     .choose(rng)
                     .ok_or(anyhow!("Failed to choose target for node {:?}", node))?
-                    .clone()
             };
 
             // Try to make room if target is at max degree
@@ -101,6 +454,28 @@ impl GameMap {
                     for loop_edge in target_loops {
                         map.graph.remove_edge(loop_edge);
                     }
+                } else if config.bidirectional_edge_chance.is_some()
+                    && map.get_incoming_edges(target).len() > map.get_outgoing_edges(target).len()
+                {
+                    // One-way edges can leave the target with more ways in than out
+                    let (edge_id, other) = map
+                        .get_incoming_edges(target)
+                        .into_iter()
+                        .map(|e| (e.id(), e.source()))
+                        .next()
+                        .ok_or(anyhow!(
+                            "Failed to find incoming edges for node {:?}",
+                            target
+                        ))?;
+
+                    map.graph.remove_edge(edge_id);
+                    if let Some(back_edge) = map
+                        .get_outgoing_edges(target)
+                        .into_iter()
+                        .find(|e| e.target() == other)
+                    {
+                        map.graph.remove_edge(back_edge.id());
+                    }
                 } else {
                     let (edge_id, other) = map
                         .get_outgoing_edges(target)
@@ -125,9 +500,22 @@ impl GameMap {
 
             map.graph.add_edge(node, target, ());
 
-            // Add bidirectional edge if node or target is unbalanced or if random roll succeeds
-            if !map.is_node_balanced(target) || !map.is_node_balanced(node) || rng.random_bool(0.85)
-            {
+            let add_back_edge = match config.bidirectional_edge_chance {
+                // Add bidirectional edge if the random roll succeeds or a one-way edge would leave
+                // the target without a way out or the node without a way in
+                Some(chance) => {
+                    rng.random_bool(chance)
+                        || map.get_outgoing_nodes(target).is_empty()
+                        || map.get_incoming_edges(node).is_empty()
+                }
+                // Add bidirectional edge if node or target is unbalanced or if random roll succeeds
+                None => {
+                    !map.is_node_balanced(target)
+                        || !map.is_node_balanced(node)
+                        || rng.random_bool(0.85)
+                }
+            };
+            if add_back_edge {
                 map.graph.add_edge(target, node, ());
             }
         }
@@ -137,7 +525,26 @@ impl GameMap {
 
     #[cfg(test)]
     pub fn from_graph(graph: DiGraph<MapNodeType, ()>) -> Self {
-        Self { graph }
+        Self {
+            graph,
+            greater_healing: BTreeSet::new(),
+        }
+    }
+
+    /// Makes `node`, a healing node, a greater healing node.
+    #[cfg(test)]
+    pub fn set_greater_healing(&mut self, node: NodeIndex) {
+        self.greater_healing.insert(node);
+    }
+
+    /// Serializes the map topology so a game can be re-rendered without regenerating it.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).context("Failed to serialize map")
+    }
+
+    /// Restores a map serialized with `to_json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Failed to deserialize map")
     }
 
     // Replace graph() with specific utility methods
@@ -145,6 +552,11 @@ impl GameMap {
         self.graph.node_weight(node).copied()
     }
 
+    /// Whether `node` is a healing node that heals more than the others.
+    pub fn is_greater_healing(&self, node: NodeIndex) -> bool {
+        self.greater_healing.contains(&node)
+    }
+
     #[cfg(test)]
     pub fn node_weights(&self) -> Vec<MapNodeType> {
         self.graph.node_weights().copied().collect()
@@ -156,7 +568,6 @@ impl GameMap {
             .collect()
     }
 
-    #[cfg(test)]
     pub fn get_incoming_edges(&self, node: NodeIndex) -> Vec<EdgeReference<'_, ()>> {
         self.graph
             .edges_directed(node, petgraph::Direction::Incoming)
@@ -178,7 +589,7 @@ impl GameMap {
         &self,
         from: NodeIndex,
         blocked_positions: &[NodeIndex],
-        rng: &mut StdRng,
+        rng: &mut impl Rng,
     ) -> Vec<NodeIndex> {
         let mut moves = self.get_available_moves(from, blocked_positions);
         moves.shuffle(rng);
@@ -188,7 +599,7 @@ impl GameMap {
     pub fn get_random_empty_node(
         &self,
         blocked_positions: &[NodeIndex],
-        rng: &mut StdRng,
+        rng: &mut impl Rng,
     ) -> Option<NodeIndex> {
         let mut available: Vec<_> = self
             .node_indices()
@@ -207,6 +618,53 @@ impl GameMap {
         }
     }
 
+    /// A random empty node at least `min_distance` moves away from `from` in either direction.
+    /// When the map has none, the best effort is a random one of the farthest empty nodes. Nodes
+    /// that can't reach `from` nor be reached from it are only picked if no other node is left.
+    pub fn get_random_node_away_from(
+        &self,
+        from: NodeIndex,
+        min_distance: usize,
+        rng: &mut impl Rng,
+    ) -> Option<NodeIndex> {
+        let mut available: Vec<_> = self
+            .node_indices()
+            .into_iter()
+            .filter(|&node| {
+                node != from && !matches!(self.get_node_type(node), Some(MapNodeType::Teleport))
+            })
+            .filter_map(|node| Some((node, self.moves_apart(from, node)?)))
+            .collect();
+
+        let Some(farthest) = available.iter().map(|&(_, distance)| distance).max() else {
+            return self.get_random_empty_node(&[from], rng);
+        };
+        if farthest < min_distance {
+            debug!(
+                "No node is {} moves from {:?}, the farthest are {} moves away",
+                min_distance, from, farthest
+            );
+        }
+        let min_distance = min_distance.min(farthest);
+        available.retain(|&(_, distance)| distance >= min_distance);
+        available.shuffle(rng);
+        Some(available[0].0)
+    }
+
+    /// Moves it takes whichever of `a` and `b` is closer to reach the other, `None` if neither
+    /// can.
+    pub fn moves_apart(&self, a: NodeIndex, b: NodeIndex) -> Option<usize> {
+        [(a, b), (b, a)]
+            .into_iter()
+            .filter_map(|(from, to)| self.shortest_path(from, to))
+            .map(|path| path.len() - 1)
+            .min()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
     pub fn node_indices(&self) -> Vec<NodeIndex> {
         self.graph.node_indices().collect()
     }
@@ -227,6 +685,22 @@ impl GameMap {
             .collect()
     }
 
+    /// The nodes one move away from `from` that are of `node_type`.
+    pub fn neighbors_of_type(&self, from: NodeIndex, node_type: MapNodeType) -> Vec<NodeIndex> {
+        self.get_outgoing_nodes(from)
+            .into_iter()
+            .filter(|&node| self.get_node_type(node) == Some(node_type))
+            .collect()
+    }
+
+    /// The types of the nodes one move away from `from`, in the order of `get_outgoing_nodes`.
+    pub fn node_types_adjacent(&self, from: NodeIndex) -> Vec<MapNodeType> {
+        self.get_outgoing_nodes(from)
+            .into_iter()
+            .filter_map(|node| self.get_node_type(node))
+            .collect()
+    }
+
     pub fn get_loops(&self, node: NodeIndex) -> Vec<petgraph::graph::EdgeIndex> {
         self.graph
             .edges_directed(node, petgraph::Direction::Outgoing)
@@ -235,6 +709,50 @@ impl GameMap {
             .collect()
     }
 
+    /// Fewest-moves route from `from` to `to` following outgoing edges, including both ends.
+    /// `None` if `to` can't be reached.
+    pub fn shortest_path(&self, from: NodeIndex, to: NodeIndex) -> Option<Vec<NodeIndex>> {
+        let (_, previous) = self.breadth_first(from, |node| node == to)?;
+        Some(path_to(to, &previous))
+    }
+
+    /// Number of moves from `from` to the closest node of `node_type`, or `None` if there is none
+    /// reachable. Zero when `from` already is one.
+    pub fn distance_to_nearest(&self, from: NodeIndex, node_type: MapNodeType) -> Option<usize> {
+        let (nearest, previous) =
+            self.breadth_first(from, |node| self.get_node_type(node) == Some(node_type))?;
+        Some(path_to(nearest, &previous).len() - 1)
+    }
+
+    /// Breadth-first search from `from` until `is_goal` matches, returning the matching node and
+    /// each visited node's predecessor. `None` if no reachable node matches.
+    fn breadth_first(
+        &self,
+        from: NodeIndex,
+        is_goal: impl Fn(NodeIndex) -> bool,
+    ) -> Option<(NodeIndex, HashMap<NodeIndex, NodeIndex>)> {
+        self.graph.node_weight(from)?;
+        let mut previous = HashMap::new();
+        let mut visited = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(node) = queue.pop_front() {
+            if is_goal(node) {
+                return Some((node, previous));
+            }
+            for next in self.get_outgoing_nodes(node) {
+                if visited.insert(next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn is_strongly_connected(&self) -> bool {
+        petgraph::algo::kosaraju_scc(&self.graph).len() <= 1
+    }
+
     pub fn is_node_balanced(&self, node: NodeIndex) -> bool {
         let outgoing = self.graph.edges_directed(node, petgraph::Outgoing).count();
         let incoming = self.graph.edges_directed(node, petgraph::Incoming).count();
@@ -242,12 +760,124 @@ impl GameMap {
         outgoing == incoming
     }
 
+    /// Labels for whoever stands on `node`: `A`/`B` for the players and `E` for enemies, each
+    /// followed by health/max health and power if `show_stats` is set.
+    fn occupants(
+        node: NodeIndex,
+        player_positions: [NodeIndex; 2],
+        enemy_positions: &[NodeIndex],
+        players: &[PlayerState; 2],
+        enemies: &[PlayerState],
+        show_stats: bool,
+    ) -> Vec<String> {
+        let describe = |tag: &str, state: &PlayerState| {
+            if show_stats {
+                format!(
+                    "{} {}/{} {}",
+                    tag, state.health, state.max_health, state.power
+                )
+            } else {
+                tag.to_string()
+            }
+        };
+
+        let mut occupants = Vec::new();
+        let pieces = [
+            ("A", player_positions[0], &players[0]),
+            ("B", player_positions[1], &players[1]),
+        ]
+        .into_iter()
+        .chain(
+            enemy_positions
+                .iter()
+                .zip(enemies)
+                .map(|(&position, state)| ("E", position, state)),
+        );
+        for (tag, position, state) in pieces {
+            if position == node {
+                occupants.push(describe(tag, state));
+            }
+        }
+        occupants
+    }
+
+    /// The type of `node` (`Healing+` for greater healing) and its sorted, comma-separated
+    /// outgoing targets, as shown in the text renderings.
+    fn describe_node(&self, node: NodeIndex) -> (String, String) {
+        let node_type = if self.is_greater_healing(node) {
+            "Healing+".to_string()
+        } else {
+            format!("{:?}", self.graph[node])
+        };
+        let mut targets: Vec<_> = self
+            .get_outgoing_nodes(node)
+            .iter()
+            .map(|n| n.index())
+            .collect();
+        targets.sort_unstable();
+        let targets = targets
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        (node_type, targets)
+    }
+
+    /// Compact adjacency list of the map: one `<node> <type> -> <targets>` line per node.
+    pub fn adjacency_list(&self) -> String {
+        let mut text = String::new();
+        for node_idx in self.graph.node_indices() {
+            let (node_type, targets) = self.describe_node(node_idx);
+            text.push_str(&format!(
+                "{} {} -> {}\n",
+                node_idx.index(),
+                node_type,
+                targets
+            ));
+        }
+        text
+    }
+
+    /// Plain-text version of `render_to_file`: one line per node with its type, outgoing
+    /// edges and occupants.
+    pub fn render_to_text(
+        &self,
+        player_positions: [NodeIndex; 2],
+        enemy_positions: &[NodeIndex],
+        players: &[PlayerState; 2],
+        enemies: &[PlayerState],
+    ) -> String {
+        let mut text = String::new();
+        for node_idx in self.graph.node_indices() {
+            let (node_type, targets) = self.describe_node(node_idx);
+            let occupants = Self::occupants(
+                node_idx,
+                player_positions,
+                enemy_positions,
+                players,
+                enemies,
+                true,
+            );
+            let line = format!(
+                "{:>2} {:<8} -> {:<12} {}",
+                node_idx.index(),
+                node_type,
+                targets,
+                occupants.join("  ")
+            );
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
     pub fn render_to_file(
         &self,
         player_positions: [NodeIndex; 2],
-        enemy_positions: [NodeIndex; 2],
+        enemy_positions: &[NodeIndex],
         players: &[PlayerState; 2],
-        enemies: &[PlayerState; 2],
+        enemies: &[PlayerState],
+        render: &RenderConfig,
         path: &Path,
     ) -> Result<()> {
         let mut visual = VisualGraph::new(Orientation::TopToBottom);
@@ -258,45 +888,23 @@ impl GameMap {
             let node_type = self.graph.node_weight(node_idx).unwrap();
             let fill_color = match node_type {
                 MapNodeType::Teleport => 0xb3dbbaff,
+                MapNodeType::Healing if self.is_greater_healing(node_idx) => 0x1e7b1eff,
                 MapNodeType::Healing => 0x4cc037ff,
                 MapNodeType::Normal => 0xcfcfcfff,
                 MapNodeType::Gamble => 0xf1c232ff,
             };
 
-            let label = {
-                let mut label = node_idx.index().to_string();
-
-                if player_positions[0] == node_idx {
-                    let player = &players[0];
-                    label.push_str(&format!(
-                        "\nA {}/{} {}",
-                        player.health, player.max_health, player.power
-                    ));
-                }
-                if player_positions[1] == node_idx {
-                    let player = &players[1];
-                    label.push_str(&format!(
-                        "\nB {}/{} {}",
-                        player.health, player.max_health, player.power
-                    ));
-                }
-                if enemy_positions[0] == node_idx {
-                    let enemy = &enemies[0];
-                    label.push_str(&format!(
-                        "\nE {}/{} {}",
-                        enemy.health, enemy.max_health, enemy.power
-                    ));
-                }
-                if enemy_positions[1] == node_idx {
-                    let enemy = &enemies[1];
-                    label.push_str(&format!(
-                        "\nE {}/{} {}",
-                        enemy.health, enemy.max_health, enemy.power
-                    ));
-                }
-
-                label
-            };
+            let label = std::iter::once(node_idx.index().to_string())
+                .chain(Self::occupants(
+                    node_idx,
+                    player_positions,
+                    enemy_positions,
+                    players,
+                    enemies,
+                    render.show_stats,
+                ))
+                .collect::<Vec<_>>()
+                .join("\n");
 
             let element = Element::create(
                 ShapeKind::Box(label),
@@ -305,10 +913,10 @@ impl GameMap {
                     1,
                     Some(Color::new(fill_color)),
                     3,
-                    32,
+                    render.font_size,
                 ),
                 Orientation::TopToBottom,
-                Point::new(100.0, 100.0),
+                Point::new(render.node_size, render.node_size),
             );
 
             let node = visual.add_node(element);
@@ -348,9 +956,10 @@ impl GameMap {
             };
 
             let arrow = if is_there_bidirectional_edge || has_bidirectional_loop {
-                let mut arrow = Arrow::default();
-                arrow.end = LineEndKind::None;
-                arrow
+                Arrow {
+                    end: LineEndKind::None,
+                    ..Default::default()
+                }
             } else {
                 Arrow::default()
             };
@@ -8,81 +8,21 @@ use quickcheck::{TestResult, quickcheck};
 use rand::{SeedableRng, rngs::StdRng};
 use rplcs_events::tournament_1::{MapNodeType, PlayerState};
 
-use crate::game_map::GameMap;
+use crate::game_map::{GameMap, MapConfig, NodeCount, NodeProportions, RenderConfig};
 
-use super::{MAX_DEGREE, MIN_DEGREE};
+use super::{MAX_DEGREE, MIN_DEGREE, MIN_NORMAL_NODES, node_type_index};
 
 fn validate_map(map: &GameMap, seed: i64) -> TestResult {
-    // Check node counts
-    let node_weights: Vec<_> = map.node_weights();
-    let node_count = node_weights.len();
-
-    if !(12..=16).contains(&node_count) {
-        return TestResult::error(format!(
-            "Map should have between 12 and 16 nodes (has {}) [seed: {}]",
-            node_count, seed
-        ));
+    match map.validate(&MapConfig::default()) {
+        Ok(()) => TestResult::passed(),
+        Err(e) => TestResult::error(format!("{} [seed: {}]", e, seed)),
     }
-
-    let teleport_count = node_weights
-        .iter()
-        .filter(|&&n| matches!(n, MapNodeType::Teleport))
-        .count();
-    if teleport_count != 1 {
-        return TestResult::error(format!(
-            "Map should have exactly 1 teleport node (has {}) [seed: {}]",
-            teleport_count, seed
-        ));
-    }
-
-    let healing_count = node_weights
-        .iter()
-        .filter(|&&n| matches!(n, MapNodeType::Healing))
-        .count();
-    if healing_count < 1 {
-        return TestResult::error(format!(
-            "Map should have at least 1 healing node (has {}) [seed: {}]",
-            healing_count, seed
-        ));
-    }
-
-    let gamble_count = node_weights
-        .iter()
-        .filter(|&&n| matches!(n, MapNodeType::Gamble))
-        .count();
-    if gamble_count < 1 {
-        return TestResult::error(format!(
-            "Map should have at least 1 gamble node (has {}) [seed: {}]",
-            gamble_count, seed
-        ));
-    }
-
-    // Check edge constraints
-    for node in map.node_indices() {
-        let loops = map.get_loops(node).len();
-        if loops >= 3 {
-            return TestResult::error(format!(
-                "Node {:?} has invalid number of loops: {} [seed: {}]",
-                node, loops, seed
-            ));
-        }
-
-        let node_degree = map.get_node_degree(node);
-        if node_degree < MIN_DEGREE || node_degree > MAX_DEGREE {
-            return TestResult::error(format!(
-                "Node {:?} has invalid degree: {} [seed: {}]",
-                node, node_degree, seed
-            ));
-        }
-    }
-
-    TestResult::passed()
 }
 
 quickcheck! {
     fn test_map_generation(seed: i64) -> TestResult {
         let mut rng = StdRng::seed_from_u64(seed as u64);
-        let map = GameMap::new(&mut rng).expect("Failed to generate map");
+        let map = GameMap::new(&mut rng, &MapConfig::default()).expect("Failed to generate map");
         validate_map(&map, seed)
     }
 }
@@ -137,6 +77,388 @@ quickcheck! {
     }
 }
 
+quickcheck! {
+    fn test_map_generation_with_node_proportions(seed: i64) -> TestResult {
+        let proportions = NodeProportions {
+            normal: 5.0,
+            healing: 2.0,
+            gamble: 2.0,
+            teleport: 1.0,
+        };
+        let config = MapConfig {
+            node_proportions: Some(proportions),
+            ..MapConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let map = GameMap::new(&mut rng, &config).expect("Failed to generate map");
+
+        let node_weights = map.node_weights();
+        let node_count = node_weights.len() as f64;
+        let total = proportions.normal + proportions.healing + proportions.gamble + proportions.teleport;
+        for (node_type, weight) in [
+            (MapNodeType::Normal, proportions.normal),
+            (MapNodeType::Healing, proportions.healing),
+            (MapNodeType::Gamble, proportions.gamble),
+            (MapNodeType::Teleport, proportions.teleport),
+        ] {
+            let count = node_weights.iter().filter(|&&n| n == node_type).count() as f64;
+            let expected = weight / total * node_count;
+            if (count - expected).abs() >= 2.0 {
+                return TestResult::error(format!(
+                    "{:?} count {} too far from expected {:.2} [seed: {}]",
+                    node_type, count, expected, seed
+                ));
+            }
+        }
+
+        for node in map.node_indices() {
+            let node_degree = map.get_node_degree(node);
+            if !(MIN_DEGREE..=MAX_DEGREE).contains(&node_degree) {
+                return TestResult::error(format!(
+                    "Node {:?} has invalid degree: {} [seed: {}]",
+                    node, node_degree, seed
+                ));
+            }
+        }
+
+        TestResult::passed()
+    }
+}
+
+quickcheck! {
+    fn test_one_way_heavy_maps_stay_strongly_connected(seed: i64, chance: u8) -> TestResult {
+        let config = MapConfig {
+            bidirectional_edge_chance: Some((chance % 5) as f64 / 10.0),
+            ..MapConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let map = GameMap::new(&mut rng, &config).expect("Failed to generate map");
+
+        if !map.is_strongly_connected() {
+            return TestResult::error(format!(
+                "Map is not strongly connected (chance: {:?}) [seed: {}]",
+                config.bidirectional_edge_chance, seed
+            ));
+        }
+        for node in map.node_indices() {
+            let node_degree = map.get_node_degree(node);
+            if !(MIN_DEGREE..=MAX_DEGREE).contains(&node_degree) {
+                return TestResult::error(format!(
+                    "Node {:?} has invalid degree: {} [seed: {}]",
+                    node, node_degree, seed
+                ));
+            }
+        }
+
+        TestResult::passed()
+    }
+}
+
+/// 0 -> 1 -> 2 -> 3 with a shortcut 0 -> 2 and a way back 3 -> 0. Node 4 is unreachable.
+fn path_test_map() -> GameMap {
+    let mut graph = DiGraph::new();
+    let nodes = [
+        graph.add_node(MapNodeType::Normal),
+        graph.add_node(MapNodeType::Gamble),
+        graph.add_node(MapNodeType::Normal),
+        graph.add_node(MapNodeType::Healing),
+        graph.add_node(MapNodeType::Teleport),
+    ];
+    for (from, to) in [(0, 1), (1, 2), (2, 3), (0, 2), (3, 0), (4, 0)] {
+        graph.add_edge(nodes[from], nodes[to], ());
+    }
+    GameMap::from_graph(graph)
+}
+
+#[test]
+fn test_shortest_path_follows_outgoing_edges() {
+    let map = path_test_map();
+    let node = NodeIndex::new;
+
+    assert_eq!(
+        map.shortest_path(node(0), node(3)),
+        Some(vec![node(0), node(2), node(3)])
+    );
+    // Going back has to use the one-way edge 3 -> 0
+    assert_eq!(
+        map.shortest_path(node(2), node(1)),
+        Some(vec![node(2), node(3), node(0), node(1)])
+    );
+    assert_eq!(map.shortest_path(node(1), node(1)), Some(vec![node(1)]));
+    assert_eq!(map.shortest_path(node(0), node(4)), None);
+    assert_eq!(map.shortest_path(node(0), node(9)), None);
+}
+
+#[test]
+fn test_unreachable_nodes_are_not_placed_as_farthest() {
+    // A one-way loop 0 -> 1 -> 2 -> 3, a teleport 4 into it and a node 5 no edge touches
+    let mut graph = DiGraph::new();
+    let nodes: Vec<_> = (0..6)
+        .map(|i| {
+            graph.add_node(match i {
+                4 => MapNodeType::Teleport,
+                _ => MapNodeType::Normal,
+            })
+        })
+        .collect();
+    for (from, to) in [(0, 1), (1, 2), (2, 3), (3, 0), (4, 0)] {
+        graph.add_edge(nodes[from], nodes[to], ());
+    }
+    let map = GameMap::from_graph(graph);
+    let node = NodeIndex::new;
+
+    assert_eq!(map.moves_apart(node(0), node(2)), Some(2));
+    assert_eq!(map.moves_apart(node(0), node(5)), None);
+    for seed in 0..50 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        assert_eq!(
+            map.get_random_node_away_from(node(0), 10, &mut rng),
+            Some(node(2)),
+            "seed {seed}"
+        );
+    }
+
+    // With nothing reachable, any empty node will do
+    let mut rng = StdRng::seed_from_u64(0);
+    assert_eq!(
+        map.get_random_node_away_from(node(5), 10, &mut rng)
+            .map(|found| found != node(5) && found != node(4)),
+        Some(true)
+    );
+}
+
+#[test]
+fn test_neighbors_are_queried_by_node_type() {
+    let map = path_test_map();
+    let node = NodeIndex::new;
+
+    assert_eq!(
+        map.neighbors_of_type(node(0), MapNodeType::Gamble),
+        [node(1)]
+    );
+    assert_eq!(
+        map.neighbors_of_type(node(0), MapNodeType::Normal),
+        [node(2)]
+    );
+    assert!(
+        map.neighbors_of_type(node(0), MapNodeType::Healing)
+            .is_empty()
+    );
+    // Only outgoing edges count: the teleport points at node 0, not the other way around
+    assert!(
+        map.neighbors_of_type(node(0), MapNodeType::Teleport)
+            .is_empty()
+    );
+    assert_eq!(
+        map.neighbors_of_type(node(2), MapNodeType::Healing),
+        [node(3)]
+    );
+
+    // Types line up with the outgoing nodes, so they can stand for move choices
+    for from in map.node_indices() {
+        let expected: Vec<_> = map
+            .get_outgoing_nodes(from)
+            .into_iter()
+            .map(|to| map.get_node_type(to).unwrap())
+            .collect();
+        assert_eq!(map.node_types_adjacent(from), expected);
+    }
+    let mut types = map.node_types_adjacent(node(0));
+    types.sort_by_key(|&t| node_type_index(t));
+    assert_eq!(types, [MapNodeType::Gamble, MapNodeType::Normal]);
+    assert_eq!(map.node_types_adjacent(node(4)), [MapNodeType::Normal]);
+}
+
+#[test]
+fn test_distance_to_nearest_node_type() {
+    let map = path_test_map();
+    let node = NodeIndex::new;
+
+    assert_eq!(
+        map.distance_to_nearest(node(0), MapNodeType::Healing),
+        Some(2)
+    );
+    assert_eq!(
+        map.distance_to_nearest(node(2), MapNodeType::Gamble),
+        Some(3)
+    );
+    assert_eq!(
+        map.distance_to_nearest(node(3), MapNodeType::Healing),
+        Some(0)
+    );
+    assert_eq!(
+        map.distance_to_nearest(node(0), MapNodeType::Teleport),
+        None
+    );
+    assert_eq!(
+        map.distance_to_nearest(node(4), MapNodeType::Healing),
+        Some(3)
+    );
+}
+
+#[test]
+fn test_low_bidirectional_chance_produces_one_way_edges() {
+    let config = MapConfig {
+        bidirectional_edge_chance: Some(0.0),
+        ..MapConfig::default()
+    };
+    let one_way_edges: usize = (0..50)
+        .map(|seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let map = GameMap::new(&mut rng, &config).expect("Failed to generate map");
+            map.node_indices()
+                .into_iter()
+                .flat_map(|node| {
+                    map.get_outgoing_nodes(node)
+                        .into_iter()
+                        .map(move |t| (node, t))
+                })
+                .filter(|&(node, target)| !map.get_outgoing_nodes(target).contains(&node))
+                .count()
+        })
+        .sum();
+
+    assert!(one_way_edges > 0);
+}
+
+#[test]
+fn test_greater_healing_nodes_are_generated_and_serialized() {
+    let config = MapConfig {
+        greater_healing_chance: 1.0,
+        ..MapConfig::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let map = GameMap::new(&mut rng, &config).unwrap();
+    for node in map.node_indices() {
+        let healing = map.get_node_type(node) == Some(MapNodeType::Healing);
+        assert_eq!(map.is_greater_healing(node), healing);
+    }
+
+    let restored = GameMap::from_json(&map.to_json().unwrap()).unwrap();
+    assert!(
+        map.node_indices()
+            .into_iter()
+            .all(|node| restored.is_greater_healing(node) == map.is_greater_healing(node))
+    );
+
+    // Without any, maps serialize like the bare graph they used to be
+    let plain = GameMap::new(&mut rng, &MapConfig::default()).unwrap();
+    assert!(!plain.to_json().unwrap().contains("greater_healing"));
+}
+
+#[test]
+fn test_node_proportions_keep_a_teleport_node() {
+    let proportions = NodeProportions {
+        normal: 1.0,
+        healing: 1.0,
+        gamble: 1.0,
+        teleport: 0.0,
+    };
+    for num_nodes in 12..=16 {
+        let counts = proportions.node_counts(num_nodes).unwrap();
+        assert_eq!(counts[0], 1);
+        assert_eq!(counts.iter().sum::<usize>(), num_nodes);
+    }
+}
+
+#[test]
+fn test_invalid_node_proportions_are_rejected() {
+    let zero = NodeProportions {
+        normal: 0.0,
+        healing: 0.0,
+        gamble: 0.0,
+        teleport: 0.0,
+    };
+    assert!(zero.node_counts(12).is_err());
+
+    let negative = NodeProportions {
+        normal: 1.0,
+        healing: -1.0,
+        gamble: 1.0,
+        teleport: 1.0,
+    };
+    assert!(negative.node_counts(12).is_err());
+}
+
+#[test]
+fn test_configured_node_counts_are_used() {
+    let config = MapConfig {
+        healing_nodes: NodeCount::Fixed(3),
+        gamble_nodes: NodeCount::Range { min: 2, max: 4 },
+        ..MapConfig::default()
+    };
+    for seed in 0..50 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let weights = GameMap::new(&mut rng, &config).unwrap().node_weights();
+        let count = |node_type| weights.iter().filter(|&&n| n == node_type).count();
+
+        assert_eq!(count(MapNodeType::Teleport), 1);
+        assert_eq!(count(MapNodeType::Healing), 3);
+        assert!((2..=4).contains(&count(MapNodeType::Gamble)));
+        assert!(count(MapNodeType::Normal) >= MIN_NORMAL_NODES);
+    }
+}
+
+#[test]
+fn test_over_specified_node_counts_are_rejected() {
+    let configs = [
+        MapConfig {
+            healing_nodes: NodeCount::Fixed(20),
+            ..MapConfig::default()
+        },
+        MapConfig {
+            healing_nodes: NodeCount::Range { min: 1, max: 5 },
+            gamble_nodes: NodeCount::Range { min: 1, max: 5 },
+            ..MapConfig::default()
+        },
+        MapConfig {
+            gamble_nodes: NodeCount::Range { min: 3, max: 1 },
+            ..MapConfig::default()
+        },
+        MapConfig {
+            teleport_nodes: NodeCount::Fixed(0),
+            ..MapConfig::default()
+        },
+    ];
+    for config in configs {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(GameMap::new(&mut rng, &config).is_err(), "{:?}", config);
+    }
+}
+
+fn render_svg(render: &RenderConfig) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("map.svg");
+    let mut rng = StdRng::seed_from_u64(7);
+    let map = GameMap::new(&mut rng, &MapConfig::default()).unwrap();
+    map.render_to_file(
+        [NodeIndex::new(0), NodeIndex::new(1)],
+        &[NodeIndex::new(2), NodeIndex::new(3)],
+        &[PlayerState::default(), PlayerState::default()],
+        &[PlayerState::default(), PlayerState::default()],
+        render,
+        &path,
+    )
+    .unwrap();
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_compact_render_omits_stats() {
+    let stats = {
+        let state = PlayerState::default();
+        format!("{}/{} {}", state.health, state.max_health, state.power)
+    };
+
+    let detailed = render_svg(&RenderConfig::default());
+    assert!(detailed.contains(&format!("A {}", stats)));
+
+    let compact = render_svg(&RenderConfig::compact());
+    assert!(!compact.contains(&stats));
+    assert!(compact.contains(">A<"));
+    assert!(compact.contains(">E<"));
+}
+
 #[test]
 #[ignore]
 fn test_specific_seeds() {
@@ -145,12 +467,13 @@ fn test_specific_seeds() {
 
     for &seed in &problem_seeds {
         let mut rng = StdRng::seed_from_u64(seed as u64);
-        let map = GameMap::new(&mut rng).expect("Failed to generate map");
+        let map = GameMap::new(&mut rng, &MapConfig::default()).expect("Failed to generate map");
         map.render_to_file(
             [NodeIndex::new(0), NodeIndex::new(1)],
-            [NodeIndex::new(2), NodeIndex::new(3)],
+            &[NodeIndex::new(2), NodeIndex::new(3)],
             &[PlayerState::default(), PlayerState::default()],
             &[PlayerState::default(), PlayerState::default()],
+            &RenderConfig::default(),
             Path::new("problem_map.svg"),
         )
         .expect("Failed to render map");
@@ -161,3 +484,28 @@ fn test_specific_seeds() {
         }
     }
 }
+
+#[test]
+fn test_malformed_map_fails_validation() {
+    // A one-way ring has the right size but no special nodes and degrees of 1
+    let mut graph = DiGraph::new();
+    let nodes: Vec<_> = (0..12)
+        .map(|_| graph.add_node(MapNodeType::Normal))
+        .collect();
+    for (i, &node) in nodes.iter().enumerate() {
+        graph.add_edge(node, nodes[(i + 1) % nodes.len()], ());
+    }
+    let error = GameMap::from_graph(graph.clone())
+        .validate(&MapConfig::default())
+        .unwrap_err();
+    assert!(error.to_string().contains("Teleport"), "{error}");
+
+    // With the special nodes in place, the degrees are what's wrong
+    graph[nodes[0]] = MapNodeType::Teleport;
+    graph[nodes[1]] = MapNodeType::Healing;
+    graph[nodes[2]] = MapNodeType::Gamble;
+    let error = GameMap::from_graph(graph)
+        .validate(&MapConfig::default())
+        .unwrap_err();
+    assert!(error.to_string().contains("invalid degree"), "{error}");
+}
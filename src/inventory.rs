@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use figment::{
+    Figment,
+    providers::{Format as _, Toml},
+};
+use serde::Deserialize;
+use std::{fmt, path::Path};
+
+use rplcs_events::tournament_1::PlayerState;
+
+use crate::{
+    load_submission_names,
+    manifest::{Backend, Manifest},
+    submission::STARTING_STATE,
+};
+
+mod tests;
+
+/// Optional `meta.toml` in a submission's directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SubmissionMeta {
+    pub display_name: Option<String>,
+    pub author: Option<String>,
+    /// Starting stats replacing the usual ones, to handicap a submission in exhibition matches.
+    pub health: Option<u32>,
+    pub max_health: Option<u32>,
+    pub power: Option<u32>,
+}
+
+impl SubmissionMeta {
+    /// Reads `meta.toml` in `dir`, which is all defaults if there is none.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let meta_path = dir.join("meta.toml");
+        if !meta_path.exists() {
+            return Ok(Self::default());
+        }
+        Figment::from(Toml::file(&meta_path))
+            .extract()
+            .with_context(|| format!("Failed to read {}", meta_path.display()))
+    }
+
+    /// The state the submission starts every game in, its health capped at its maximum.
+    pub fn starting_state(&self) -> PlayerState {
+        let max_health = self.max_health.unwrap_or(STARTING_STATE.max_health);
+        PlayerState {
+            health: self.health.unwrap_or(STARTING_STATE.health).min(max_health),
+            max_health,
+            power: self.power.unwrap_or(STARTING_STATE.power),
+        }
+    }
+}
+
+/// What is known about a submission before running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmissionEntry {
+    pub name: String,
+    pub meta: SubmissionMeta,
+    /// Whether the submission's container image has been built.
+    pub image: bool,
+    /// Whether the directory holds a `.wasm` file.
+    pub wasm: bool,
+}
+
+impl SubmissionEntry {
+    /// Whether there is anything to run for the submission.
+    pub fn is_runnable(&self) -> bool {
+        self.image || self.wasm
+    }
+}
+
+impl fmt::Display for SubmissionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(display_name) = &self.meta.display_name {
+            write!(f, " \"{}\"", display_name)?;
+        }
+        if let Some(author) = &self.meta.author {
+            write!(f, " by {}", author)?;
+        }
+        write!(
+            f,
+            ": image {}, wasm {}",
+            if self.image { "found" } else { "MISSING" },
+            if self.wasm { "found" } else { "none" }
+        )
+    }
+}
+
+/// Lists the submissions in `submissions_dir` by name, checking each one's image with
+/// `image_exists`.
+pub async fn list_submissions(
+    submissions_dir: &Path,
+    image_exists: impl AsyncFn(&str) -> Result<bool>,
+) -> Result<Vec<SubmissionEntry>> {
+    let mut names = load_submission_names(
+        submissions_dir
+            .to_str()
+            .context("Submissions directory is not valid UTF-8")?,
+    )?;
+    names.sort();
+
+    let mut entries = Vec::new();
+    for name in names {
+        let dir = submissions_dir.join(&name);
+        let meta = SubmissionMeta::load(&dir)?;
+        let wasm = has_wasm(&dir)?;
+        let image = image_exists(&name).await?;
+        entries.push(SubmissionEntry {
+            name,
+            meta,
+            image,
+            wasm,
+        });
+    }
+    Ok(entries)
+}
+
+/// Lists the submissions of `manifest` by name, checking the image of each container one with
+/// `image_exists`.
+pub async fn list_manifest_submissions(
+    manifest: &Manifest,
+    image_exists: impl AsyncFn(&str) -> Result<bool>,
+) -> Result<Vec<SubmissionEntry>> {
+    let mut manifest_entries: Vec<_> = manifest.submissions.iter().collect();
+    manifest_entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut entries = Vec::new();
+    for entry in manifest_entries {
+        let path = entry.path();
+        let image = match entry.backend {
+            Backend::Container => image_exists(&entry.image()).await?,
+            Backend::Wasm => false,
+        };
+        entries.push(SubmissionEntry {
+            name: entry.name.clone(),
+            meta: SubmissionMeta::load(&path)?,
+            image,
+            wasm: has_wasm(&path)?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Whether `path` is a `.wasm` file or a directory holding one.
+fn has_wasm(path: &Path) -> Result<bool> {
+    let is_wasm = |path: &Path| path.extension().is_some_and(|ext| ext == "wasm");
+    if !path.is_dir() {
+        return Ok(path.is_file() && is_wasm(path));
+    }
+    Ok(path
+        .read_dir()
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .filter_map(Result::ok)
+        .any(|entry| is_wasm(&entry.path())))
+}
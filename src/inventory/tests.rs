@@ -0,0 +1,150 @@
+#![cfg(test)]
+
+use std::{fs, path::Path, sync::Arc};
+
+use rplcs_events::tournament_1::PlayerState;
+
+use crate::{
+    bots::built_in_bots,
+    manifest::{Backend, Manifest, ManifestEntry},
+    submission::{STARTING_STATE, Submission},
+};
+
+use super::{SubmissionMeta, list_manifest_submissions, list_submissions};
+
+#[tokio::test]
+async fn test_list_includes_submissions_and_flags_missing_images() {
+    let submissions_dir = tempfile::tempdir().unwrap();
+    for name in ["bravo", "alpha", "charlie"] {
+        fs::create_dir(submissions_dir.path().join(name)).unwrap();
+    }
+    fs::write(
+        submissions_dir.path().join("alpha/meta.toml"),
+        "display_name = \"Alpha Bot\"\nauthor = \"ferris\"\n",
+    )
+    .unwrap();
+    fs::write(submissions_dir.path().join("charlie/bot.wasm"), b"\0asm").unwrap();
+    fs::write(submissions_dir.path().join("README.md"), "not a submission").unwrap();
+
+    let entries = list_submissions(submissions_dir.path(), async |name: &str| {
+        Ok(name == "alpha")
+    })
+    .await
+    .unwrap();
+
+    let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, ["alpha", "bravo", "charlie"]);
+
+    let [alpha, bravo, charlie] = &entries[..] else {
+        unreachable!()
+    };
+    assert_eq!(
+        alpha.meta,
+        SubmissionMeta {
+            display_name: Some("Alpha Bot".to_string()),
+            author: Some("ferris".to_string()),
+            ..SubmissionMeta::default()
+        }
+    );
+    assert_eq!(
+        alpha.to_string(),
+        "alpha \"Alpha Bot\" by ferris: image found, wasm none"
+    );
+    assert!(!bravo.is_runnable());
+    assert_eq!(bravo.to_string(), "bravo: image MISSING, wasm none");
+    assert!(!charlie.image && charlie.wasm && charlie.is_runnable());
+}
+
+#[tokio::test]
+async fn test_manifest_list_has_its_entries_instead_of_the_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let remote_dir = dir.path().join("remote");
+    fs::create_dir(&remote_dir).unwrap();
+    fs::write(remote_dir.join("meta.toml"), "author = \"ferris\"\n").unwrap();
+    let module = dir.path().join("module.wasm");
+    fs::write(&module, b"\0asm").unwrap();
+    let entry = |name: &str, backend, image: Option<&str>, path: Option<&Path>| ManifestEntry {
+        name: name.to_string(),
+        backend,
+        image: image.map(str::to_string),
+        path: path.map(Path::to_path_buf),
+    };
+    let manifest = Manifest {
+        submissions: vec![
+            entry(
+                "remote",
+                Backend::Container,
+                Some("ghcr.io/me/remote:v2"),
+                Some(&remote_dir),
+            ),
+            entry("module", Backend::Wasm, None, Some(&module)),
+        ],
+    };
+
+    let entries = list_manifest_submissions(&manifest, async |image: &str| {
+        Ok(image == "ghcr.io/me/remote:v2")
+    })
+    .await
+    .unwrap();
+
+    let [module, remote] = &entries[..] else {
+        panic!("{entries:?}")
+    };
+    assert_eq!(module.to_string(), "module: image MISSING, wasm found");
+    assert_eq!(
+        remote.to_string(),
+        "remote by ferris: image found, wasm none"
+    );
+}
+
+#[test]
+fn test_meta_stats_override_each_submission_starting_state() {
+    let submissions_dir = tempfile::tempdir().unwrap();
+    let handicapped = submissions_dir.path().join("strong");
+    let boosted = submissions_dir.path().join("weak");
+    fs::create_dir(&handicapped).unwrap();
+    fs::create_dir(&boosted).unwrap();
+    fs::write(
+        handicapped.join("meta.toml"),
+        "health = 1
+power = 3
+",
+    )
+    .unwrap();
+    fs::write(
+        boosted.join("meta.toml"),
+        "max_health = 5
+health = 5
+",
+    )
+    .unwrap();
+
+    let bot = Arc::clone(&built_in_bots(None)[0].1);
+    let strong = Submission::new("strong", bot.clone())
+        .with_starting_state(SubmissionMeta::load(&handicapped).unwrap().starting_state());
+    let weak = Submission::new("weak", bot)
+        .with_starting_state(SubmissionMeta::load(&boosted).unwrap().starting_state());
+
+    assert_eq!(
+        *strong.player_state(),
+        PlayerState {
+            health: 1,
+            max_health: 3,
+            power: 3
+        }
+    );
+    assert_eq!(
+        *weak.player_state(),
+        PlayerState {
+            health: 5,
+            max_health: 5,
+            power: 5
+        }
+    );
+    // Without a meta.toml, the usual stats
+    let plain = submissions_dir.path().join("plain");
+    assert_eq!(
+        SubmissionMeta::load(&plain).unwrap().starting_state(),
+        STARTING_STATE
+    );
+}
@@ -0,0 +1,77 @@
+//! Version marker of the results directory, so a run doesn't mix its files into a directory
+//! written with a different layout by another version of the runner.
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+mod tests;
+
+/// Version of the layout of the results directory. Bump it whenever files are moved, renamed or
+/// change format in a way older runs can't be mixed with.
+pub const LAYOUT_VERSION: u32 = 2;
+
+/// Name of the marker file inside the results directory.
+pub const MARKER_FILE: &str = ".layout";
+
+/// Contents of the marker file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutMarker {
+    /// Version of the runner that first wrote to the directory.
+    pub crate_version: String,
+    pub layout: u32,
+}
+
+impl LayoutMarker {
+    pub fn current() -> Self {
+        LayoutMarker {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            layout: LAYOUT_VERSION,
+        }
+    }
+}
+
+/// Checks that `dir` was written with the current layout, writing the marker if it has none yet.
+/// An incompatible or unreadable marker is an error, unless `force` is set, in which case it is
+/// replaced by the current one.
+pub fn ensure_layout(dir: &Path, force: bool) -> Result<()> {
+    let path = dir.join(MARKER_FILE);
+    if path.exists() {
+        let found = fs::read_to_string(&path)
+            .context("Failed to read the marker")
+            .and_then(|json| serde_json::from_str::<LayoutMarker>(&json).map_err(Into::into));
+        let problem = match found {
+            Ok(marker) if marker.layout == LAYOUT_VERSION => return Ok(()),
+            Ok(marker) => format!(
+                "was written with layout version {} by version {} of the runner, but this one \
+                 uses layout version {}",
+                marker.layout, marker.crate_version, LAYOUT_VERSION
+            ),
+            Err(e) => format!("has an unreadable layout marker ({:#})", e),
+        };
+        if !force {
+            bail!(
+                "The results directory {} {}. Move it away, or pass --force to write into it \
+                 anyway",
+                dir.display(),
+                problem
+            );
+        }
+        warn!(
+            "The results directory {} {}, replacing its marker because of --force",
+            dir.display(),
+            problem
+        );
+    } else {
+        info!(
+            "Marking {} with results layout version {}",
+            dir.display(),
+            LAYOUT_VERSION
+        );
+    }
+
+    fs::create_dir_all(dir)?;
+    let json = serde_json::to_string_pretty(&LayoutMarker::current())?;
+    fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,42 @@
+#![cfg(test)]
+
+use std::fs;
+
+use super::{LAYOUT_VERSION, LayoutMarker, MARKER_FILE, ensure_layout};
+
+#[test]
+fn test_first_run_writes_the_layout_marker() {
+    let dir = tempfile::tempdir().unwrap();
+    ensure_layout(dir.path(), false).unwrap();
+
+    let json = fs::read_to_string(dir.path().join(MARKER_FILE)).unwrap();
+    let marker: LayoutMarker = serde_json::from_str(&json).unwrap();
+    assert_eq!(marker, LayoutMarker::current());
+    // Later runs accept their own marker
+    ensure_layout(dir.path(), false).unwrap();
+}
+
+#[test]
+fn test_incompatible_layout_marker_is_refused_without_force() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = LayoutMarker {
+        crate_version: "0.0.1".to_string(),
+        layout: LAYOUT_VERSION + 1,
+    };
+    let path = dir.path().join(MARKER_FILE);
+    fs::write(&path, serde_json::to_string(&old).unwrap()).unwrap();
+
+    let error = ensure_layout(dir.path(), false).unwrap_err().to_string();
+    assert!(error.contains("--force"), "{error}");
+    assert!(error.contains("0.0.1"), "{error}");
+    // The marker is left alone until forced
+    let json = fs::read_to_string(&path).unwrap();
+    assert_eq!(serde_json::from_str::<LayoutMarker>(&json).unwrap(), old);
+
+    ensure_layout(dir.path(), true).unwrap();
+    let json = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        serde_json::from_str::<LayoutMarker>(&json).unwrap(),
+        LayoutMarker::current()
+    );
+}
@@ -0,0 +1,151 @@
+//! Live state of the games currently being played, served as JSON on `GET /games` for spectator
+//! UIs. Games publish their state every turn and are removed once they end. `GET /health` is a
+//! readiness probe for the results database.
+
+use anyhow::{Context, Result};
+use log::{debug, info};
+use rplcs_events::tournament_1::PlayerState;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::db::Database;
+
+mod tests;
+
+/// State of a game in progress, as of the start of its current turn.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiveGame {
+    pub matchup_id: i64,
+    pub game_id: i64,
+    pub turn: i64,
+    /// Names of the players, the first mover first.
+    pub players: [String; 2],
+    pub player_positions: [usize; 2],
+    pub player_states: [PlayerState; 2],
+    pub enemy_positions: Vec<usize>,
+    pub enemies: Vec<PlayerState>,
+}
+
+/// Games in progress by matchup and game id, since game ids restart in every matchup.
+static GAMES: OnceLock<Mutex<BTreeMap<(i64, i64), LiveGame>>> = OnceLock::new();
+
+fn games() -> &'static Mutex<BTreeMap<(i64, i64), LiveGame>> {
+    GAMES.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Database checked by `GET /health`, the one of the command being run.
+static DATABASE: Mutex<Option<Database>> = Mutex::new(None);
+
+/// Makes `GET /health` report on `db`.
+pub fn watch_database(db: &Database) {
+    *DATABASE.lock().unwrap() = Some(db.clone());
+}
+
+/// Publishes the state of a game, replacing the one from its previous turn.
+pub fn publish(game: LiveGame) {
+    games()
+        .lock()
+        .unwrap()
+        .insert((game.matchup_id, game.game_id), game);
+}
+
+/// Removes a game from the live games once dropped, whether it ended, failed, or was cut short
+/// by `game_timeout` dropping its future.
+pub struct LiveGameGuard {
+    matchup_id: i64,
+    game_id: i64,
+}
+
+impl LiveGameGuard {
+    pub fn new(matchup_id: i64, game_id: i64) -> Self {
+        LiveGameGuard {
+            matchup_id,
+            game_id,
+        }
+    }
+}
+
+impl Drop for LiveGameGuard {
+    fn drop(&mut self) {
+        games()
+            .lock()
+            .unwrap()
+            .remove(&(self.matchup_id, self.game_id));
+    }
+}
+
+/// Every game in progress, ordered by matchup and game id.
+pub fn snapshot() -> Vec<LiveGame> {
+    games().lock().unwrap().values().cloned().collect()
+}
+
+/// Serves the live games on `port` of every interface, 0 for any free port. Returns the port
+/// and the server task.
+pub async fn serve(port: u16) -> Result<(u16, JoinHandle<()>)> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    let port = listener.local_addr()?.port();
+    info!(
+        "Serving live game states on http://localhost:{}/games",
+        port
+    );
+
+    let server = tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                if let Err(e) = respond(stream).await {
+                    debug!("Failed to answer live state request: {:#}", e);
+                }
+            });
+        }
+    });
+    Ok((port, server))
+}
+
+/// Answers a single request, `GET /games` with the live games, `GET /health` with whether the
+/// database answers and anything else with a 404.
+async fn respond(mut stream: TcpStream) -> Result<()> {
+    // Only the request line matters, and it fits in the first read
+    let mut buf = [0; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let target = request.split(' ').take(2).collect::<Vec<_>>();
+
+    let (status, body) = if target == ["GET", "/games"] {
+        ("200 OK", serde_json::to_string(&snapshot())?)
+    } else if target == ["GET", "/health"] {
+        let db = DATABASE.lock().unwrap().clone();
+        match db {
+            Some(db) => match db.health().await {
+                Ok(()) => ("200 OK", r#"{"status":"ok"}"#.to_string()),
+                Err(e) => (
+                    "503 Service Unavailable",
+                    serde_json::json!({ "error": format!("{:#}", e) }).to_string(),
+                ),
+            },
+            None => (
+                "503 Service Unavailable",
+                r#"{"error":"no database yet"}"#.to_string(),
+            ),
+        }
+    } else {
+        ("404 Not Found", r#"{"error":"not found"}"#.to_string())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
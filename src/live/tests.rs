@@ -0,0 +1,157 @@
+#![cfg(test)]
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rplcs_events::tournament_1::{ChoiceResponse, FightChoices, FightInfo};
+use tokio::sync::Semaphore;
+
+use crate::{
+    Config,
+    db::Database,
+    game::GameBuilder,
+    submission::{ChoicesRequest, GambleResponse, Player, Submission},
+};
+
+use super::{serve, watch_database};
+
+/// Takes the first move, but only once the test hands out a permit for it.
+struct GatedPlayer {
+    moves: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl Player for GatedPlayer {
+    async fn get_choices(&self, _: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        self.moves.acquire().await?.forget();
+        Ok(ChoiceResponse { choice_index: 0 })
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+async fn live_games(port: u16, matchup_id: i64) -> Vec<serde_json::Value> {
+    let games: Vec<serde_json::Value> = reqwest::get(format!("http://localhost:{}/games", port))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    games
+        .into_iter()
+        .filter(|game| game["matchup_id"] == matchup_id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_running_game_state_is_served_until_it_ends() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 4,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("live_a", "live_b").await.unwrap();
+    let (port, server) = serve(0).await.unwrap();
+
+    let moves = Arc::new(Semaphore::new(0));
+    let mut game = GameBuilder::new(
+        Submission::new(
+            "live_a",
+            Arc::new(GatedPlayer {
+                moves: moves.clone(),
+            }),
+        ),
+        Submission::new(
+            "live_b",
+            Arc::new(GatedPlayer {
+                moves: moves.clone(),
+            }),
+        ),
+    )
+    .ids(3, matchup_id)
+    .config(config)
+    .build()
+    .unwrap();
+    let running = tokio::spawn(async move { game.result(&db).await });
+
+    // The game waits for the first move on turn 0
+    let mut games = Vec::new();
+    for _ in 0..100 {
+        games = live_games(port, matchup_id).await;
+        if !games.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(games.len(), 1, "{games:?}");
+    assert_eq!(games[0]["game_id"], 3);
+    assert_eq!(games[0]["turn"], 0);
+    assert_eq!(games[0]["players"], serde_json::json!(["live_a", "live_b"]));
+    assert_eq!(games[0]["player_states"][0]["health"], 3);
+
+    moves.add_permits(4);
+    running.await.unwrap().unwrap();
+    assert!(live_games(port, matchup_id).await.is_empty());
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_dropped_game_is_no_longer_served() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("drop_a", "drop_b").await.unwrap();
+    let (port, server) = serve(0).await.unwrap();
+
+    let moves = Arc::new(Semaphore::new(0));
+    let player = || {
+        Arc::new(GatedPlayer {
+            moves: moves.clone(),
+        })
+    };
+    let mut game = GameBuilder::new(
+        Submission::new("drop_a", player()),
+        Submission::new("drop_b", player()),
+    )
+    .ids(0, matchup_id)
+    .config(config)
+    .build()
+    .unwrap();
+
+    // Like `game_timeout`, give up on the game while it waits for a move
+    let timed_out = tokio::time::timeout(Duration::from_millis(200), game.result(&db)).await;
+    assert!(timed_out.is_err());
+    assert!(live_games(port, matchup_id).await.is_empty());
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_health_reports_on_the_watched_database() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let (port, server) = serve(0).await.unwrap();
+
+    watch_database(&Database::new(&config).unwrap());
+    let response = reqwest::get(format!("http://localhost:{}/health", port))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    server.abort();
+}
@@ -1,44 +1,359 @@
-use anyhow::{Context, Result, bail};
-use container::{Container, ContainerHandle};
-use figment::providers::{Env, Format as _, Serialized, Toml};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Parser;
+use cli::{Cli, Command};
+use container::Podman;
+use container_pool::ContainerPool;
 use figment::Figment;
-use game::{Game, GameResult};
-use log::{debug, error, info, warn, LevelFilter};
+use figment::providers::{Env, Format as _, Serialized, Toml};
+use game::{
+    EnemyMovement, FightModel, GambleCostStat, GameBuilder, GameResult, NoMovesPolicy, TieBreak,
+    TimeoutPolicy, TurnOrder, Visibility,
+};
+use game_map::{GameMap, MapConfig, NodeCount, RenderConfig};
+use inventory::SubmissionMeta;
+use log::{LevelFilter, debug, error, info, warn};
+use manifest::Manifest;
+use petgraph::graph::NodeIndex;
+use profile::Bucket;
+use rand::{SeedableRng, rngs::StdRng};
+use report::TieScoring;
+use rplcs_events::tournament_1::{MapNodeType, PlayerState};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs;
-use submission::Submission;
-use tokio::task::JoinSet;
-use tokio::time::timeout;
+use std::future::Future;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use submission::{PendingCall, Player, Submission, SubmissionThrottle};
+use tokio::sync::Semaphore;
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::{Instant, sleep, timeout, timeout_at};
 
+mod bots;
+mod cli;
 mod container;
+mod container_pool;
 mod db;
+mod error;
 mod game;
 mod game_map;
+mod inventory;
+mod layout;
+#[cfg(feature = "live")]
+mod live;
+mod manifest;
+mod map_stats;
 mod port_utils;
+mod profile;
+mod recording_rng;
+mod report;
+mod scheduler;
+mod schema;
+mod simulation;
 mod submission;
-use db::Database;
+mod test_utils;
+mod tests;
+use db::{Database, Disqualification, DisqualificationReason, MatchupRow, PendingPolicy};
+use error::{MatchupTimeout, SubmissionFault, TournamentError};
+
+/// Directory with one subdirectory per submission.
+const SUBMISSIONS_DIR: &str = "submissions";
+
+/// Built-in bundles of mechanic and map settings, applied before `config.toml` and environment
+/// overrides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Preset {
+    Classic,
+    Chaos,
+    Hardcore,
+}
+
+/// How many games a matchup lasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum MatchupLength {
+    /// Exactly this many games.
+    FixedRounds(u64),
+    /// Until a submission has won `wins` games, ties not counting, or `max` games were played.
+    FirstTo { wins: u64, max: u64 },
+}
+
+impl MatchupLength {
+    /// Most games the matchup can last.
+    fn max_games(self) -> u64 {
+        match self {
+            MatchupLength::FixedRounds(rounds) => rounds,
+            MatchupLength::FirstTo { max, .. } => max,
+        }
+    }
+
+    /// Whether a submission has already won the matchup with these wins.
+    fn is_decided(self, a_wins: usize, b_wins: usize) -> bool {
+        match self {
+            MatchupLength::FixedRounds(_) => false,
+            MatchupLength::FirstTo { wins, .. } => a_wins.max(b_wins) as u64 >= wins,
+        }
+    }
+}
 
 #[derive(Deserialize, Serialize, Clone)]
 struct Config {
+    preset: Option<Preset>,
     container_timeout: f32,
     game_timeout: f32,
+    game_retries: u32,
+    matchup_timeout: f32,
     rounds_per_pair: u64,
+    matchup_length: Option<MatchupLength>,
     turns_per_game: u64,
+    max_concurrent_games_per_matchup: usize,
+    max_concurrent_games_per_submission: usize,
+    round_pause: f32,
+    map: MapConfig,
+    render: RenderConfig,
+    results_dir: String,
+    min_call_interval: f32,
+    keep_containers_warm: bool,
+    container_pool_size: usize,
+    container_idle_ttl: f32,
+    shutdown_timeout: f32,
+    container_start_retries: u32,
+    warmup_requests: u32,
+    deep_readiness_check: bool,
+    post_ready_delay: f32,
+    matchup_retries: u32,
+    max_submission_failures: Option<u64>,
+    tie_break: TieBreak,
+    tie_scoring: TieScoring,
+    standings_since: Option<String>,
+    tie_rematch: bool,
+    tie_rematch_turns: u64,
+    timeout_policy: TimeoutPolicy,
+    pending_policy: PendingPolicy,
+    no_moves_policy: NoMovesPolicy,
+    max_invalid_moves: u64,
+    gamble_cost: u32,
+    gamble_cost_stat: GambleCostStat,
+    min_power: u32,
+    greater_healing_amount: u32,
+    enemies_per_nodes: Option<NonZeroUsize>,
+    continue_on_panic: bool,
+    feedback: bool,
+    force_layout: bool,
+    visibility: Visibility,
+    fight_health_factor: f64,
+    fight_model: FightModel,
+    reward_scaling: f64,
+    enemy_move_chance: f64,
+    enemy_movement: EnemyMovement,
+    turn_order: TurnOrder,
+    min_start_distance: usize,
+    stall_penalty_turns: Option<u64>,
+    stall_damage: u32,
+    sudden_death_after: Option<u64>,
+    teleport_cooldown: u64,
+    turn_order_threshold: f64,
+    slowest_count: usize,
+    record_call_latencies: bool,
+    db_max_retries: u32,
+    db_retry_base_delay: f32,
+    db_retry_max_delay: f32,
+    verbose_game: bool,
+    choice_diagnostics: bool,
+    record_turns_without_render: bool,
+    combine_turn_svgs: bool,
+    render_every_nth_turn: u64,
+    max_render_nodes: Option<usize>,
+    max_visualization_bytes: Option<u64>,
+    ndjson_results: bool,
+    tournament_seed: Option<u64>,
+    deterministic: bool,
+    worker_threads: usize,
+    max_total_games: Option<u64>,
+    max_runtime: Option<f32>,
+    trace_calls: bool,
+    tournament_name: String,
+    podman_command: String,
+    max_podman_ops: usize,
+    port_seed: Option<u64>,
+    reuse_existing_containers: bool,
+    container_name_prefix: String,
+    rng_audit: bool,
+    rng_draws: bool,
+    reproduce_hints: bool,
+    profile_timing: bool,
+    #[cfg(feature = "live")]
+    live_port: Option<u16>,
+    /// Loaded from `--manifest`, replacing the scan of the submissions folder.
+    #[serde(skip)]
+    manifest: Option<Arc<Manifest>>,
+    /// Podman process slots shared by every clone of the config, sized by `max_podman_ops` at
+    /// startup.
+    #[serde(skip, default = "default_podman_ops")]
+    podman_ops: Arc<Semaphore>,
+}
+
+fn default_podman_ops() -> Arc<Semaphore> {
+    Arc::new(Semaphore::new(container::DEFAULT_MAX_PODMAN_OPS))
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            preset: None,
             container_timeout: 10.0,
             game_timeout: 30.0,
+            game_retries: 0,
+            matchup_timeout: 3600.0,
             rounds_per_pair: 50,
+            matchup_length: None,
             turns_per_game: 100,
+            max_concurrent_games_per_matchup: 8,
+            max_concurrent_games_per_submission: 8,
+            round_pause: 0.0,
+            map: MapConfig::default(),
+            render: RenderConfig::default(),
+            results_dir: "results".to_string(),
+            min_call_interval: 0.0,
+            keep_containers_warm: false,
+            container_pool_size: 4,
+            container_idle_ttl: 300.0,
+            shutdown_timeout: 10.0,
+            container_start_retries: 2,
+            warmup_requests: 0,
+            deep_readiness_check: false,
+            post_ready_delay: 0.0,
+            matchup_retries: 0,
+            max_submission_failures: None,
+            tie_break: TieBreak::None,
+            tie_scoring: TieScoring::Separate,
+            standings_since: None,
+            tie_rematch: false,
+            tie_rematch_turns: 20,
+            timeout_policy: TimeoutPolicy::FailGame,
+            pending_policy: PendingPolicy::Rerun,
+            no_moves_policy: NoMovesPolicy::SkipTurn,
+            max_invalid_moves: 3,
+            gamble_cost: 0,
+            gamble_cost_stat: GambleCostStat::Health,
+            min_power: 1,
+            greater_healing_amount: 2,
+            enemies_per_nodes: None,
+            continue_on_panic: false,
+            feedback: false,
+            force_layout: false,
+            visibility: Visibility::SelfOnly,
+            fight_health_factor: 0.0,
+            fight_model: FightModel::Probabilistic,
+            reward_scaling: 0.0,
+            enemy_move_chance: 1.0,
+            enemy_movement: EnemyMovement::Random,
+            turn_order: TurnOrder::Alternating,
+            min_start_distance: 0,
+            stall_penalty_turns: None,
+            stall_damage: 1,
+            sudden_death_after: None,
+            teleport_cooldown: 0,
+            turn_order_threshold: 0.25,
+            slowest_count: 5,
+            record_call_latencies: false,
+            db_max_retries: 10,
+            db_retry_base_delay: 0.01,
+            db_retry_max_delay: 1.0,
+            verbose_game: false,
+            choice_diagnostics: false,
+            record_turns_without_render: false,
+            combine_turn_svgs: false,
+            render_every_nth_turn: 1,
+            max_render_nodes: None,
+            max_visualization_bytes: None,
+            ndjson_results: false,
+            tournament_seed: None,
+            deterministic: false,
+            worker_threads: 12,
+            max_total_games: None,
+            max_runtime: None,
+            trace_calls: false,
+            tournament_name: db::DEFAULT_TOURNAMENT.to_string(),
+            podman_command: "podman".to_string(),
+            max_podman_ops: container::DEFAULT_MAX_PODMAN_OPS,
+            port_seed: None,
+            reuse_existing_containers: false,
+            container_name_prefix: "rplcs-tournament-1".to_string(),
+            rng_audit: false,
+            rng_draws: false,
+            reproduce_hints: true,
+            profile_timing: false,
+            #[cfg(feature = "live")]
+            live_port: None,
+            manifest: None,
+            podman_ops: default_podman_ops(),
         }
     }
 }
 
 impl Config {
+    fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Classic => Self::classic(),
+            Preset::Chaos => Self::chaos(),
+            Preset::Hardcore => Self::hardcore(),
+        }
+    }
+
+    /// The default rules.
+    fn classic() -> Self {
+        Self {
+            preset: Some(Preset::Classic),
+            ..Self::default()
+        }
+    }
+
+    /// Mostly one-way maps full of gamble and teleport nodes.
+    fn chaos() -> Self {
+        Self {
+            preset: Some(Preset::Chaos),
+            map: MapConfig {
+                teleport_nodes: NodeCount::Fixed(2),
+                gamble_nodes: NodeCount::Range { min: 2, max: 4 },
+                bidirectional_edge_chance: Some(0.5),
+                ..MapConfig::default()
+            },
+            ..Self::default()
+        }
+    }
+
+    /// A single healing node, wounds weaken fighters, and games running out of turns go to the
+    /// healthier player.
+    fn hardcore() -> Self {
+        Self {
+            preset: Some(Preset::Hardcore),
+            map: MapConfig {
+                healing_nodes: NodeCount::Fixed(1),
+                ..MapConfig::default()
+            },
+            tie_break: TieBreak::HealthThenPower,
+            fight_health_factor: 0.5,
+            ..Self::default()
+        }
+    }
+
+    /// Extracts the config from `overrides`, on top of the defaults of the preset they select.
+    fn load(overrides: Figment) -> Result<Self, TournamentError> {
+        let preset: Option<Preset> = overrides.extract_inner("preset").ok();
+        let base = preset.map(Self::preset).unwrap_or_default();
+        Figment::from(Serialized::defaults(base))
+            .merge(overrides)
+            .extract()
+            .map_err(|e| TournamentError::Config(e.into()))
+    }
+
     fn container_timeout(&self) -> Duration {
         Duration::from_secs_f32(self.container_timeout)
     }
@@ -46,6 +361,88 @@ impl Config {
     fn game_timeout(&self) -> Duration {
         Duration::from_secs_f32(self.game_timeout)
     }
+
+    fn matchup_timeout(&self) -> Duration {
+        Duration::from_secs_f32(self.matchup_timeout)
+    }
+
+    fn round_pause(&self) -> Duration {
+        Duration::from_secs_f32(self.round_pause)
+    }
+
+    fn min_call_interval(&self) -> Duration {
+        Duration::from_secs_f32(self.min_call_interval)
+    }
+
+    fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs_f32(self.shutdown_timeout)
+    }
+
+    fn post_ready_delay(&self) -> Duration {
+        Duration::from_secs_f32(self.post_ready_delay)
+    }
+
+    fn max_runtime(&self) -> Option<Duration> {
+        self.max_runtime.map(Duration::from_secs_f32)
+    }
+
+    fn container_idle_ttl(&self) -> Duration {
+        Duration::from_secs_f32(self.container_idle_ttl)
+    }
+
+    /// Seed every game and built-in bot is derived from. Deterministic runs without one use 0.
+    fn tournament_seed(&self) -> Option<u64> {
+        self.tournament_seed.or(self.deterministic.then_some(0))
+    }
+
+    /// `matchup_length`, or `rounds_per_pair` fixed rounds if it isn't set.
+    fn matchup_length(&self) -> MatchupLength {
+        self.matchup_length
+            .unwrap_or(MatchupLength::FixedRounds(self.rounds_per_pair))
+    }
+
+    /// How many games of a matchup may run at once. Deterministic runs play them one by one.
+    fn game_concurrency(&self) -> usize {
+        if self.deterministic {
+            1
+        } else {
+            self.max_concurrent_games_per_matchup.max(1)
+        }
+    }
+
+    /// Directory of the turn SVGs of a matchup, `visualizations/<matchup_id>_<first>_vs_<second>`
+    /// in the results directory. The id keeps matchups of the same pair apart, e.g. from other
+    /// tournaments sharing the results directory.
+    fn matchup_visualization_dir(&self, matchup_id: i64, first: &str, second: &str) -> PathBuf {
+        self.matchup_results_dir("visualizations", matchup_id, first, second)
+    }
+
+    /// Directory of a matchup's files of one `kind`, `<kind>/<matchup_id>_<first>_vs_<second>` in
+    /// the results directory, like `matchup_visualization_dir`.
+    fn matchup_results_dir(
+        &self,
+        kind: &str,
+        matchup_id: i64,
+        first: &str,
+        second: &str,
+    ) -> PathBuf {
+        Path::new(&self.results_dir)
+            .join(kind)
+            .join(format!("{}_{}_vs_{}", matchup_id, first, second))
+    }
+
+    /// The podman program, limited to the config's podman process slots.
+    fn podman(&self) -> Podman {
+        Podman::new(&self.podman_command, self.podman_ops.clone())
+    }
+
+    /// Directory of a submission, `submissions/<name>` unless the manifest says otherwise.
+    fn submission_dir(&self, name: &str) -> PathBuf {
+        match self.manifest.as_ref().and_then(|m| m.entry(name)) {
+            Some(entry) => entry.path(),
+            None => Path::new(SUBMISSIONS_DIR).join(name),
+        }
+    }
 }
 
 /// Tournament runner for RPLCS HTTP submissions
@@ -55,192 +452,1336 @@ impl Config {
 /// - Bash/Shell: `RUST_LOG=info cargo run`
 ///
 /// Available log levels: error, warn, info, debug, trace
-#[tokio::main(flavor = "multi_thread", worker_threads = 12)]
-async fn main() -> Result<()> {
-    env_logger::builder()
-        .filter_level(LevelFilter::Info)
-        .init();
+fn main() -> Result<()> {
+    env_logger::builder().filter_level(LevelFilter::Info).init();
+    let cli = Cli::parse();
+
+    // Load config options.
+    let mut config = Config::load(
+        Figment::new()
+            .merge(Toml::file("config.toml"))
+            .merge(Env::prefixed("RPLCS_")),
+    )?;
+    config.verbose_game |= cli.verbose_game;
+    config.choice_diagnostics |= cli.choice_diagnostics;
+    config.ndjson_results |= cli.ndjson;
+    config.deterministic |= cli.deterministic;
+    config.trace_calls |= cli.trace;
+    config.rng_audit |= cli.rng_audit;
+    config.rng_draws |= cli.rng_draws;
+    config.profile_timing |= cli.profile_timing;
+    config.continue_on_panic |= cli.continue_on_panic;
+    config.force_layout |= cli.force;
+    if let Some(threads) = cli.threads {
+        config.worker_threads = threads;
+    }
+    if cli.max_runtime.is_some() {
+        config.max_runtime = cli.max_runtime;
+    }
+    if cli.since.is_some() {
+        config.standings_since = cli.since;
+    }
+    if let Some(path) = &cli.manifest {
+        config.manifest = Some(Arc::new(Manifest::load(path)?));
+    }
+    config.podman_ops = Arc::new(Semaphore::new(config.max_podman_ops.max(1)));
+    port_utils::seed_port_allocator(config.port_seed);
+
+    // A single thread keeps the order of every await, and with it the results, reproducible
+    let runtime = if config.deterministic {
+        info!(
+            "Running deterministically with tournament seed {}",
+            config.tournament_seed().unwrap_or_default()
+        );
+        tokio::runtime::Builder::new_current_thread()
+    } else {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.worker_threads(config.worker_threads.max(1));
+        builder
+    }
+    .enable_all()
+    .build()
+    .context("Failed to start the async runtime")?;
+
+    runtime.block_on(async {
+        #[cfg(feature = "live")]
+        if let Some(port) = config.live_port {
+            live::serve(port).await?;
+        }
+        run_command(cli.command, cli.dump, config).await
+    })
+}
 
+async fn run_command(
+    command: Option<Command>,
+    dump: Option<PathBuf>,
+    mut config: Config,
+) -> Result<()> {
+    match command {
+        None => {
+            run_tournament(&config).await?;
+            if let Some(path) = dump {
+                Database::new(&config)?.export_sqlite_dump(&path).await?;
+                info!("Dumped the results database to {}", path.display());
+            }
+            Ok(())
+        }
+        Some(Command::Simulate { rounds }) => {
+            if let Some(rounds) = rounds {
+                config.rounds_per_pair = rounds;
+            }
+            config.results_dir = format!("{}/simulation", config.results_dir);
+
+            let db = Database::new(&config)?;
+            #[cfg(feature = "live")]
+            live::watch_database(&db);
+            simulation::simulate(&db, &config).await?;
+            report::log_turn_order_audit(&db, config.turn_order_threshold).await?;
+            report::log_slowest(&db, config.slowest_count).await?;
+            report::log_node_visits(&db).await?;
+            report::log_passive_games(&db).await?;
+            write_standings(&db, &config).await?;
+            if let Some(path) = dump {
+                db.export_sqlite_dump(&path).await?;
+                info!("Dumped the results database to {}", path.display());
+            }
+            info!("Simulation completed successfully");
+            Ok(())
+        }
+        Some(Command::RunGame {
+            submission_a,
+            submission_b,
+            rounds,
+        }) => {
+            if let Some(rounds) = rounds {
+                config.rounds_per_pair = rounds;
+            }
+            let summary = run_single_matchup(&submission_a, &submission_b, &config).await?;
+            println!("{}", summary);
+            Ok(())
+        }
+        Some(Command::List) => {
+            let podman = config.podman();
+            let entries = match &config.manifest {
+                Some(manifest) => {
+                    inventory::list_manifest_submissions(manifest, async |image| {
+                        container::image_exists(&podman, image).await
+                    })
+                    .await?
+                }
+                None => {
+                    inventory::list_submissions(Path::new(SUBMISSIONS_DIR), async |name| {
+                        container::image_exists(&podman, &container::image_name(name)).await
+                    })
+                    .await?
+                }
+            };
+            for entry in &entries {
+                println!("{}", entry);
+            }
+            let missing = entries.iter().filter(|entry| !entry.is_runnable()).count();
+            if missing > 0 {
+                warn!(
+                    "{} of {} submissions have nothing to run",
+                    missing,
+                    entries.len()
+                );
+            }
+            Ok(())
+        }
+        Some(Command::Schema { out }) => {
+            for path in schema::write_schemas(&out)? {
+                info!("Wrote {}", path.display());
+            }
+            Ok(())
+        }
+        Some(Command::RenderMap { seed, out, compact }) => {
+            let out = out.unwrap_or_else(|| PathBuf::from(format!("map_{seed}.svg")));
+            let render = if compact {
+                RenderConfig::compact()
+            } else {
+                config.render
+            };
+            let map = map_for_seed(&config, seed).await?;
+            render_map(&map, &out, &render)?;
+            info!("Rendered map for seed {} to {}", seed, out.display());
+            Ok(())
+        }
+        Some(Command::Standings) => {
+            let db = Database::new(&config)?;
+            write_standings(&db, &config).await
+        }
+        Some(Command::Reset { matchup }) => {
+            let db = Database::new(&config)?;
+            match matchup.as_deref() {
+                Some([a, b]) => {
+                    if db.reset_matchup(a, b).await? {
+                        info!("Deleted the matchup between {} and {}", a, b);
+                    } else {
+                        warn!("No matchup between {} and {} to delete", a, b);
+                    }
+                }
+                _ => {
+                    let count = db.reset().await?;
+                    info!(
+                        "Deleted {} matchups of tournament {}",
+                        count, config.tournament_name
+                    );
+                }
+            }
+            Ok(())
+        }
+        Some(Command::Maps { count, first_seed }) => {
+            let stats = map_stats::map_stats(&config.map, count, first_seed)?;
+            println!("{}", stats);
+            Ok(())
+        }
+    }
+}
+
+/// The map a game with `seed` was played on: the one stored with the game if the results
+/// database has it, so later changes to map generation don't alter it, and otherwise the map
+/// generated from the seed. Logs which recorded games used the seed.
+async fn map_for_seed(config: &Config, seed: i64) -> Result<GameMap> {
+    let mut stored = None;
+    // Point at the games played with this seed, without creating a database for it
+    if Database::file(config).exists() {
+        let db = Database::new(config)?;
+        for game in db.get_game_by_seed(seed).await? {
+            info!(
+                "Seed {} was used by game {} of {} vs {} (matchup {}), winner: {}",
+                seed, game.game_number, game.player_a, game.player_b, game.matchup_id, game.winner
+            );
+            if stored.is_none() {
+                stored = db.get_game_map(game.matchup_id, game.game_number).await?;
+            }
+        }
+    }
+    match stored {
+        Some(json) => {
+            info!("Rendering the map stored with the game for seed {}", seed);
+            GameMap::from_json(&json)
+        }
+        None => {
+            // Seeded the same way as `GameBuilder::build`, so seeds from the database reproduce
+            // their map
+            let mut rng = StdRng::seed_from_u64(seed as u64);
+            GameMap::new(&mut rng, &config.map).context("Failed to generate map")
+        }
+    }
+}
+
+/// Renders `map` with players and enemies on the first nodes.
+fn render_map(map: &GameMap, out: &Path, render: &RenderConfig) -> Result<()> {
+    for (player, start) in ["A", "B"].into_iter().zip([0, 1]) {
+        let start = NodeIndex::new(start);
+        let distance = |node_type| {
+            map.distance_to_nearest(start, node_type)
+                .map_or("unreachable".to_string(), |moves| moves.to_string())
+        };
+        info!(
+            "Player {} starts {} moves from healing and {} from a teleport",
+            player,
+            distance(MapNodeType::Healing),
+            distance(MapNodeType::Teleport)
+        );
+    }
+    map.render_to_file(
+        [NodeIndex::new(0), NodeIndex::new(1)],
+        &[NodeIndex::new(2), NodeIndex::new(3)],
+        &[PlayerState::default(), PlayerState::default()],
+        &[PlayerState::default(), PlayerState::default()],
+        render,
+        out,
+    )
+}
+
+async fn run_tournament(config: &Config) -> Result<(), TournamentError> {
     info!("Starting the tournament runner");
 
-    // Load config options.
-    let config: Config = Figment::new()
-        .merge(Serialized::defaults(Config::default()))
-        .merge(Toml::file("config.toml"))
-        .merge(Env::prefixed("RPLCS_"))
-        .extract()?;
-
-    let submissions_dir = "submissions";
-    let submission_names =
-        load_submission_names(submissions_dir).context("Failed to load submissions")?;
+    let submission_names = match &config.manifest {
+        Some(manifest) => {
+            manifest
+                .validate(async |image| container::image_exists(&config.podman(), image).await)
+                .await
+                .map_err(TournamentError::Submission)?;
+            manifest.names()
+        }
+        None => load_submission_names(SUBMISSIONS_DIR)
+            .context("Failed to load submissions")
+            .map_err(TournamentError::Submission)?,
+    };
     info!(
         "Found {} submissions: {:?}",
         submission_names.len(),
         submission_names
     );
 
-    let round_robin_pairs = round_robin::generate_rounds(submission_names);
+    let rounds = scheduler::generate_rounds_grouped(submission_names);
     info!(
-        "Generated {} matchups for round-robin tournament",
-        round_robin_pairs.len()
+        "Generated {} matchups in {} rounds for round-robin tournament",
+        rounds.iter().map(Vec::len).sum::<usize>(),
+        rounds.len()
     );
 
-    let db = Database::new()?;
+    let db = Database::new(config).map_err(TournamentError::Database)?;
+    #[cfg(feature = "live")]
+    live::watch_database(&db);
+    let pending = db
+        .sweep_pending_games(config.pending_policy)
+        .await
+        .map_err(TournamentError::Database)?;
+    if pending > 0 {
+        info!(
+            "Found {} games left pending by an earlier run, applying {:?}",
+            pending, config.pending_policy
+        );
+    }
+    // Containers of an earlier run of this tournament that never got stopped, unless adopting them
+    if !config.reuse_existing_containers {
+        let removed =
+            container::remove_tournament_containers(&config.podman(), &config.tournament_name)
+                .await
+                .map_err(TournamentError::Container)?;
+        if removed > 0 {
+            warn!(
+                "Removed {} containers left over from an earlier run",
+                removed
+            );
+        }
+    }
+    let pool = ContainerPool::new(config);
+    let throttle = SubmissionThrottle::new(config.max_concurrent_games_per_submission);
+    let budget = GameBudget::from_config(config);
+    let breaker = FailureBreaker::new(config.max_submission_failures);
+    let unplayed = std::sync::Mutex::new(Vec::new());
 
-    for (submission_a, submission_b) in round_robin_pairs {
-        info!("Starting matchup: {} vs {}", submission_a, submission_b);
-        debug!("Initializing containers for both submissions");
+    let round_count = rounds.len();
+    let matchup_count = rounds.iter().map(Vec::len).sum::<usize>();
+    for (round_number, round) in rounds.into_iter().enumerate() {
+        info!("Starting round {}/{}", round_number + 1, round_count);
 
-        let container_results = tokio::join!(
-            async {
-                timeout(config.container_timeout(), Container::new(&submission_a))
-                    .await
-                    .context("Container A startup timed out")?
-                    .context(format!("Failed to create container A for {submission_a}"))
-            },
-            async {
-                timeout(config.container_timeout(), Container::new(&submission_b))
+        let abandoned = play_round(round, config.matchup_retries, async |a: &str, b: &str| {
+            if budget.is_exhausted() {
+                info!(
+                    "Skipping {} vs {}, {} was reached",
+                    a,
+                    b,
+                    budget.exhausted_limit()
+                );
+                unplayed.lock().unwrap().push(format!("{} vs {}", a, b));
+                return db.record_unplayed_matchup(a, b).await;
+            }
+            if is_matchup_complete(&db, a, b, config.matchup_length()).await? {
+                info!("Skipping {} vs {}, all games were already played", a, b);
+                return Ok(());
+            }
+            if let Some(tripped) = [a, b].into_iter().find(|s| breaker.is_tripped(s)) {
+                let winner = if tripped == a { b } else { a };
+                if breaker.is_tripped(winner) {
+                    info!("Skipping {} vs {}, both were disqualified", a, b);
+                    return Ok(());
+                }
+                return forfeit_matchup(a, b, tripped, &db, config)
                     .await
-                    .context("Container B startup timed out")?
-                    .context(format!("Failed to create container B for {submission_b}"))
+                    .map(|_| ());
             }
+            run_matchup(a, b, &db, &pool, &throttle, &budget, &breaker, config)
+                .await
+                .map(|_| ())
+        })
+        .await;
+        if !abandoned.is_empty() {
+            warn!(
+                "Gave up on {} matchups of round {}: {:?}",
+                abandoned.len(),
+                round_number + 1,
+                abandoned
+            );
+        }
+
+        info!(
+            "Round {}/{} completed, {} of {} matchups finished",
+            round_number + 1,
+            round_count,
+            count_complete_matchups(&db, config.matchup_length()).await?,
+            matchup_count
+        );
+        if round_number + 1 < round_count && !config.round_pause().is_zero() {
+            info!(
+                "Pausing for {:?} before the next round",
+                config.round_pause()
+            );
+            sleep(config.round_pause()).await;
+        }
+    }
+    pool.shutdown().await;
+
+    let unplayed = unplayed.into_inner().unwrap();
+    if !unplayed.is_empty() {
+        warn!(
+            "Stopped at {}, {} matchups were not played: {:?}",
+            budget.exhausted_limit(),
+            unplayed.len(),
+            unplayed
         );
+    }
+    for line in disqualification_summary(&db).await? {
+        warn!("{}", line);
+    }
+    report::log_turn_order_audit(&db, config.turn_order_threshold).await?;
+    report::log_slowest(&db, config.slowest_count).await?;
+    report::log_node_visits(&db).await?;
+    report::log_passive_games(&db).await?;
+    write_standings(&db, config).await?;
+    if config.feedback {
+        report::write_feedback(&db, &Path::new(&config.results_dir).join("feedback")).await?;
+    }
+    if config.profile_timing {
+        info!("Time spent in the whole run: {}", profile::run_total());
+    }
+    info!("Tournament completed successfully");
+    Ok(())
+}
 
-        let (container_a, container_b) = match container_results {
-            (Ok(a), Ok(b)) => (a, b),
-            (Err(e), Err(e2)) => {
-                error!("Failed to initialize container A: {:?}", e);
-                error!("Failed to initialize container B: {:?}", e2);
-                continue;
+/// Plays one matchup outside of the round-robin, stopping its containers afterwards.
+async fn run_single_matchup(
+    submission_a: &str,
+    submission_b: &str,
+    config: &Config,
+) -> Result<MatchupSummary, TournamentError> {
+    let db = Database::new(config).map_err(TournamentError::Database)?;
+    #[cfg(feature = "live")]
+    live::watch_database(&db);
+    let pool = ContainerPool::new(config);
+    let throttle = SubmissionThrottle::new(config.max_concurrent_games_per_submission);
+    let budget = GameBudget::from_config(config);
+    let breaker = FailureBreaker::new(config.max_submission_failures);
+
+    let summary = run_matchup(
+        submission_a,
+        submission_b,
+        &db,
+        &pool,
+        &throttle,
+        &budget,
+        &breaker,
+        config,
+    )
+    .await;
+    pool.shutdown().await;
+    Ok(summary?)
+}
+
+/// Writes the final rankings to `standings.md` and `standings.json` in the results directory.
+async fn write_standings(db: &Database, config: &Config) -> Result<()> {
+    let dir = Path::new(&config.results_dir);
+    let since = config.standings_since.as_deref();
+    report::write_standings_markdown(db, config.tie_scoring, since, &dir.join("standings.md"))
+        .await?;
+    report::write_standings_json(db, config.tie_scoring, since, &dir.join("standings.json"))
+        .await?;
+    match since {
+        Some(since) => info!(
+            "Wrote the standings of the games since {} to {}",
+            since,
+            dir.join("standings.md").display()
+        ),
+        None => info!(
+            "Wrote the standings to {}",
+            dir.join("standings.md").display()
+        ),
+    }
+    Ok(())
+}
+
+/// The message a task panicked with, if it panicked with a string.
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or("(no message)".to_string(), |message| message.to_string()),
+    }
+}
+
+/// One line per disqualification recorded so far, for the end-of-tournament summary.
+async fn disqualification_summary(db: &Database) -> Result<Vec<String>> {
+    let disqualifications = db.list_disqualifications().await?;
+    if disqualifications.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut lines = vec![format!("{} disqualifications:", disqualifications.len())];
+    lines.extend(disqualifications.iter().map(|dq| {
+        let location = match (dq.game_number, dq.turn_number) {
+            (Some(game), Some(turn)) => format!(", game {} turn {}", game, turn),
+            (Some(game), None) => format!(", game {}", game),
+            _ => String::new(),
+        };
+        format!(
+            "  {} in matchup {}{}: {}",
+            dq.submission, dq.matchup_id, location, dq.reason
+        )
+    }));
+    Ok(lines)
+}
+
+/// Plays every matchup of a round. A failed matchup is queued behind the rest of the round and
+/// retried up to `retries` times, in case the failure was transient, unless it timed out. Returns
+/// the matchups that still failed.
+async fn play_round(
+    round: Vec<(String, String)>,
+    retries: u32,
+    mut play: impl AsyncFnMut(&str, &str) -> Result<()>,
+) -> Vec<(String, String)> {
+    let mut queue: VecDeque<_> = round.into_iter().map(|pair| (pair, 0)).collect();
+    let mut abandoned = Vec::new();
+    while let Some(((submission_a, submission_b), retry)) = queue.pop_front() {
+        match play(&submission_a, &submission_b).await {
+            Ok(()) => {}
+            Err(e) if retry < retries && e.downcast_ref::<MatchupTimeout>().is_none() => {
+                warn!(
+                    "Matchup {} vs {} failed, retrying after the rest of the round ({}/{}): {:?}",
+                    submission_a,
+                    submission_b,
+                    retry + 1,
+                    retries,
+                    e
+                );
+                queue.push_back(((submission_a, submission_b), retry + 1));
             }
-            (Err(e), _) | (_, Err(e)) => {
-                error!("Failed to initialize containers: {:?}", e);
-                continue;
+            Err(e) => {
+                error!(
+                    "Matchup {} vs {} failed: {:?}",
+                    submission_a, submission_b, e
+                );
+                abandoned.push((submission_a, submission_b));
             }
-        };
+        }
+    }
+    abandoned
+}
 
-        let _result = {
-            let handle_a = container_a.handle();
-            let handle_b = container_b.handle();
-
-            run_games(
-                submission_a.clone(),
-                submission_b.clone(),
-                handle_a,
-                handle_b,
-                &db,
-                &config,
-            )
-            .await
-        };
+/// Whether an earlier run already played every game of this matchup, in either player order.
+async fn is_matchup_complete(
+    db: &Database,
+    submission_a: &str,
+    submission_b: &str,
+    length: MatchupLength,
+) -> Result<bool> {
+    for matchup in db.list_matchups().await? {
+        let same_pair = (matchup.player_a == submission_a && matchup.player_b == submission_b)
+            || (matchup.player_a == submission_b && matchup.player_b == submission_a);
+        if same_pair {
+            let (completed, expected) = db.count_completed_games(matchup.id).await?;
+            return Ok(completed >= expected || is_matchup_decided(db, &matchup, length).await?);
+        }
+    }
+    Ok(false)
+}
 
-        // Shutdown containers
-        let (shutdown_a, shutdown_b) = tokio::join!(container_a.shutdown(), container_b.shutdown());
+/// Whether a submission has won enough of the matchup's recorded games to end it early.
+async fn is_matchup_decided(
+    db: &Database,
+    matchup: &MatchupRow,
+    length: MatchupLength,
+) -> Result<bool> {
+    if let MatchupLength::FixedRounds(_) = length {
+        return Ok(false);
+    }
 
-        if let Err(e) = shutdown_a {
-            warn!("Failed to shutdown container {}: {}", submission_a, e);
+    let (mut a_wins, mut b_wins) = (0, 0);
+    for game in db.list_matchup_games(matchup.id).await? {
+        // Ties, like errored, timed out and pending games, have no winner
+        match game.winner_name() {
+            Some(winner) if winner == matchup.player_a => a_wins += 1,
+            Some(_) => b_wins += 1,
+            None => {}
         }
-        if let Err(e) = shutdown_b {
-            warn!("Failed to shutdown container {}: {}", submission_b, e);
+    }
+    Ok(length.is_decided(a_wins, b_wins))
+}
+
+async fn count_complete_matchups(db: &Database, length: MatchupLength) -> Result<usize> {
+    let mut complete = 0;
+    for matchup in db.list_matchups().await? {
+        let (completed, expected) = db.count_completed_games(matchup.id).await?;
+        if completed >= expected || is_matchup_decided(db, &matchup, length).await? {
+            complete += 1;
         }
     }
+    Ok(complete)
+}
 
-    info!("Tournament completed successfully");
+#[allow(clippy::too_many_arguments)]
+async fn run_matchup(
+    submission_a: &str,
+    submission_b: &str,
+    db: &Database,
+    pool: &ContainerPool,
+    throttle: &SubmissionThrottle,
+    budget: &GameBudget,
+    breaker: &FailureBreaker,
+    config: &Config,
+) -> Result<MatchupSummary> {
+    info!("Starting matchup: {} vs {}", submission_a, submission_b);
+    let profile = config
+        .profile_timing
+        .then(|| Arc::new(profile::Profile::default()));
+    let summary = profile::scope(
+        profile.clone(),
+        play_matchup(
+            submission_a,
+            submission_b,
+            db,
+            pool,
+            throttle,
+            budget,
+            breaker,
+            config,
+        ),
+    )
+    .await;
+
+    if let Some(profile) = profile {
+        info!(
+            "Time spent in {} vs {}: {}",
+            submission_a, submission_b, profile
+        );
+        profile::run_total().merge(&profile);
+    }
+    summary
+}
+
+/// Starts both containers and plays the games of a matchup. Every game of a submission whose
+/// container fails to start, and every game that failed because of it or that it forfeits,
+/// counts towards its `breaker` failures.
+#[allow(clippy::too_many_arguments)]
+async fn play_matchup(
+    submission_a: &str,
+    submission_b: &str,
+    db: &Database,
+    pool: &ContainerPool,
+    throttle: &SubmissionThrottle,
+    budget: &GameBudget,
+    breaker: &FailureBreaker,
+    config: &Config,
+) -> Result<MatchupSummary> {
+    // Both sides would wait on the same container lease
+    if submission_a == submission_b {
+        bail!("Submission {} cannot play against itself", submission_a);
+    }
+    debug!("Initializing containers for both submissions");
+    let container_results = profile::time_async(Bucket::ContainerStartup, async {
+        let (a, b) = pool.acquire_pair(submission_a, submission_b, config).await;
+        (
+            a.context(format!("Failed to create container A for {submission_a}")),
+            b.context(format!("Failed to create container B for {submission_b}")),
+        )
+    })
+    .await;
+
+    let games = || game_numbers(config.matchup_length()).map(|(game_number, _)| game_number);
+    let (container_a, container_b) = match container_results {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), Err(e2)) => {
+            breaker.record(submission_a, submission_b, games());
+            breaker.record(submission_b, submission_a, games());
+            error!("Failed to initialize container A: {:?}", e);
+            return Err(e2).context("Failed to initialize container B");
+        }
+        (Err(e), Ok(_)) => {
+            breaker.record(submission_a, submission_b, games());
+            pool.release(submission_b).await;
+            return Err(e).context("Failed to initialize containers");
+        }
+        (Ok(_), Err(e)) => {
+            breaker.record(submission_b, submission_a, games());
+            pool.release(submission_a).await;
+            return Err(e).context("Failed to initialize containers");
+        }
+    };
+
+    let result = run_games(
+        submission_a.to_string(),
+        submission_b.to_string(),
+        throttle.wrap(submission_a, Arc::new(container_a.handle())),
+        throttle.wrap(submission_b, Arc::new(container_b.handle())),
+        db,
+        budget,
+        config,
+    )
+    .await;
+
+    // Hand the containers back; the pool stops them unless they're kept warm
+    tokio::join!(pool.release(submission_a), pool.release(submission_b));
+
+    if let Ok(summary) = &result {
+        breaker.record_failed_games(summary);
+    }
+    result
+}
+
+/// Awards every game of a matchup not played yet to the opponent of `disqualified`, without
+/// starting any containers, recording the disqualification.
+async fn forfeit_matchup(
+    submission_a: &str,
+    submission_b: &str,
+    disqualified: &str,
+    db: &Database,
+    config: &Config,
+) -> Result<MatchupSummary> {
+    let winner = if disqualified == submission_a {
+        submission_b
+    } else {
+        submission_a
+    };
+    warn!(
+        "{} is disqualified, awarding {} vs {} to {}",
+        disqualified, submission_a, submission_b, winner
+    );
+    let matchup_id = db.start_matchup(submission_a, submission_b).await?;
+    let disqualification = Disqualification {
+        submission: disqualified.to_string(),
+        matchup_id,
+        reason: DisqualificationReason::RepeatedFailures,
+        game_number: None,
+        turn_number: None,
+    };
+    db.record_disqualification(&disqualification).await?;
+
+    let mut summary = MatchupSummary::new(matchup_id, submission_a, submission_b);
+    summary.disqualifications.push(disqualification);
+    let played: Vec<i64> = db
+        .list_matchup_games(matchup_id)
+        .await?
+        .into_iter()
+        .filter(|game| game.winner != "pending")
+        .map(|game| game.game_number)
+        .collect();
+    // Up to the wins a first-to matchup needs
+    let length = config.matchup_length();
+    let needed = match length {
+        MatchupLength::FixedRounds(rounds) => rounds,
+        MatchupLength::FirstTo { wins, .. } => wins.min(length.max_games()),
+    };
+    let games = game_numbers(length)
+        .take(needed as usize)
+        .filter(|(game_number, _)| !played.contains(game_number));
+    record_forfeits(db, &mut summary, winner, games).await?;
+    Ok(summary)
+}
+
+/// Records `games`, numbers and whether the players were swapped, as won by `winner`.
+async fn record_forfeits(
+    db: &Database,
+    summary: &mut MatchupSummary,
+    winner: &str,
+    games: impl IntoIterator<Item = (i64, bool)>,
+) -> Result<()> {
+    let (submission_a, submission_b) = (summary.submission_a.clone(), summary.submission_b.clone());
+    for (game_number, is_reversed) in games {
+        let (first_mover, second_mover) = if is_reversed {
+            (&submission_b, &submission_a)
+        } else {
+            (&submission_a, &submission_b)
+        };
+        // Results are relative to the player that moved first in that game
+        let result = [GameResult::Player1Win, GameResult::Player2Win]
+            .into_iter()
+            .find(|result| result.to_winner_submission(first_mover, second_mover) == Some(winner))
+            .context("Forfeit winner is not in the matchup")?;
+        db.record_forfeit(summary.matchup_id, game_number, first_mover, result)
+            .await?;
+        summary.record(result, is_reversed);
+        let loser = if winner == submission_a {
+            &submission_b
+        } else {
+            &submission_a
+        };
+        summary.failed_games.push((loser.clone(), game_number));
+    }
     Ok(())
 }
 
+/// Number of a matchup's `game`-th game out of `game_count`, and whether the players are swapped
+/// in it. Swapped games are numbered after every unswapped one, so numbers never collide.
+fn numbered_game(game_count: i64, game: i64) -> (i64, bool) {
+    let is_reversed = game % 2 != 0;
+    let game_number = if is_reversed { game_count + game } else { game };
+    (game_number, is_reversed)
+}
+
+/// Numbers of the games of a matchup of `length`, in the order they are started, with whether
+/// the players are swapped in each.
+fn game_numbers(length: MatchupLength) -> impl Iterator<Item = (i64, bool)> {
+    let game_count = length.max_games() as i64;
+    (0..game_count).map(move |game| numbered_game(game_count, game))
+}
+
 fn load_submission_names(submissions_dir: &str) -> Result<Vec<String>> {
     debug!("Loading submissions from directory: {}", submissions_dir);
+    let (names, skipped) = scan_submission_dirs(submissions_dir)?;
+    for path in skipped {
+        warn!(
+            "Skipping submission {}: its directory name is not valid UTF-8, which container and \
+             image names require",
+            path.display()
+        );
+    }
+
+    info!("Successfully loaded {} submissions", names.len());
+    Ok(names)
+}
+
+/// Names of the directories in `submissions_dir`, and the paths of those whose name isn't valid
+/// UTF-8 and so can't be used as a submission name.
+fn scan_submission_dirs(submissions_dir: &str) -> Result<(Vec<String>, Vec<PathBuf>)> {
     let entries = fs::read_dir(submissions_dir).context("Failed to read submissions directory")?;
     let mut names = Vec::new();
+    let mut skipped = Vec::new();
 
     for entry in entries {
         let entry = entry.context("Failed to read entry")?;
-        if entry.path().is_dir() {
-            if let Some(name) = entry.file_name().to_str() {
-                names.push(name.to_string());
-            }
+        if !entry.path().is_dir() {
+            continue;
+        }
+        match entry.file_name().into_string() {
+            Ok(name) => names.push(name),
+            Err(_) => skipped.push(entry.path()),
         }
     }
-
-    info!("Successfully loaded {} submissions", names.len());
-    Ok(names)
+    Ok((names, skipped))
 }
 
 async fn run_games(
     submission_a: String,
     submission_b: String,
-    container_a: ContainerHandle,
-    container_b: ContainerHandle,
+    container_a: Arc<dyn Player>,
+    container_b: Arc<dyn Player>,
     db: &Database,
+    budget: &GameBudget,
     config: &Config,
-) -> Result<Vec<GameResult>> {
+) -> Result<MatchupSummary> {
+    if submission_a == submission_b {
+        bail!("Submission {} cannot play against itself", submission_a);
+    }
+    let mut config = config.clone();
+    if !config.record_turns_without_render && visualizations_over_cap(&config)? {
+        warn!(
+            "Visualizations take more than max_visualization_bytes, not rendering {} vs {}",
+            submission_a, submission_b
+        );
+        config.record_turns_without_render = true;
+    }
+    let config = &config;
     let matchup_id = db.start_matchup(&submission_a, &submission_b).await?;
-    // let rounds_per_pair = get_rounds_per_pair();
-    let rounds_per_pair = config.rounds_per_pair as i64;
+    let length = config.matchup_length();
+    let game_count = length.max_games() as i64;
+
+    // Bound how many games hit the container pair at once
+    let game_slots = Arc::new(Semaphore::new(config.game_concurrency()));
+
+    // Games an earlier run finished, errored and timed out ones included, are kept rather than
+    // played again, and those with a result count toward deciding the matchup
+    let mut summary = MatchupSummary::new(matchup_id, &submission_a, &submission_b);
+    let finished: HashMap<i64, Option<GameResult>> = db
+        .list_matchup_games(matchup_id)
+        .await?
+        .into_iter()
+        .filter(|game| game.winner != "pending")
+        .map(|game| (game.game_number, game.result()))
+        .collect();
+    for (game_number, is_reversed) in game_numbers(length) {
+        if let Some(&Some(result)) = finished.get(&game_number) {
+            summary.record(result, is_reversed);
+        }
+    }
 
     let mut tasks = JoinSet::new();
-    for game_number in 0..rounds_per_pair {
-        let is_reversed = game_number % 2 != 0;
-        let effective_game_number = if is_reversed {
-            rounds_per_pair + game_number
-        } else {
-            game_number
+    let mut unfinished = HashMap::new();
+    let mut next_game = 0;
+    if length.is_decided(summary.a_wins, summary.b_wins) {
+        next_game = game_count;
+    }
+    // Starts game `next_game`, returning false once every game was started or the cap was reached
+    let start_next_game =
+        |tasks: &mut JoinSet<_>, unfinished: &mut HashMap<_, _>, next_game: &mut i64| {
+            while *next_game < game_count
+                && finished.contains_key(&numbered_game(game_count, *next_game).0)
+            {
+                *next_game += 1;
+            }
+            if *next_game >= game_count {
+                return false;
+            }
+            let game_number = *next_game;
+            if !budget.try_start() {
+                warn!(
+                    "Reached {}, {} vs {} stops after {} of {} games",
+                    budget.exhausted_limit(),
+                    submission_a,
+                    submission_b,
+                    game_number,
+                    game_count
+                );
+                *next_game = game_count;
+                return false;
+            }
+            *next_game += 1;
+
+            let (effective_game_number, is_reversed) = numbered_game(game_count, game_number);
+
+            let (first_sub, second_sub, first_container, second_container) = if !is_reversed {
+                (
+                    submission_a.clone(),
+                    submission_b.clone(),
+                    container_a.clone(),
+                    container_b.clone(),
+                )
+            } else {
+                (
+                    submission_b.clone(),
+                    submission_a.clone(),
+                    container_b.clone(),
+                    container_a.clone(),
+                )
+            };
+
+            let task = spawn_bounded(
+                tasks,
+                &game_slots,
+                run_game(
+                    effective_game_number,
+                    first_sub,
+                    second_sub,
+                    first_container,
+                    second_container,
+                    matchup_id,
+                    db.clone(),
+                    config.clone(),
+                ),
+            );
+            unfinished.insert(task.id(), (effective_game_number, is_reversed));
+            true
         };
 
-        let (first_sub, second_sub, first_container, second_container) = if !is_reversed {
-            (
-                submission_a.clone(),
-                submission_b.clone(),
-                container_a.clone(),
-                container_b.clone(),
-            )
-        } else {
-            (
-                submission_b.clone(),
-                submission_a.clone(),
-                container_b.clone(),
-                container_a.clone(),
-            )
+    // Fixed length matchups start every game at once, the slots pacing them. Others only start
+    // as many as can run, so no game is started after the matchup is decided, nor, with a
+    // `max_runtime`, after the time is up.
+    let initial_games = match length {
+        MatchupLength::FixedRounds(_) if budget.deadline.is_none() => game_count,
+        _ => config.game_concurrency() as i64,
+    };
+    for _ in 0..initial_games {
+        if !start_next_game(&mut tasks, &mut unfinished, &mut next_game) {
+            break;
+        }
+    }
+
+    // Safety net for games stuck outside their own `game_timeout`, e.g. retrying the database
+    let deadline = Instant::now() + config.matchup_timeout();
+    let mut timed_out = false;
+    loop {
+        let result = match timeout_at(deadline, tasks.join_next_with_id()).await {
+            Ok(Some(result)) => result,
+            Ok(None) => break,
+            Err(_) => {
+                tasks.abort_all();
+                timed_out = true;
+                break;
+            }
+        };
+        let (task_id, result) = match result {
+            Ok(finished) => finished,
+            Err(e) if e.is_cancelled() => continue,
+            Err(e) if e.is_panic() && config.continue_on_panic => {
+                if let Some((game_number, _)) = unfinished.remove(&e.id()) {
+                    error!(
+                        "Game {} of {} vs {} panicked, recording it as errored: {}",
+                        game_number,
+                        submission_a,
+                        submission_b,
+                        panic_message(e.into_panic())
+                    );
+                    db.mark_game_errored(matchup_id, game_number).await?;
+                }
+                start_next_game(&mut tasks, &mut unfinished, &mut next_game);
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to join task"),
         };
 
-        tasks.spawn(run_game(
-            effective_game_number,
-            first_sub,
-            second_sub,
-            first_container,
-            second_container,
-            matchup_id,
-            db.clone(),
-            config.clone(),
-        ));
-    }
-
-    let mut results = Vec::with_capacity(rounds_per_pair as usize);
-    while let Some(result) = tasks.join_next().await {
-        match result
-            .context("Failed to join task")?
-            .context("Failed to run game")
-        {
-            Ok(res) => results.push(res),
+        match result.context("Failed to run game") {
+            Ok(res) => {
+                if let Some((_, is_reversed)) = unfinished.remove(&task_id) {
+                    summary.record(res, is_reversed);
+                }
+                if length.is_decided(summary.a_wins, summary.b_wins) {
+                    if next_game < game_count {
+                        info!(
+                            "{} vs {} was decided after {} games",
+                            submission_a,
+                            submission_b,
+                            summary.results.len()
+                        );
+                    }
+                    next_game = game_count;
+                } else {
+                    start_next_game(&mut tasks, &mut unfinished, &mut next_game);
+                }
+            }
             Err(e) => {
                 for error in e.chain() {
                     warn!("Error: {}", error);
                 }
-                continue;
+                if let Some(&(game_number, _)) = unfinished.get(&task_id)
+                    && let Some(fault) = e.downcast_ref::<SubmissionFault>()
+                {
+                    summary
+                        .failed_games
+                        .push((fault.submission.clone(), game_number));
+                }
+                start_next_game(&mut tasks, &mut unfinished, &mut next_game);
+            }
+        }
+
+        // A crashed container fails every remaining game, so stop early instead of churning
+        if (container_a.is_dead() || container_b.is_dead()) && !tasks.is_empty() {
+            warn!(
+                "Container died during {} vs {}, aborting remaining games",
+                submission_a, submission_b
+            );
+            tasks.abort_all();
+        }
+    }
+
+    let forfeit = match (container_a.is_dead(), container_b.is_dead()) {
+        (true, false) => Some((&submission_b, &submission_a)),
+        (false, true) => Some((&submission_a, &submission_b)),
+        _ => None,
+    };
+    if let Some((winner, crashed)) = forfeit {
+        let disqualification = Disqualification {
+            submission: crashed.clone(),
+            matchup_id,
+            reason: DisqualificationReason::ContainerCrash,
+            game_number: None,
+            turn_number: None,
+        };
+        db.record_disqualification(&disqualification).await?;
+        summary.disqualifications.push(disqualification);
+
+        info!(
+            "Recording {} unfinished games of {} vs {} as forfeits won by {}",
+            unfinished.len(),
+            submission_a,
+            submission_b,
+            winner
+        );
+        let games = std::mem::take(&mut unfinished).into_values();
+        record_forfeits(db, &mut summary, winner, games).await?;
+    }
+    // Unless a crashed container forfeited them, the games a timeout cut short are left pending
+    if timed_out {
+        return Err(MatchupTimeout {
+            submission_a,
+            submission_b,
+            timeout: config.matchup_timeout(),
+            pending: unfinished.len(),
+        }
+        .into());
+    }
+
+    let (first, second) = db.get_matchup_order(&submission_a, &submission_b).await;
+    let matchup_dir = config.matchup_visualization_dir(matchup_id, &first, &second);
+    if !config.record_turns_without_render && matchup_dir.exists() {
+        report::write_matchup_index(&matchup_dir.join("index.html"))?;
+    }
+    report::write_matchup_timeline(db, matchup_id, &matchup_dir.join("timeline.json")).await?;
+
+    if config.ndjson_results {
+        summary
+            .write_ndjson(&mut io::stdout().lock())
+            .context("Failed to write matchup results to stdout")?;
+    }
+
+    Ok(summary)
+}
+
+/// Whether the files in the `visualizations` folder add up to more than
+/// `max_visualization_bytes`.
+fn visualizations_over_cap(config: &Config) -> Result<bool> {
+    let Some(cap) = config.max_visualization_bytes else {
+        return Ok(false);
+    };
+    let dir = Path::new(&config.results_dir).join("visualizations");
+    let size = dir_size(&dir).with_context(|| format!("Failed to measure {}", dir.display()))?;
+    Ok(size > cap)
+}
+
+/// Total size of the files under `dir`, 0 if it doesn't exist.
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Final tally of a matchup, from the perspective of its two submissions rather than of whoever
+/// moved first in each game.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchupSummary {
+    matchup_id: i64,
+    #[serde(rename = "a")]
+    submission_a: String,
+    #[serde(rename = "b")]
+    submission_b: String,
+    a_wins: usize,
+    b_wins: usize,
+    ties: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    disqualifications: Vec<Disqualification>,
+    /// Results in completion order, relative to the first mover of each game
+    #[serde(skip)]
+    results: Vec<GameResult>,
+    /// Submission to blame and number of every game that failed or was forfeited.
+    #[serde(skip)]
+    failed_games: Vec<(String, i64)>,
+}
+
+impl MatchupSummary {
+    fn new(matchup_id: i64, submission_a: &str, submission_b: &str) -> Self {
+        Self {
+            matchup_id,
+            submission_a: submission_a.to_string(),
+            submission_b: submission_b.to_string(),
+            a_wins: 0,
+            b_wins: 0,
+            ties: 0,
+            disqualifications: Vec::new(),
+            results: Vec::new(),
+            failed_games: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, result: GameResult, is_reversed: bool) {
+        let (first, second) = if is_reversed {
+            (&self.submission_b, &self.submission_a)
+        } else {
+            (&self.submission_a, &self.submission_b)
+        };
+        match result.to_winner_submission(first, second) {
+            None => self.ties += 1,
+            Some(winner) if winner == self.submission_a => self.a_wins += 1,
+            Some(_) => self.b_wins += 1,
+        }
+        self.results.push(result);
+    }
+
+    /// Writes the summary as a single JSON line, for piping matchup results into other tools.
+    fn write_ndjson(&self, out: &mut impl Write) -> Result<()> {
+        serde_json::to_writer(&mut *out, self)?;
+        writeln!(out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for MatchupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} vs {} after {} games: {} wins {}, {} wins {}, {} ties",
+            self.submission_a,
+            self.submission_b,
+            self.a_wins + self.b_wins + self.ties,
+            self.submission_a,
+            self.a_wins,
+            self.submission_b,
+            self.b_wins,
+            self.ties
+        )?;
+        for dq in &self.disqualifications {
+            write!(f, ", {} disqualified ({})", dq.submission, dq.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Caps the number of games started across the whole run, and the wall-clock time they can be
+/// started in, shared by every matchup.
+struct GameBudget {
+    limit: Option<u64>,
+    started: AtomicU64,
+    /// The run's `max_runtime`, and when it is up.
+    deadline: Option<(Duration, Instant)>,
+}
+
+impl GameBudget {
+    fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            started: AtomicU64::new(0),
+            deadline: None,
+        }
+    }
+
+    /// The budget of a run starting now.
+    fn from_config(config: &Config) -> Self {
+        let mut budget = Self::new(config.max_total_games);
+        budget.deadline = config
+            .max_runtime()
+            .map(|runtime| (runtime, Instant::now() + runtime));
+        budget
+    }
+
+    fn is_out_of_time(&self) -> bool {
+        self.deadline
+            .is_some_and(|(_, deadline)| Instant::now() >= deadline)
+    }
+
+    /// Counts a game as started, unless the cap has been reached or the time is up.
+    fn try_start(&self) -> bool {
+        if self.is_out_of_time() {
+            return false;
+        }
+        let Some(limit) = self.limit else {
+            return true;
+        };
+        self.started
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |started| {
+                (started < limit).then_some(started + 1)
+            })
+            .is_ok()
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.is_out_of_time()
+            || self
+                .limit
+                .is_some_and(|limit| self.started.load(Ordering::SeqCst) >= limit)
+    }
+
+    /// Which limit an exhausted budget ran into, for logs.
+    fn exhausted_limit(&self) -> String {
+        match self.deadline {
+            Some((runtime, _)) if self.is_out_of_time() => {
+                format!("the max runtime of {:?}", runtime)
             }
+            _ => format!("the cap of {} total games", self.limit.unwrap_or_default()),
+        }
+    }
+}
+
+/// Trips for a submission once more than `max_failures` of its games failed across every
+/// matchup, so its remaining matchups are forfeited instead of starting its containers again.
+struct FailureBreaker {
+    max_failures: Option<u64>,
+    /// Failed games of each submission by opponent and game number, so a game failing again when
+    /// its matchup is retried counts once.
+    failures: std::sync::Mutex<HashMap<String, HashSet<(String, i64)>>>,
+}
+
+impl FailureBreaker {
+    fn new(max_failures: Option<u64>) -> Self {
+        Self {
+            max_failures,
+            failures: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts the games numbered `game_numbers` of `submission` against `opponent` as failed.
+    fn record(
+        &self,
+        submission: &str,
+        opponent: &str,
+        game_numbers: impl IntoIterator<Item = i64>,
+    ) {
+        let Some(max_failures) = self.max_failures else {
+            return;
+        };
+        let mut failures = self.failures.lock().unwrap();
+        let failed = failures.entry(submission.to_string()).or_default();
+        let was_tripped = failed.len() as u64 > max_failures;
+        failed.extend(
+            game_numbers
+                .into_iter()
+                .map(|game_number| (opponent.to_string(), game_number)),
+        );
+        if !was_tripped && failed.len() as u64 > max_failures {
+            error!(
+                "{} failed {} games, more than max_submission_failures ({}), forfeiting its \
+                 remaining matchups",
+                submission,
+                failed.len(),
+                max_failures
+            );
+        }
+    }
+
+    /// Counts the games that failed because of a submission in `summary`, or that it forfeited.
+    fn record_failed_games(&self, summary: &MatchupSummary) {
+        for (submission, game_number) in &summary.failed_games {
+            let opponent = if *submission == summary.submission_a {
+                &summary.submission_b
+            } else {
+                &summary.submission_a
+            };
+            self.record(submission, opponent, [*game_number]);
         }
     }
 
-    Ok(results)
+    fn is_tripped(&self, submission: &str) -> bool {
+        self.max_failures.is_some_and(|max_failures| {
+            self.failures
+                .lock()
+                .unwrap()
+                .get(submission)
+                .is_some_and(|failed| failed.len() as u64 > max_failures)
+        })
+    }
+}
+
+/// Spawns `task` onto `tasks`, but only lets it start once a permit from `slots` is available.
+fn spawn_bounded<T, F>(
+    tasks: &mut JoinSet<Result<T>>,
+    slots: &Arc<Semaphore>,
+    task: F,
+) -> AbortHandle
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T>> + Send + 'static,
+{
+    let slots = slots.clone();
+    // Games time into the profile of the matchup they belong to
+    tasks.spawn(profile::scope(profile::current(), async move {
+        let _permit = slots
+            .acquire_owned()
+            .await
+            .context("Game semaphore closed")?;
+        task.await
+    }))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_game(
     game_id: i64,
     first_submission: String,
     second_submission: String,
-    first_container: ContainerHandle,
-    second_container: ContainerHandle,
+    first_container: Arc<dyn Player>,
+    second_container: Arc<dyn Player>,
     matchup_id: i64,
     db: Database,
     config: Config,
@@ -250,26 +1791,77 @@ async fn run_game(
         game_id, first_submission, second_submission
     );
 
-    let game_future = async {
-        let first = Submission::new(first_submission.as_str(), first_container);
-        let second = Submission::new(second_submission.as_str(), second_container);
+    let pending_call = PendingCall::default();
+    // A fresh attempt builds a fresh game, with a fresh seed unless it comes from the tournament
+    // seed
+    let play_game = || async {
+        let starting_state = |name: &str| {
+            let dir = config.submission_dir(name);
+            SubmissionMeta::load(&dir).map(|meta| meta.starting_state())
+        };
+        let mut first = Submission::new(first_submission.as_str(), first_container.clone())
+            .with_starting_state(starting_state(&first_submission)?)
+            .with_pending_call(pending_call.clone());
+        let mut second = Submission::new(second_submission.as_str(), second_container.clone())
+            .with_starting_state(starting_state(&second_submission)?)
+            .with_pending_call(pending_call.clone());
+        if config.trace_calls {
+            let (a, b) = db
+                .get_matchup_order(&first_submission, &second_submission)
+                .await;
+            let path = config
+                .matchup_results_dir("traces", matchup_id, &a, &b)
+                .join(format!("game_{}.jsonl", game_id));
+            // A restarted game starts a fresh trace
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+            first = first.with_trace(path.clone());
+            second = second.with_trace(path);
+        }
 
-        let mut game = Game::new(first, second, game_id, matchup_id, config.clone());
+        let mut game = GameBuilder::new(first, second)
+            .ids(game_id, matchup_id)
+            .config(config.clone())
+            .build()?;
         game.result(&db).await.context("Failed to run game")
     };
 
-    match timeout(config.game_timeout(), game_future).await {
-        Ok(result) => {
-            let result = result.context("Failed to get game result")?;
-            info!(
-                "Game {} completed: {} vs {} - {:?}",
-                game_id, first_submission, second_submission, result
-            );
-            Ok(result)
-        }
-        Err(_) => {
-            error!("Game {} timed out after {:?}", game_id, config.game_timeout());
-            bail!("Game timed out")
-        }
+    let mut attempt = 0;
+    loop {
+        // A game left pending is restarted cleanly by the next attempt
+        let can_retry = attempt < config.game_retries
+            && !first_container.is_dead()
+            && !second_container.is_dead();
+        let failure = match timeout(config.game_timeout(), play_game()).await {
+            Ok(Ok(result)) => {
+                debug!(
+                    "Game {} completed: {} vs {} - {:?}",
+                    game_id, first_submission, second_submission, result
+                );
+                return Ok(result);
+            }
+            Ok(Err(e)) if can_retry => format!("failed: {:#}", e),
+            Ok(Err(e)) => return Err(e).context("Failed to get game result"),
+            Err(_) if can_retry => format!("timed out after {:?}", config.game_timeout()),
+            Err(_) => {
+                error!(
+                    "Game {} timed out after {:?}, keeping the turns it recorded",
+                    game_id,
+                    config.game_timeout()
+                );
+                db.mark_game_timed_out(matchup_id, game_id).await?;
+                let error = anyhow!("Game timed out");
+                return Err(match pending_call.lock().unwrap().take() {
+                    Some(submission) => error.context(SubmissionFault { submission }),
+                    None => error,
+                });
+            }
+        };
+        attempt += 1;
+        warn!(
+            "Game {} of {} vs {} {}, retrying ({} of {})",
+            game_id, first_submission, second_submission, failure, attempt, config.game_retries
+        );
     }
 }
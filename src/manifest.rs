@@ -0,0 +1,123 @@
+use anyhow::{Context, Result, bail};
+use figment::{
+    Figment,
+    providers::{Format as _, Toml},
+};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{SUBMISSIONS_DIR, container::image_name};
+
+mod tests;
+
+/// How a submission is run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// A container started from an image.
+    #[default]
+    Container,
+    /// A WebAssembly module.
+    Wasm,
+}
+
+/// One `[[submission]]` of a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    #[serde(default)]
+    pub backend: Backend,
+    /// Container image, `localhost/rplcs-tournament-1/<name>:latest` by default.
+    pub image: Option<String>,
+    /// The submission's directory, `submissions/<name>` by default. For the wasm backend, the
+    /// module itself.
+    pub path: Option<PathBuf>,
+}
+
+impl ManifestEntry {
+    pub fn image(&self) -> String {
+        self.image.clone().unwrap_or_else(|| image_name(&self.name))
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| Path::new(SUBMISSIONS_DIR).join(&self.name))
+    }
+}
+
+/// Submissions listed explicitly in a TOML file, instead of found in the `submissions` folder:
+///
+/// ```toml
+/// [[submission]]
+/// name = "my_bot"
+/// image = "ghcr.io/me/my_bot:v2"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Manifest {
+    #[serde(default, rename = "submission")]
+    pub submissions: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`, failing on duplicate or unusable names.
+    pub fn load(path: &Path) -> Result<Self> {
+        let manifest: Manifest = Figment::from(Toml::file_exact(path))
+            .extract()
+            .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+
+        let mut names = HashSet::new();
+        for entry in &manifest.submissions {
+            // Names end up in container and image names
+            if entry.name.is_empty() || entry.name.contains(['/', ':']) {
+                bail!("Invalid submission name {:?} in manifest", entry.name);
+            }
+            if !names.insert(&entry.name) {
+                bail!(
+                    "Submission {} is listed more than once in manifest",
+                    entry.name
+                );
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.submissions
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&ManifestEntry> {
+        self.submissions.iter().find(|entry| entry.name == name)
+    }
+
+    /// Checks that what every submission is launched from exists, using `image_exists` for
+    /// container images.
+    pub async fn validate(&self, image_exists: impl AsyncFn(&str) -> Result<bool>) -> Result<()> {
+        for entry in &self.submissions {
+            if let Some(path) = &entry.path
+                && !path.exists()
+            {
+                bail!("Path {} of {} does not exist", path.display(), entry.name);
+            }
+            match entry.backend {
+                Backend::Container => {
+                    let image = entry.image();
+                    if !image_exists(&image).await? {
+                        bail!("Image {} of {} does not exist", image, entry.name);
+                    }
+                }
+                Backend::Wasm => bail!(
+                    "Submission {} uses the wasm backend, which this runner can't launch",
+                    entry.name
+                ),
+            }
+        }
+        Ok(())
+    }
+}
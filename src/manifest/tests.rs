@@ -0,0 +1,82 @@
+#![cfg(test)]
+
+use std::{fs, path::Path};
+
+use anyhow::Result;
+
+use crate::scheduler::generate_rounds_grouped;
+
+use super::{Backend, Manifest};
+
+fn write_manifest(dir: &Path, toml: &str) -> Manifest {
+    let path = dir.join("manifest.toml");
+    fs::write(&path, toml).unwrap();
+    Manifest::load(&path).unwrap()
+}
+
+async fn only_image(image: &str) -> Result<bool> {
+    Ok(image == "ghcr.io/me/alpha:v2" || image == "localhost/rplcs-tournament-1/beta:latest")
+}
+
+#[tokio::test]
+async fn test_manifest_with_two_entries_drives_a_two_submission_schedule() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = write_manifest(
+        dir.path(),
+        r#"
+            [[submission]]
+            name = "alpha"
+            image = "ghcr.io/me/alpha:v2"
+
+            [[submission]]
+            name = "beta"
+            backend = "container"
+        "#,
+    );
+
+    manifest.validate(only_image).await.unwrap();
+    assert_eq!(manifest.entry("beta").unwrap().backend, Backend::Container);
+    assert_eq!(
+        manifest.entry("beta").unwrap().path(),
+        Path::new("submissions/beta")
+    );
+
+    let rounds = generate_rounds_grouped(manifest.names());
+    assert_eq!(
+        rounds,
+        [[("alpha".to_string(), "beta".to_string())]],
+        "{rounds:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_manifest_validation_checks_images_paths_and_backends() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing_image = write_manifest(dir.path(), "[[submission]]\nname = \"gamma\"");
+    assert!(missing_image.validate(only_image).await.is_err());
+
+    let missing_path = write_manifest(
+        dir.path(),
+        "[[submission]]\nname = \"beta\"\npath = \"no/such/dir\"",
+    );
+    assert!(missing_path.validate(only_image).await.is_err());
+
+    // There is no runtime for wasm modules, however valid the file
+    fs::write(dir.path().join("bot.wasm"), b"\0asm").unwrap();
+    let wasm = write_manifest(
+        dir.path(),
+        &format!(
+            "[[submission]]\nname = \"delta\"\nbackend = \"wasm\"\npath = {:?}",
+            dir.path().join("bot.wasm")
+        ),
+    );
+    assert!(wasm.validate(only_image).await.is_err());
+
+    let path = dir.path().join("manifest.toml");
+    fs::write(
+        &path,
+        "[[submission]]\nname = \"a\"\n[[submission]]\nname = \"a\"",
+    )
+    .unwrap();
+    assert!(Manifest::load(&path).is_err());
+}
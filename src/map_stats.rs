@@ -0,0 +1,106 @@
+use crate::game_map::{GameMap, MapConfig, NODE_TYPES, node_type_index};
+use anyhow::{Context, Result};
+use rand::{SeedableRng, rngs::StdRng};
+use rplcs_events::tournament_1::MapNodeType;
+use std::fmt;
+
+mod tests;
+
+/// Aggregate statistics of many generated maps, for balancing map generation without playing
+/// any games.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapStats {
+    pub maps: usize,
+    pub mean_nodes: f64,
+    /// Share of all nodes of each type, in the order teleport, healing, gamble, normal.
+    pub node_type_shares: [f64; 4],
+    pub mean_degree: f64,
+    /// Share of nodes with a healing node one move away.
+    pub next_to_healing: f64,
+    /// Mean number of moves between two distinct nodes.
+    pub mean_distance: f64,
+    /// Share of maps that were strongly connected on the first try. The others were regenerated,
+    /// since every map a game is played on is strongly connected.
+    pub first_try_connected: f64,
+    pub mean_attempts: f64,
+}
+
+/// Generates `count` maps from the seeds `first_seed`, `first_seed + 1` and so on, seeded like
+/// games are so `render-map` reproduces any of them, and aggregates their statistics.
+pub fn map_stats(config: &MapConfig, count: usize, first_seed: i64) -> Result<MapStats> {
+    let mut nodes = 0;
+    let mut type_counts = [0; 4];
+    let mut degrees = 0;
+    let mut next_to_healing = 0;
+    let (mut distances, mut pairs) = (0, 0);
+    let (mut first_try, mut attempts) = (0, 0);
+
+    for seed in (first_seed..).take(count) {
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let (map, map_attempts) = GameMap::new_counting_attempts(&mut rng, config)
+            .with_context(|| format!("Failed to generate map for seed {}", seed))?;
+        attempts += map_attempts;
+        // Without one-way edges the first map is kept even if it isn't strongly connected
+        first_try += usize::from(map_attempts == 1 && map.is_strongly_connected());
+
+        nodes += map.node_count();
+        for node in map.node_indices() {
+            degrees += map.get_node_degree(node);
+            if !map.neighbors_of_type(node, MapNodeType::Healing).is_empty() {
+                next_to_healing += 1;
+            }
+            if let Some(node_type) = map.get_node_type(node) {
+                type_counts[node_type_index(node_type)] += 1;
+            }
+            for other in map.node_indices() {
+                if other != node
+                    && let Some(path) = map.shortest_path(node, other)
+                {
+                    distances += path.len() - 1;
+                    pairs += 1;
+                }
+            }
+        }
+    }
+
+    let mean = |total: usize, of: usize| {
+        if of == 0 {
+            0.0
+        } else {
+            total as f64 / of as f64
+        }
+    };
+    Ok(MapStats {
+        maps: count,
+        mean_nodes: mean(nodes, count),
+        node_type_shares: type_counts.map(|n| mean(n, nodes)),
+        mean_degree: mean(degrees, nodes),
+        next_to_healing: mean(next_to_healing, nodes),
+        mean_distance: mean(distances, pairs),
+        first_try_connected: mean(first_try, count),
+        mean_attempts: mean(attempts, count),
+    })
+}
+
+impl fmt::Display for MapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Maps: {}", self.maps)?;
+        writeln!(f, "Mean nodes: {:.2}", self.mean_nodes)?;
+        for (node_type, share) in NODE_TYPES.iter().zip(self.node_type_shares) {
+            writeln!(f, "{:?} nodes: {:.1}%", node_type, share * 100.0)?;
+        }
+        writeln!(f, "Mean degree: {:.2}", self.mean_degree)?;
+        writeln!(
+            f,
+            "Nodes next to healing: {:.1}%",
+            self.next_to_healing * 100.0
+        )?;
+        writeln!(f, "Mean distance between nodes: {:.2}", self.mean_distance)?;
+        writeln!(
+            f,
+            "Strongly connected on the first try: {:.1}%",
+            self.first_try_connected * 100.0
+        )?;
+        write!(f, "Mean generation attempts: {:.2}", self.mean_attempts)
+    }
+}
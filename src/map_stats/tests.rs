@@ -0,0 +1,27 @@
+#![cfg(test)]
+
+use crate::game_map::{MAX_DEGREE, MAX_NODES, MIN_DEGREE, MIN_NODES, MapConfig};
+
+use super::map_stats;
+
+#[test]
+fn test_map_stats_stay_within_generation_bounds() {
+    let config = MapConfig::default();
+    let stats = map_stats(&config, 50, 0).unwrap();
+
+    assert_eq!(stats.maps, 50);
+    assert!((MIN_NODES as f64..=MAX_NODES as f64).contains(&stats.mean_nodes));
+    assert!((MIN_DEGREE as f64..=MAX_DEGREE as f64).contains(&stats.mean_degree));
+    let total: f64 = stats.node_type_shares.iter().sum();
+    assert!((total - 1.0).abs() < 1e-9, "{stats:?}");
+    // Every map has a teleport and mostly normal nodes
+    let [teleport, _, _, normal] = stats.node_type_shares;
+    assert!(teleport > 0.0 && normal > 0.5, "{stats:?}");
+    assert!(stats.next_to_healing > 0.0 && stats.next_to_healing < 1.0);
+    assert!(stats.mean_distance >= 1.0 && stats.mean_distance < MAX_NODES as f64);
+    assert!(stats.first_try_connected > 0.0 && stats.first_try_connected <= 1.0);
+    assert!(stats.mean_attempts >= 1.0);
+
+    // Sequential seeds make the statistics reproducible
+    assert_eq!(map_stats(&config, 50, 0).unwrap(), stats);
+}
@@ -1,25 +1,81 @@
 use anyhow::Result;
-use std::sync::{Arc, OnceLock};
-use tokio::sync::Mutex;
+use rand::random;
+use std::{collections::HashSet, sync::Mutex};
+
+mod tests;
 
 const MIN_PORT: u16 = 49152; // Start of dynamic/private ports
 const MAX_PORT: u16 = 65535; // End of valid ports
+/// Size of the windows the port range is split into. Runs with different seeds start in different
+/// windows, so they only collide once one of them has used up its window.
+const PORT_WINDOW: u16 = 256;
 
-static PORT_ALLOCATOR: OnceLock<Arc<Mutex<u16>>> = OnceLock::new();
+static PORT_ALLOCATOR: Mutex<Option<PortAllocator>> = Mutex::new(None);
 
-fn get_port_allocator() -> &'static Arc<Mutex<u16>> {
-    PORT_ALLOCATOR.get_or_init(|| Arc::new(Mutex::new(MIN_PORT)))
+/// Hands out ports in order, wrapping around at the end of the dynamic range, skipping the
+/// ports of adopted containers.
+#[derive(Debug)]
+struct PortAllocator {
+    next: u16,
+    reserved: HashSet<u16>,
 }
 
-pub async fn get_next_port() -> Result<u16> {
-    let mut port = get_port_allocator().lock().await;
+impl PortAllocator {
+    /// Starts at the beginning of window `seed` (modulo the number of windows).
+    fn from_seed(seed: u64) -> Self {
+        let windows = (MAX_PORT - MIN_PORT) as u64 / PORT_WINDOW as u64;
+        let window = (seed % windows) as u16;
+        PortAllocator {
+            next: MIN_PORT + window * PORT_WINDOW,
+            reserved: HashSet::new(),
+        }
+    }
 
-    let current = *port;
-    if current >= MAX_PORT {
-        *port = MIN_PORT;
-    } else {
-        *port += 1;
+    fn next_port(&mut self) -> u16 {
+        loop {
+            let current = self.next;
+            if current == MAX_PORT {
+                self.next = MIN_PORT;
+            } else {
+                self.next += 1;
+            }
+            if !self.reserved.contains(&current) {
+                return current;
+            }
+        }
     }
+}
+
+/// Starts handing out ports from the window picked by `seed`, or by a random seed without one,
+/// so concurrent runs on the same host tend to use disjoint ports.
+pub fn seed_port_allocator(seed: Option<u64>) {
+    *PORT_ALLOCATOR.lock().unwrap() = Some(PortAllocator::from_seed(seed.unwrap_or_else(random)));
+}
 
-    Ok(current)
+/// Keeps `port` from being handed out, because a container adopted from an earlier run already
+/// publishes on it.
+pub fn reserve_port(port: u16) {
+    PORT_ALLOCATOR
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| PortAllocator::from_seed(random()))
+        .reserved
+        .insert(port);
+}
+
+/// Whether `port` was reserved with `reserve_port`.
+#[cfg(test)]
+pub fn is_port_reserved(port: u16) -> bool {
+    PORT_ALLOCATOR
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|allocator| allocator.reserved.contains(&port))
+}
+
+pub async fn get_next_port() -> Result<u16> {
+    let mut allocator = PORT_ALLOCATOR.lock().unwrap();
+    Ok(allocator
+        .get_or_insert_with(|| PortAllocator::from_seed(random()))
+        .next_port())
 }
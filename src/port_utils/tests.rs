@@ -0,0 +1,39 @@
+#![cfg(test)]
+
+use std::collections::HashSet;
+
+use super::{MAX_PORT, MIN_PORT, PORT_WINDOW, PortAllocator};
+
+fn first_ports(seed: u64) -> HashSet<u16> {
+    let mut allocator = PortAllocator::from_seed(seed);
+    (0..PORT_WINDOW).map(|_| allocator.next_port()).collect()
+}
+
+#[test]
+fn test_allocators_with_different_seeds_start_on_disjoint_ports() {
+    let first = first_ports(1);
+    let second = first_ports(2);
+    assert_eq!(first.len(), PORT_WINDOW as usize);
+    assert!(first.is_disjoint(&second));
+    assert_eq!(first_ports(1), first);
+}
+
+#[test]
+fn test_allocator_wraps_around_at_the_end_of_the_range() {
+    let mut allocator = PortAllocator {
+        next: MAX_PORT,
+        reserved: HashSet::new(),
+    };
+    assert_eq!(allocator.next_port(), MAX_PORT);
+    assert_eq!(allocator.next_port(), MIN_PORT);
+}
+
+#[test]
+fn test_reserved_ports_are_skipped() {
+    let mut allocator = PortAllocator {
+        next: MIN_PORT,
+        reserved: HashSet::from([MIN_PORT + 1, MIN_PORT + 2]),
+    };
+    assert_eq!(allocator.next_port(), MIN_PORT);
+    assert_eq!(allocator.next_port(), MIN_PORT + 3);
+}
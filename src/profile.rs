@@ -0,0 +1,132 @@
+//! Opt-in breakdown of where the wall-clock time of a matchup goes. Timed regions add their
+//! duration to a bucket of the profile of the task they run in; outside of a profiled matchup
+//! they only pay for a task-local lookup.
+
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+mod tests;
+
+/// A kind of work timed by the profiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    /// Starting submission containers and waiting for them to answer.
+    ContainerStartup,
+    /// Calls to submissions, from sending the request to parsing the response.
+    SubmissionCalls,
+    /// Rendering turn SVGs.
+    Rendering,
+    /// Statements run against the results database, retries included.
+    Database,
+}
+
+const BUCKETS: [Bucket; 4] = [
+    Bucket::ContainerStartup,
+    Bucket::SubmissionCalls,
+    Bucket::Rendering,
+    Bucket::Database,
+];
+
+impl Bucket {
+    fn label(self) -> &'static str {
+        match self {
+            Bucket::ContainerStartup => "container startup",
+            Bucket::SubmissionCalls => "submission calls",
+            Bucket::Rendering => "rendering",
+            Bucket::Database => "database",
+        }
+    }
+}
+
+/// Time spent in each bucket. Regions of concurrent games overlap, so the buckets can add up to
+/// more than the wall-clock time.
+#[derive(Debug, Default)]
+pub struct Profile {
+    nanos: [AtomicU64; BUCKETS.len()],
+}
+
+impl Profile {
+    pub fn total(&self, bucket: Bucket) -> Duration {
+        Duration::from_nanos(self.nanos[bucket as usize].load(Ordering::Relaxed))
+    }
+
+    fn add(&self, bucket: Bucket, duration: Duration) {
+        self.nanos[bucket as usize].fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Adds every bucket of `other` to this profile.
+    pub fn merge(&self, other: &Profile) {
+        for bucket in BUCKETS {
+            self.add(bucket, other.total(bucket));
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, bucket) in BUCKETS.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {:.2?}", bucket.label(), self.total(bucket))?;
+        }
+        Ok(())
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: Arc<Profile>;
+}
+
+/// Profile of the current task, if it is being profiled.
+pub fn current() -> Option<Arc<Profile>> {
+    CURRENT.try_with(Arc::clone).ok()
+}
+
+/// Runs `future` with `profile` as its profile, or unprofiled if it is `None`.
+pub async fn scope<F: Future>(profile: Option<Arc<Profile>>, future: F) -> F::Output {
+    match profile {
+        Some(profile) => CURRENT.scope(profile, future).await,
+        None => future.await,
+    }
+}
+
+/// Adds `duration` to `bucket` of the current task's profile, if there is one.
+pub fn record(bucket: Bucket, duration: Duration) {
+    let _ = CURRENT.try_with(|profile| profile.add(bucket, duration));
+}
+
+/// Runs `f`, timing it into `bucket` if the current task is being profiled.
+pub fn time<T>(bucket: Bucket, f: impl FnOnce() -> T) -> T {
+    if CURRENT.try_with(|_| ()).is_err() {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    record(bucket, start.elapsed());
+    result
+}
+
+/// Like `time`, for a future.
+pub async fn time_async<F: Future>(bucket: Bucket, future: F) -> F::Output {
+    if CURRENT.try_with(|_| ()).is_err() {
+        return future.await;
+    }
+    let start = Instant::now();
+    let result = future.await;
+    record(bucket, start.elapsed());
+    result
+}
+
+/// Every profiled matchup of the run, added up.
+pub fn run_total() -> &'static Profile {
+    static RUN: OnceLock<Profile> = OnceLock::new();
+    RUN.get_or_init(Profile::default)
+}
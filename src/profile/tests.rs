@@ -0,0 +1,38 @@
+#![cfg(test)]
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{Config, bots::built_in_bots, db::Database, game::GameBuilder, submission::Submission};
+
+use super::{Bucket, Profile, scope};
+
+#[tokio::test]
+async fn test_rendering_game_fills_render_bucket() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 3,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+
+    let mut bots = built_in_bots(None).into_iter();
+    let (_, bot_a) = bots.next().unwrap();
+    let (_, bot_b) = bots.next().unwrap();
+    let mut game = GameBuilder::new(Submission::new("a", bot_a), Submission::new("b", bot_b))
+        .ids(0, matchup_id)
+        .config(config)
+        .build()
+        .unwrap();
+
+    let profile = Arc::new(Profile::default());
+    scope(Some(profile.clone()), game.result(&db))
+        .await
+        .unwrap();
+
+    assert!(results_dir.path().join("visualizations").exists());
+    assert!(profile.total(Bucket::Rendering) > Duration::ZERO);
+    assert!(profile.total(Bucket::Database) > Duration::ZERO);
+    assert_eq!(profile.total(Bucket::ContainerStartup), Duration::ZERO);
+}
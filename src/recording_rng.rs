@@ -0,0 +1,96 @@
+use rand::{RngCore, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Which `RngCore` method a draw came from. Replaying a game's draws has to call the same ones,
+/// since `StdRng` buffers its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawKind {
+    U32,
+    U64,
+    Bytes,
+}
+
+/// One value drawn from a game's rng.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Draw {
+    /// What the game was doing, e.g. `map` or `fight a vs Enemy 0`.
+    pub context: String,
+    pub kind: DrawKind,
+    /// The drawn value in hex, with the bytes of `Bytes` draws in order.
+    pub value: String,
+}
+
+/// A game's rng, which can record every value drawn from it, so fairness disputes can be settled
+/// by replaying a fresh `StdRng` from the game's seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingRng {
+    rng: StdRng,
+    /// The draws so far, if recording.
+    draws: Option<Vec<Draw>>,
+    context: String,
+}
+
+impl RecordingRng {
+    pub fn new(rng: StdRng) -> Self {
+        RecordingRng {
+            rng,
+            draws: None,
+            context: String::new(),
+        }
+    }
+
+    pub fn start_recording(&mut self) {
+        self.draws.get_or_insert_with(Vec::new);
+    }
+
+    /// Labels the following draws with `context`. Free unless recording.
+    pub fn set_context(&mut self, context: impl fmt::Display) {
+        if self.draws.is_some() {
+            self.context = context.to_string();
+        }
+    }
+
+    /// Every draw since recording started, `None` if it didn't.
+    pub fn draws(&self) -> Option<&[Draw]> {
+        self.draws.as_deref()
+    }
+
+    /// The next `u64` the rng would produce, drawn from a copy of it, so neither the rng nor
+    /// the recorded draws change.
+    pub fn peek_u64(&self) -> u64 {
+        self.rng.clone().next_u64()
+    }
+
+    fn record(&mut self, kind: DrawKind, value: impl FnOnce() -> String) {
+        if let Some(draws) = &mut self.draws {
+            draws.push(Draw {
+                context: self.context.clone(),
+                kind,
+                value: value(),
+            });
+        }
+    }
+}
+
+impl RngCore for RecordingRng {
+    fn next_u32(&mut self) -> u32 {
+        let value = self.rng.next_u32();
+        self.record(DrawKind::U32, || format!("{:08x}", value));
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.rng.next_u64();
+        self.record(DrawKind::U64, || format!("{:016x}", value));
+        value
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.rng.fill_bytes(dst);
+        self.record(DrawKind::Bytes, || {
+            dst.iter().map(|byte| format!("{:02x}", byte)).collect()
+        });
+    }
+}
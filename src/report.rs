@@ -0,0 +1,991 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    db::{Database, GameDuration},
+    game::GameEvent,
+    game_map::{NODE_TYPES, node_type_name},
+};
+
+mod tests;
+
+/// How one submission did in a matchup when moving first compared to moving second. Ties count
+/// as games but not as wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnOrderSplit {
+    pub matchup_id: i64,
+    pub submission: String,
+    pub opponent: String,
+    pub first_games: u32,
+    pub first_wins: u32,
+    pub second_games: u32,
+    pub second_wins: u32,
+    /// Whether the two win rates differ by more than the audit's threshold.
+    pub anomalous: bool,
+}
+
+impl TurnOrderSplit {
+    pub fn first_win_rate(&self) -> Option<f64> {
+        win_rate(self.first_wins, self.first_games)
+    }
+
+    pub fn second_win_rate(&self) -> Option<f64> {
+        win_rate(self.second_wins, self.second_games)
+    }
+}
+
+fn win_rate(wins: u32, games: u32) -> Option<f64> {
+    (games > 0).then(|| f64::from(wins) / f64::from(games))
+}
+
+/// Splits every decided game by turn order, per matchup and submission, and flags submissions
+/// whose win rate moving first and moving second differ by more than `threshold`. Games recorded
+/// before the first mover was stored are left out.
+pub async fn turn_order_audit(db: &Database, threshold: f64) -> Result<Vec<TurnOrderSplit>> {
+    let mut splits: BTreeMap<(i64, String), TurnOrderSplit> = BTreeMap::new();
+    for game in db.list_decided_games().await? {
+        let Some(first_mover) = &game.first_mover else {
+            continue;
+        };
+        let winner = game.winner_name();
+
+        for (submission, opponent) in [
+            (&game.player_a, &game.player_b),
+            (&game.player_b, &game.player_a),
+        ] {
+            let split = splits
+                .entry((game.matchup_id, submission.clone()))
+                .or_insert_with(|| TurnOrderSplit {
+                    matchup_id: game.matchup_id,
+                    submission: submission.clone(),
+                    opponent: opponent.clone(),
+                    first_games: 0,
+                    first_wins: 0,
+                    second_games: 0,
+                    second_wins: 0,
+                    anomalous: false,
+                });
+            let won = u32::from(winner == Some(submission.as_str()));
+            if submission == first_mover {
+                split.first_games += 1;
+                split.first_wins += won;
+            } else {
+                split.second_games += 1;
+                split.second_wins += won;
+            }
+        }
+    }
+
+    Ok(splits
+        .into_values()
+        .map(|mut split| {
+            split.anomalous = match (split.first_win_rate(), split.second_win_rate()) {
+                (Some(first), Some(second)) => (first - second).abs() > threshold,
+                _ => false,
+            };
+            split
+        })
+        .collect())
+}
+
+/// Logs the splits flagged by `turn_order_audit`.
+pub async fn log_turn_order_audit(db: &Database, threshold: f64) -> Result<()> {
+    let splits = turn_order_audit(db, threshold).await?;
+    let anomalies: Vec<_> = splits.iter().filter(|split| split.anomalous).collect();
+    if anomalies.is_empty() {
+        info!(
+            "Turn order audit: no win rate gap above {:.0}% in {} matchup sides",
+            threshold * 100.0,
+            splits.len()
+        );
+    }
+    for split in anomalies {
+        warn!(
+            "Turn order audit: {} wins {:.0}% of {} games moving first but {:.0}% of {} moving \
+             second against {} (matchup {})",
+            split.submission,
+            split.first_win_rate().unwrap_or_default() * 100.0,
+            split.first_games,
+            split.second_win_rate().unwrap_or_default() * 100.0,
+            split.second_games,
+            split.opponent,
+            split.matchup_id
+        );
+    }
+    Ok(())
+}
+
+/// A submission's call latency over the whole tournament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubmissionLatency {
+    pub submission: String,
+    pub calls: usize,
+    pub p95_ms: f64,
+}
+
+/// The slowest games, and the submissions whose calls are slowest at the 95th percentile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slowest {
+    pub games: Vec<GameDuration>,
+    pub submissions: Vec<SubmissionLatency>,
+}
+
+/// The `k` slowest games and the `k` submissions with the worst p95 call latency, slowest first.
+pub async fn slowest(db: &Database, k: usize) -> Result<Slowest> {
+    let mut submissions: Vec<_> = db
+        .call_latency_stats()
+        .await?
+        .into_iter()
+        .map(|stats| SubmissionLatency {
+            submission: stats.submission,
+            calls: stats.calls,
+            p95_ms: stats.p95_ms,
+        })
+        .collect();
+    submissions.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+    submissions.truncate(k);
+
+    Ok(Slowest {
+        games: db.list_slowest_games(k).await?,
+        submissions,
+    })
+}
+
+/// Logs the `k` slowest games and submissions found by `slowest`.
+pub async fn log_slowest(db: &Database, k: usize) -> Result<()> {
+    if k == 0 {
+        return Ok(());
+    }
+    let slowest = slowest(db, k).await?;
+    for (rank, game) in slowest.games.iter().enumerate() {
+        info!(
+            "Slowest game #{}: game {} of {} vs {} (matchup {}) took {:.1}s",
+            rank + 1,
+            game.game_number,
+            game.player_a,
+            game.player_b,
+            game.matchup_id,
+            game.duration_ms / 1000.0
+        );
+    }
+    for (rank, submission) in slowest.submissions.iter().enumerate() {
+        info!(
+            "Slowest submission #{}: {} answered {} calls with a p95 latency of {:.1}ms",
+            rank + 1,
+            submission.submission,
+            submission.calls,
+            submission.p95_ms
+        );
+    }
+    Ok(())
+}
+
+/// Everything that happened in a matchup, for replay viewers to render it from a single file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchupTimeline {
+    pub matchup_id: i64,
+    pub players: [String; 2],
+    pub games: Vec<TimelineGame>,
+    /// Wins of each of `players` over the games with a result.
+    pub score: [u32; 2],
+    pub ties: u32,
+}
+
+/// A game of a `MatchupTimeline`, in the order the games were created.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineGame {
+    pub game_number: i64,
+    pub first_mover: Option<String>,
+    /// `None` for ties and unfinished games.
+    pub winner: Option<String>,
+    pub tie: bool,
+    /// Empty for forfeits, which were never played.
+    pub events: Vec<TimelineEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineEvent {
+    pub turn: i64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+/// Gathers the games of a matchup, with their events and outcome, and the final score.
+pub async fn matchup_timeline(db: &Database, matchup_id: i64) -> Result<MatchupTimeline> {
+    let matchup = db
+        .list_matchups()
+        .await?
+        .into_iter()
+        .find(|matchup| matchup.id == matchup_id)
+        .with_context(|| format!("No matchup {} in this tournament", matchup_id))?;
+    let players = [matchup.player_a, matchup.player_b];
+
+    let mut events: BTreeMap<i64, Vec<TimelineEvent>> = BTreeMap::new();
+    for (game_number, turn, event) in db.list_matchup_events(matchup_id).await? {
+        events
+            .entry(game_number)
+            .or_default()
+            .push(TimelineEvent { turn, event });
+    }
+
+    let (mut score, mut ties) = ([0; 2], 0);
+    let mut games = Vec::new();
+    for game in db.list_matchup_games(matchup_id).await? {
+        let winner = game.winner_name().map(str::to_string);
+        if let Some(i) = players.iter().position(|p| Some(p) == winner.as_ref()) {
+            score[i] += 1;
+        }
+        let tie = game.winner == "tie";
+        ties += u32::from(tie);
+        games.push(TimelineGame {
+            game_number: game.game_number,
+            events: events.remove(&game.game_number).unwrap_or_default(),
+            first_mover: game.first_mover,
+            winner,
+            tie,
+        });
+    }
+
+    Ok(MatchupTimeline {
+        matchup_id,
+        players,
+        games,
+        score,
+        ties,
+    })
+}
+
+/// Writes the `matchup_timeline` of a matchup to `out` as JSON.
+pub async fn write_matchup_timeline(db: &Database, matchup_id: i64, out: &Path) -> Result<()> {
+    let timeline = matchup_timeline(db, matchup_id).await?;
+    write_creating_dir(out, serde_json::to_string(&timeline)?)
+}
+
+/// How often a submission lands on each node type in a game, on average.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeVisits {
+    pub submission: String,
+    /// Mean visits per game, in `NODE_TYPES` order.
+    pub per_game: [f64; NODE_TYPES.len()],
+}
+
+/// Mean visits per game of every submission to each node type, by submission name.
+pub async fn node_visits(db: &Database) -> Result<Vec<NodeVisits>> {
+    let mut visits: BTreeMap<String, [f64; NODE_TYPES.len()]> = BTreeMap::new();
+    for (submission, node_type, mean) in db.list_node_visit_means().await? {
+        if let Some(i) = NODE_TYPES
+            .iter()
+            .position(|&t| node_type_name(t) == node_type)
+        {
+            visits.entry(submission).or_default()[i] = mean;
+        }
+    }
+    Ok(visits
+        .into_iter()
+        .map(|(submission, per_game)| NodeVisits {
+            submission,
+            per_game,
+        })
+        .collect())
+}
+
+/// Logs the `node_visits` of every submission.
+pub async fn log_node_visits(db: &Database) -> Result<()> {
+    for visits in node_visits(db).await? {
+        let per_type: Vec<String> = NODE_TYPES
+            .iter()
+            .zip(visits.per_game)
+            .map(|(&node_type, mean)| format!("{} {:.2}", node_type_name(node_type), mean))
+            .collect();
+        info!(
+            "{} lands per game on: {}",
+            visits.submission,
+            per_type.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// A tied game in which a submission never fought, which may have hidden from the opponent and
+/// the enemies until the turns ran out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassiveGame {
+    pub matchup_id: i64,
+    pub game_number: i64,
+    /// The submissions of the game without a single fight, in name order.
+    pub passive: Vec<String>,
+}
+
+/// Every tied game with a submission that neither started a fight nor was attacked, for
+/// organizers to check the map and the submission for a hiding exploit. Forfeits, which have no
+/// events, are left out.
+pub async fn flag_passive_games(db: &Database) -> Result<Vec<PassiveGame>> {
+    let mut flagged = Vec::new();
+    for matchup in db.list_matchups().await? {
+        // Who fought in each game with events
+        let mut fighters: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+        for (game_number, _, event) in db.list_matchup_events(matchup.id).await? {
+            let fought = fighters.entry(game_number).or_default();
+            if let GameEvent::Fight {
+                player, opponent, ..
+            } = event
+            {
+                fought.extend([player, opponent]);
+            }
+        }
+
+        for game in db.list_matchup_games(matchup.id).await? {
+            let Some(fought) = fighters.remove(&game.game_number) else {
+                continue;
+            };
+            if game.winner != "tie" {
+                continue;
+            }
+            let mut passive: Vec<String> = [&matchup.player_a, &matchup.player_b]
+                .into_iter()
+                .filter(|&name| !fought.contains(name))
+                .cloned()
+                .collect();
+            passive.sort();
+            if !passive.is_empty() {
+                flagged.push(PassiveGame {
+                    matchup_id: matchup.id,
+                    game_number: game.game_number,
+                    passive,
+                });
+            }
+        }
+    }
+    Ok(flagged)
+}
+
+/// Warns about the games found by `flag_passive_games`.
+pub async fn log_passive_games(db: &Database) -> Result<()> {
+    for game in flag_passive_games(db).await? {
+        warn!(
+            "Matchup {} game {} tied without {} ever fighting",
+            game.matchup_id,
+            game.game_number,
+            game.passive.join(" or ")
+        );
+    }
+    Ok(())
+}
+
+/// How many games a submission's feedback suggests looking at.
+const EXAMPLE_GAMES: usize = 3;
+
+/// Why a submission lost a game, judged from what happened to it on the game's last turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LossCause {
+    /// The game was awarded to the opponent without being played.
+    Forfeit,
+    /// It didn't answer in time.
+    Timeout,
+    /// Its last health went on an out-of-range move choice.
+    InvalidMove,
+    /// It lost a fight with its last health.
+    Fight,
+    /// It was still alive when the turns ran out, and lost the tie break.
+    TurnsRanOut,
+    /// Its last health went some other way, e.g. stalling or gambling.
+    OtherDamage,
+}
+
+impl LossCause {
+    fn label(self) -> &'static str {
+        match self {
+            LossCause::Forfeit => "forfeit",
+            LossCause::Timeout => "timeout",
+            LossCause::InvalidMove => "invalid move",
+            LossCause::Fight => "lost fight",
+            LossCause::TurnsRanOut => "turns ran out",
+            LossCause::OtherDamage => "other damage",
+        }
+    }
+}
+
+/// Why `loser` lost a game with `events`, `loser_index` being 0 if it moved first. `None` for
+/// games recorded without an end event.
+fn loss_cause(loser: &str, loser_index: usize, events: &[(i64, GameEvent)]) -> Option<LossCause> {
+    if events.is_empty() {
+        return Some(LossCause::Forfeit);
+    }
+    let (end_turn, alive) = events.iter().find_map(|(turn, event)| match event {
+        GameEvent::End { players, .. } => Some((*turn, players[loser_index].health > 0)),
+        _ => None,
+    })?;
+
+    let last = events
+        .iter()
+        .rev()
+        .filter(|(turn, _)| *turn == end_turn)
+        .find_map(|(_, event)| match event {
+            GameEvent::Timeout { player } if player == loser => Some(LossCause::Timeout),
+            GameEvent::InvalidMove { player } if player == loser => Some(LossCause::InvalidMove),
+            GameEvent::Fight {
+                player,
+                opponent,
+                won,
+            } if (player == loser && !won) || (opponent == loser && *won) => Some(LossCause::Fight),
+            _ => None,
+        });
+    Some(match (alive, last) {
+        (true, Some(LossCause::Timeout)) => LossCause::Timeout,
+        (true, _) => LossCause::TurnsRanOut,
+        (false, Some(cause)) => cause,
+        (false, None) => LossCause::OtherDamage,
+    })
+}
+
+/// A game a submission's author may want to look at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExampleGame {
+    pub matchup_id: i64,
+    pub game_number: i64,
+    pub opponent: String,
+    /// `won`, `tie`, or `lost` with the `LossCause`.
+    pub outcome: String,
+}
+
+/// How a submission did over the tournament, for its author.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feedback {
+    pub submission: String,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    /// Losses by cause, most common first. Games recorded without events count as forfeits;
+    /// played games recorded without an end event are left out.
+    pub loss_causes: Vec<(LossCause, u32)>,
+    pub mean_latency_ms: Option<f64>,
+    /// Mean turns of its played games, forfeits left out.
+    pub mean_game_turns: Option<f64>,
+    /// A few games to look at, losses first.
+    pub example_games: Vec<ExampleGame>,
+}
+
+impl Feedback {
+    fn new(submission: String) -> Self {
+        Feedback {
+            submission,
+            wins: 0,
+            losses: 0,
+            ties: 0,
+            loss_causes: Vec::new(),
+            mean_latency_ms: None,
+            mean_game_turns: None,
+            example_games: Vec::new(),
+        }
+    }
+
+    pub fn win_rate(&self) -> Option<f64> {
+        win_rate(self.wins, self.wins + self.losses + self.ties)
+    }
+}
+
+impl fmt::Display for Feedback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Feedback for {}", self.submission)?;
+        writeln!(
+            f,
+            "Games: {} wins, {} losses, {} ties",
+            self.wins, self.losses, self.ties
+        )?;
+        if let Some(win_rate) = self.win_rate() {
+            writeln!(f, "Win rate: {:.1}%", win_rate * 100.0)?;
+        }
+        if let Some((cause, count)) = self.loss_causes.first() {
+            writeln!(
+                f,
+                "Most common loss cause: {} ({} of {} losses)",
+                cause.label(),
+                count,
+                self.losses
+            )?;
+            for (cause, count) in &self.loss_causes {
+                writeln!(f, "  {}: {}", cause.label(), count)?;
+            }
+        }
+        if let Some(latency) = self.mean_latency_ms {
+            writeln!(f, "Mean response latency: {:.1}ms", latency)?;
+        }
+        if let Some(turns) = self.mean_game_turns {
+            writeln!(f, "Mean game length: {:.1} turns", turns)?;
+        }
+        if !self.example_games.is_empty() {
+            writeln!(f, "Games to look at:")?;
+            for game in &self.example_games {
+                writeln!(
+                    f,
+                    "  matchup {} game {} against {}: {}",
+                    game.matchup_id, game.game_number, game.opponent, game.outcome
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Gathers the `Feedback` of every submission with a decided game, by submission name. Like the
+/// standings, games recorded before the first mover was stored are left out.
+pub async fn feedback(db: &Database) -> Result<Vec<Feedback>> {
+    let mut feedback: BTreeMap<String, Feedback> = BTreeMap::new();
+    let mut causes: BTreeMap<String, BTreeMap<LossCause, u32>> = BTreeMap::new();
+    let mut turns: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    let mut examples: BTreeMap<String, (Vec<ExampleGame>, Vec<ExampleGame>)> = BTreeMap::new();
+
+    for matchup in db.list_matchups().await? {
+        let mut events: BTreeMap<i64, Vec<(i64, GameEvent)>> = BTreeMap::new();
+        for (game_number, turn, event) in db.list_matchup_events(matchup.id).await? {
+            events.entry(game_number).or_default().push((turn, event));
+        }
+
+        for game in db.list_matchup_games(matchup.id).await? {
+            let Some(first_mover) = &game.first_mover else {
+                continue;
+            };
+            let winner = game.winner_name();
+            if winner.is_none() && game.winner != "tie" {
+                continue;
+            }
+            let events = events.remove(&game.game_number).unwrap_or_default();
+            let end_turn = events
+                .iter()
+                .find_map(|(turn, event)| matches!(event, GameEvent::End { .. }).then_some(*turn));
+
+            for (submission, opponent) in [
+                (&game.player_a, &game.player_b),
+                (&game.player_b, &game.player_a),
+            ] {
+                let entry = feedback
+                    .entry(submission.clone())
+                    .or_insert_with(|| Feedback::new(submission.clone()));
+                if let Some(turn) = end_turn {
+                    turns.entry(submission.clone()).or_default().push(turn + 1);
+                }
+                let (losses, others) = examples.entry(submission.clone()).or_default();
+                let mut example = ExampleGame {
+                    matchup_id: matchup.id,
+                    game_number: game.game_number,
+                    opponent: opponent.clone(),
+                    outcome: String::new(),
+                };
+
+                if winner == Some(submission.as_str()) {
+                    entry.wins += 1;
+                    example.outcome = "won".to_string();
+                    others.push(example);
+                } else if winner == Some(opponent.as_str()) {
+                    entry.losses += 1;
+                    let loser_index = usize::from(first_mover != submission);
+                    let cause = loss_cause(submission, loser_index, &events);
+                    if let Some(cause) = cause {
+                        *causes
+                            .entry(submission.clone())
+                            .or_default()
+                            .entry(cause)
+                            .or_default() += 1;
+                    }
+                    example.outcome = match cause {
+                        Some(cause) => format!("lost ({})", cause.label()),
+                        None => "lost".to_string(),
+                    };
+                    losses.push(example);
+                } else {
+                    entry.ties += 1;
+                    example.outcome = "tie".to_string();
+                    others.push(example);
+                }
+            }
+        }
+    }
+
+    let latencies: BTreeMap<String, f64> = db
+        .call_latency_stats()
+        .await?
+        .into_iter()
+        .map(|stats| (stats.submission, stats.mean_ms))
+        .collect();
+
+    Ok(feedback
+        .into_values()
+        .map(|mut entry| {
+            let mut loss_causes: Vec<_> = causes
+                .remove(&entry.submission)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            // Stable, so causes with the same count stay in `LossCause` order
+            loss_causes.sort_by_key(|&(_, count)| Reverse(count));
+            entry.loss_causes = loss_causes;
+            entry.mean_latency_ms = latencies.get(&entry.submission).copied();
+            entry.mean_game_turns = turns
+                .get(&entry.submission)
+                .map(|turns| turns.iter().sum::<i64>() as f64 / turns.len() as f64);
+            if let Some((losses, others)) = examples.remove(&entry.submission) {
+                entry.example_games = losses
+                    .into_iter()
+                    .chain(others)
+                    .take(EXAMPLE_GAMES)
+                    .collect();
+            }
+            entry
+        })
+        .collect())
+}
+
+/// Writes the `feedback` of every submission to `<submission>.txt` in `dir`.
+pub async fn write_feedback(db: &Database, dir: &Path) -> Result<()> {
+    let feedback = feedback(db).await?;
+    for entry in &feedback {
+        write_creating_dir(
+            &dir.join(format!("{}.txt", entry.submission)),
+            entry.to_string(),
+        )?;
+    }
+    info!(
+        "Wrote feedback for {} submissions to {}",
+        feedback.len(),
+        dir.display()
+    );
+    Ok(())
+}
+
+/// `path` as linked from a page in `dir`: relative when it is inside `dir`.
+fn link(path: &Path, dir: &Path) -> String {
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes an HTML page to `out` that shows the SVGs in `turn_paths` one at a time, in order, with
+/// previous/next buttons (or the arrow keys) and a list of links to every turn.
+pub fn write_game_index(turn_paths: &[PathBuf], out: &Path) -> Result<()> {
+    let dir = out.parent().unwrap_or(Path::new(""));
+    let turns: Vec<String> = turn_paths.iter().map(|path| link(path, dir)).collect();
+    let title = escape_html(&dir.file_name().unwrap_or_default().to_string_lossy());
+
+    let items: String = turns
+        .iter()
+        .enumerate()
+        .map(|(turn, href)| {
+            format!(
+                "<li><a href=\"{}\">Turn {}</a></li>\n",
+                escape_html(href),
+                turn
+            )
+        })
+        .collect();
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>{title}</title></head>
+<body>
+<h1>{title}</h1>
+<p><button id="prev">Previous</button> <span id="label"></span> <button id="next">Next</button></p>
+<img id="turn" alt="Current turn">
+<ol start="0">
+{items}</ol>
+<script>
+const turns = {turns};
+let current = 0;
+function show(turn) {{
+  current = Math.max(0, Math.min(turns.length - 1, turn));
+  document.getElementById("turn").src = turns[current];
+  document.getElementById("label").textContent = `Turn ${{current}} of ${{turns.length - 1}}`;
+}}
+document.getElementById("prev").onclick = () => show(current - 1);
+document.getElementById("next").onclick = () => show(current + 1);
+document.onkeydown = (e) => {{
+  if (e.key === "ArrowLeft") show(current - 1);
+  if (e.key === "ArrowRight") show(current + 1);
+}};
+show(0);
+</script>
+</body>
+</html>
+"#,
+        // `</script>` in a path must not end the script early
+        turns = serde_json::to_string(&turns)?.replace("</", "<\\/"),
+    );
+
+    fs::write(out, html).with_context(|| format!("Failed to write {}", out.display()))
+}
+
+/// Height of the label above each frame of a combined game SVG.
+const FRAME_LABEL_HEIGHT: f64 = 48.0;
+
+/// Number in a `turn_<t>.svg` file name, or the position in `turn_paths` if it has none.
+fn turn_label(path: &Path, position: usize) -> String {
+    let turn = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_prefix("turn_"))
+        .map_or(position.to_string(), str::to_string);
+    format!("Turn {}", turn)
+}
+
+/// Value of the numeric attribute `name` in the opening tag `tag`.
+fn svg_attribute(tag: &str, name: &str) -> Option<f64> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = start + tag[start..].find('"')?;
+    tag[start..end].trim_end_matches("px").parse().ok()
+}
+
+/// Stacks the SVGs in `turn_paths` top to bottom into a single SVG at `out`, each below a label
+/// with its turn, so a whole game can be scrolled through as one document.
+pub fn combine_game_svgs(turn_paths: &[PathBuf], out: &Path) -> Result<()> {
+    let mut frames = String::new();
+    let (mut width, mut height) = (0.0_f64, 0.0_f64);
+    for (position, path) in turn_paths.iter().enumerate() {
+        let svg = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        // Drop the XML declaration, which may only start a document
+        let start = svg
+            .find("<svg")
+            .with_context(|| format!("{} is not an SVG", path.display()))?;
+        let svg = &svg[start..];
+        let tag = &svg[..svg.find('>').unwrap_or(svg.len())];
+        let (frame_width, frame_height) = svg_attribute(tag, "width")
+            .zip(svg_attribute(tag, "height"))
+            .with_context(|| format!("{} has no width and height", path.display()))?;
+
+        frames.push_str(&format!(
+            "<text x=\"10\" y=\"{}\" font-size=\"32\" font-family=\"sans-serif\">{}</text>\n",
+            height + FRAME_LABEL_HEIGHT * 0.75,
+            escape_html(&turn_label(path, position))
+        ));
+        height += FRAME_LABEL_HEIGHT;
+        // Nested at its offset, the frame keeps its own coordinate system
+        frames.push_str(&format!("<svg x=\"0\" y=\"{}\"{}\n", height, &svg[4..]));
+        width = width.max(frame_width);
+        height += frame_height;
+    }
+
+    let combined = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+         <svg width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\" \
+         xmlns=\"http://www.w3.org/2000/svg\">\n{frames}</svg>\n"
+    );
+    fs::write(out, combined).with_context(|| format!("Failed to write {}", out.display()))
+}
+
+/// Writes an HTML page to `out` linking every `game_<n>/index.html` next to it, by game number.
+pub fn write_matchup_index(out: &Path) -> Result<()> {
+    let dir = out.parent().unwrap_or(Path::new(""));
+    let mut games: Vec<(i64, PathBuf)> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let number = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("game_")?
+                .parse()
+                .ok()?;
+            let index = entry.path().join("index.html");
+            index.exists().then_some((number, index))
+        })
+        .collect();
+    games.sort();
+
+    let title = escape_html(&dir.file_name().unwrap_or_default().to_string_lossy());
+    let items: String = games
+        .iter()
+        .map(|(number, index)| {
+            format!(
+                "<li><a href=\"{}\">Game {}</a></li>\n",
+                escape_html(&link(index, dir)),
+                number
+            )
+        })
+        .collect();
+    let html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+         <body>\n<h1>{title}</h1>\n<ul>\n{items}</ul>\n</body>\n</html>\n"
+    );
+
+    fs::write(out, html).with_context(|| format!("Failed to write {}", out.display()))
+}
+
+/// A submission's place in the final rankings.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Standing {
+    pub submission: String,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    /// Share of the games with a winner that the submission won, or with `half_win` ties, share
+    /// of all its games won with ties counting half. 0 when it has no such games.
+    pub points: f64,
+    /// 1 for the best submissions. Submissions with the same points and wins share a rank.
+    pub rank: usize,
+}
+
+/// How ties count in the standings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieScoring {
+    /// Ties are their own category, left out of the win/loss ratio.
+    #[default]
+    Separate,
+    /// Ties count as half a win for both players.
+    HalfWin,
+}
+
+impl Standing {
+    /// Wins, plus half the ties with `half_win`, for breaking ties in points.
+    fn effective_wins(&self, tie_scoring: TieScoring) -> f64 {
+        match tie_scoring {
+            TieScoring::Separate => f64::from(self.wins),
+            TieScoring::HalfWin => f64::from(self.wins) + f64::from(self.ties) / 2.0,
+        }
+    }
+}
+
+/// Ranks every submission with a decided game by points, then by wins, counting only the games
+/// started `since` that time if given. Games recorded before the first mover was stored are left
+/// out, since their winner is unknown.
+pub async fn compute_standings(
+    db: &Database,
+    tie_scoring: TieScoring,
+    since: Option<&str>,
+) -> Result<Vec<Standing>> {
+    let games = match since {
+        Some(since) => db.list_decided_games_since(since).await?,
+        None => db.list_decided_games().await?,
+    };
+    let mut records: BTreeMap<String, [u32; 3]> = BTreeMap::new();
+    for game in games {
+        if game.first_mover.is_none() {
+            continue;
+        }
+        let winner = game.winner_name().map(str::to_string);
+        for (player, opponent) in [
+            (&game.player_a, &game.player_b),
+            (&game.player_b, &game.player_a),
+        ] {
+            let record = records.entry(player.clone()).or_default();
+            if winner.as_ref() == Some(player) {
+                record[0] += 1;
+            } else if winner.as_ref() == Some(opponent) {
+                record[1] += 1;
+            } else {
+                record[2] += 1;
+            }
+        }
+    }
+
+    let mut standings: Vec<Standing> = records
+        .into_iter()
+        .map(|(submission, [wins, losses, ties])| {
+            let mut standing = Standing {
+                submission,
+                wins,
+                losses,
+                ties,
+                points: 0.0,
+                rank: 0,
+            };
+            let games = match tie_scoring {
+                TieScoring::Separate => wins + losses,
+                TieScoring::HalfWin => wins + losses + ties,
+            };
+            if games > 0 {
+                standing.points = standing.effective_wins(tie_scoring) / f64::from(games);
+            }
+            standing
+        })
+        .collect();
+    // Stable, so submissions sharing a rank stay in name order
+    let wins = |standing: &Standing| standing.effective_wins(tie_scoring);
+    standings.sort_by(|a, b| {
+        b.points
+            .total_cmp(&a.points)
+            .then(wins(b).total_cmp(&wins(a)))
+    });
+    for i in 0..standings.len() {
+        standings[i].rank = if i > 0
+            && standings[i].points == standings[i - 1].points
+            && wins(&standings[i]) == wins(&standings[i - 1])
+        {
+            standings[i - 1].rank
+        } else {
+            i + 1
+        };
+    }
+    Ok(standings)
+}
+
+fn write_creating_dir(out: &Path, contents: String) -> Result<()> {
+    if let Some(dir) = out.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(out, contents).with_context(|| format!("Failed to write {}", out.display()))
+}
+
+/// Writes the `compute_standings` to `out` as a Markdown table, followed by the matchups a run
+/// stopped before playing, if any.
+pub async fn write_standings_markdown(
+    db: &Database,
+    tie_scoring: TieScoring,
+    since: Option<&str>,
+    out: &Path,
+) -> Result<()> {
+    let points = match tie_scoring {
+        TieScoring::Separate => "Win/loss ratio",
+        TieScoring::HalfWin => "Points",
+    };
+    let mut markdown = format!(
+        "| Rank | Submission | Wins | Losses | Ties | {} |\n\
+         |-----:|------------|-----:|-------:|-----:|{}:|\n",
+        points,
+        "-".repeat(points.len() + 1)
+    );
+    for standing in compute_standings(db, tie_scoring, since).await? {
+        markdown.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {:.3} |\n",
+            standing.rank,
+            standing.submission,
+            standing.wins,
+            standing.losses,
+            standing.ties,
+            standing.points
+        ));
+    }
+    let unplayed = db.list_unplayed_matchups().await?;
+    if !unplayed.is_empty() {
+        markdown.push_str("\nNot played:\n\n");
+        for (player_a, player_b) in unplayed {
+            markdown.push_str(&format!("- {} vs {}\n", player_a, player_b));
+        }
+    }
+    write_creating_dir(out, markdown)
+}
+
+/// Writes the `compute_standings` to `out` as a JSON array, best first.
+pub async fn write_standings_json(
+    db: &Database,
+    tie_scoring: TieScoring,
+    since: Option<&str>,
+    out: &Path,
+) -> Result<()> {
+    let standings = compute_standings(db, tie_scoring, since).await?;
+    write_creating_dir(out, serde_json::to_string(&standings)?)
+}
@@ -0,0 +1,657 @@
+#![cfg(test)]
+
+use std::{fs, time::Duration};
+
+use rplcs_events::tournament_1::PlayerState;
+use tempfile::TempDir;
+
+use crate::{
+    Config,
+    bots::built_in_bots,
+    db::Database,
+    game::{GameBuilder, GameEvent, GameResult},
+    submission::{CallLatency, Submission},
+};
+
+use super::{
+    LossCause, Standing, TieScoring, combine_game_svgs, compute_standings, feedback,
+    flag_passive_games, node_visits, slowest, turn_order_audit, write_feedback, write_game_index,
+    write_matchup_index, write_matchup_timeline, write_standings_json, write_standings_markdown,
+};
+
+fn temp_db() -> (TempDir, Database) {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    (results_dir, db)
+}
+
+/// Records a finished game of `matchup_id`.
+async fn play(db: &Database, matchup_id: i64, game: i64, first_mover: &str, result: GameResult) {
+    db.create_game(matchup_id, game, first_mover, game, None)
+        .await
+        .unwrap();
+    db.update_game_result(matchup_id, game, result)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_turn_order_audit_splits_and_flags_win_rates() {
+    let (_results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+
+    // Alice wins all 4 games she starts and 1 of 4 games Bob starts, with one tie
+    for game in 0..4 {
+        play(&db, matchup, game, "alice", GameResult::Player1Win).await;
+    }
+    play(&db, matchup, 4, "bob", GameResult::Player2Win).await;
+    play(&db, matchup, 5, "bob", GameResult::Player1Win).await;
+    play(&db, matchup, 6, "bob", GameResult::Tie).await;
+    play(&db, matchup, 7, "bob", GameResult::Player1Win).await;
+    // Unfinished games don't count
+    db.create_game(matchup, 8, "bob", 8, None).await.unwrap();
+
+    let splits = turn_order_audit(&db, 0.5).await.unwrap();
+
+    assert_eq!(splits.len(), 2);
+    let alice = &splits[0];
+    assert_eq!(alice.submission, "alice");
+    assert_eq!(alice.opponent, "bob");
+    assert_eq!((alice.first_wins, alice.first_games), (4, 4));
+    assert_eq!((alice.second_wins, alice.second_games), (1, 4));
+    assert_eq!(alice.first_win_rate(), Some(1.0));
+    assert_eq!(alice.second_win_rate(), Some(0.25));
+    assert!(alice.anomalous);
+
+    let bob = &splits[1];
+    assert_eq!(bob.submission, "bob");
+    assert_eq!((bob.first_wins, bob.first_games), (2, 4));
+    assert_eq!((bob.second_wins, bob.second_games), (0, 4));
+    assert!(!bob.anomalous);
+
+    // A looser threshold flags nobody
+    let splits = turn_order_audit(&db, 0.8).await.unwrap();
+    assert!(splits.iter().all(|split| !split.anomalous));
+}
+
+#[tokio::test]
+async fn test_turn_order_audit_needs_both_orders_to_flag() {
+    let (_results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+    play(&db, matchup, 0, "alice", GameResult::Player1Win).await;
+
+    let splits = turn_order_audit(&db, 0.1).await.unwrap();
+
+    assert_eq!(splits[0].second_win_rate(), None);
+    assert!(splits.iter().all(|split| !split.anomalous));
+}
+
+fn calls(latency_ms: f64, count: usize) -> Vec<CallLatency> {
+    vec![
+        CallLatency {
+            endpoint: "choices",
+            latency_ms,
+        };
+        count
+    ]
+}
+
+#[tokio::test]
+async fn test_slowest_orders_games_and_submission_p95s() {
+    let (_results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+    for (game, duration_ms) in [(0, 120), (1, 3000), (2, 450)] {
+        let game_id = db
+            .create_game(matchup, game, "alice", game, None)
+            .await
+            .unwrap();
+        db.update_game_result(matchup, game, GameResult::Tie)
+            .await
+            .unwrap();
+        db.record_game_duration(matchup, game, Duration::from_millis(duration_ms))
+            .await
+            .unwrap();
+        db.record_call_latencies(game_id, 0, "bob", calls(50.0, 10))
+            .await
+            .unwrap();
+    }
+    // Forfeits have no duration
+    play(&db, matchup, 3, "alice", GameResult::Player1Win).await;
+
+    // A single slow call out of 21 doesn't reach alice's p95
+    let game_id = db.create_game(matchup, 4, "alice", 4, None).await.unwrap();
+    db.record_call_latencies(game_id, 0, "alice", calls(10.0, 20))
+        .await
+        .unwrap();
+    db.record_call_latencies(game_id, 1, "alice", calls(900.0, 1))
+        .await
+        .unwrap();
+    let other = db.start_matchup("carol", "dave").await.unwrap();
+    let game_id = db.create_game(other, 0, "carol", 0, None).await.unwrap();
+    db.record_call_latencies(game_id, 0, "carol", calls(200.0, 3))
+        .await
+        .unwrap();
+
+    let top = slowest(&db, 2).await.unwrap();
+
+    let games: Vec<_> = top
+        .games
+        .iter()
+        .map(|game| (game.game_number, game.duration_ms))
+        .collect();
+    assert_eq!(games, [(1, 3000.0), (2, 450.0)]);
+    let submissions: Vec<_> = top
+        .submissions
+        .iter()
+        .map(|s| (s.submission.as_str(), s.calls, s.p95_ms))
+        .collect();
+    assert_eq!(submissions, [("carol", 3, 200.0), ("bob", 30, 50.0)]);
+
+    let all = slowest(&db, 10).await.unwrap();
+    assert_eq!(all.games.len(), 3);
+    assert_eq!(all.submissions[2].submission, "alice");
+    assert_eq!(all.submissions[2].p95_ms, 10.0);
+}
+
+#[test]
+fn test_game_index_references_each_turn_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let game_dir = dir.path().join("a_vs_b/game_3");
+    fs::create_dir_all(&game_dir).unwrap();
+    let turn_paths: Vec<_> = (0..12)
+        .map(|turn| game_dir.join(format!("turn_{turn}.svg")))
+        .collect();
+
+    write_game_index(&turn_paths, &game_dir.join("index.html")).unwrap();
+
+    let html = fs::read_to_string(game_dir.join("index.html")).unwrap();
+    let expected: Vec<_> = (0..12).map(|turn| format!("\"turn_{turn}.svg\"")).collect();
+    assert!(html.contains(&format!("const turns = [{}];", expected.join(","))));
+    let links: Vec<_> = expected
+        .iter()
+        .map(|href| html.find(&format!("<a href={href}>")).unwrap())
+        .collect();
+    assert!(links.is_sorted(), "{html}");
+    assert!(html.contains("<title>game_3</title>"));
+}
+
+#[test]
+fn test_combined_svg_stacks_each_turn_under_its_label() {
+    let dir = tempfile::tempdir().unwrap();
+    let turn_paths: Vec<_> = [0, 5, 7]
+        .into_iter()
+        .map(|turn| {
+            let path = dir.path().join(format!("turn_{turn}.svg"));
+            let svg = format!(
+                "<?xml version=\"1.0\"?><svg width=\"{}\" height=\"100\" \
+                 xmlns=\"http://www.w3.org/2000/svg\"><text>frame {turn}</text></svg>",
+                200 + turn
+            );
+            fs::write(&path, svg).unwrap();
+            path
+        })
+        .collect();
+    let out = dir.path().join("game.svg");
+
+    combine_game_svgs(&turn_paths, &out).unwrap();
+
+    let svg = fs::read_to_string(out).unwrap();
+    assert_eq!(svg.matches("<?xml").count(), 1);
+    let frames: Vec<_> = [0, 5, 7]
+        .iter()
+        .map(|turn| {
+            let label = svg.find(&format!(">Turn {turn}</text>")).unwrap();
+            let frame = svg.find(&format!("<text>frame {turn}</text>")).unwrap();
+            assert!(label < frame);
+            frame
+        })
+        .collect();
+    assert!(frames.is_sorted(), "{svg}");
+    // As wide as the widest frame, and as tall as the frames and their labels
+    assert!(svg.contains("width=\"207\" height=\"444\""), "{svg}");
+    assert!(
+        svg.contains("<svg x=\"0\" y=\"196\" width=\"205\""),
+        "{svg}"
+    );
+}
+
+#[test]
+fn test_game_index_script_is_not_closed_by_turn_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let game_dir = dir.path().join("game_0");
+    fs::create_dir(&game_dir).unwrap();
+    let turn = game_dir.join("</script><b>x").join("turn_0.svg");
+
+    write_game_index(&[turn], &game_dir.join("index.html")).unwrap();
+
+    let html = fs::read_to_string(game_dir.join("index.html")).unwrap();
+    assert_eq!(html.matches("</script>").count(), 1, "{html}");
+    assert!(
+        html.contains(r#"const turns = ["<\/script><b>x/turn_0.svg"];"#),
+        "{html}"
+    );
+}
+
+#[test]
+fn test_matchup_index_links_game_indexes_by_number() {
+    let dir = tempfile::tempdir().unwrap();
+    for game in [10, 2, 0] {
+        let game_dir = dir.path().join(format!("game_{game}"));
+        fs::create_dir(&game_dir).unwrap();
+        write_game_index(&[game_dir.join("turn_0.svg")], &game_dir.join("index.html")).unwrap();
+    }
+    // A game that rendered nothing has no index to link
+    fs::create_dir(dir.path().join("game_5")).unwrap();
+
+    write_matchup_index(&dir.path().join("index.html")).unwrap();
+
+    let html = fs::read_to_string(dir.path().join("index.html")).unwrap();
+    let links: Vec<_> = [0, 2, 10]
+        .iter()
+        .map(|game| {
+            html.find(&format!(
+                "<a href=\"game_{game}/index.html\">Game {game}</a>"
+            ))
+            .unwrap()
+        })
+        .collect();
+    assert!(links.is_sorted(), "{html}");
+    assert!(!html.contains("game_5"));
+}
+
+#[tokio::test]
+async fn test_matchup_timeline_lists_games_and_final_score() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        turns_per_game: 6,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let bots = built_in_bots(Some(0));
+    let (name_a, name_b) = (&bots[0].0, &bots[1].0);
+    let matchup = db.start_matchup(name_a, name_b).await.unwrap();
+    for game_id in 0..2 {
+        let mut game = GameBuilder::new(
+            Submission::new(name_a, bots[0].1.clone()),
+            Submission::new(name_b, bots[1].1.clone()),
+        )
+        .ids(game_id, matchup)
+        .config(config.clone())
+        .build()
+        .unwrap();
+        game.result(&db).await.unwrap();
+    }
+    // A forfeit has a result but no events
+    play(&db, matchup, 2, name_b, GameResult::Player1Win).await;
+
+    let out = results_dir.path().join("timeline.json");
+    write_matchup_timeline(&db, matchup, &out).await.unwrap();
+    let timeline: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&out).unwrap()).unwrap();
+
+    let games = timeline["games"].as_array().unwrap();
+    assert_eq!(games.len(), 3);
+    for game in &games[..2] {
+        let events = game["events"].as_array().unwrap();
+        let end = events.last().unwrap();
+        assert_eq!(end["kind"], "end", "{game}");
+        let end: GameEvent = serde_json::from_value(end.clone()).unwrap();
+        let GameEvent::End { winner, .. } = end else {
+            unreachable!()
+        };
+        assert_eq!(game["winner"].as_str(), winner.as_deref());
+    }
+    assert_eq!(games[2]["winner"], name_b.as_str());
+    assert!(games[2]["events"].as_array().unwrap().is_empty());
+
+    let score: Vec<u64> = serde_json::from_value(timeline["score"].clone()).unwrap();
+    assert!(score[1] >= 1);
+    assert_eq!(
+        score.iter().sum::<u64>() + timeline["ties"].as_u64().unwrap(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn test_node_visits_average_per_game() {
+    let (_results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+    for (game, healing) in [(0, 1), (1, 4)] {
+        let game_id = db
+            .create_game(matchup, game, "alice", game, None)
+            .await
+            .unwrap();
+        db.record_node_visits(game_id, "alice", [0, healing, 1, 10])
+            .await
+            .unwrap();
+        db.record_node_visits(game_id, "bob", [2, 0, 0, 12])
+            .await
+            .unwrap();
+    }
+
+    let visits = node_visits(&db).await.unwrap();
+
+    assert_eq!(visits.len(), 2);
+    assert_eq!(visits[0].submission, "alice");
+    assert_eq!(visits[0].per_game, [0.0, 2.5, 1.0, 10.0]);
+    assert_eq!(visits[1].submission, "bob");
+    assert_eq!(visits[1].per_game, [2.0, 0.0, 0.0, 12.0]);
+}
+
+#[tokio::test]
+async fn test_standings_markdown_lists_unplayed_matchups() {
+    let (results_dir, db) = temp_db();
+    let alice_bob = db.start_matchup("alice", "bob").await.unwrap();
+    play(&db, alice_bob, 0, "alice", GameResult::Player1Win).await;
+    db.record_unplayed_matchup("alice", "carol").await.unwrap();
+
+    let markdown_path = results_dir.path().join("standings.md");
+    write_standings_markdown(&db, TieScoring::Separate, None, &markdown_path)
+        .await
+        .unwrap();
+
+    let markdown = fs::read_to_string(markdown_path).unwrap();
+    assert!(
+        markdown.ends_with("\nNot played:\n\n- alice vs carol\n"),
+        "{markdown}"
+    );
+}
+
+#[tokio::test]
+async fn test_standings_json_ranks_match_markdown_order() {
+    let (results_dir, db) = temp_db();
+    let alice_bob = db.start_matchup("alice", "bob").await.unwrap();
+    let alice_carol = db.start_matchup("alice", "carol").await.unwrap();
+    let bob_carol = db.start_matchup("bob", "carol").await.unwrap();
+
+    // Alice wins all 4 of her games, Bob and Carol win 1 of 4 each and tie the last
+    play(&db, alice_bob, 0, "alice", GameResult::Player1Win).await;
+    play(&db, alice_bob, 1, "bob", GameResult::Player2Win).await;
+    play(&db, alice_carol, 0, "alice", GameResult::Player1Win).await;
+    play(&db, alice_carol, 1, "carol", GameResult::Player2Win).await;
+    play(&db, bob_carol, 0, "bob", GameResult::Player1Win).await;
+    play(&db, bob_carol, 1, "carol", GameResult::Player1Win).await;
+    play(&db, bob_carol, 2, "carol", GameResult::Tie).await;
+
+    let markdown_path = results_dir.path().join("standings.md");
+    let json_path = results_dir.path().join("standings.json");
+    write_standings_markdown(&db, TieScoring::Separate, None, &markdown_path)
+        .await
+        .unwrap();
+    write_standings_json(&db, TieScoring::Separate, None, &json_path)
+        .await
+        .unwrap();
+
+    let markdown = fs::read_to_string(markdown_path).unwrap();
+    let markdown_rows: Vec<(usize, String)> = markdown
+        .lines()
+        .skip(2)
+        .map(|line| {
+            let cells: Vec<&str> = line.split('|').map(str::trim).collect();
+            (cells[1].parse().unwrap(), cells[2].to_string())
+        })
+        .collect();
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(json_path).unwrap()).unwrap();
+    let json_rows: Vec<(usize, String)> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|standing| {
+            (
+                standing["rank"].as_u64().unwrap() as usize,
+                standing["submission"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+
+    assert_eq!(json_rows, markdown_rows);
+    assert_eq!(
+        json_rows,
+        [
+            (1, "alice".to_string()),
+            (2, "bob".to_string()),
+            (2, "carol".to_string())
+        ]
+    );
+    assert_eq!(json[1]["ties"], 1);
+    assert_eq!(json[0]["points"], 1.0);
+    assert_eq!(json[2]["points"], 0.25);
+}
+
+#[tokio::test]
+async fn test_passive_games_flag_ties_with_a_submission_that_never_fought() {
+    let (_results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+    let moves = |player: &str| GameEvent::Move {
+        player: player.to_string(),
+        from: 0,
+        to: 1,
+    };
+    let fight = |player: &str, opponent: &str| GameEvent::Fight {
+        player: player.to_string(),
+        opponent: opponent.to_string(),
+        won: false,
+    };
+
+    // Alice hides while Bob fights an enemy, then the two fight each other, then Alice wins
+    // without a fight
+    for (game, result, events) in [
+        (
+            0,
+            GameResult::Tie,
+            vec![moves("alice"), fight("bob", "Enemy 0")],
+        ),
+        (1, GameResult::Tie, vec![fight("bob", "alice")]),
+        (2, GameResult::Player1Win, vec![moves("alice")]),
+    ] {
+        let id = db
+            .create_game(matchup, game, "alice", game, None)
+            .await
+            .unwrap();
+        db.update_game_result(matchup, game, result).await.unwrap();
+        let events = events.into_iter().map(|event| (0, event)).collect();
+        db.record_game_events(id, events).await.unwrap();
+    }
+    // A tied game without events isn't flagged either
+    play(&db, matchup, 3, "bob", GameResult::Tie).await;
+
+    let flagged = flag_passive_games(&db).await.unwrap();
+
+    assert_eq!(flagged.len(), 1, "{flagged:?}");
+    assert_eq!(flagged[0].matchup_id, matchup);
+    assert_eq!(flagged[0].game_number, 0);
+    assert_eq!(flagged[0].passive, ["alice"]);
+}
+
+#[tokio::test]
+async fn test_half_win_tie_scoring_reorders_standings() {
+    let (_results_dir, db) = temp_db();
+    let xavier_zoe = db.start_matchup("xavier", "zoe").await.unwrap();
+    let yara_zoe = db.start_matchup("yara", "zoe").await.unwrap();
+
+    // Xavier wins 3 of 4, Yara wins her only decided game and ties the other 3
+    for game in 0..3 {
+        play(&db, xavier_zoe, game, "xavier", GameResult::Player1Win).await;
+        play(&db, yara_zoe, game, "yara", GameResult::Tie).await;
+    }
+    play(&db, xavier_zoe, 3, "zoe", GameResult::Player1Win).await;
+    play(&db, yara_zoe, 3, "yara", GameResult::Player1Win).await;
+
+    let standings = |tie_scoring| {
+        let db = &db;
+        async move {
+            compute_standings(db, tie_scoring, None)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|standing| (standing.submission, standing.ties, standing.points))
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(
+        standings(TieScoring::Separate).await,
+        [
+            ("yara".to_string(), 3, 1.0),
+            ("xavier".to_string(), 0, 0.75),
+            ("zoe".to_string(), 3, 0.2)
+        ]
+    );
+    assert_eq!(
+        standings(TieScoring::HalfWin).await,
+        [
+            ("xavier".to_string(), 0, 0.75),
+            ("yara".to_string(), 3, 0.625),
+            ("zoe".to_string(), 3, 0.3125)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_feedback_reports_wins_and_loss_causes() {
+    let (results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+    let end = |alice_health, bob_health| GameEvent::End {
+        winner: None,
+        players: [alice_health, bob_health].map(|health| PlayerState {
+            health,
+            max_health: 5,
+            power: 1,
+        }),
+    };
+
+    // Alice wins a fight with Bob's last health, then times out, then wins by forfeit
+    let games = [
+        (
+            GameResult::Player1Win,
+            vec![
+                (
+                    4,
+                    GameEvent::Fight {
+                        player: "bob".to_string(),
+                        opponent: "alice".to_string(),
+                        won: false,
+                    },
+                ),
+                (4, end(3, 0)),
+            ],
+        ),
+        (
+            GameResult::Player2Win,
+            vec![
+                (
+                    9,
+                    GameEvent::Timeout {
+                        player: "alice".to_string(),
+                    },
+                ),
+                (9, end(3, 2)),
+            ],
+        ),
+    ];
+    for (game, (result, events)) in (0..).zip(games) {
+        let id = db
+            .create_game(matchup, game, "alice", game, None)
+            .await
+            .unwrap();
+        db.update_game_result(matchup, game, result).await.unwrap();
+        db.record_game_events(id, events).await.unwrap();
+        db.record_call_latencies(id, 0, "alice", calls(20.0, 2))
+            .await
+            .unwrap();
+    }
+    play(&db, matchup, 2, "alice", GameResult::Player1Win).await;
+
+    let dir = results_dir.path().join("feedback");
+    write_feedback(&db, &dir).await.unwrap();
+
+    let feedback = feedback(&db).await.unwrap();
+    let [alice, bob] = &feedback[..] else {
+        panic!("{feedback:?}");
+    };
+    assert_eq!((alice.wins, alice.losses, alice.ties), (2, 1, 0));
+    assert_eq!(alice.loss_causes, [(LossCause::Timeout, 1)]);
+    assert_eq!(alice.mean_latency_ms, Some(20.0));
+    assert_eq!(alice.mean_game_turns, Some(7.5));
+    assert_eq!(alice.example_games[0].outcome, "lost (timeout)");
+    assert_eq!(
+        bob.loss_causes,
+        [(LossCause::Forfeit, 1), (LossCause::Fight, 1)]
+    );
+    assert_eq!(bob.mean_latency_ms, None);
+
+    let text = fs::read_to_string(dir.join("alice.txt")).unwrap();
+    assert!(text.contains("Games: 2 wins, 1 losses, 0 ties"), "{text}");
+    assert!(text.contains("Most common loss cause: timeout"), "{text}");
+    let text = fs::read_to_string(dir.join("bob.txt")).unwrap();
+    assert!(text.contains("Games: 1 wins, 2 losses, 0 ties"), "{text}");
+}
+
+#[tokio::test]
+async fn test_pending_games_are_left_out_of_standings() {
+    let (_results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+    play(&db, matchup, 0, "alice", GameResult::Player1Win).await;
+    // Left pending by an interrupted run
+    db.create_game(matchup, 1, "bob", 1, None).await.unwrap();
+
+    let standings = compute_standings(&db, TieScoring::HalfWin, None)
+        .await
+        .unwrap();
+
+    let records: Vec<_> = standings
+        .iter()
+        .map(|standing| (standing.wins, standing.losses, standing.ties))
+        .collect();
+    assert_eq!(records, [(1, 0, 0), (0, 1, 0)]);
+}
+
+#[tokio::test]
+async fn test_standings_since_leave_out_earlier_games() {
+    let (results_dir, db) = temp_db();
+    let matchup = db.start_matchup("alice", "bob").await.unwrap();
+    for game in 0..3 {
+        play(&db, matchup, game, "alice", GameResult::Player1Win).await;
+    }
+    play(&db, matchup, 3, "bob", GameResult::Player1Win).await;
+    // Alice's wins were played on an earlier day
+    let conn = rusqlite::Connection::open(results_dir.path().join("results.sqlite")).unwrap();
+    conn.execute(
+        "UPDATE games SET timestamp = '2026-01-01 12:00:00' WHERE game_number < 3",
+        [],
+    )
+    .unwrap();
+
+    let records = |standings: Vec<Standing>| {
+        standings
+            .into_iter()
+            .map(|standing| (standing.submission, standing.wins, standing.losses))
+            .collect::<Vec<_>>()
+    };
+    let all = compute_standings(&db, TieScoring::Separate, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        records(all),
+        [("alice".to_string(), 3, 1), ("bob".to_string(), 1, 3)]
+    );
+    let since = compute_standings(&db, TieScoring::Separate, Some("2026-01-02"))
+        .await
+        .unwrap();
+    assert_eq!(
+        records(since),
+        [("bob".to_string(), 1, 0), ("alice".to_string(), 0, 1)]
+    );
+
+    assert!(
+        compute_standings(&db, TieScoring::Separate, Some("yesterday"))
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,54 @@
+use log::warn;
+use std::collections::HashSet;
+
+mod tests;
+
+/// Groups a round-robin over `submissions` into rounds using the circle method.
+///
+/// Every pair of submissions meets exactly once, and no submission plays twice within a round.
+/// With an odd number of submissions one of them sits out each round. Duplicated names are
+/// scheduled once, so a submission never plays against itself.
+///
+/// This replaces `round_robin::generate_rounds`, which can't be grouped into rounds: it returns
+/// no pairings for an odd number of submissions, and rotates the players only once, so every
+/// round after the first has the same pairings and most pairs never meet.
+pub fn generate_rounds_grouped(submissions: Vec<String>) -> Vec<Vec<(String, String)>> {
+    let mut seen = HashSet::new();
+    let mut slots: Vec<Option<String>> = submissions
+        .into_iter()
+        .filter(|name| {
+            let first = seen.insert(name.clone());
+            if !first {
+                warn!(
+                    "Submission {} is listed more than once, scheduling it once",
+                    name
+                );
+            }
+            first
+        })
+        .map(Some)
+        .collect();
+    if !slots.len().is_multiple_of(2) {
+        // Whoever is paired with the empty slot gets a bye
+        slots.push(None);
+    }
+
+    let slot_count = slots.len();
+    let mut rounds = Vec::with_capacity(slot_count.saturating_sub(1));
+    for _ in 1..slot_count {
+        let round: Vec<_> = (0..slot_count / 2)
+            .filter_map(|i| match (&slots[i], &slots[slot_count - 1 - i]) {
+                (Some(a), Some(b)) if a != b => Some((a.clone(), b.clone())),
+                _ => None,
+            })
+            .collect();
+        if !round.is_empty() {
+            rounds.push(round);
+        }
+
+        // Keep the first slot fixed and rotate everyone else
+        slots[1..].rotate_right(1);
+    }
+
+    rounds
+}
@@ -0,0 +1,63 @@
+#![cfg(test)]
+
+use std::collections::HashSet;
+
+use quickcheck::quickcheck;
+
+use super::generate_rounds_grouped;
+
+fn names(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("submission_{}", i)).collect()
+}
+
+quickcheck! {
+    fn test_no_submission_plays_twice_in_a_round(count: u8) -> bool {
+        let rounds = generate_rounds_grouped(names(count as usize % 20));
+
+        rounds.iter().all(|round| {
+            let mut seen = HashSet::new();
+            round.iter().all(|(a, b)| seen.insert(a.clone()) && seen.insert(b.clone()))
+        })
+    }
+}
+
+quickcheck! {
+    fn test_every_pair_plays_exactly_once(count: u8) -> bool {
+        let count = count as usize % 20;
+        let rounds = generate_rounds_grouped(names(count));
+
+        let mut pairs = HashSet::new();
+        for (a, b) in rounds.into_iter().flatten() {
+            let pair = if a < b { (a, b) } else { (b, a) };
+            if pair.0 == pair.1 || !pairs.insert(pair) {
+                return false;
+            }
+        }
+        pairs.len() == count * count.saturating_sub(1) / 2
+    }
+}
+
+#[test]
+fn test_round_counts() {
+    assert!(generate_rounds_grouped(names(0)).is_empty());
+    assert!(generate_rounds_grouped(names(1)).is_empty());
+    assert_eq!(generate_rounds_grouped(names(2)).len(), 1);
+    assert_eq!(generate_rounds_grouped(names(4)).len(), 3);
+
+    // Odd counts get an extra round so everyone can sit out once
+    let rounds = generate_rounds_grouped(names(5));
+    assert_eq!(rounds.len(), 5);
+    assert!(rounds.iter().all(|round| round.len() == 2));
+}
+
+#[test]
+fn test_duplicated_name_never_plays_itself() {
+    let submissions = ["a", "b", "a", "c"].map(String::from).to_vec();
+    let pairs: Vec<_> = generate_rounds_grouped(submissions)
+        .into_iter()
+        .flatten()
+        .collect();
+
+    assert!(pairs.iter().all(|(a, b)| a != b));
+    assert_eq!(pairs.len(), 3);
+}
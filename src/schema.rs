@@ -0,0 +1,157 @@
+//! JSON Schemas for the submission HTTP protocol, so authors writing servers in other languages
+//! can validate their requests and responses.
+//!
+//! `rplcs_events` doesn't derive `JsonSchema`, so the protocol types are mirrored here and must be
+//! kept in sync with `rplcs_events::tournament_1` and the request and response types of
+//! `crate::submission`. The
+//! mirrors only exist to derive schemas and are never constructed.
+#![allow(dead_code)]
+
+use anyhow::{Context, Result};
+use schemars::{JsonSchema, Schema, schema_for};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+mod tests;
+
+/// Represents the current state of a player or enemy.
+#[derive(JsonSchema)]
+#[schemars(remote = "rplcs_events::tournament_1::PlayerState")]
+struct PlayerState {
+    /// Current health points. When this reaches 0, the entity is defeated.
+    health: u32,
+    /// Maximum possible health points. Healing cannot exceed this value.
+    max_health: u32,
+    /// Combat power used in fight calculations. Higher power means better chances of winning.
+    power: u32,
+}
+
+/// Different types of nodes that can exist in the game map.
+#[derive(JsonSchema)]
+#[schemars(remote = "rplcs_events::tournament_1::MapNodeType")]
+enum MapNodeType {
+    /// Basic node with no special effects
+    Normal,
+    /// Heals the player by 1 HP when landed on
+    Healing,
+    /// Allows the player to gamble their stats
+    Gamble,
+    /// Teleports the player to a random empty node
+    Teleport,
+}
+
+/// Represents the available move choices a player can make during their turn.
+#[derive(JsonSchema)]
+#[schemars(remote = "rplcs_events::tournament_1::MoveChoices")]
+struct MoveChoices {
+    /// A list of adjacent node types the player can move to. Returned in shuffled order.
+    choices: Vec<MapNodeType>,
+}
+
+/// Body of a `/choices` request: the move choices plus the current state of the player and,
+/// unless visibility is `self_only`, of its opponent.
+#[derive(JsonSchema)]
+#[schemars(remote = "crate::submission::ChoicesRequest")]
+struct ChoicesRequest {
+    #[serde(flatten)]
+    choices: MoveChoices,
+    /// The requesting player's current state.
+    player: PlayerState,
+    /// The opponent's current state. Left out when visibility is `self_only`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    opponent: Option<PlayerState>,
+    #[serde(flatten)]
+    positions: VisiblePositions,
+}
+
+/// Map nodes the player, its opponent and the enemies stand on, depending on the tournament's
+/// `visibility`. Hidden positions are left out.
+#[derive(JsonSchema)]
+#[schemars(remote = "crate::submission::VisiblePositions")]
+struct VisiblePositions {
+    /// Node the requesting player stands on. Sent unless visibility is `self_only`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<usize>,
+    /// Node the opponent stands on, with `full` visibility or when it is one move away with
+    /// `adjacent` visibility.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    opponent_position: Option<usize>,
+    /// Nodes of the enemies that can be seen, under the same rules as the opponent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    enemy_positions: Vec<usize>,
+}
+
+/// The response from a player's bot indicating which choice they selected.
+#[derive(JsonSchema)]
+#[schemars(remote = "rplcs_events::tournament_1::ChoiceResponse")]
+struct ChoiceResponse {
+    /// Index into the MoveChoices `choices` array indicating which node the player selected to
+    /// move to.
+    choice_index: usize,
+}
+
+/// Choices available when landing on a gamble node: the `GambleChoices` variants plus
+/// `MaxHealth`.
+#[derive(JsonSchema)]
+#[schemars(remote = "crate::submission::GambleResponse")]
+enum GambleResponse {
+    /// Gamble power stat (50% chance to gain/lose, 10% to double/halve)
+    Power,
+    /// Gamble health stat (50% chance to gain/lose, 10% to double/halve)
+    Health,
+    /// Gamble maximum health (50% chance to gain/lose, 10% to double/halve). Health above the
+    /// new maximum is lowered to it
+    MaxHealth,
+    /// Skip the gamble
+    Skip,
+}
+
+/// Information about a potential fight encounter.
+#[derive(JsonSchema)]
+#[schemars(remote = "rplcs_events::tournament_1::FightInfo")]
+enum FightInfo {
+    /// Fight against another player
+    Player(PlayerState),
+    /// Fight against an enemy
+    Enemy(PlayerState),
+}
+
+/// Available actions when encountering an enemy.
+#[derive(JsonSchema)]
+#[schemars(remote = "rplcs_events::tournament_1::FightChoices")]
+enum FightChoices {
+    /// Fight them
+    Fight,
+    /// Attempt to escape to an adjacent node
+    Flee,
+}
+
+/// Schemas of every request and response body, by type name.
+fn protocol_schemas() -> Vec<(&'static str, Schema)> {
+    vec![
+        ("MoveChoices", schema_for!(MoveChoices)),
+        ("ChoicesRequest", schema_for!(ChoicesRequest)),
+        ("ChoiceResponse", schema_for!(ChoiceResponse)),
+        ("GambleResponse", schema_for!(GambleResponse)),
+        ("FightInfo", schema_for!(FightInfo)),
+        ("FightChoices", schema_for!(FightChoices)),
+    ]
+}
+
+/// Writes one `<Type>.json` schema per protocol type into `dir`, returning the written paths.
+pub fn write_schemas(dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).context("Failed to create schema directory")?;
+
+    protocol_schemas()
+        .into_iter()
+        .map(|(name, schema)| {
+            let path = dir.join(format!("{name}.json"));
+            let json = serde_json::to_string_pretty(&schema)?;
+            fs::write(&path, json)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
@@ -0,0 +1,46 @@
+#![cfg(test)]
+
+use std::fs;
+
+use super::write_schemas;
+
+#[test]
+fn test_choice_response_schema_has_choice_index() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let paths = write_schemas(dir.path()).unwrap();
+    assert_eq!(paths.len(), 6);
+
+    let schema: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("ChoiceResponse.json")).unwrap())
+            .unwrap();
+    assert_eq!(schema["title"], "ChoiceResponse");
+    assert_eq!(schema["properties"]["choice_index"]["type"], "integer");
+    assert_eq!(schema["required"][0], "choice_index");
+}
+
+#[test]
+fn test_nested_types_use_protocol_names() {
+    let dir = tempfile::tempdir().unwrap();
+    write_schemas(dir.path()).unwrap();
+
+    let schema = fs::read_to_string(dir.path().join("MoveChoices.json")).unwrap();
+    assert!(schema.contains("\"MapNodeType\""));
+    assert!(schema.contains("\"Teleport\""));
+}
+
+#[test]
+fn test_choices_request_schema_flattens_move_choices() {
+    let dir = tempfile::tempdir().unwrap();
+    write_schemas(dir.path()).unwrap();
+
+    let schema: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("ChoicesRequest.json")).unwrap())
+            .unwrap();
+    for property in ["choices", "player", "opponent"] {
+        assert!(
+            schema["properties"].get(property).is_some(),
+            "missing {property}: {schema}"
+        );
+    }
+}
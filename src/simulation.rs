@@ -0,0 +1,55 @@
+use crate::{
+    Config, GameBudget, MatchupSummary, bots::built_in_bots, db::Database, run_games, scheduler,
+};
+use anyhow::{Context, Result};
+use log::info;
+use std::collections::HashMap;
+
+mod tests;
+
+/// Plays a round-robin between the built-in bots entirely in-process, recording the games in `db`
+/// exactly like a tournament would. Useful for balancing mechanics without any containers.
+pub async fn simulate(db: &Database, config: &Config) -> Result<Vec<MatchupSummary>> {
+    let bots: HashMap<_, _> = built_in_bots(config.tournament_seed())
+        .into_iter()
+        .collect();
+    let mut names: Vec<_> = bots.keys().cloned().collect();
+    names.sort();
+
+    let budget = GameBudget::new(config.max_total_games);
+    let mut matchups = Vec::new();
+    for (bot_a, bot_b) in scheduler::generate_rounds_grouped(names)
+        .into_iter()
+        .flatten()
+    {
+        if budget.is_exhausted() {
+            info!("Reached the total game cap, skipping the remaining matchups");
+            break;
+        }
+        info!("Simulating matchup: {} vs {}", bot_a, bot_b);
+        let summary = run_games(
+            bot_a.clone(),
+            bot_b.clone(),
+            bots[&bot_a].clone(),
+            bots[&bot_b].clone(),
+            db,
+            &budget,
+            config,
+        )
+        .await
+        .with_context(|| format!("Failed to simulate {} vs {}", bot_a, bot_b))?;
+
+        info!(
+            "Simulated {} games of {} vs {}: {} - {} ({} ties)",
+            summary.results.len(),
+            bot_a,
+            bot_b,
+            summary.a_wins,
+            summary.b_wins,
+            summary.ties
+        );
+        matchups.push(summary);
+    }
+
+    Ok(matchups)
+}
@@ -1,38 +1,333 @@
-use crate::container::ContainerHandle;
+use crate::{
+    container::ContainerHandle,
+    error::SubmissionFault,
+    profile::{self, Bucket},
+};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::warn;
 use rplcs_events::tournament_1::{
-    ChoiceResponse, FightChoices, FightInfo, GambleChoices, MoveChoices, PlayerState,
+    ChoiceResponse, FightChoices, FightInfo, MoveChoices, PlayerState,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+mod tests;
+
+tokio::task_local! {
+    /// When the call being timed by `Submission::traced` actually went out, if it first had to
+    /// wait for a throttle slot or for `min_call_interval`.
+    static CALL_SENT: Cell<Option<Instant>>;
+}
+
+/// Marks the call being timed as sent now, so waiting before it doesn't count as latency.
+pub fn mark_call_sent() {
+    let _ = CALL_SENT.try_with(|sent| sent.set(Some(Instant::now())));
+}
+
+/// Health and power every player starts a game with.
+pub const STARTING_STATE: PlayerState = PlayerState {
+    health: 3,
+    max_health: 3,
+    power: 5,
 };
 
+/// Body of a `/choices` request: the move choices, flattened so submissions reading a plain
+/// `MoveChoices` keep working, plus the current state of the player and, unless the game's
+/// visibility hides it, of its opponent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChoicesRequest {
+    #[serde(flatten)]
+    pub choices: MoveChoices,
+    pub player: PlayerState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opponent: Option<PlayerState>,
+    #[serde(flatten)]
+    pub positions: VisiblePositions,
+}
+
+/// Map nodes the player, its opponent and the enemies stand on, as far as the game's visibility
+/// lets the player see them. Hidden positions are left out of the request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VisiblePositions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opponent_position: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enemy_positions: Vec<usize>,
+}
+
+/// Response to a `/gamble` request. The variants of `GambleChoices` plus `MaxHealth`, with the
+/// same names, so submissions answering with a `GambleChoices` keep working.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GambleResponse {
+    Power,
+    Health,
+    MaxHealth,
+    Skip,
+}
+
+/// Makes the decisions for one side of a game, either over HTTP or in-process.
+#[async_trait]
+pub trait Player: Send + Sync {
+    async fn get_choices(&self, request: &ChoicesRequest, game_id: i64) -> Result<ChoiceResponse>;
+
+    async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleResponse>;
+
+    async fn get_fight_choice(&self, fight_info: &FightInfo, game_id: i64) -> Result<FightChoices>;
+
+    /// Whether the player can no longer be reached, e.g. because its container crashed.
+    fn is_dead(&self) -> bool {
+        false
+    }
+}
+
+#[async_trait]
+impl Player for ContainerHandle {
+    async fn get_choices(&self, request: &ChoicesRequest, game_id: i64) -> Result<ChoiceResponse> {
+        self.call("choices", game_id, request).await
+    }
+
+    async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleResponse> {
+        self.call("gamble", game_id, &()).await
+    }
+
+    async fn get_fight_choice(&self, fight_info: &FightInfo, game_id: i64) -> Result<FightChoices> {
+        self.call("fight", game_id, fight_info).await
+    }
+
+    fn is_dead(&self) -> bool {
+        ContainerHandle::is_dead(self)
+    }
+}
+
+/// Caps how many requests each submission serves at once across the whole tournament, however
+/// many matchups it is part of.
+pub struct SubmissionThrottle {
+    limit: usize,
+    slots: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl SubmissionThrottle {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wraps `player` so every call first takes one of `name`'s slots.
+    pub fn wrap(&self, name: &str, player: Arc<dyn Player>) -> Arc<dyn Player> {
+        let slots = self
+            .slots
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limit)))
+            .clone();
+        Arc::new(ThrottledPlayer { player, slots })
+    }
+}
+
+struct ThrottledPlayer {
+    player: Arc<dyn Player>,
+    slots: Arc<Semaphore>,
+}
+
+impl ThrottledPlayer {
+    async fn slot(&self) -> Result<OwnedSemaphorePermit> {
+        self.slots
+            .clone()
+            .acquire_owned()
+            .await
+            .context("Submission semaphore closed")
+    }
+}
+
+#[async_trait]
+impl Player for ThrottledPlayer {
+    async fn get_choices(&self, request: &ChoicesRequest, game_id: i64) -> Result<ChoiceResponse> {
+        let _slot = self.slot().await?;
+        mark_call_sent();
+        self.player.get_choices(request, game_id).await
+    }
+
+    async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleResponse> {
+        let _slot = self.slot().await?;
+        mark_call_sent();
+        self.player.get_gamble_choice(game_id).await
+    }
+
+    async fn get_fight_choice(&self, fight_info: &FightInfo, game_id: i64) -> Result<FightChoices> {
+        let _slot = self.slot().await?;
+        mark_call_sent();
+        self.player.get_fight_choice(fight_info, game_id).await
+    }
+
+    fn is_dead(&self) -> bool {
+        self.player.is_dead()
+    }
+}
+
+/// One call to a submission, as written to a game's trace file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceRecord {
+    pub submission: String,
+    pub endpoint: String,
+    pub request: serde_json::Value,
+    /// The parsed response, or `None` if the call failed.
+    pub response: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub latency_ms: f64,
+}
+
+/// How long a submission took to answer one call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CallLatency {
+    pub endpoint: &'static str,
+    pub latency_ms: f64,
+}
+
+/// Name of the submission whose call a game is waiting on, shared by both sides of the game.
+pub type PendingCall = Arc<Mutex<Option<String>>>;
+
 pub struct Submission {
     pub name: String,
-    container: ContainerHandle,
+    player: Arc<dyn Player>,
     player_state: PlayerState,
+    trace: Option<PathBuf>,
+    /// Calls answered since the game last took them with `take_latencies`.
+    latencies: Mutex<Vec<CallLatency>>,
+    /// Set to this submission's name while one of its calls is running.
+    pending_call: Option<PendingCall>,
 }
 
 impl Submission {
-    pub fn new(name: &str, container: ContainerHandle) -> Self {
+    pub fn new(name: &str, player: Arc<dyn Player>) -> Self {
         Submission {
             name: name.to_string(),
-            container,
-            player_state: PlayerState {
-                health: 3,
-                max_health: 3,
-                power: 5,
-            },
+            player,
+            player_state: STARTING_STATE,
+            trace: None,
+            latencies: Mutex::new(Vec::new()),
+            pending_call: None,
         }
     }
 
-    pub async fn get_choices(&self, choices: &MoveChoices, game_id: i64) -> Result<ChoiceResponse> {
-        self.container
-            .call("choices", game_id, choices)
-            .await
-            .context("Failed to get choices")
+    /// Starts every game in `state` rather than `STARTING_STATE`.
+    pub fn with_starting_state(mut self, state: PlayerState) -> Self {
+        self.player_state = state;
+        self
+    }
+
+    /// Appends a `TraceRecord` line to `path` for every call made through this submission.
+    pub fn with_trace(mut self, path: PathBuf) -> Self {
+        self.trace = Some(path);
+        self
+    }
+
+    /// Keeps this submission's name in `pending_call` while one of its calls is running, so if the
+    /// game is given up on mid-call, `pending_call` tells which submission it was waiting on.
+    pub fn with_pending_call(mut self, pending_call: PendingCall) -> Self {
+        self.pending_call = Some(pending_call);
+        self
+    }
+
+    /// Latencies of the calls answered since the last time they were taken.
+    pub fn take_latencies(&self) -> Vec<CallLatency> {
+        std::mem::take(&mut *self.latencies.lock().unwrap())
+    }
+
+    /// Runs `call`, recording its latency and tracing it as a call to `endpoint` with `request`
+    /// if tracing is on. The latency starts once the call is sent, see `mark_call_sent`. A failed
+    /// call is blamed on this submission with a `SubmissionFault`.
+    async fn traced<R: Serialize>(
+        &self,
+        endpoint: &'static str,
+        request: &impl Serialize,
+        call: impl Future<Output = Result<R>>,
+    ) -> Result<R> {
+        if let Some(pending) = &self.pending_call {
+            *pending.lock().unwrap() = Some(self.name.clone());
+        }
+        let (result, elapsed) = CALL_SENT
+            .scope(Cell::new(None), async {
+                let start = Instant::now();
+                let result = call.await;
+                let sent = CALL_SENT.with(Cell::get).unwrap_or(start);
+                (result, sent.elapsed())
+            })
+            .await;
+        // Left set if the game gives up on the call, naming who it was waiting on
+        if let Some(pending) = &self.pending_call {
+            *pending.lock().unwrap() = None;
+        }
+        profile::record(Bucket::SubmissionCalls, elapsed);
+        let latency_ms = elapsed.as_secs_f64() * 1000.0;
+        self.latencies.lock().unwrap().push(CallLatency {
+            endpoint,
+            latency_ms,
+        });
+
+        if let Some(path) = &self.trace {
+            let record = TraceRecord {
+                submission: self.name.clone(),
+                endpoint: endpoint.to_string(),
+                request: serde_json::to_value(request).unwrap_or_default(),
+                response: result
+                    .as_ref()
+                    .ok()
+                    .and_then(|response| serde_json::to_value(response).ok()),
+                error: result.as_ref().err().map(|e| format!("{:#}", e)),
+                latency_ms,
+            };
+            if let Err(e) = append_trace(path, &record) {
+                warn!("Failed to write trace to {}: {:#}", path.display(), e);
+            }
+        }
+        result.map_err(|e| {
+            e.context(SubmissionFault {
+                submission: self.name.clone(),
+            })
+        })
+    }
+
+    /// Asks for a move, telling the player its own state, `opponent`'s if visible and the visible
+    /// positions.
+    pub async fn get_choices(
+        &self,
+        choices: MoveChoices,
+        opponent: Option<PlayerState>,
+        positions: VisiblePositions,
+        game_id: i64,
+    ) -> Result<ChoiceResponse> {
+        let request = ChoicesRequest {
+            choices,
+            player: self.player_state,
+            opponent,
+            positions,
+        };
+        self.traced(
+            "choices",
+            &request,
+            self.player.get_choices(&request, game_id),
+        )
+        .await
+        .context("Failed to get choices")
     }
 
-    pub async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleChoices> {
-        self.container
-            .call("gamble", game_id, &())
+    pub async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleResponse> {
+        self.traced("gamble", &(), self.player.get_gamble_choice(game_id))
             .await
             .context("Failed to get gamble choice")
     }
@@ -42,10 +337,13 @@ impl Submission {
         fight_info: &FightInfo,
         game_id: i64,
     ) -> Result<FightChoices> {
-        self.container
-            .call("fight", game_id, fight_info)
-            .await
-            .context("Failed to get fight choice")
+        self.traced(
+            "fight",
+            fight_info,
+            self.player.get_fight_choice(fight_info, game_id),
+        )
+        .await
+        .context("Failed to get fight choice")
     }
 
     pub fn name(&self) -> &str {
@@ -60,3 +358,12 @@ impl Submission {
         &mut self.player_state
     }
 }
+
+fn append_trace(path: &Path, record: &TraceRecord) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
@@ -0,0 +1,194 @@
+#![cfg(test)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rplcs_events::tournament_1::{
+    ChoiceResponse, FightChoices, FightInfo, GambleChoices, MapNodeType, MoveChoices, PlayerState,
+};
+
+use super::{
+    ChoicesRequest, GambleResponse, Player, STARTING_STATE, Submission, SubmissionThrottle,
+    VisiblePositions,
+};
+
+/// Answers slowly and tracks how many calls it is serving at once.
+#[derive(Default)]
+struct SlowPlayer {
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+impl SlowPlayer {
+    async fn serve(&self) {
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl Player for SlowPlayer {
+    async fn get_choices(&self, _: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        self.serve().await;
+        Ok(ChoiceResponse { choice_index: 0 })
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        self.serve().await;
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        self.serve().await;
+        Ok(FightChoices::Flee)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_throttle_caps_calls_per_submission_across_matchups() {
+    let throttle = SubmissionThrottle::new(3);
+    let server = Arc::new(SlowPlayer::default());
+
+    // Two matchups hand out their own wrappers around the same submission
+    let wrappers = [
+        throttle.wrap("busy", server.clone()),
+        throttle.wrap("busy", server.clone()),
+    ];
+    let other = Arc::new(SlowPlayer::default());
+    let other_wrapper = throttle.wrap("other", other.clone());
+
+    let mut calls = Vec::new();
+    for game_id in 0..12 {
+        let player = wrappers[game_id % 2].clone();
+        let other_player = other_wrapper.clone();
+        calls.push(tokio::spawn(async move {
+            let request = ChoicesRequest {
+                choices: MoveChoices {
+                    choices: vec![MapNodeType::Normal],
+                },
+                player: STARTING_STATE,
+                opponent: Some(STARTING_STATE),
+                positions: VisiblePositions::default(),
+            };
+            player.get_choices(&request, game_id as i64).await.unwrap();
+            other_player
+                .get_gamble_choice(game_id as i64)
+                .await
+                .unwrap();
+        }));
+    }
+    for call in calls {
+        call.await.unwrap();
+    }
+
+    assert_eq!(server.max_in_flight.load(Ordering::SeqCst), 3);
+    assert_eq!(other.max_in_flight.load(Ordering::SeqCst), 3);
+}
+
+/// Keeps the JSON body of every `/choices` request it gets.
+#[derive(Default)]
+struct RecordingPlayer {
+    requests: Mutex<Vec<serde_json::Value>>,
+}
+
+#[async_trait]
+impl Player for RecordingPlayer {
+    async fn get_choices(&self, request: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        let body = serde_json::to_value(request)?;
+        self.requests.lock().unwrap().push(body);
+        Ok(ChoiceResponse { choice_index: 0 })
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+#[tokio::test]
+async fn test_choices_request_includes_current_player_state() {
+    let recorder = Arc::new(RecordingPlayer::default());
+    let mut submission = Submission::new("wounded", recorder.clone());
+    *submission.player_state_mut() = PlayerState {
+        health: 1,
+        max_health: 3,
+        power: 9,
+    };
+    let opponent = PlayerState {
+        health: 2,
+        max_health: 4,
+        power: 6,
+    };
+
+    let choices = MoveChoices {
+        choices: vec![MapNodeType::Healing, MapNodeType::Gamble],
+    };
+    submission
+        .get_choices(choices, Some(opponent), VisiblePositions::default(), 0)
+        .await
+        .unwrap();
+
+    let requests = recorder.requests.lock().unwrap();
+    let body = &requests[0];
+    // Move choices stay at the top level, so plain `MoveChoices` readers still work
+    assert_eq!(body["choices"], serde_json::json!(["Healing", "Gamble"]));
+    assert_eq!(
+        body["player"],
+        serde_json::json!({"health": 1, "max_health": 3, "power": 9})
+    );
+    assert_eq!(
+        body["opponent"],
+        serde_json::json!({"health": 2, "max_health": 4, "power": 6})
+    );
+    // Hidden positions are left out rather than sent as null
+    assert!(body.get("position").is_none(), "{body}");
+    assert!(body.get("enemy_positions").is_none(), "{body}");
+    let old_reader: MoveChoices = serde_json::from_value(body.clone()).unwrap();
+    assert_eq!(old_reader.choices.len(), 2);
+}
+
+#[test]
+fn test_gamble_response_accepts_gamble_choices() {
+    for (choice, response) in [
+        (GambleChoices::Power, GambleResponse::Power),
+        (GambleChoices::Health, GambleResponse::Health),
+        (GambleChoices::Skip, GambleResponse::Skip),
+    ] {
+        let json = serde_json::to_string(&choice).unwrap();
+        assert_eq!(
+            serde_json::from_str::<GambleResponse>(&json).unwrap(),
+            response
+        );
+    }
+    assert_eq!(
+        serde_json::from_str::<GambleResponse>(r#""MaxHealth""#).unwrap(),
+        GambleResponse::MaxHealth
+    );
+}
+
+#[tokio::test]
+async fn test_latency_leaves_out_waiting_for_a_throttle_slot() {
+    let throttle = SubmissionThrottle::new(1);
+    let server = Arc::new(SlowPlayer::default());
+    let first = Submission::new("busy", throttle.wrap("busy", server.clone()));
+    let second = Submission::new("busy", throttle.wrap("busy", server));
+
+    let (a, b) = tokio::join!(first.get_gamble_choice(0), second.get_gamble_choice(1));
+    a.unwrap();
+    b.unwrap();
+
+    // Whichever call waited for the other one still only took about 20ms to answer
+    for submission in [&first, &second] {
+        let latencies = submission.take_latencies();
+        assert_eq!(latencies.len(), 1);
+        assert!(latencies[0].latency_ms < 38.0, "{latencies:?}");
+    }
+}
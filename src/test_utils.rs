@@ -0,0 +1,129 @@
+//! Mock submissions and a fake podman shared by the test modules.
+#![cfg(test)]
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Reads a single HTTP request (headers and body) from `stream`.
+async fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    loop {
+        let n = stream.read(&mut buf).await.unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+
+        let text = String::from_utf8_lossy(&request);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if request.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&request).into_owned()
+}
+
+/// Starts a minimal HTTP server on a random local port that answers every request with `body`.
+pub(crate) async fn mock_server(body: &'static str) -> (u16, JoinHandle<()>) {
+    let (port, _, server) = recording_server(body).await;
+    (port, server)
+}
+
+/// Like `mock_server`, but also keeps the request line of every request it received.
+pub(crate) async fn recording_server(
+    body: &'static str,
+) -> (u16, Arc<Mutex<Vec<String>>>, JoinHandle<()>) {
+    routed_server(move |_| (200, body)).await
+}
+
+/// Like `recording_server`, answering each request with the status and body `route` returns for
+/// its request line.
+pub(crate) async fn routed_server(
+    route: impl Fn(&str) -> (u16, &'static str) + Send + Sync + 'static,
+) -> (u16, Arc<Mutex<Vec<String>>>, JoinHandle<()>) {
+    let route = Arc::new(route);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let received = requests.clone();
+    let server = tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                break;
+            };
+            let received = received.clone();
+            let route = route.clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut stream).await;
+                let request_line = request.lines().next().unwrap_or_default().to_string();
+                let (status, body) = route(&request_line);
+                received.lock().unwrap().push(request_line);
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    (port, requests, server)
+}
+
+/// A shell script standing in for podman, logging its arguments to `podman.log` in `dir` and
+/// running `script` on them. Returns the command to run it with.
+#[cfg(unix)]
+pub(crate) fn fake_podman(dir: &std::path::Path, script: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let podman = dir.join("podman");
+    std::fs::write(
+        &podman,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> '{}'\n{}\n",
+            dir.join("podman.log").display(),
+            script
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&podman, std::fs::Permissions::from_mode(0o755)).unwrap();
+    podman.to_str().unwrap().to_string()
+}
+
+/// A `fake_podman` reporting every container as running on `port`, for adopting it.
+#[cfg(unix)]
+pub(crate) fn running_podman(dir: &std::path::Path, port: u16) -> String {
+    fake_podman(
+        dir,
+        &format!(
+            "case \"$1\" in\n  inspect) echo true ;;\n  port) echo 0.0.0.0:{} ;;\nesac",
+            port
+        ),
+    )
+}
+
+#[cfg(unix)]
+/// The commands a `fake_podman` in `dir` was run with, one per line.
+pub(crate) fn podman_commands(dir: &std::path::Path) -> Vec<String> {
+    std::fs::read_to_string(dir.join("podman.log"))
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,1286 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use figment::Figment;
+use figment::providers::{Format, Toml};
+use petgraph::graph::DiGraph;
+use rplcs_events::tournament_1::{ChoiceResponse, FightChoices, FightInfo, MapNodeType};
+use rusqlite::Connection;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::bots::built_in_bots;
+use crate::db::{Database, DisqualificationReason, PendingPolicy};
+use crate::error::{MatchupTimeout, TournamentError};
+use crate::game::{
+    EnemyMovement, FightModel, GameResult, NoMovesPolicy, TieBreak, TimeoutPolicy, TurnOrder,
+    Visibility,
+};
+use crate::game_map::{GameMap, MapConfig, NodeCount, RenderConfig};
+use crate::report::TieScoring;
+use crate::submission::{ChoicesRequest, GambleResponse, Player, TraceRecord};
+use crate::test_utils::{routed_server, running_podman};
+use crate::{
+    Config, FailureBreaker, GameBudget, MatchupLength, Preset, count_complete_matchups,
+    disqualification_summary, forfeit_matchup, game_numbers, is_matchup_complete, map_for_seed,
+    play_round, render_map, run_games, run_single_matchup, scan_submission_dirs, spawn_bounded,
+};
+
+/// A player that never answers.
+struct StalledPlayer;
+
+#[async_trait]
+impl Player for StalledPlayer {
+    async fn get_choices(&self, _: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        std::future::pending().await
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        std::future::pending().await
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_spawn_bounded_respects_limit() {
+    let limit = 3;
+    let slots = Arc::new(Semaphore::new(limit));
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let mut tasks: JoinSet<Result<()>> = JoinSet::new();
+    for _ in 0..12 {
+        let running = running.clone();
+        let max_running = max_running.clone();
+        spawn_bounded(&mut tasks, &slots, async move {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        });
+    }
+
+    let mut completed = 0;
+    while let Some(result) = tasks.join_next().await {
+        result.unwrap().unwrap();
+        completed += 1;
+    }
+
+    assert_eq!(completed, 12);
+    assert!(max_running.load(Ordering::SeqCst) <= limit);
+    assert!(max_running.load(Ordering::SeqCst) > 0);
+}
+
+#[tokio::test]
+async fn test_render_map_writes_svg_for_seed() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let out = results_dir.path().join("map.svg");
+
+    let map = map_for_seed(&config, -478597674355546704).await.unwrap();
+    render_map(&map, &out, &RenderConfig::default()).unwrap();
+
+    let svg = std::fs::read_to_string(&out).unwrap();
+    assert!(svg.contains("<svg"));
+}
+
+#[tokio::test]
+async fn test_render_map_prefers_the_map_stored_with_the_game() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let generated = map_for_seed(&config, 5).await.unwrap();
+
+    // A map generation would never make for this seed, standing in for an older algorithm's
+    let mut graph = DiGraph::new();
+    let a = graph.add_node(MapNodeType::Normal);
+    let b = graph.add_node(MapNodeType::Healing);
+    graph.add_edge(a, b, ());
+    graph.add_edge(b, a, ());
+    let stored = GameMap::from_graph(graph);
+    let db = Database::new(&config).unwrap();
+    let matchup_id = db.start_matchup("a", "b").await.unwrap();
+    db.create_game(matchup_id, 0, "a", 5, Some(&stored.to_json().unwrap()))
+        .await
+        .unwrap();
+
+    let map = map_for_seed(&config, 5).await.unwrap();
+    assert_eq!(map.node_weights(), stored.node_weights());
+    assert_ne!(map.node_weights(), generated.node_weights());
+}
+
+#[tokio::test]
+async fn test_stalled_matchup_is_aborted_after_matchup_timeout() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 4,
+        game_timeout: 3600.0,
+        matchup_timeout: 0.2,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+
+    let result = tokio::time::timeout(
+        Duration::from_secs(10),
+        run_games(
+            "a".to_string(),
+            "b".to_string(),
+            Arc::new(StalledPlayer),
+            Arc::new(StalledPlayer),
+            &db,
+            &GameBudget::new(None),
+            &config,
+        ),
+    )
+    .await
+    .expect("matchup should stop at its timeout");
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("4 games were left pending"), "{error}");
+}
+
+/// A player whose container hung and was then found dead, so its games never finish.
+struct HungDeadPlayer;
+
+#[async_trait]
+impl Player for HungDeadPlayer {
+    async fn get_choices(&self, _: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        std::future::pending().await
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        std::future::pending().await
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        std::future::pending().await
+    }
+
+    fn is_dead(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn test_timed_out_matchup_records_forfeits_of_a_dead_container() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 4,
+        game_timeout: 3600.0,
+        matchup_timeout: 0.2,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+
+    let error = run_games(
+        "hung".to_string(),
+        "steady".to_string(),
+        Arc::new(HungDeadPlayer),
+        Arc::new(StalledPlayer),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap_err()
+    .to_string();
+
+    assert!(error.contains("0 games were left pending"), "{error}");
+    let games = db.list_matchup_games(1).await.unwrap();
+    assert_eq!(games.len(), 4);
+    assert!(
+        games
+            .iter()
+            .all(|game| game.winner_name() == Some("steady")),
+        "{games:?}"
+    );
+    let disqualifications = db.list_disqualifications().await.unwrap();
+    assert_eq!(disqualifications[0].submission, "hung");
+}
+
+/// A player whose container has crashed.
+struct CrashedPlayer;
+
+#[async_trait]
+impl Player for CrashedPlayer {
+    async fn get_choices(&self, _: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        anyhow::bail!("Container is dead")
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        anyhow::bail!("Container is dead")
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        anyhow::bail!("Container is dead")
+    }
+
+    fn is_dead(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn test_crashed_container_is_disqualified_in_the_summary() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 4,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+
+    let summary = run_games(
+        "crashy".to_string(),
+        "steady".to_string(),
+        Arc::new(CrashedPlayer),
+        Arc::new(StalledPlayer),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(summary.b_wins, 4);
+    assert_eq!(summary.disqualifications.len(), 1);
+    assert_eq!(summary.disqualifications[0].submission, "crashy");
+    assert_eq!(
+        summary.disqualifications[0].reason,
+        DisqualificationReason::ContainerCrash
+    );
+    assert_eq!(
+        db.list_disqualifications().await.unwrap(),
+        summary.disqualifications
+    );
+
+    let lines = disqualification_summary(&db).await.unwrap();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains("crashy"), "{}", lines[1]);
+    assert!(lines[1].contains("container_crash"), "{}", lines[1]);
+}
+
+#[tokio::test]
+async fn test_repeatedly_failing_submission_forfeits_its_remaining_matchups() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 4,
+        max_submission_failures: Some(6),
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let breaker = FailureBreaker::new(config.max_submission_failures);
+
+    // Crashing forfeits all 4 games of each matchup, tripping the breaker on the second
+    for opponent in ["steady", "sturdy"] {
+        assert!(!breaker.is_tripped("crashy"));
+        let summary = run_games(
+            "crashy".to_string(),
+            opponent.to_string(),
+            Arc::new(CrashedPlayer),
+            Arc::new(StalledPlayer),
+            &db,
+            &GameBudget::new(None),
+            &config,
+        )
+        .await
+        .unwrap();
+        breaker.record_failed_games(&summary);
+    }
+    assert!(breaker.is_tripped("crashy"));
+    assert!(!breaker.is_tripped("steady"));
+
+    let summary = forfeit_matchup("solid", "crashy", "crashy", &db, &config)
+        .await
+        .unwrap();
+    assert_eq!((summary.a_wins, summary.b_wins), (4, 0));
+    assert_eq!(
+        summary.disqualifications[0].reason,
+        DisqualificationReason::RepeatedFailures
+    );
+    let reasons: Vec<_> = db
+        .list_disqualifications()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|dq| (dq.submission, dq.reason))
+        .collect();
+    assert_eq!(reasons.len(), 3);
+    assert_eq!(
+        reasons[2],
+        (
+            "crashy".to_string(),
+            DisqualificationReason::RepeatedFailures
+        )
+    );
+}
+
+/// A player whose calls all fail, without its container dying.
+struct BrokenPlayer;
+
+#[async_trait]
+impl Player for BrokenPlayer {
+    async fn get_choices(&self, _: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        anyhow::bail!("Internal server error")
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        anyhow::bail!("Internal server error")
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        anyhow::bail!("Internal server error")
+    }
+}
+
+#[tokio::test]
+async fn test_errored_games_are_blamed_on_the_failing_submission() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 4,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let bots = built_in_bots(Some(1));
+
+    let summary = run_games(
+        "broken".to_string(),
+        bots[0].0.clone(),
+        Arc::new(BrokenPlayer),
+        bots[0].1.clone(),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap();
+
+    let mut failed = summary.failed_games.clone();
+    failed.sort();
+    let mut expected: Vec<_> = game_numbers(MatchupLength::FixedRounds(4))
+        .map(|(game_number, _)| ("broken".to_string(), game_number))
+        .collect();
+    expected.sort();
+    assert_eq!(failed, expected);
+
+    let breaker = FailureBreaker::new(Some(3));
+    breaker.record_failed_games(&summary);
+    assert!(breaker.is_tripped("broken"));
+    assert!(!breaker.is_tripped(&bots[0].0));
+}
+
+#[test]
+fn test_failure_breaker_is_off_by_default() {
+    let breaker = FailureBreaker::new(Config::default().max_submission_failures);
+    breaker.record("broken", "steady", 0..1000);
+    assert!(!breaker.is_tripped("broken"));
+}
+
+#[test]
+fn test_failure_breaker_counts_each_failed_game_once() {
+    let breaker = FailureBreaker::new(Some(4));
+    let games = || game_numbers(MatchupLength::FixedRounds(4)).map(|(game_number, _)| game_number);
+
+    // A matchup whose container failed to start, and failed again when it was retried
+    breaker.record("broken", "steady", games());
+    breaker.record("broken", "steady", games());
+    assert!(!breaker.is_tripped("broken"));
+
+    breaker.record("broken", "sturdy", [0]);
+    assert!(breaker.is_tripped("broken"));
+}
+
+/// Plays like `inner`, except that it panics when asked for a move in game 0.
+struct PanickyPlayer {
+    inner: Arc<dyn Player>,
+}
+
+#[async_trait]
+impl Player for PanickyPlayer {
+    async fn get_choices(&self, request: &ChoicesRequest, game_id: i64) -> Result<ChoiceResponse> {
+        assert_ne!(game_id, 0, "scripted panic");
+        self.inner.get_choices(request, game_id).await
+    }
+
+    async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleResponse> {
+        self.inner.get_gamble_choice(game_id).await
+    }
+
+    async fn get_fight_choice(&self, fight_info: &FightInfo, game_id: i64) -> Result<FightChoices> {
+        self.inner.get_fight_choice(fight_info, game_id).await
+    }
+}
+
+#[tokio::test]
+async fn test_panicking_game_is_recorded_as_errored_with_continue_on_panic() {
+    for continue_on_panic in [false, true] {
+        let results_dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            rounds_per_pair: 4,
+            turns_per_game: 10,
+            record_turns_without_render: true,
+            continue_on_panic,
+            results_dir: results_dir.path().to_str().unwrap().to_string(),
+            ..Config::default()
+        };
+        let db = Database::new(&config).unwrap();
+        let bots = built_in_bots(None);
+
+        let summary = run_games(
+            "panicky".to_string(),
+            bots[1].0.clone(),
+            Arc::new(PanickyPlayer {
+                inner: bots[0].1.clone(),
+            }),
+            bots[1].1.clone(),
+            &db,
+            &GameBudget::new(None),
+            &config,
+        )
+        .await;
+
+        if !continue_on_panic {
+            assert!(summary.is_err());
+            continue;
+        }
+        // The other games of the matchup still finish
+        assert_eq!(summary.unwrap().results.len(), 3);
+        let conn = Connection::open(Database::file(&config)).unwrap();
+        let winner: String = conn
+            .query_row(
+                "SELECT winner FROM games WHERE game_number = 0",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(winner, "error");
+        assert_eq!(db.list_decided_games().await.unwrap().len(), 3);
+    }
+}
+
+/// Plays like `inner` for a few moves, then stops answering.
+struct TiringPlayer {
+    inner: Arc<dyn Player>,
+    moves_left: AtomicUsize,
+}
+
+#[async_trait]
+impl Player for TiringPlayer {
+    async fn get_choices(&self, request: &ChoicesRequest, game_id: i64) -> Result<ChoiceResponse> {
+        if self.moves_left.fetch_sub(1, Ordering::SeqCst) == 0 {
+            std::future::pending::<()>().await;
+        }
+        self.inner.get_choices(request, game_id).await
+    }
+
+    async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleResponse> {
+        self.inner.get_gamble_choice(game_id).await
+    }
+
+    async fn get_fight_choice(&self, fight_info: &FightInfo, game_id: i64) -> Result<FightChoices> {
+        self.inner.get_fight_choice(fight_info, game_id).await
+    }
+}
+
+#[tokio::test]
+async fn test_timed_out_game_keeps_its_recorded_turns() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 1,
+        turns_per_game: 100,
+        game_timeout: 1.0,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        // A map where the game is still going when the submission stalls
+        tournament_seed: Some(0),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let bots = built_in_bots(Some(1));
+
+    let summary = run_games(
+        "tiring".to_string(),
+        bots[1].0.clone(),
+        Arc::new(TiringPlayer {
+            inner: bots[0].1.clone(),
+            moves_left: AtomicUsize::new(3),
+        }),
+        bots[1].1.clone(),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap();
+
+    assert!(summary.results.is_empty());
+    // The game was waiting on the stalled submission when time ran out
+    assert_eq!(summary.failed_games, [("tiring".to_string(), 0)]);
+    let conn = Connection::open(Database::file(&config)).unwrap();
+    let (winner, turns): (String, i64) = conn
+        .query_row(
+            "SELECT g.winner, (SELECT COUNT(*) FROM turns t WHERE t.game_id = g.id)
+             FROM games g WHERE g.game_number = 0",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(winner, "timeout");
+    assert!(turns > 0, "no turns were kept");
+    assert!(db.list_decided_games().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_timed_out_game_is_retried_with_game_retries() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 1,
+        turns_per_game: 100,
+        game_timeout: 1.0,
+        game_retries: 1,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let bots = built_in_bots(Some(1));
+
+    // Stalls once, on its fourth move, then keeps answering
+    let summary = run_games(
+        "tiring".to_string(),
+        bots[1].0.clone(),
+        Arc::new(TiringPlayer {
+            inner: bots[0].1.clone(),
+            moves_left: AtomicUsize::new(3),
+        }),
+        bots[1].1.clone(),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(summary.results.len(), 1);
+    let games = db.list_decided_games().await.unwrap();
+    assert_eq!(games.len(), 1);
+    assert!(!["pending", "timeout", "error"].contains(&games[0].winner.as_str()));
+}
+
+#[tokio::test]
+async fn test_total_game_cap_is_shared_across_matchups() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 4,
+        turns_per_game: 10,
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let budget = GameBudget::new(Some(5));
+    let bots = built_in_bots(None);
+    let (name_a, bot_a) = &bots[0];
+    let (name_b, bot_b) = &bots[1];
+
+    let mut played = Vec::new();
+    for suffix in ["1", "2", "3"] {
+        let summary = run_games(
+            format!("{name_a}_{suffix}"),
+            format!("{name_b}_{suffix}"),
+            bot_a.clone(),
+            bot_b.clone(),
+            &db,
+            &budget,
+            &config,
+        )
+        .await
+        .unwrap();
+        played.push(summary.results.len());
+    }
+
+    assert_eq!(played, [4, 1, 0]);
+    assert!(budget.is_exhausted());
+    let conn = Connection::open(Database::file(&config)).unwrap();
+    let games: i64 = conn
+        .query_row("SELECT COUNT(*) FROM games", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(games, 5);
+}
+
+/// Plays like `inner`, taking `delay` to answer each move.
+struct SlowPlayer {
+    inner: Arc<dyn Player>,
+    delay: Duration,
+}
+
+#[async_trait]
+impl Player for SlowPlayer {
+    async fn get_choices(&self, request: &ChoicesRequest, game_id: i64) -> Result<ChoiceResponse> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.get_choices(request, game_id).await
+    }
+
+    async fn get_gamble_choice(&self, game_id: i64) -> Result<GambleResponse> {
+        self.inner.get_gamble_choice(game_id).await
+    }
+
+    async fn get_fight_choice(&self, fight_info: &FightInfo, game_id: i64) -> Result<FightChoices> {
+        self.inner.get_fight_choice(fight_info, game_id).await
+    }
+}
+
+#[tokio::test]
+async fn test_max_runtime_stops_starting_games() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 10,
+        turns_per_game: 2,
+        max_runtime: Some(0.2),
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let budget = GameBudget::from_config(&config);
+    let bots = built_in_bots(None);
+    let slow = |i: usize| -> Arc<dyn Player> {
+        Arc::new(SlowPlayer {
+            inner: bots[i].1.clone(),
+            delay: Duration::from_millis(250),
+        })
+    };
+
+    let mut played = Vec::new();
+    for suffix in ["1", "2"] {
+        let summary = run_games(
+            format!("a_{suffix}"),
+            format!("b_{suffix}"),
+            slow(0),
+            slow(1),
+            &db,
+            &budget,
+            &config,
+        )
+        .await
+        .unwrap();
+        played.push(summary.results.len());
+    }
+
+    // Only the games started in time are played, each of them to the end
+    assert_eq!(played[1], 0);
+    assert!(
+        played[0] >= 1 && played[0] <= config.game_concurrency(),
+        "{played:?}"
+    );
+    assert!(budget.is_exhausted());
+    assert!(budget.exhausted_limit().contains("max runtime"));
+    let conn = Connection::open(Database::file(&config)).unwrap();
+    let unfinished: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM games WHERE winner = 'pending'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(unfinished, 0);
+}
+
+#[tokio::test]
+async fn test_trace_records_choices_requests_and_responses() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        rounds_per_pair: 2,
+        turns_per_game: 4,
+        record_turns_without_render: true,
+        trace_calls: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let bots = built_in_bots(None);
+
+    run_games(
+        "alice".to_string(),
+        "bob".to_string(),
+        bots[0].1.clone(),
+        bots[1].1.clone(),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap();
+
+    // The first matchup of a fresh database has id 1
+    let trace = std::fs::read_to_string(
+        results_dir
+            .path()
+            .join("traces/1_alice_vs_bob/game_0.jsonl"),
+    )
+    .unwrap();
+    let records: Vec<TraceRecord> = trace
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let choices = records
+        .iter()
+        .find(|record| record.endpoint == "choices")
+        .unwrap();
+    assert_eq!(choices.submission, "alice");
+    assert!(choices.request["choices"].is_array(), "{:?}", choices);
+    assert_eq!(choices.request["player"]["health"], 3);
+    assert!(choices.response.as_ref().unwrap()["choice_index"].is_u64());
+    assert!(choices.error.is_none());
+    assert!(
+        results_dir
+            .path()
+            .join("traces/1_alice_vs_bob/game_3.jsonl")
+            .exists()
+    );
+}
+
+#[tokio::test]
+async fn test_self_matchup_is_rejected() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+
+    let result = run_games(
+        "a".to_string(),
+        "a".to_string(),
+        Arc::new(StalledPlayer),
+        Arc::new(StalledPlayer),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await;
+
+    assert!(result.unwrap_err().to_string().contains("against itself"));
+    assert!(db.list_matchups().await.unwrap().is_empty());
+}
+
+#[test]
+fn test_preset_sets_mechanics_and_overrides_still_win() {
+    let config = Config::load(Figment::from(Toml::string(r#"preset = "hardcore""#))).unwrap();
+    assert_eq!(config.preset, Some(Preset::Hardcore));
+    assert_eq!(config.tie_break, TieBreak::HealthThenPower);
+    assert_eq!(config.fight_health_factor, 0.5);
+    assert_eq!(config.map.healing_nodes, NodeCount::Fixed(1));
+    assert_eq!(config.rounds_per_pair, Config::default().rounds_per_pair);
+
+    let config = Config::load(Figment::from(Toml::string(
+        r#"
+        preset = "hardcore"
+        fight_health_factor = 0.25
+
+        [map]
+        healing_nodes = 2
+        "#,
+    )))
+    .unwrap();
+    assert_eq!(config.fight_health_factor, 0.25);
+    assert_eq!(config.map.healing_nodes, NodeCount::Fixed(2));
+    assert_eq!(config.tie_break, TieBreak::HealthThenPower);
+}
+
+#[test]
+fn test_no_preset_keeps_defaults() {
+    let config = Config::load(Figment::new()).unwrap();
+    assert_eq!(config.preset, None);
+    assert_eq!(config.tie_break, TieBreak::None);
+    assert_eq!(config.map.healing_nodes, MapConfig::default().healing_nodes);
+}
+
+/// Checks that a config value was set by the matching TOML line.
+type ConfigCheck = fn(&Config) -> bool;
+
+#[test]
+fn test_config_names() {
+    let cases: &[(&str, ConfigCheck)] = &[
+        (r#"tie_break = "none""#, |c| c.tie_break == TieBreak::None),
+        (r#"tie_break = "health""#, |c| {
+            c.tie_break == TieBreak::Health
+        }),
+        (r#"tie_break = "power""#, |c| c.tie_break == TieBreak::Power),
+        (r#"tie_break = "health_then_power""#, |c| {
+            c.tie_break == TieBreak::HealthThenPower
+        }),
+        (r#"tie_scoring = "separate""#, |c| {
+            c.tie_scoring == TieScoring::Separate
+        }),
+        (r#"tie_scoring = "half_win""#, |c| {
+            c.tie_scoring == TieScoring::HalfWin
+        }),
+        (r#"fight_model = "probabilistic""#, |c| {
+            c.fight_model == FightModel::Probabilistic
+        }),
+        (r#"fight_model = "deterministic""#, |c| {
+            c.fight_model == FightModel::Deterministic
+        }),
+        (r#"enemy_movement = "random""#, |c| {
+            c.enemy_movement == EnemyMovement::Random
+        }),
+        (r#"enemy_movement = "lowest_index""#, |c| {
+            c.enemy_movement == EnemyMovement::LowestIndex
+        }),
+        (r#"turn_order = "alternating""#, |c| {
+            c.turn_order == TurnOrder::Alternating
+        }),
+        (r#"turn_order = "initiative""#, |c| {
+            c.turn_order == TurnOrder::Initiative
+        }),
+        (r#"turn_order = "random""#, |c| {
+            c.turn_order == TurnOrder::Random
+        }),
+        (r#"timeout_policy = "fail_game""#, |c| {
+            c.timeout_policy == TimeoutPolicy::FailGame
+        }),
+        (r#"timeout_policy = "lose_game""#, |c| {
+            c.timeout_policy == TimeoutPolicy::LoseGame
+        }),
+        (r#"timeout_policy = "skip_turn_with_damage""#, |c| {
+            c.timeout_policy == TimeoutPolicy::SkipTurnWithDamage
+        }),
+        (r#"no_moves_policy = "skip_turn""#, |c| {
+            c.no_moves_policy == NoMovesPolicy::SkipTurn
+        }),
+        (r#"no_moves_policy = "teleport""#, |c| {
+            c.no_moves_policy == NoMovesPolicy::Teleport
+        }),
+        (r#"visibility = "full""#, |c| {
+            c.visibility == Visibility::Full
+        }),
+        (r#"visibility = "self_only""#, |c| {
+            c.visibility == Visibility::SelfOnly
+        }),
+        (r#"visibility = "adjacent""#, |c| {
+            c.visibility == Visibility::Adjacent
+        }),
+        (r#"pending_policy = "rerun""#, |c| {
+            c.pending_policy == PendingPolicy::Rerun
+        }),
+        (r#"pending_policy = "exclude""#, |c| {
+            c.pending_policy == PendingPolicy::Exclude
+        }),
+        (r#"pending_policy = "delete""#, |c| {
+            c.pending_policy == PendingPolicy::Delete
+        }),
+    ];
+    for (toml, is_set) in cases {
+        let config = Config::load(Figment::from(Toml::string(toml))).unwrap();
+        assert!(is_set(&config), "{toml}");
+    }
+}
+
+#[test]
+fn test_invalid_config_is_a_config_error() {
+    let loaded = Config::load(Figment::from(Toml::string(r#"turns_per_game = "many""#)));
+    let Err(TournamentError::Config(source)) = loaded else {
+        panic!("expected a config error");
+    };
+    assert!(source.to_string().contains("turns_per_game"), "{source}");
+}
+
+#[test]
+fn test_zero_enemies_per_nodes_is_a_config_error() {
+    let loaded = Config::load(Figment::from(Toml::string("enemies_per_nodes = 0")));
+    let Err(TournamentError::Config(source)) = loaded else {
+        panic!("expected a config error");
+    };
+    assert!(source.to_string().contains("enemies_per_nodes"), "{source}");
+}
+
+fn pairs(names: &[(&str, &str)]) -> Vec<(String, String)> {
+    names
+        .iter()
+        .map(|(a, b)| (a.to_string(), b.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_failed_matchup_is_retried_after_the_rest_of_the_round() {
+    let mut played = Vec::new();
+    let mut failed_once = false;
+
+    let abandoned = play_round(
+        pairs(&[("a", "b"), ("c", "d")]),
+        2,
+        async |a: &str, b: &str| {
+            played.push(format!("{a}-{b}"));
+            if a == "a" && !failed_once {
+                failed_once = true;
+                anyhow::bail!("both containers failed to start");
+            }
+            Ok(())
+        },
+    )
+    .await;
+
+    assert!(abandoned.is_empty());
+    assert_eq!(played, ["a-b", "c-d", "a-b"]);
+}
+
+#[tokio::test]
+async fn test_matchup_is_abandoned_after_its_retries() {
+    let mut attempts = 0;
+
+    let abandoned = play_round(pairs(&[("a", "b")]), 2, async |_: &str, _: &str| {
+        attempts += 1;
+        anyhow::bail!("containers failed to start")
+    })
+    .await;
+
+    assert_eq!(abandoned, pairs(&[("a", "b")]));
+    assert_eq!(attempts, 3);
+}
+
+#[tokio::test]
+async fn test_timed_out_matchup_is_not_retried() {
+    let mut attempts = 0;
+
+    let abandoned = play_round(pairs(&[("a", "b")]), 2, async |a: &str, b: &str| {
+        attempts += 1;
+        Err(anyhow::Error::from(MatchupTimeout {
+            submission_a: a.to_string(),
+            submission_b: b.to_string(),
+            timeout: Duration::from_secs(1),
+            pending: 1,
+        })
+        .context("Failed to run matchup"))
+    })
+    .await;
+
+    assert_eq!(abandoned, pairs(&[("a", "b")]));
+    assert_eq!(attempts, 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_submission_directory_is_reported() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let submissions_dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(submissions_dir.path().join("valid_bot")).unwrap();
+    let invalid = submissions_dir
+        .path()
+        .join(OsStr::from_bytes(b"bad_\xff_bot"));
+    std::fs::create_dir(&invalid).unwrap();
+
+    let (names, skipped) = scan_submission_dirs(submissions_dir.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(names, ["valid_bot"]);
+    assert_eq!(skipped, [invalid]);
+    assert!(
+        skipped[0]
+            .display()
+            .to_string()
+            .contains("bad_\u{fffd}_bot")
+    );
+}
+
+/// Answers like a passive submission: it takes the first move, skips gambles and flees fights.
+fn passive_route(request_line: &str) -> (u16, &'static str) {
+    let path = request_line.split(' ').nth(1).unwrap_or_default();
+    let body = match path.split('?').next().unwrap_or_default() {
+        "/choices" => r#"{"choice_index":0}"#,
+        "/gamble" => r#""Skip""#,
+        "/fight" => r#""Flee""#,
+        _ => "{}",
+    };
+    (200, body)
+}
+
+#[tokio::test]
+async fn test_single_matchup_against_itself_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        results_dir: dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+
+    let error = tokio::time::timeout(
+        Duration::from_secs(5),
+        run_single_matchup("left", "left", &config),
+    )
+    .await
+    .expect("run-game of a submission against itself hangs")
+    .unwrap_err();
+
+    assert!(
+        format!("{:#}", error).contains("cannot play against itself"),
+        "{error:#}"
+    );
+}
+
+/// A shell script stands in for podman, reporting both submissions as running containers served
+/// by a passive mock submission.
+#[cfg(unix)]
+#[tokio::test]
+async fn test_single_matchup_plays_the_requested_games() {
+    let (port, _, _server) = routed_server(passive_route).await;
+    let dir = tempfile::tempdir().unwrap();
+    let podman = running_podman(dir.path(), port);
+    let config = Config {
+        rounds_per_pair: 3,
+        turns_per_game: 6,
+        podman_command: podman,
+        reuse_existing_containers: true,
+        record_turns_without_render: true,
+        results_dir: dir.path().join("results").to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+
+    let summary = run_single_matchup("left", "right", &config).await.unwrap();
+
+    assert_eq!(summary.results.len(), 3);
+    assert!(
+        summary
+            .to_string()
+            .starts_with("left vs right after 3 games:")
+    );
+    let conn = Connection::open(dir.path().join("results/results.sqlite")).unwrap();
+    let decided: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM games WHERE winner != 'pending'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(decided, 3);
+}
+
+/// Always returns an out-of-range move, losing 1 health every turn.
+struct InvalidMover;
+
+#[async_trait]
+impl Player for InvalidMover {
+    async fn get_choices(&self, _: &ChoicesRequest, _: i64) -> Result<ChoiceResponse> {
+        Ok(ChoiceResponse { choice_index: 99 })
+    }
+
+    async fn get_gamble_choice(&self, _: i64) -> Result<GambleResponse> {
+        Ok(GambleResponse::Skip)
+    }
+
+    async fn get_fight_choice(&self, _: &FightInfo, _: i64) -> Result<FightChoices> {
+        Ok(FightChoices::Flee)
+    }
+}
+
+#[tokio::test]
+async fn test_first_to_matchup_stops_once_decided() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let length = MatchupLength::FirstTo { wins: 3, max: 9 };
+    let config = Config {
+        matchup_length: Some(length),
+        max_concurrent_games_per_matchup: 1,
+        tournament_seed: Some(7),
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let bots = built_in_bots(Some(7));
+    let (name, bot) = &bots[0];
+
+    let summary = run_games(
+        "invalid".to_string(),
+        name.clone(),
+        Arc::new(InvalidMover),
+        bot.clone(),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!((summary.a_wins, summary.b_wins), (0, 3));
+    assert_eq!(summary.results.len(), 3);
+    assert!(
+        is_matchup_complete(&db, "invalid", name, length)
+            .await
+            .unwrap()
+    );
+    assert_eq!(count_complete_matchups(&db, length).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_resumed_first_to_matchup_keeps_its_finished_games() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let length = MatchupLength::FirstTo { wins: 3, max: 9 };
+    let config = Config {
+        matchup_length: Some(length),
+        max_concurrent_games_per_matchup: 1,
+        tournament_seed: Some(7),
+        record_turns_without_render: true,
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    };
+    let db = Database::new(&config).unwrap();
+    let bots = built_in_bots(Some(7));
+    let (name, bot) = &bots[0];
+    // An earlier run had the bot win the first game from either side, then timed out the third
+    let matchup_id = db.start_matchup("invalid", name).await.unwrap();
+    db.create_game(matchup_id, 0, "invalid", 1, None)
+        .await
+        .unwrap();
+    db.update_game_result(matchup_id, 0, GameResult::Player2Win)
+        .await
+        .unwrap();
+    db.create_game(matchup_id, 10, name, 2, None).await.unwrap();
+    db.update_game_result(matchup_id, 10, GameResult::Player1Win)
+        .await
+        .unwrap();
+    db.create_game(matchup_id, 2, "invalid", 3, None)
+        .await
+        .unwrap();
+    db.mark_game_timed_out(matchup_id, 2).await.unwrap();
+
+    let summary = run_games(
+        "invalid".to_string(),
+        name.clone(),
+        Arc::new(InvalidMover),
+        bot.clone(),
+        &db,
+        &GameBudget::new(None),
+        &config,
+    )
+    .await
+    .unwrap();
+
+    // Only the deciding game is played, and the summary counts the kept ones with a result
+    assert_eq!((summary.a_wins, summary.b_wins), (0, 3));
+    assert_eq!(summary.results.len(), 3);
+    assert_eq!(db.count_completed_games(matchup_id).await.unwrap(), (4, 9));
+    let kept: Vec<(i64, String)> = Connection::open(Database::file(&config))
+        .unwrap()
+        .prepare(
+            "SELECT seed, winner FROM games WHERE game_number IN (0, 2, 10) ORDER BY game_number",
+        )
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap();
+    assert_eq!(
+        kept,
+        [
+            (1, "player_b".to_string()),
+            (3, "timeout".to_string()),
+            (2, "player_a".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_concurrent_matchups_of_the_same_pair_write_distinct_svgs() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let bots = built_in_bots(Some(0));
+    let configs = ["first", "second"].map(|name| Config {
+        rounds_per_pair: 2,
+        turns_per_game: 3,
+        tournament_name: name.to_string(),
+        results_dir: results_dir.path().to_str().unwrap().to_string(),
+        ..Config::default()
+    });
+    let dbs = configs
+        .each_ref()
+        .map(|config| Database::new(config).unwrap());
+    let budget = GameBudget::new(None);
+
+    let play = |i: usize| {
+        run_games(
+            bots[0].0.clone(),
+            bots[1].0.clone(),
+            bots[0].1.clone(),
+            bots[1].1.clone(),
+            &dbs[i],
+            &budget,
+            &configs[i],
+        )
+    };
+    let (first, second) = tokio::join!(play(0), play(1));
+    assert_ne!(first.unwrap().matchup_id, second.unwrap().matchup_id);
+
+    let conn = Connection::open(Database::file(&configs[0])).unwrap();
+    let svg_paths: Vec<String> = conn
+        .prepare("SELECT svg_path FROM turns")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    // Two games of each matchup, each with at least its first and last turn
+    assert!(svg_paths.len() >= 8, "{svg_paths:?}");
+    let unique: std::collections::HashSet<_> = svg_paths.iter().collect();
+    assert_eq!(unique.len(), svg_paths.len(), "{svg_paths:?}");
+    for path in &svg_paths {
+        assert!(std::path::Path::new(path).exists(), "{path}");
+    }
+}
+
+#[tokio::test]
+async fn test_rendering_stops_once_visualizations_exceed_the_cap() {
+    let results_dir = tempfile::tempdir().unwrap();
+    let visualizations = results_dir.path().join("visualizations");
+    std::fs::create_dir(&visualizations).unwrap();
+    std::fs::write(visualizations.join("earlier.svg"), [0; 2000]).unwrap();
+    let bots = built_in_bots(Some(0));
+
+    for (cap, rendered) in [(1_000_000, true), (1000, false)] {
+        let config = Config {
+            rounds_per_pair: 1,
+            turns_per_game: 2,
+            max_visualization_bytes: Some(cap),
+            tournament_name: format!("cap_{cap}"),
+            results_dir: results_dir.path().to_str().unwrap().to_string(),
+            ..Config::default()
+        };
+        let db = Database::new(&config).unwrap();
+        let summary = run_games(
+            bots[0].0.clone(),
+            bots[1].0.clone(),
+            bots[0].1.clone(),
+            bots[1].1.clone(),
+            &db,
+            &GameBudget::new(None),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let game_dir = config
+            .matchup_visualization_dir(summary.matchup_id, &bots[0].0, &bots[1].0)
+            .join("game_0");
+        assert_eq!(game_dir.join("turn_0.svg").exists(), rendered, "cap {cap}");
+    }
+}
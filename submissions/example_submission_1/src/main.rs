@@ -5,13 +5,29 @@ use axum::{
 };
 use log::{debug, info};
 use rplcs_events::tournament_1::{
-    ChoiceResponse, FightChoices, FightInfo, GambleChoices, MoveChoices,
+    ChoiceResponse, FightChoices, FightInfo, MapNodeType, MoveChoices, PlayerState,
 };
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     sync::atomic::{AtomicU8, Ordering},
 };
 
+/// `/gamble` response. `GambleChoices` from `rplcs_events` has no `MaxHealth` option.
+#[derive(Debug, Serialize)]
+enum GambleResponse {
+    MaxHealth,
+}
+
+/// `/choices` body: the move choices plus our own state. The request also carries an `opponent`
+/// state, which this bot ignores.
+#[derive(Debug, Deserialize)]
+struct ChoicesRequest {
+    #[serde(flatten)]
+    choices: MoveChoices,
+    player: PlayerState,
+}
+
 #[derive(Clone)]
 struct AppState {
     choice_counter: std::sync::Arc<AtomicU8>,
@@ -46,18 +62,31 @@ async fn health_check() -> &'static str {
 async fn handle_choices(
     State(state): State<AppState>,
     Query(_params): Query<HashMap<String, String>>,
-    Json(_choices): Json<MoveChoices>,
+    Json(request): Json<ChoicesRequest>,
 ) -> Json<ChoiceResponse> {
-    debug!("Received choices: {:?}", _choices);
+    debug!("Received choices: {:?}", request);
+
+    // Head for healing while wounded
+    if request.player.health < request.player.max_health {
+        let healing = request
+            .choices
+            .choices
+            .iter()
+            .position(|&node| node == MapNodeType::Healing);
+        if let Some(choice_index) = healing {
+            return Json(ChoiceResponse { choice_index });
+        }
+    }
+
     let current = state.choice_counter.fetch_xor(1, Ordering::SeqCst);
     Json(ChoiceResponse {
         choice_index: current as usize,
     })
 }
 
-async fn handle_gamble(Query(_params): Query<HashMap<String, String>>) -> Json<GambleChoices> {
+async fn handle_gamble(Query(_params): Query<HashMap<String, String>>) -> Json<GambleResponse> {
     debug!("Received gamble request");
-    Json(GambleChoices::Health)
+    Json(GambleResponse::MaxHealth)
 }
 
 async fn handle_fight(